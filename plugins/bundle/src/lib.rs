@@ -0,0 +1,277 @@
+use stuart_core::plugins::{NodeParser, NodeProcessor};
+use stuart_core::process::ProcessOutput;
+use stuart_core::{declare_plugin, Environment, Stuart};
+
+use std::borrow::Cow;
+use std::path::{Component, Path, PathBuf};
+
+declare_plugin! {
+    name: "bundle",
+    version: "0.1.0",
+    functions: [],
+    parsers: [BundleParser],
+}
+
+struct BundleParser;
+
+/// A bundle manifest, such as `app.css.bundle`, listing the files to combine into `app.css`.
+struct BundleProcessor {
+    path: PathBuf,
+    kind: BundleKind,
+    entries: Vec<Entry>,
+}
+
+/// The type of the files being bundled, determined by the extension of the bundle's output name.
+#[derive(Clone, Copy)]
+enum BundleKind {
+    Css,
+    Js,
+}
+
+/// A file listed in the manifest, with the line it was listed on.
+struct Entry {
+    path: String,
+    line: usize,
+}
+
+impl NodeParser for BundleParser {
+    fn extensions(&self) -> Vec<&'static str> {
+        vec!["bundle"]
+    }
+
+    fn parse(&self, contents: &[u8], path: &Path) -> Result<Box<dyn NodeProcessor>, String> {
+        let manifest = std::str::from_utf8(contents)
+            .map_err(|_| format!("bundle manifest is not valid UTF-8: {}", path.display()))?;
+
+        let kind = match Path::new(path.file_stem().unwrap_or_default())
+            .extension()
+            .and_then(|e| e.to_str())
+        {
+            Some("css") => BundleKind::Css,
+            Some("js") => BundleKind::Js,
+            _ => {
+                return Err(format!(
+                    "bundle error in {}: bundle names must end in `.css.bundle` or `.js.bundle`",
+                    path.display()
+                ))
+            }
+        };
+
+        // Each non-empty line which isn't a comment is the path of a file, relative to the manifest.
+        let entries = manifest
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(line, entry)| Entry {
+                path: entry.to_string(),
+                line,
+            })
+            .collect();
+
+        Ok(Box::new(BundleProcessor {
+            path: path.to_path_buf(),
+            kind,
+            entries,
+        }))
+    }
+}
+
+impl NodeProcessor for BundleProcessor {
+    fn process(&self, stuart: &Stuart, _: Environment) -> Result<ProcessOutput, String> {
+        let input = stuart
+            .input
+            .as_ref()
+            .ok_or_else(|| format!("bundle error in {}: no input", self.path.display()))?;
+
+        // Entries are looked up in the input tree, so files in the data directory can be bundled without being saved.
+        let dir = self
+            .path
+            .parent()
+            .and_then(|dir| dir.strip_prefix(input.source()).ok())
+            .unwrap_or_else(|| Path::new(""));
+
+        let mut bundle = String::new();
+
+        for entry in &self.entries {
+            let error = |message: String| {
+                format!(
+                    "bundle error in {} (line {}): {}",
+                    self.path.display(),
+                    entry.line,
+                    message
+                )
+            };
+
+            let node = resolve(dir, &entry.path)
+                .and_then(|path| input.get_at_path(&path))
+                .filter(|node| node.is_file())
+                .ok_or_else(|| error(format!("could not find `{}`", entry.path)))?;
+
+            // Streamed files are not read into memory, so are read from their source instead.
+            let contents = if node.parsed_contents().is_streamed() {
+                Cow::Owned(
+                    std::fs::read(node.source())
+                        .map_err(|e| error(format!("could not read `{}`: {}", entry.path, e)))?,
+                )
+            } else {
+                Cow::Borrowed(node.contents().unwrap())
+            };
+
+            let source = std::str::from_utf8(&contents)
+                .map_err(|_| error(format!("`{}` is not valid UTF-8", entry.path)))?;
+
+            match self.kind {
+                BundleKind::Css => {
+                    let minified = minifier::css::minify(source)
+                        .map_err(|e| error(format!("could not minify `{}`: {}", entry.path, e)))?;
+
+                    bundle.push_str(minified.to_string().trim());
+                }
+                BundleKind::Js => {
+                    // Separate the files so that one which omits its final semicolon can't run into the next.
+                    if !bundle.is_empty() {
+                        bundle.push(';');
+                    }
+
+                    bundle.push_str(&minifier::js::minify(source).to_string());
+                }
+            }
+        }
+
+        let name = self.path.file_stem().unwrap_or_default().to_string_lossy();
+
+        Ok(ProcessOutput {
+            new_contents: Some(bundle.into_bytes()),
+            new_name: Some(name.to_string()),
+        })
+    }
+}
+
+/// Joins the path of an entry to the directory of the manifest within the input tree, returning `None` if the
+///   entry would be outside the input tree.
+fn resolve(dir: &Path, entry: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+
+    for component in dir.join(entry).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if !path.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use stuart_core::{Config, Error, Node};
+
+    /// Builds a project containing the given files, with `src` as its data directory, returning the output.
+    fn build(name: &str, files: &[(&str, &str)]) -> Result<Node, Error> {
+        let path = std::env::temp_dir().join(format!("stuart-bundle-test-{}", name));
+
+        for (file, contents) in files {
+            std::fs::create_dir_all(path.join(file).parent().unwrap()).unwrap();
+            std::fs::write(path.join(file), contents).unwrap();
+        }
+
+        let config = Config {
+            data_dir: Some("src".to_string()),
+            ..Default::default()
+        };
+
+        let plugin = unsafe { *Box::from_raw(_stuart_plugin_init()) };
+        let mut stuart = Stuart::new(&path)
+            .with_config(config)
+            .with_plugins(vec![plugin]);
+        let result = stuart.build("production".to_string());
+        std::fs::remove_dir_all(&path).unwrap();
+        result?;
+
+        Ok(stuart.output.unwrap())
+    }
+
+    #[test]
+    fn bundle_css() {
+        let output = build(
+            "app",
+            &[
+                ("root.html", "{{ insert(\"main\") }}"),
+                (
+                    "app.css.bundle",
+                    "# styles\nsrc/base.css\n\nsrc/layout.css\n",
+                ),
+                ("src/base.css", "body {\n  margin: 0;\n}\n"),
+                (
+                    "src/layout.css",
+                    "/* layout */\n.nav a {\n  color: red;\n}\n",
+                ),
+            ],
+        )
+        .unwrap();
+
+        let bundle = output.get_at_path(Path::new("app.css")).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(bundle.contents().unwrap()).unwrap(),
+            "body{margin:0;}.nav a{color:red;}"
+        );
+        assert!(output.get_at_path(Path::new("src")).is_none());
+    }
+
+    #[test]
+    fn bundle_relative_entries() {
+        let output = build(
+            "relative",
+            &[
+                ("root.html", "{{ insert(\"main\") }}"),
+                ("assets/app.js.bundle", "../src/a.js\n./b.js\n"),
+                ("assets/b.js", "let b = 2"),
+                ("src/a.js", "let a = 1"),
+            ],
+        )
+        .unwrap();
+
+        let bundle = output.get_at_path(Path::new("assets/app.js")).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(bundle.contents().unwrap()).unwrap(),
+            "let a=1;let b=2"
+        );
+    }
+
+    #[test]
+    fn missing_entry() {
+        let result = build(
+            "missing",
+            &[
+                ("root.html", "{{ insert(\"main\") }}"),
+                ("missing.css.bundle", "src/base.css\nsrc/missing.css\n"),
+                ("src/base.css", "body { margin: 0; }"),
+            ],
+        );
+
+        match result {
+            Err(Error::Plugin(message)) => {
+                assert!(message.starts_with("bundle error in "));
+                assert!(message.contains("(line 2): could not find `src/missing.css`"));
+            }
+            _ => panic!("expected a plugin error"),
+        }
+
+        assert!(resolve(Path::new(""), "../outside.css").is_none());
+        assert_eq!(
+            resolve(Path::new("assets"), "../src/a.css"),
+            Some(PathBuf::from("src/a.css"))
+        );
+    }
+}
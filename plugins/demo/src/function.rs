@@ -1,7 +1,7 @@
-use stuart_core::functions::{Function, FunctionParser};
+use stuart_core::functions::{Function, FunctionParser, Signature};
 use stuart_core::parse::{ParseError, RawFunction};
 use stuart_core::process::{ProcessError, Scope};
-use stuart_core::{quiet_assert, TracebackError};
+use stuart_core::TracebackError;
 
 pub struct DemoParser;
 
@@ -13,10 +13,11 @@ impl FunctionParser for DemoParser {
         "demo"
     }
 
-    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
-        quiet_assert!(raw.positional_args.is_empty())?;
-        quiet_assert!(raw.named_args.is_empty())?;
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(0))
+    }
 
+    fn parse(&self, _raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
         Ok(Box::new(DemoFunction))
     }
 }
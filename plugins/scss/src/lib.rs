@@ -0,0 +1,118 @@
+use stuart_core::plugins::{NodeParser, NodeProcessor};
+use stuart_core::process::ProcessOutput;
+use stuart_core::{declare_plugin, Environment, Stuart};
+
+use grass::{from_string, Options};
+
+use std::path::{Path, PathBuf};
+
+declare_plugin! {
+    name: "scss",
+    version: "0.1.0",
+    functions: [],
+    parsers: [ScssParser],
+}
+
+struct ScssParser;
+
+struct ScssProcessor {
+    source: String,
+    path: PathBuf,
+}
+
+impl NodeParser for ScssParser {
+    fn extensions(&self) -> Vec<&'static str> {
+        vec!["scss"]
+    }
+
+    fn parse(&self, contents: &[u8], path: &Path) -> Result<Box<dyn NodeProcessor>, String> {
+        let source = String::from_utf8(contents.to_vec())
+            .map_err(|_| format!("scss file is not valid UTF-8: {}", path.display()))?;
+
+        Ok(Box::new(ScssProcessor {
+            source,
+            path: path.to_path_buf(),
+        }))
+    }
+}
+
+impl NodeProcessor for ScssProcessor {
+    fn process(&self, _: &Stuart, _: Environment) -> Result<ProcessOutput, String> {
+        // Imports are resolved relative to the file being compiled.
+        let options = match self.path.parent() {
+            Some(dir) => Options::default().load_path(dir),
+            None => Options::default(),
+        };
+
+        let css = from_string(self.source.clone(), &options)
+            .map_err(|e| format!("scss compilation error in {}: {}", self.path.display(), e))?;
+
+        let name = self.path.file_stem().unwrap_or_default().to_string_lossy();
+
+        Ok(ProcessOutput {
+            new_contents: Some(css.into_bytes()),
+            new_name: Some(format!("{}.css", name)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use stuart_core::plugins::Plugin;
+    use stuart_core::{Error, Node};
+
+    /// Builds a project containing the given SCSS file, returning the output node for it.
+    fn build(scss: &str) -> Result<Node, Error> {
+        let path = std::env::temp_dir().join(format!("stuart-scss-test-{}", scss.len()));
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("root.html"), "{{ insert(\"main\") }}").unwrap();
+        std::fs::write(path.join("style.scss"), scss).unwrap();
+
+        let plugins = vec![Plugin {
+            name: "scss".to_string(),
+            version: "0.1.0".to_string(),
+            functions: Vec::new(),
+            parsers: vec![Box::new(ScssParser)],
+        }];
+
+        let mut stuart = Stuart::new(&path).with_plugins(plugins);
+        let result = stuart.build("production".to_string());
+        std::fs::remove_dir_all(&path).unwrap();
+        result?;
+
+        Ok(stuart
+            .output
+            .unwrap()
+            .children()
+            .unwrap()
+            .iter()
+            .find(|node| node.name().ends_with("css"))
+            .unwrap()
+            .clone())
+    }
+
+    #[test]
+    fn compile_nested_rule() {
+        let output = build(".nav {\n  color: red;\n  a { color: blue; }\n}\n").unwrap();
+        let css = std::str::from_utf8(output.contents().unwrap()).unwrap();
+
+        assert_eq!(output.name(), "style.css");
+        assert_eq!(
+            css,
+            ".nav {\n  color: red;\n}\n.nav a {\n  color: blue;\n}\n"
+        );
+    }
+
+    #[test]
+    fn compilation_error() {
+        match build(".nav {\n  color: $undefined;\n}\n") {
+            Err(Error::Plugin(message)) => {
+                assert!(message.starts_with("scss compilation error in "));
+                assert!(message.contains("Undefined variable"));
+            }
+            _ => panic!("expected a plugin error"),
+        }
+    }
+}
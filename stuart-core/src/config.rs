@@ -1,18 +1,92 @@
 //! Provides the [`Config`] type.
 
+use crate::parse::{ColumnMode, Delimiters};
+
+use humphrey_json::prelude::*;
+use humphrey_json::Value;
+
+/// The line endings which text files in the output are converted to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEndings {
+    /// Unix-style line endings (`\n`).
+    Lf,
+    /// Windows-style line endings (`\r\n`).
+    Crlf,
+}
+
 /// Represents the configuration of a project.
+///
+/// Configurations can be layered with [`Config::merge`], where optional fields which are `None` are inherited
+///   from the configuration underneath. Settings which are still `None` after merging behave as their defaults,
+///   which are filled in explicitly with [`Config::resolve`] when the configuration is given to a builder.
+///
+/// Optional settings with a default should be read through the accessor method of the same name, such as
+///   [`Config::strip_extensions`], which returns the default if the setting is `None`.
 #[derive(Clone, Debug)]
 pub struct Config {
     /// The name of the project.
     pub name: String,
     /// The author of the project.
     pub author: Option<String>,
-    /// Whether to remove HTML extensions by creating folders containing `index.html` files.
-    pub strip_extensions: bool,
+    /// Whether to remove HTML extensions by creating folders containing `index.html` files, which defaults to `true`.
+    pub strip_extensions: Option<bool>,
     /// Whether to save JSON files.
-    pub save_data_files: bool,
+    pub save_data_files: Option<bool>,
     /// Whether to output the build metadata.
-    pub save_metadata: bool,
+    pub save_metadata: Option<bool>,
+    /// The name of the template which wraps HTML pages in its directory and subdirectories.
+    pub root_template: String,
+    /// The name of the template which wraps markdown pages in its directory and subdirectories.
+    pub md_template: String,
+    /// The name of directories whose files can be used by templates but are never saved to the output.
+    pub data_dir: Option<String>,
+    /// The name of the template used to generate index pages for directories of markdown files without one.
+    ///
+    /// If this is `None`, no index pages are generated.
+    pub markdown_index: Option<String>,
+    /// Whether to minify HTML files when saving them. This requires the `minify` feature.
+    pub minify_html: Option<bool>,
+    /// Whether to keep comments when minifying HTML.
+    pub minify_html_keep_comments: Option<bool>,
+    /// Whether to keep optional closing tags when minifying HTML.
+    pub minify_html_keep_closing_tags: Option<bool>,
+    /// The maximum size in bytes of files which can be inlined with the `data_uri` function.
+    pub data_uri_max_size: usize,
+    /// The delimiters which surround functions and variables in templates.
+    pub delimiters: Delimiters,
+    /// The unit in which the columns of parse errors are counted, which defaults to Unicode scalar values.
+    pub column_mode: Option<ColumnMode>,
+    /// The seed used to shuffle `for` loops with `shuffle=true`.
+    pub shuffle_seed: u64,
+    /// The extensions of plain-text files, such as `txt`, which are processed as templates without being wrapped
+    ///   by the root template.
+    pub text_extensions: Vec<String>,
+    /// The names of files without an extension, such as `_redirects`, which are processed as plain-text
+    ///   templates. Other files without an extension are copied verbatim.
+    pub template_files: Vec<String>,
+    /// Whether to lint HTML files in the output after building.
+    pub lint_html: Option<bool>,
+    /// Whether to generate an `index.html` listing the contents of output directories without one.
+    pub directory_listings: Option<bool>,
+    /// The path in the output of a JSON search index of the markdown pages, such as `search.json`.
+    ///
+    /// If this is `None`, no search index is generated.
+    pub search_index: Option<String>,
+    /// The size in bytes above which files which are not parsed are copied from their source when saved,
+    ///   instead of being read into memory.
+    ///
+    /// If this is `None`, every file is read into memory.
+    pub stream_threshold: Option<u64>,
+    /// The line endings which HTML files and plain-text templates are converted to when saved.
+    ///
+    /// If this is `None`, line endings are saved as they were written.
+    pub line_endings: Option<LineEndings>,
+    /// Whether to process files after the files they reference, such as with `import`, rather than in
+    ///   filesystem order.
+    pub dependency_order: Option<bool>,
+    /// Whether to return errors from features which are disabled in the current mode, instead of silently
+    ///   skipping them.
+    pub strict: Option<bool>,
 }
 
 impl Default for Config {
@@ -20,9 +94,214 @@ impl Default for Config {
         Self {
             name: "".to_string(),
             author: None,
-            strip_extensions: true,
-            save_data_files: false,
-            save_metadata: false,
+            strip_extensions: None,
+            save_data_files: None,
+            save_metadata: None,
+            root_template: "root.html".to_string(),
+            md_template: "md.html".to_string(),
+            data_dir: None,
+            markdown_index: None,
+            minify_html: None,
+            minify_html_keep_comments: None,
+            minify_html_keep_closing_tags: None,
+            data_uri_max_size: 16384,
+            delimiters: Delimiters::default(),
+            column_mode: None,
+            shuffle_seed: 0,
+            text_extensions: Vec::new(),
+            template_files: Vec::new(),
+            lint_html: None,
+            directory_listings: None,
+            search_index: None,
+            stream_threshold: None,
+            line_endings: None,
+            dependency_order: None,
+            strict: None,
+        }
+    }
+}
+
+impl Config {
+    /// Returns the default configuration with every optional setting filled in.
+    pub fn default_resolved() -> Self {
+        Self::default().resolve()
+    }
+
+    /// Layers the other configuration on top of this one.
+    ///
+    /// Optional fields are taken from the other configuration if they are `Some`, and from this configuration
+    ///   otherwise. Fields which are not optional, such as the name, are taken from the other configuration if
+    ///   they differ from their defaults, and from this configuration otherwise.
+    pub fn merge(mut self, other: Config) -> Config {
+        let default = Config::default();
+
+        if other.name != default.name {
+            self.name = other.name;
+        }
+        if other.root_template != default.root_template {
+            self.root_template = other.root_template;
         }
+        if other.md_template != default.md_template {
+            self.md_template = other.md_template;
+        }
+        if other.data_uri_max_size != default.data_uri_max_size {
+            self.data_uri_max_size = other.data_uri_max_size;
+        }
+        if other.delimiters != default.delimiters {
+            self.delimiters = other.delimiters;
+        }
+        if other.shuffle_seed != default.shuffle_seed {
+            self.shuffle_seed = other.shuffle_seed;
+        }
+        if other.text_extensions != default.text_extensions {
+            self.text_extensions = other.text_extensions;
+        }
+        if other.template_files != default.template_files {
+            self.template_files = other.template_files;
+        }
+
+        self.author = other.author.or(self.author);
+        self.column_mode = other.column_mode.or(self.column_mode);
+        self.strip_extensions = other.strip_extensions.or(self.strip_extensions);
+        self.save_data_files = other.save_data_files.or(self.save_data_files);
+        self.save_metadata = other.save_metadata.or(self.save_metadata);
+        self.data_dir = other.data_dir.or(self.data_dir);
+        self.markdown_index = other.markdown_index.or(self.markdown_index);
+        self.minify_html = other.minify_html.or(self.minify_html);
+        self.minify_html_keep_comments = other
+            .minify_html_keep_comments
+            .or(self.minify_html_keep_comments);
+        self.minify_html_keep_closing_tags = other
+            .minify_html_keep_closing_tags
+            .or(self.minify_html_keep_closing_tags);
+        self.lint_html = other.lint_html.or(self.lint_html);
+        self.directory_listings = other.directory_listings.or(self.directory_listings);
+        self.search_index = other.search_index.or(self.search_index);
+        self.stream_threshold = other.stream_threshold.or(self.stream_threshold);
+        self.line_endings = other.line_endings.or(self.line_endings);
+        self.dependency_order = other.dependency_order.or(self.dependency_order);
+        self.strict = other.strict.or(self.strict);
+
+        self
+    }
+
+    /// Fills in the defaults of any optional settings which are `None`.
+    ///
+    /// Settings which have no default, such as `author`, `data_dir` and `search_index`, are left as they are.
+    pub fn resolve(mut self) -> Config {
+        self.strip_extensions = Some(self.strip_extensions());
+        self.save_data_files = Some(self.save_data_files());
+        self.save_metadata = Some(self.save_metadata());
+        self.minify_html = Some(self.minify_html());
+        self.minify_html_keep_comments = Some(self.minify_html_keep_comments());
+        self.minify_html_keep_closing_tags = Some(self.minify_html_keep_closing_tags());
+        self.lint_html = Some(self.lint_html());
+        self.directory_listings = Some(self.directory_listings());
+        self.dependency_order = Some(self.dependency_order());
+        self.strict = Some(self.strict());
+        self.column_mode = Some(self.column_mode());
+
+        self
+    }
+
+    /// Whether to remove HTML extensions, which defaults to `true`.
+    pub fn strip_extensions(&self) -> bool {
+        self.strip_extensions.unwrap_or(true)
+    }
+
+    /// Whether to save JSON files, which defaults to `false`.
+    pub fn save_data_files(&self) -> bool {
+        self.save_data_files.unwrap_or(false)
+    }
+
+    /// Whether to output the build metadata, which defaults to `false`.
+    pub fn save_metadata(&self) -> bool {
+        self.save_metadata.unwrap_or(false)
+    }
+
+    /// Whether to minify HTML files, which defaults to `false`.
+    pub fn minify_html(&self) -> bool {
+        self.minify_html.unwrap_or(false)
+    }
+
+    /// Whether to keep comments when minifying HTML, which defaults to `false`.
+    pub fn minify_html_keep_comments(&self) -> bool {
+        self.minify_html_keep_comments.unwrap_or(false)
+    }
+
+    /// Whether to keep optional closing tags when minifying HTML, which defaults to `false`.
+    pub fn minify_html_keep_closing_tags(&self) -> bool {
+        self.minify_html_keep_closing_tags.unwrap_or(false)
+    }
+
+    /// Whether to lint HTML files in the output, which defaults to `false`.
+    pub fn lint_html(&self) -> bool {
+        self.lint_html.unwrap_or(false)
+    }
+
+    /// Whether to generate directory listings, which defaults to `false`.
+    pub fn directory_listings(&self) -> bool {
+        self.directory_listings.unwrap_or(false)
+    }
+
+    /// Whether to process files in dependency order, which defaults to `false`.
+    pub fn dependency_order(&self) -> bool {
+        self.dependency_order.unwrap_or(false)
+    }
+
+    /// Whether to return errors from disabled features, which defaults to `false`.
+    pub fn strict(&self) -> bool {
+        self.strict.unwrap_or(false)
+    }
+
+    /// The unit in which the columns of parse errors are counted, which defaults to Unicode scalar values.
+    pub fn column_mode(&self) -> ColumnMode {
+        self.column_mode.unwrap_or_default()
+    }
+
+    /// Returns the configuration as a JSON object keyed by the names of its fields, with the defaults of optional
+    ///   settings filled in as by [`Config::resolve`].
+    ///
+    /// Settings which have no default and are not set are `null`.
+    pub fn to_json(&self) -> Value {
+        let config = self.clone().resolve();
+
+        json!({
+            "name": (config.name),
+            "author": (config.author),
+            "strip_extensions": (config.strip_extensions),
+            "save_data_files": (config.save_data_files),
+            "save_metadata": (config.save_metadata),
+            "root_template": (config.root_template),
+            "md_template": (config.md_template),
+            "data_dir": (config.data_dir),
+            "markdown_index": (config.markdown_index),
+            "minify_html": (config.minify_html),
+            "minify_html_keep_comments": (config.minify_html_keep_comments),
+            "minify_html_keep_closing_tags": (config.minify_html_keep_closing_tags),
+            "data_uri_max_size": (config.data_uri_max_size),
+            "delimiters": {
+                "open": (config.delimiters.open),
+                "close": (config.delimiters.close)
+            },
+            "column_mode": (config.column_mode.map(|column_mode| match column_mode {
+                ColumnMode::ByteOffset => "byte",
+                ColumnMode::CharOffset => "char",
+                ColumnMode::Utf16Offset => "utf16",
+            })),
+            "shuffle_seed": (config.shuffle_seed),
+            "text_extensions": (config.text_extensions),
+            "template_files": (config.template_files),
+            "lint_html": (config.lint_html),
+            "directory_listings": (config.directory_listings),
+            "search_index": (config.search_index),
+            "stream_threshold": (config.stream_threshold),
+            "line_endings": (config.line_endings.map(|line_endings| match line_endings {
+                LineEndings::Lf => "lf",
+                LineEndings::Crlf => "crlf",
+            })),
+            "dependency_order": (config.dependency_order),
+            "strict": (config.strict)
+        })
     }
 }
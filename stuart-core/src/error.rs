@@ -19,6 +19,8 @@ pub enum Error {
     NotBuilt,
     /// Metadata was requested, but its generation is not enabled in the configuration.
     MetadataNotEnabled,
+    /// The configuration was invalid.
+    InvalidConfig(String),
 }
 
 /// Encapsulates an error and its location.
@@ -26,9 +28,13 @@ pub enum Error {
 pub struct TracebackError<T: Clone + Debug> {
     /// The path of the file in which the error occurred.
     pub path: PathBuf,
-    /// The line number at which the error occurred.
+    /// The line number at which the error occurred, starting from 1.
     pub line: u32,
-    /// The column number at which the error occurred.
+    /// The column number at which the error occurred, starting from 1.
+    ///
+    /// Columns are counted in Unicode scalar values by default, so a multi-byte character such as `é` counts as
+    ///   one column. Other units can be chosen with the `column_mode` setting of the
+    ///   [`Config`](crate::Config), which is passed to the parser as a [`ColumnMode`](crate::parse::ColumnMode).
     pub column: u32,
     /// The error.
     pub kind: T,
@@ -45,6 +51,10 @@ pub enum FsError {
     Write,
     /// A conflict occurred when merging two virtual filesystems.
     Conflict(PathBuf, PathBuf),
+    /// The path was refused for removal since it does not look like a build output directory.
+    UnsafeRemove(PathBuf),
+    /// The output path already exists as a file, so a directory cannot be created there.
+    OutputIsFile(PathBuf),
 }
 
 /// Represents an error which can occur during the parsing of a file.
@@ -72,6 +82,30 @@ pub enum ParseError {
     InvalidJson,
     /// An assertion with the [`quiet_assert`] macro failed.
     AssertionError(String),
+    /// The template delimiters were empty or identical.
+    InvalidDelimiters,
+    /// A function was called with the wrong number of positional arguments.
+    WrongArgumentCount {
+        /// The name of the function.
+        function: String,
+        /// The minimum number of positional arguments.
+        min: usize,
+        /// The maximum number of positional arguments, or `None` if there is no maximum.
+        max: Option<usize>,
+        /// The number of positional arguments which were given.
+        found: usize,
+    },
+    /// A function was called with a named argument which it does not accept.
+    UnknownNamedArgument {
+        /// The name of the function.
+        function: String,
+        /// The name of the unknown argument.
+        name: String,
+        /// The names of the named arguments which the function accepts.
+        expected: Vec<String>,
+    },
+    /// A named argument was given more than once.
+    DuplicateNamedArgument(String),
 }
 
 /// Represents an error which can occur during the processing of a file.
@@ -87,24 +121,63 @@ pub enum ProcessError {
     EndWithoutBegin,
     /// An `else()` function was called without a previous `ifeq`, `ifne`, etc.
     ElseWithoutIf,
+    /// An `else` or `end` function was labelled differently to the conditional it belongs to.
+    MismatchedLabel {
+        /// The label of the `else` or `end` function.
+        label: String,
+        /// The label of the conditional, if it has one.
+        expected: Option<String>,
+    },
     /// A JSON array was expected but not found.
     NotJsonArray,
+    /// A JSON value could not be parsed.
+    InvalidJson,
     /// An invalid date was found.
     InvalidDate,
     /// The end of the file was reached unexpectedly.
     UnexpectedEndOfFile,
     /// The crate feature required for this function was not enabled.
     FeatureNotEnabled(String),
+    /// The feature is not available in the current `STUART_ENV` mode.
+    FeatureDisabledInMode {
+        /// The name of the feature.
+        feature: String,
+        /// The mode in which the feature is disabled.
+        mode: String,
+    },
     /// The variable already exists.
     VariableAlreadyExists(String),
     /// The variable does not exist.
     UndefinedVariable(String),
     /// The function does not exist.
     UndefinedSection(String),
+    /// The snippet does not exist.
+    UndefinedSnippet(String),
+    /// Snippet calls were nested too deeply, usually because the snippet calls itself.
+    SnippetTooDeep(String),
     /// The variable is null.
     NullError(String),
     /// The file was not found.
     NotFound(String),
+    /// A file was expected, but the path is a directory.
+    ExpectedFile(String),
+    /// A directory was expected, but the path is a file.
+    ExpectedDirectory(String),
+    /// The file was too large to be used in this context.
+    FileTooLarge(String),
+    /// The frontmatter of the markdown file does not contain the key.
+    MissingFrontmatterKey {
+        /// The path of the markdown file.
+        file: String,
+        /// The missing key.
+        key: String,
+    },
+    /// Files reference each other in a cycle, so cannot be processed in dependency order.
+    DependencyCycle(Vec<String>),
+    /// A function provided by a plugin failed.
+    PluginError(String),
+    /// A filesystem error occurred, such as when a file could not be read.
+    Fs(FsError),
 
     /// The data type of the variable was invalid.
     InvalidDataType {
@@ -116,3 +189,17 @@ pub enum ProcessError {
         found: String,
     },
 }
+
+impl From<FsError> for ProcessError {
+    /// Converts an error from looking up a file in the input tree, such as with [`Node::get_at_path_result`].
+    ///
+    /// Files which are not found are reported with [`ProcessError::NotFound`], and any other error is kept as it is.
+    ///
+    /// [`Node::get_at_path_result`]: crate::fs::Node::get_at_path_result
+    fn from(e: FsError) -> Self {
+        match e {
+            FsError::NotFound(path) => ProcessError::NotFound(path),
+            e => ProcessError::Fs(e),
+        }
+    }
+}
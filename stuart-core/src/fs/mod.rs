@@ -4,18 +4,27 @@
 //!   in memory. They are saved back to disk after processing. In this way, you can think of the entire build process
 //!   as simply a function that maps `Node -> Node`. This function is called [`Node::process`].
 
+use crate::config::LineEndings;
 use crate::error::{FsError, ParseError};
-use crate::parse::{parse_html, parse_markdown};
-use crate::plugins::Manager;
-use crate::{Config, Error, TracebackError};
+use crate::parse::{parse_html_with_delimiters, parse_markdown_with_delimiters};
+use crate::plugins::{Manager, ParseStep};
+use crate::{Config, Error, StuartBuildHook, TracebackError};
+
+#[cfg(feature = "watch")]
+mod watch;
 
 pub use crate::parse::ParsedContents;
 
+#[cfg(feature = "watch")]
+pub use watch::Changes;
+
 use humphrey_json::prelude::*;
 use humphrey_json::Value;
 
+use std::borrow::Cow;
+use std::cmp::Reverse;
 use std::fmt::Debug;
-use std::fs::{create_dir, metadata, read, read_dir, remove_dir_all, write};
+use std::fs::{copy, create_dir, create_dir_all, metadata, read, read_dir, remove_dir_all, write};
 use std::io::ErrorKind;
 use std::path::{Component, Path, PathBuf};
 use std::rc::Rc;
@@ -50,13 +59,7 @@ pub enum Node {
 impl Node {
     /// Constructs a new virtual filesystem tree from the given filesystem path.
     pub fn new(root: impl AsRef<Path>, parse: bool) -> Result<Self, Error> {
-        let root = root.as_ref().to_path_buf().canonicalize().map_err(|_| {
-            Error::Fs(FsError::NotFound(
-                root.as_ref().to_string_lossy().to_string(),
-            ))
-        })?;
-
-        Self::create_from_dir(root, parse, None)
+        Self::new_with_options(root, parse, None, &Config::default())
     }
 
     /// Constructs a new virtual filesystem tree from the given filesystem path, with the configured plugins.
@@ -64,6 +67,20 @@ impl Node {
         root: impl AsRef<Path>,
         parse: bool,
         plugins: &dyn Manager,
+    ) -> Result<Self, Error> {
+        Self::new_with_options(root, parse, Some(plugins), &Config::default())
+    }
+
+    /// Constructs a new virtual filesystem tree from the given filesystem path, with the given plugins, parsing
+    ///   files with the template delimiters and text extensions of the configuration.
+    ///
+    /// Files larger than the configured stream threshold in bytes which would not be parsed are not read into
+    ///   memory, and are instead copied from their source when saved.
+    pub fn new_with_options(
+        root: impl AsRef<Path>,
+        parse: bool,
+        plugins: Option<&dyn Manager>,
+        config: &Config,
     ) -> Result<Self, Error> {
         let root = root.as_ref().to_path_buf().canonicalize().map_err(|_| {
             Error::Fs(FsError::NotFound(
@@ -71,7 +88,7 @@ impl Node {
             ))
         })?;
 
-        Self::create_from_dir(root, parse, Some(plugins))
+        Self::create_from_dir(root, parse, plugins, config)
     }
 
     /// Returns `true` if the node is a directory.
@@ -139,7 +156,63 @@ impl Node {
         }
     }
 
-    /// Attempts to get a node at the given path of the filesystem.
+    /// Returns an iterator over all the files below the node, with their paths relative to the node.
+    ///
+    /// Files are yielded in depth-first order, with the children of each directory in their stored order.
+    pub fn iter_files(&self) -> impl Iterator<Item = (PathBuf, &Node)> {
+        let mut files = Vec::new();
+        self.collect_files(PathBuf::new(), &mut files);
+        files.into_iter()
+    }
+
+    /// Recursively collects the files below the node into the given list.
+    fn collect_files<'a>(&'a self, path: PathBuf, files: &mut Vec<(PathBuf, &'a Node)>) {
+        match self {
+            Node::File { .. } => files.push((path, self)),
+            Node::Directory { children, .. } => {
+                for child in children {
+                    child.collect_files(path.join(child.name()), files);
+                }
+            }
+        }
+    }
+
+    /// Recursively sorts the children of every directory below the node alphabetically by name.
+    pub fn sort_children_by_name(&mut self) {
+        self.sort_children_by(|node| node.name().to_string());
+    }
+
+    /// Recursively sorts the children of every directory below the node by the given key.
+    ///
+    /// The sort is stable, so children with equal keys keep their existing order.
+    pub fn sort_children_by<F, K>(&mut self, key: F)
+    where
+        F: Fn(&Node) -> K,
+        K: Ord,
+    {
+        self.sort_children_by_ref(&key);
+    }
+
+    /// Recursively sorts the children of every directory below the node by the given key, taken by reference
+    ///   so that it can be passed down to each directory.
+    fn sort_children_by_ref<F, K>(&mut self, key: &F)
+    where
+        F: Fn(&Node) -> K,
+        K: Ord,
+    {
+        if let Node::Directory { children, .. } = self {
+            children.sort_by_key(|child| key(child));
+
+            for child in children {
+                child.sort_children_by_ref(key);
+            }
+        }
+    }
+
+    /// Attempts to get a node at the given path of the filesystem, relative to this node.
+    ///
+    /// Paths which could refer to something outside this node, such as those containing `..` or absolute paths,
+    ///   are rejected rather than normalized, so `None` is returned even if the path would lead back inside it.
     pub fn get_at_path(&self, path: &Path) -> Option<&Self> {
         let mut working_path = vec![self];
 
@@ -154,36 +227,47 @@ impl Node {
                     );
                 }
                 Component::CurDir => (),
-                _ => return None,
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
             }
         }
 
         working_path.last().copied()
     }
 
+    /// Attempts to get a node at the given path of the filesystem, returning `FsError::NotFound` if there is none.
+    pub fn get_at_path_result(&self, path: &Path) -> Result<&Self, FsError> {
+        self.get_at_path(path)
+            .ok_or_else(|| FsError::NotFound(path.to_string_lossy().to_string()))
+    }
+
     /// Creates a new node from a directory of the filesystem.
     pub(crate) fn create_from_dir(
         dir: impl AsRef<Path>,
         parse: bool,
         plugins: Option<&dyn Manager>,
+        config: &Config,
     ) -> Result<Self, Error> {
         let dir = dir.as_ref();
         let content = read_dir(dir)
             .map_err(|_| Error::Fs(FsError::NotFound(dir.to_string_lossy().to_string())))?;
 
-        let children = content
+        let mut children: Vec<Node> = content
             .flatten()
             .map(|path| {
                 let path = path.path();
 
                 match metadata(&path).map(|m| m.file_type()) {
-                    Ok(t) if t.is_dir() => Self::create_from_dir(&path, parse, plugins),
-                    Ok(t) if t.is_file() => Self::create_from_file(&path, parse, plugins),
+                    Ok(t) if t.is_dir() => Self::create_from_dir(&path, parse, plugins, config),
+                    Ok(t) if t.is_file() => Self::create_from_file(&path, parse, plugins, config),
                     _ => Err(Error::Fs(FsError::Read)),
                 }
             })
             .collect::<Result<_, _>>()?;
 
+        // The order of `read_dir` depends on the platform, so children are sorted to keep builds deterministic.
+        // Subdirectories were sorted when they were created, so this is equivalent to `sort_children_by_name`.
+        children.sort_by(|a, b| a.name().cmp(b.name()));
+
         Ok(Node::Directory {
             name: dir.file_name().unwrap().to_string_lossy().to_string(),
             children,
@@ -191,94 +275,436 @@ impl Node {
         })
     }
 
+    /// Creates a new file node from in-memory contents, parsing them according to the file name's extension.
+    ///
+    /// The source path does not need to exist, and is only used for error messages and metadata.
+    pub fn from_bytes(
+        name: impl AsRef<str>,
+        contents: impl Into<Vec<u8>>,
+        source: impl AsRef<Path>,
+        plugins: Option<&dyn Manager>,
+    ) -> Result<Self, Error> {
+        let mut name = name.as_ref().to_string();
+        let mut contents = contents.into();
+        let source = source.as_ref();
+        let parsed_contents = Self::parse_contents(
+            &mut name,
+            &mut contents,
+            source,
+            plugins,
+            &Config::default(),
+        )?;
+
+        Ok(Node::File {
+            name,
+            contents,
+            parsed_contents,
+            metadata: None,
+            source: source.to_path_buf(),
+        })
+    }
+
     /// Creates a new node from a file of the filesystem.
     pub(crate) fn create_from_file(
         file: impl AsRef<Path>,
         parse: bool,
         plugins: Option<&dyn Manager>,
+        config: &Config,
     ) -> Result<Self, Error> {
         let file = file.as_ref();
         let name = file.file_name().unwrap().to_string_lossy().to_string();
-        let contents = read(file).map_err(|_| Error::Fs(FsError::Read))?;
+
+        if let Some(threshold) = config.stream_threshold {
+            let size = metadata(file).map_err(|_| Error::Fs(FsError::Read))?.len();
+
+            // HTML files are never streamed, since they may be minified or have their extensions stripped.
+            if size > threshold
+                && !name.ends_with(".html")
+                && (!parse || !is_parsed(&name, plugins, config))
+            {
+                return Ok(Node::File {
+                    name,
+                    contents: Vec::new(),
+                    parsed_contents: ParsedContents::Streamed,
+                    metadata: None,
+                    source: file.to_path_buf(),
+                });
+            }
+        }
+
+        let mut name = name;
+        let mut contents = read(file).map_err(|_| Error::Fs(FsError::Read))?;
 
         let parsed_contents = if parse {
-            let extension = file.extension().map(|e| e.to_string_lossy().to_string());
+            Self::parse_contents(&mut name, &mut contents, file, plugins, config)?
+        } else {
+            ParsedContents::Ignored
+        };
+
+        Ok(Node::File {
+            name,
+            contents,
+            parsed_contents,
+            metadata: None,
+            source: file.to_path_buf(),
+        })
+    }
+
+    /// Parses the contents of a file according to the extension of its name.
+    ///
+    /// If a plugin parser continues with new contents under a new name, the name and contents are replaced and
+    ///   parsed again, skipping the parsers which have already been used for the file.
+    fn parse_contents(
+        name: &mut String,
+        contents: &mut Vec<u8>,
+        path: &Path,
+        plugins: Option<&dyn Manager>,
+        config: &Config,
+    ) -> Result<ParsedContents, Error> {
+        let delimiters = &config.delimiters;
+        let column_mode = config.column_mode();
+        let mut used_parsers = Vec::new();
+
+        loop {
+            let extension = Path::new(name.as_str())
+                .extension()
+                .map(|e| e.to_string_lossy().to_string());
             let contents_string =
-                std::str::from_utf8(&contents).map_err(|_| Error::Fs(FsError::Read));
+                std::str::from_utf8(contents).map_err(|_| Error::Fs(FsError::Read));
 
-            match extension.as_deref() {
+            return Ok(match extension.as_deref() {
                 Some("html") => ParsedContents::Html(
-                    parse_html(contents_string?, file, plugins).map_err(Error::Parse)?,
+                    parse_html_with_delimiters(
+                        contents_string?,
+                        path,
+                        plugins,
+                        delimiters,
+                        column_mode,
+                    )
+                    .map_err(Error::Parse)?,
                 ),
                 Some("md") => ParsedContents::Markdown(
-                    parse_markdown(contents_string?.to_string(), file, plugins)
-                        .map_err(Error::Parse)?,
+                    parse_markdown_with_delimiters(
+                        contents_string?.to_string(),
+                        path,
+                        plugins,
+                        delimiters,
+                        column_mode,
+                    )
+                    .map_err(Error::Parse)?,
                 ),
                 Some("json") => ParsedContents::Json(
                     humphrey_json::from_str(contents_string?).map_err(|_| {
                         Error::Parse(TracebackError {
-                            path: file.to_path_buf(),
+                            path: path.to_path_buf(),
                             kind: ParseError::InvalidJson,
                             column: 0,
                             line: 0,
                         })
                     })?,
                 ),
+                Some(extension) if config.text_extensions.iter().any(|e| e == extension) => {
+                    ParsedContents::Text(
+                        parse_html_with_delimiters(
+                            contents_string?,
+                            path,
+                            plugins,
+                            delimiters,
+                            column_mode,
+                        )
+                        .map_err(Error::Parse)?,
+                    )
+                }
                 Some(extension) => {
-                    let mut result = ParsedContents::None;
-
-                    if let Some(plugins) = plugins {
-                        'outer: for plugin in plugins.plugins() {
-                            for parser in &plugin.parsers {
-                                if parser.extensions().contains(&extension) {
-                                    result = ParsedContents::Custom(Rc::new(
-                                        parser.parse(&contents, file).map_err(Error::Plugin)?,
-                                    ));
-                                    break 'outer;
+                    // `min_by_key` returns the first of several equal parsers, so ties go to the earlier plugin.
+                    let parser = plugins.and_then(|plugins| {
+                        plugins
+                            .plugins()
+                            .iter()
+                            .flat_map(|plugin| &plugin.parsers)
+                            .enumerate()
+                            .filter(|(i, parser)| {
+                                !used_parsers.contains(i)
+                                    && parser.extensions().contains(&extension)
+                            })
+                            .min_by_key(|(_, parser)| Reverse(parser.priority()))
+                    });
+
+                    match parser {
+                        Some((i, parser)) => {
+                            match parser.parse_step(contents, path).map_err(Error::Plugin)? {
+                                ParseStep::Done(processor) => {
+                                    ParsedContents::Custom(Rc::new(processor))
+                                }
+                                ParseStep::Continue {
+                                    name: new_name,
+                                    contents: new_contents,
+                                } => {
+                                    used_parsers.push(i);
+                                    *name = new_name;
+                                    *contents = new_contents;
+                                    continue;
                                 }
                             }
                         }
+                        None => ParsedContents::None,
                     }
-
-                    result
                 }
+                None if config.template_files.contains(name) => ParsedContents::Text(
+                    parse_html_with_delimiters(
+                        contents_string?,
+                        path,
+                        plugins,
+                        delimiters,
+                        column_mode,
+                    )
+                    .map_err(Error::Parse)?,
+                ),
                 None => ParsedContents::None,
-            }
-        } else {
-            ParsedContents::Ignored
-        };
-
-        Ok(Node::File {
-            name,
-            contents,
-            parsed_contents,
-            metadata: None,
-            source: file.to_path_buf(),
-        })
+            });
+        }
     }
 
     /// Save the node to the filesystem with the given configuration.
     pub fn save(&self, path: impl AsRef<Path>, config: &Config) -> Result<(), Error> {
+        self.save_with_hooks(path, config, &[])
+    }
+
+    /// Save the node to the filesystem with the given configuration like [`Node::save`], renaming files with the
+    ///   [`StuartBuildHook::rename_output`] method of the given hooks.
+    pub fn save_with_hooks(
+        &self,
+        path: impl AsRef<Path>,
+        config: &Config,
+        hooks: &[Box<dyn StuartBuildHook>],
+    ) -> Result<(), Error> {
+        let path = path.as_ref().to_path_buf();
+
+        match self {
+            Self::Directory { children, .. } => {
+                create_output_dir(&path)?;
+
+                for child in children {
+                    child.save_recur(&path, config, hooks)?;
+                }
+
+                if config.directory_listings() {
+                    self.save_listings(&path, "/", config, hooks)?;
+                }
+            }
+            _ => panic!("`Node::save` should only be used on the root directory"),
+        }
+
+        Ok(())
+    }
+
+    /// Save the node to the given path without removing anything already there.
+    /// Existing files are overwritten by those in the node, but all other files are left untouched.
+    pub fn save_merged(&self, path: impl AsRef<Path>, config: &Config) -> Result<(), Error> {
+        self.save_merged_with_hooks(path, config, &[])
+    }
+
+    /// Save the node to the given path without removing anything already there like [`Node::save_merged`],
+    ///   renaming files with the [`StuartBuildHook::rename_output`] method of the given hooks.
+    pub fn save_merged_with_hooks(
+        &self,
+        path: impl AsRef<Path>,
+        config: &Config,
+        hooks: &[Box<dyn StuartBuildHook>],
+    ) -> Result<(), Error> {
         let path = path.as_ref().to_path_buf();
 
-        if path.exists() && path.is_dir() {
-            remove_dir_all(&path).map_err(|_| Error::Fs(FsError::Write))?;
+        if path.is_file() {
+            return Err(Error::Fs(FsError::OutputIsFile(path)));
         }
 
         match self {
             Self::Directory { children, .. } => {
-                create_dir(&path).map_err(|_| Error::Fs(FsError::Write))?;
+                create_dir_all(&path).map_err(|_| Error::Fs(FsError::Write))?;
 
                 for child in children {
-                    child.save_recur(&path, config)?;
+                    child.save_recur(&path, config, hooks)?;
+                }
+            }
+            _ => panic!("`Node::save_merged` should only be used on the root directory"),
+        }
+
+        Ok(())
+    }
+
+    /// Recursively saves a listing of its contents as `index.html` into each saved directory without one.
+    ///
+    /// This must be called after the node has been saved, with the path of the saved directory and its path
+    ///   relative to the output directory, which is used as the title of the listing.
+    fn save_listings(
+        &self,
+        path: &Path,
+        relative: &str,
+        config: &Config,
+        hooks: &[Box<dyn StuartBuildHook>],
+    ) -> Result<(), Error> {
+        if let Self::Directory { children, .. } = self {
+            let mut entries: Vec<String> = Vec::new();
+
+            // With `strip_extensions`, an HTML file and a directory with the same name are saved together.
+            for entry in children.iter().map(|child| child.saved_name(config, hooks)) {
+                if !entries.contains(&entry) {
+                    entries.push(entry);
+                }
+            }
+
+            if !entries.iter().any(|entry| entry == "index.html") {
+                if relative != "/" {
+                    entries.insert(0, "../".to_string());
+                }
+
+                let items = entries
+                    .iter()
+                    .map(|entry| {
+                        let entry = crate::process::escape_html(entry);
+                        format!("<li><a href=\"{}\">{}</a></li>", entry, entry)
+                    })
+                    .collect::<String>();
+
+                let listing = format!(
+                    "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Index of {0}</title></head><body><h1>Index of {0}</h1><ul>{1}</ul></body></html>",
+                    crate::process::escape_html(relative),
+                    items
+                );
+
+                write(path.join("index.html"), listing).map_err(|_| Error::Fs(FsError::Write))?;
+            }
+
+            for child in children {
+                if let Self::Directory { name, .. } = child {
+                    child.save_listings(
+                        &path.join(name),
+                        &format!("{}{}/", relative, name),
+                        config,
+                        hooks,
+                    )?;
                 }
             }
-            _ => panic!("`Node::save` should only be used on the root directory"),
         }
 
         Ok(())
     }
 
+    /// Returns the name of the node in its saved directory.
+    ///
+    /// Directories are given a trailing slash, and HTML files which are saved into their own directory because of
+    ///   `strip_extensions` are given the name of that directory.
+    fn saved_name(&self, config: &Config, hooks: &[Box<dyn StuartBuildHook>]) -> String {
+        match self {
+            Self::Directory { name, .. } => format!("{}/", name),
+            Self::File {
+                parsed_contents, ..
+            } => {
+                let name = self.output_name(hooks);
+
+                if config.strip_extensions()
+                    && name.ends_with(".html")
+                    && name != "index.html"
+                    && !parsed_contents.is_ignored()
+                {
+                    format!("{}/", name.strip_suffix(".html").unwrap())
+                } else {
+                    name
+                }
+            }
+        }
+    }
+
+    /// Returns the name under which the node is saved, after renaming it with the
+    ///   [`StuartBuildHook::rename_output`] method of each of the given hooks in turn.
+    pub(crate) fn output_name(&self, hooks: &[Box<dyn StuartBuildHook>]) -> String {
+        hooks.iter().fold(self.name().to_string(), |name, hook| {
+            hook.rename_output(self.source(), name)
+        })
+    }
+
+    /// Removes every node below this one which is not saved to the output directory with the given configuration,
+    ///   so that everything left in the tree is saved as it is.
+    ///
+    /// This removes the data directory, and the root and markdown templates from every directory, since templates in
+    ///   subdirectories only override the root-most ones and none of them are part of the output. JSON data files
+    ///   are removed unless `save_data_files` is enabled, in which case they are marked with the `data` type in the
+    ///   build metadata.
+    ///
+    /// This is called on the output of every build, since templates and data are still needed while the input is
+    ///   being processed.
+    pub fn apply_config(&mut self, config: &Config) {
+        match self {
+            Self::Directory { children, .. } => {
+                children.retain(|child| child.is_output(config));
+
+                for child in children {
+                    child.apply_config(config);
+                }
+            }
+            Self::File { name, metadata, .. } => {
+                if name.ends_with(".json") && metadata.is_none() {
+                    *metadata = Some(json!({ "type": "data" }));
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if the node itself is saved to the output directory with the given configuration.
+    ///
+    /// Directories are saved unless they are the data directory, and files are saved according to `is_saved`.
+    pub(crate) fn is_output(&self, config: &Config) -> bool {
+        match self {
+            Self::Directory { name, .. } => config.data_dir.as_ref() != Some(name),
+            Self::File { name, .. } => is_saved(name, config),
+        }
+    }
+
+    /// Returns the number of files below the node which are saved to the output directory with the given configuration.
+    pub fn count_files(&self, config: &Config) -> usize {
+        let mut files = Vec::new();
+        self.collect_saved_files(config, &mut files);
+
+        files.len()
+    }
+
+    /// Returns the total size in bytes of the files below the node which are saved to the output directory with
+    ///   the given configuration, before any minification.
+    ///
+    /// The size of streamed files is read from their source.
+    pub fn total_size(&self, config: &Config) -> u64 {
+        let mut files = Vec::new();
+        self.collect_saved_files(config, &mut files);
+
+        files
+            .iter()
+            .map(|file| match file {
+                Self::File {
+                    parsed_contents: ParsedContents::Streamed,
+                    source,
+                    ..
+                } => metadata(source).map(|m| m.len()).unwrap_or(0),
+                file => file.contents().unwrap().len() as u64,
+            })
+            .sum()
+    }
+
+    /// Recursively collects the files below the node which are saved to the output directory.
+    fn collect_saved_files<'a>(&'a self, config: &Config, files: &mut Vec<&'a Node>) {
+        if !self.is_output(config) {
+            return;
+        }
+
+        match self {
+            Self::Directory { children, .. } => {
+                for child in children {
+                    child.collect_saved_files(config, files);
+                }
+            }
+            Self::File { .. } => files.push(self),
+        }
+    }
+
     /// Save the node's metadata to the given path.
     /// The `base` argument should be a JSON object to which the metadata will be added under the key `data`.
     pub fn save_metadata(&self, mut base: Value, path: impl AsRef<Path>) -> Result<(), Error> {
@@ -328,8 +754,96 @@ impl Node {
         }
     }
 
+    /// Overlay another virtual filesystem tree onto this one.
+    /// Unlike [`Node::merge`], nodes of the other tree replace nodes of this tree which share the same path,
+    ///   while directories in both trees are overlaid recursively.
+    pub fn overlay(&mut self, other: Node) {
+        match (self, other) {
+            (
+                Self::Directory { children, .. },
+                Self::Directory {
+                    children: other_children,
+                    ..
+                },
+            ) => {
+                for other_child in other_children {
+                    if let Some(child) = children
+                        .iter_mut()
+                        .find(|child| child.name() == other_child.name())
+                    {
+                        if child.is_dir() && other_child.is_dir() {
+                            child.overlay(other_child);
+                        } else {
+                            *child = other_child;
+                        }
+                    } else {
+                        children.push(other_child);
+                    }
+                }
+            }
+            _ => panic!("`Node::overlay` should only be used on directories"),
+        }
+    }
+
+    /// Describes the tree as JSON, for tools such as editors which need to know the structure of the input.
+    ///
+    /// Each node has its `type`, `name` and `path` relative to this node. Files also have the `kind` of their
+    ///   parsed contents, and markdown files have their `frontmatter`. Unlike the build metadata, this describes
+    ///   the tree as it is, so is intended for the input rather than the output.
+    pub fn to_tree_json(&self) -> Value {
+        self.to_tree_json_recur(Path::new(""))
+    }
+
+    /// Recursively describes the node as JSON, where `path` is its path relative to the root of the tree.
+    fn to_tree_json_recur(&self, path: &Path) -> Value {
+        let path_string = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        match self {
+            Self::Directory { name, children, .. } => {
+                let children = children
+                    .iter()
+                    .map(|c| c.to_tree_json_recur(&path.join(c.name())))
+                    .collect();
+
+                json!({
+                    "type": "directory",
+                    "name": name,
+                    "path": path_string,
+                    "children": (Value::Array(children))
+                })
+            }
+            Self::File {
+                name,
+                parsed_contents,
+                ..
+            } => {
+                let mut json = json!({
+                    "type": "file",
+                    "name": name,
+                    "path": path_string,
+                    "kind": (parsed_contents.kind())
+                });
+
+                if let ParsedContents::Markdown(md) = parsed_contents {
+                    json["frontmatter"] = md.frontmatter_to_value();
+                }
+
+                json
+            }
+        }
+    }
+
     /// Recursively saves this node and its descendants to the filesystem.
-    fn save_recur(&self, path: impl AsRef<Path>, config: &Config) -> Result<(), Error> {
+    pub(crate) fn save_recur(
+        &self,
+        path: impl AsRef<Path>,
+        config: &Config,
+        hooks: &[Box<dyn StuartBuildHook>],
+    ) -> Result<(), Error> {
         let path = path.as_ref().to_path_buf();
 
         match self {
@@ -344,38 +858,37 @@ impl Node {
                 };
 
                 for child in children {
-                    child.save_recur(&dir, config)?;
+                    child.save_recur(&dir, config, hooks)?;
                 }
             }
             Self::File {
-                name,
                 contents,
                 parsed_contents,
                 ..
             } => {
-                if name != "root.html"
-                    && name != "md.html"
-                    && (config.save_data_files || !name.ends_with(".json"))
+                let name = &self.output_name(hooks);
+
+                if config.strip_extensions()
+                    && name.ends_with(".html")
+                    && name != "index.html"
+                    && !parsed_contents.is_ignored()
                 {
-                    if config.strip_extensions
-                        && name.ends_with(".html")
-                        && name != "index.html"
-                        && !parsed_contents.is_ignored()
-                    {
-                        let directory_name = name.strip_suffix(".html").unwrap().to_string();
-                        let dir = path.join(directory_name);
+                    let directory_name = name.strip_suffix(".html").unwrap().to_string();
+                    let dir = path.join(directory_name);
 
-                        match create_dir(&dir) {
-                            Ok(_) => (),
-                            Err(e) if e.kind() == ErrorKind::AlreadyExists => (),
-                            Err(_) => return Err(Error::Fs(FsError::Write)),
-                        };
+                    match create_dir(&dir) {
+                        Ok(_) => (),
+                        Err(e) if e.kind() == ErrorKind::AlreadyExists => (),
+                        Err(_) => return Err(Error::Fs(FsError::Write)),
+                    };
 
-                        write(dir.join("index.html"), contents)
-                            .map_err(|_| Error::Fs(FsError::Write))?;
-                    } else {
-                        write(path.join(name), contents).map_err(|_| Error::Fs(FsError::Write))?;
-                    }
+                    write(dir.join("index.html"), to_output(name, contents, config))
+                        .map_err(|_| Error::Fs(FsError::Write))?;
+                } else if parsed_contents.is_streamed() {
+                    copy(self.source(), path.join(name)).map_err(|_| Error::Fs(FsError::Write))?;
+                } else {
+                    write(path.join(name), to_output(name, contents, config))
+                        .map_err(|_| Error::Fs(FsError::Write))?;
                 }
             }
         }
@@ -384,6 +897,10 @@ impl Node {
     }
 
     /// Recursively exports this node's and its descendants' metadata to a JSON object.
+    ///
+    /// Every file has its `name` and a `type`, which is `file` unless its metadata gives another, so files without
+    ///   metadata, such as static files merged into the output, are represented in the same way as built files.
+    ///   Metadata which is not an object is kept under `value`.
     fn save_metadata_recur(&self, is_first: bool) -> Value {
         match self {
             Self::Directory { name, children, .. } => {
@@ -407,14 +924,19 @@ impl Node {
                 metadata: json,
                 ..
             } => {
-                let mut metadata = json!({ "name": name });
+                let mut metadata = json!({
+                    "name": name,
+                    "type": "file"
+                });
 
-                if let Some(json) = json {
-                    for (key, value) in json.as_object().unwrap() {
-                        metadata[key.as_str()] = value.clone();
+                match json {
+                    Some(Value::Object(fields)) => {
+                        for (key, value) in fields {
+                            metadata[key.as_str()] = value.clone();
+                        }
                     }
-                } else {
-                    metadata["type"] = json!("file");
+                    Some(value) => metadata["value"] = value.clone(),
+                    None => (),
                 }
 
                 metadata
@@ -423,6 +945,52 @@ impl Node {
     }
 }
 
+/// Creates an empty output directory at the given path, removing any previous output there.
+pub(crate) fn create_output_dir(path: &Path) -> Result<(), Error> {
+    if path.is_file() {
+        return Err(Error::Fs(FsError::OutputIsFile(path.to_path_buf())));
+    }
+
+    if path.exists() && path.is_dir() {
+        remove_dir_all(path).map_err(|_| Error::Fs(FsError::Write))?;
+    }
+
+    create_dir(path).map_err(|_| Error::Fs(FsError::Write))
+}
+
+/// Returns `true` if a file with the given name is parsed when it is loaded, either by Stuart or by a plugin.
+fn is_parsed(name: &str, plugins: Option<&dyn Manager>, config: &Config) -> bool {
+    let extension = match Path::new(name).extension() {
+        Some(extension) => extension.to_string_lossy(),
+        None => return config.template_files.iter().any(|file| file == name),
+    };
+
+    // JSON lines files are not parsed when loaded, but are read by `for` loops, so they must not be streamed.
+    matches!(extension.as_ref(), "html" | "md" | "json" | "jsonl")
+        || config
+            .text_extensions
+            .iter()
+            .any(|e| e == extension.as_ref())
+        || plugins.is_some_and(|plugins| {
+            plugins.plugins().iter().any(|plugin| {
+                plugin
+                    .parsers
+                    .iter()
+                    .any(|parser| parser.extensions().contains(&extension.as_ref()))
+            })
+        })
+}
+
+/// Returns `true` if a file with the given name is saved to the output directory with the given configuration.
+///
+/// Templates are never saved, and JSON files are only saved if `save_data_files` is enabled.
+fn is_saved(name: &str, config: &Config) -> bool {
+    name != config.root_template
+        && name != config.md_template
+        && config.markdown_index.as_deref() != Some(name)
+        && (config.save_data_files() || !name.ends_with(".json"))
+}
+
 impl Debug for Node {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -453,3 +1021,60 @@ impl Debug for Node {
         }
     }
 }
+
+/// Converts the contents of a file into the form in which it is saved, minifying HTML and converting line
+///   endings as configured.
+fn to_output<'a>(name: &str, contents: &'a [u8], config: &Config) -> Cow<'a, [u8]> {
+    let contents = minify(name, contents, config);
+
+    let is_text = name.ends_with(".html")
+        || config.template_files.iter().any(|t| t == name)
+        || Path::new(name).extension().is_some_and(|e| {
+            config
+                .text_extensions
+                .iter()
+                .any(|t| t == e.to_string_lossy().as_ref())
+        });
+
+    match config.line_endings {
+        Some(line_endings) if is_text => Cow::Owned(convert_line_endings(&contents, line_endings)),
+        _ => contents,
+    }
+}
+
+/// Converts every line ending in the contents, whether `\n` or `\r\n`, to the given line ending.
+///
+/// Lone carriage returns are left as they are.
+fn convert_line_endings(contents: &[u8], line_endings: LineEndings) -> Vec<u8> {
+    let mut result = Vec::with_capacity(contents.len());
+
+    for (i, &byte) in contents.iter().enumerate() {
+        match byte {
+            b'\r' if contents.get(i + 1) == Some(&b'\n') => (),
+            b'\n' if line_endings == LineEndings::Crlf => result.extend_from_slice(b"\r\n"),
+            byte => result.push(byte),
+        }
+    }
+
+    result
+}
+
+/// Minifies the contents of an HTML file if HTML minification is enabled in the configuration.
+#[cfg(feature = "minify")]
+fn minify<'a>(name: &str, contents: &'a [u8], config: &Config) -> Cow<'a, [u8]> {
+    if config.minify_html() && name.ends_with(".html") {
+        let mut cfg = minify_html::Cfg::new();
+        cfg.keep_comments = config.minify_html_keep_comments();
+        cfg.keep_closing_tags = config.minify_html_keep_closing_tags();
+
+        Cow::Owned(minify_html::minify(contents, &cfg))
+    } else {
+        Cow::Borrowed(contents)
+    }
+}
+
+/// Returns the contents unchanged, since HTML minification requires the `minify` feature.
+#[cfg(not(feature = "minify"))]
+fn minify<'a>(_: &str, contents: &'a [u8], _: &Config) -> Cow<'a, [u8]> {
+    Cow::Borrowed(contents)
+}
@@ -0,0 +1,80 @@
+//! Provides watching of a directory for changes, allowing library users to rebuild when the input changes.
+
+use super::Node;
+use crate::error::{Error, FsError};
+
+use notify::{raw_watcher, RawEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+
+/// An iterator over the paths of changed files in a watched directory, relative to the directory.
+///
+/// The directory is watched for as long as this exists. Iterating blocks until the next change, and ends if
+///   the watcher stops.
+pub struct Changes {
+    /// The underlying watcher, which stops watching when dropped.
+    _watcher: RecommendedWatcher,
+    /// The receiver of raw filesystem events.
+    rx: Receiver<RawEvent>,
+    /// The canonical path of the watched directory.
+    root: PathBuf,
+}
+
+impl Node {
+    /// Recursively watches the given directory, returning an iterator over the paths of changed files relative
+    ///   to it.
+    pub fn watch(path: impl AsRef<Path>) -> Result<Changes, Error> {
+        let root = path.as_ref().canonicalize().map_err(|_| {
+            Error::Fs(FsError::NotFound(
+                path.as_ref().to_string_lossy().to_string(),
+            ))
+        })?;
+
+        let (tx, rx) = channel();
+        let mut watcher = raw_watcher(tx).map_err(|_| Error::Fs(FsError::Read))?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|_| Error::Fs(FsError::Read))?;
+
+        Ok(Changes {
+            _watcher: watcher,
+            rx,
+            root,
+        })
+    }
+}
+
+impl Changes {
+    /// Returns the next change if one has already happened, without blocking.
+    pub fn try_next(&mut self) -> Option<PathBuf> {
+        while let Ok(event) = self.rx.try_recv() {
+            if let Some(path) = self.relative(event) {
+                return Some(path);
+            }
+        }
+
+        None
+    }
+
+    /// Returns the path of the event relative to the watched directory, if it has one.
+    fn relative(&self, event: RawEvent) -> Option<PathBuf> {
+        event
+            .path
+            .and_then(|path| path.strip_prefix(&self.root).ok().map(|p| p.to_path_buf()))
+    }
+}
+
+impl Iterator for Changes {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let event = self.rx.recv().ok()?;
+
+            if let Some(path) = self.relative(event) {
+                return Some(path);
+            }
+        }
+    }
+}
@@ -3,31 +3,63 @@
 /// Contains all the built-in function parsers.
 #[allow(clippy::missing_docs_in_private_items)]
 pub mod parsers {
+    mod add;
+    mod adjacent;
     mod begin;
+    mod capture;
+    mod data_uri;
     mod dateformat;
+    mod debug;
     mod r#else;
     mod end;
     mod excerpt;
     mod r#for;
+    mod frontmatter;
+    mod get;
     mod ifdefined;
+    mod ifenv;
+    mod ifsection;
+    mod iftrue;
     mod import;
     mod insert;
+    mod relative;
+    mod set;
+    mod snippet;
+    mod text;
     mod timetoread;
+    mod wordwrap;
 
+    pub use add::AddParser as Add;
+    pub use adjacent::AdjacentParser as Adjacent;
     pub use begin::BeginParser as Begin;
+    pub use capture::CaptureParser as Capture;
+    pub use data_uri::DataUriParser as DataUri;
     pub use dateformat::DateFormatParser as DateFormat;
+    pub use debug::DebugParser as Debug;
     pub use end::EndParser as End;
     pub use excerpt::ExcerptParser as Excerpt;
+    pub use frontmatter::FrontmatterParser as Frontmatter;
+    pub use get::GetParser as Get;
     pub use ifdefined::IfDefinedParser as IfDefined;
+    pub use ifenv::IfEnvParser as IfEnv;
+    pub use ifsection::IfSectionParser as IfSection;
+    pub use iftrue::{IfFalseParser as IfFalse, IfTrueParser as IfTrue};
     pub use import::ImportParser as Import;
     pub use insert::InsertParser as Insert;
     pub use r#else::ElseParser as Else;
     pub use r#for::ForParser as For;
+    pub use relative::RelativeParser as Relative;
+    pub use set::SetParser as Set;
+    pub use snippet::SnippetParser as Snippet;
+    pub use text::TextParser as Text;
     pub use timetoread::TimeToReadParser as TimeToRead;
+    pub use wordwrap::WordWrapParser as WordWrap;
 
     #[macro_use]
     mod r#if;
 
+    pub use r#if::IfParser as If;
+
     if_parsers![
         ifeq, IfEq, ==;
         ifne, IfNe, !=;
@@ -36,6 +68,15 @@ pub mod parsers {
         iflt, IfLt, <;
         ifle, IfLe, <;
     ];
+
+    #[macro_use]
+    mod aggregate;
+
+    aggregate_parsers![
+        sum, Sum, |numbers| Some(numbers.iter().sum());
+        min, Min, |numbers| numbers.iter().copied().reduce(f64::min);
+        max, Max, |numbers| numbers.iter().copied().reduce(f64::max);
+    ];
 }
 
 use crate::error::ProcessError;
@@ -45,7 +86,7 @@ use crate::TracebackError;
 
 use humphrey_json::Value;
 
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
 
 /// Represents a function parser.
 ///
@@ -62,12 +103,110 @@ pub trait FunctionParser {
     /// Attempts to parse the raw function into an executable function object.
     fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError>;
 
+    /// Returns the arguments which the function accepts, if the parser declares them.
+    ///
+    /// If a signature is returned, the arguments of each call are checked against it before [`FunctionParser::parse`]
+    ///   is called, so the parser does not need to check them itself and mistakes produce precise errors.
+    fn signature(&self) -> Option<Signature> {
+        None
+    }
+
     /// Returns `true` if the raw function can be parsed by this function parser.
     ///
     /// Not used for plugin functions!
     fn can_parse(&self, raw: &RawFunction) -> bool {
         raw.name == self.name()
     }
+
+    /// Returns `true` if the function is a conditional, which can have an `else` and be labelled.
+    ///
+    /// This **must** return the same value as the `is_conditional` method of the returned function.
+    fn is_conditional(&self) -> bool {
+        false
+    }
+}
+
+/// Returns `true` if the built-in function with the given name is a conditional.
+pub(crate) fn is_conditional(name: &str) -> bool {
+    crate::FUNCTION_PARSERS
+        .iter()
+        .any(|parser| parser.name() == name && parser.is_conditional())
+}
+
+/// Describes the positional and named arguments which a function accepts.
+///
+/// Only the number of positional arguments and the names of named arguments are described, since the types of
+///   arguments which functions accept are often too flexible to be usefully declared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    /// The minimum number of positional arguments.
+    pub min_args: usize,
+    /// The maximum number of positional arguments, or `None` if there is no maximum.
+    pub max_args: Option<usize>,
+    /// The names of the accepted named arguments, each of which may be given at most once.
+    pub named_args: &'static [&'static str],
+}
+
+impl Signature {
+    /// Creates a signature which accepts exactly `n` positional arguments and no named arguments.
+    pub const fn exactly(n: usize) -> Self {
+        Self::between(n, n)
+    }
+
+    /// Creates a signature which accepts at least `n` positional arguments and no named arguments.
+    pub const fn at_least(n: usize) -> Self {
+        Self {
+            min_args: n,
+            max_args: None,
+            named_args: &[],
+        }
+    }
+
+    /// Creates a signature which accepts between `min` and `max` positional arguments, inclusive, and no named
+    ///   arguments.
+    pub const fn between(min: usize, max: usize) -> Self {
+        Self {
+            min_args: min,
+            max_args: Some(max),
+            named_args: &[],
+        }
+    }
+
+    /// Sets the names of the named arguments which the signature accepts.
+    pub const fn with_named_args(mut self, named_args: &'static [&'static str]) -> Self {
+        self.named_args = named_args;
+        self
+    }
+
+    /// Checks the arguments of the raw function against the signature.
+    pub fn check(&self, raw: &RawFunction) -> Result<(), ParseError> {
+        let found = raw.positional_args.len();
+
+        if found < self.min_args || self.max_args.is_some_and(|max| found > max) {
+            return Err(ParseError::WrongArgumentCount {
+                function: raw.name.clone(),
+                min: self.min_args,
+                max: self.max_args,
+                found,
+            });
+        }
+
+        for (i, (name, _)) in raw.named_args.iter().enumerate() {
+            if !self.named_args.contains(&name.as_str()) {
+                return Err(ParseError::UnknownNamedArgument {
+                    function: raw.name.clone(),
+                    name: name.clone(),
+                    expected: self.named_args.iter().map(|s| s.to_string()).collect(),
+                });
+            }
+
+            if raw.named_args[..i].iter().any(|(other, _)| other == name) {
+                return Err(ParseError::DuplicateNamedArgument(name.clone()));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 /// Represents an executable function.
@@ -80,6 +219,47 @@ pub trait Function: Debug {
 
     /// Executes the function in the given scope.
     fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>>;
+
+    /// Executes the function in the given scope, returning the value it produces instead of outputting it.
+    ///
+    /// This is used by `set` to store the result of a function in a variable, so functions which compute a value,
+    ///   such as `add`, should override it. By default, the function is executed as normal and returns no value.
+    fn evaluate(&self, scope: &mut Scope) -> Result<Option<Value>, TracebackError<ProcessError>> {
+        self.execute(scope).map(|_| None)
+    }
+
+    /// Returns the name of the block which this function ends, such as `for` for `end(for)`.
+    ///
+    /// This allows functions which collect tokens without executing them, such as snippet definitions, to find
+    ///   the end of their block. Only `end` should need to override this.
+    fn ends_block(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the label of the conditional which this function begins, continues or ends, such as `outer` for
+    ///   `else(label="outer")`.
+    ///
+    /// Conditionals use this to check that labelled `else` and `end` functions belong to them, so only the
+    ///   conditional functions, `else` and `end` should need to override this.
+    fn label(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns `true` if the function is a conditional, which can have an `else` and be labelled.
+    ///
+    /// Conditionals use this to skip the `else` functions of the conditionals nested within them, so only the
+    ///   conditional functions should need to override this.
+    fn is_conditional(&self) -> bool {
+        false
+    }
+
+    /// Returns the path of the input file which this function reads, relative to the input directory.
+    ///
+    /// This is used to check that referenced files exist before building and to process files after the files
+    ///   they reference, so should only be overridden by functions which always read the same file.
+    fn referenced_file(&self) -> Option<&str> {
+        None
+    }
 }
 
 /// Represents an input into a function.
@@ -131,12 +311,12 @@ impl PartialOrd for Input {
     }
 }
 
-impl ToString for Input {
-    fn to_string(&self) -> String {
+impl Display for Input {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Input::Variable(v) => v.clone(),
-            Input::String(s) => s.clone(),
-            Input::Integer(i) => i.to_string(),
+            Input::Variable(v) => write!(f, "{}", v),
+            Input::String(s) => write!(f, "{}", s),
+            Input::Integer(i) => write!(f, "{}", i),
         }
     }
 }
@@ -180,3 +360,15 @@ macro_rules! quiet_assert {
 pub fn is_ident(s: &str) -> bool {
     crate::FUNCTION_PARSERS.iter().any(|f| f.name() == s)
 }
+
+/// Returns true if the string is the name of a function qualified with the name of its plugin, such as
+///   `my_plugin::my_function`.
+///
+/// Plugins aren't known when arguments are parsed, so this only checks the form of the name.
+pub fn is_plugin_ident(s: &str) -> bool {
+    let is_name =
+        |name: &str| !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+
+    s.split_once("::")
+        .is_some_and(|(plugin, function)| is_name(plugin) && is_name(function))
+}
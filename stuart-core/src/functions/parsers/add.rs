@@ -0,0 +1,86 @@
+use crate::functions::{Function, FunctionParser, Signature};
+use crate::parse::{ParseError, RawArgument, RawFunction};
+use crate::process::{ProcessError, Scope};
+use crate::{quiet_assert, TracebackError};
+
+use humphrey_json::Value;
+
+/// Parses the `add` function.
+pub struct AddParser;
+
+#[derive(Debug, Clone)]
+pub struct AddFunction {
+    inputs: Vec<RawArgument>,
+}
+
+impl FunctionParser for AddParser {
+    fn name(&self) -> &'static str {
+        "add"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::at_least(2))
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        quiet_assert!(raw
+            .positional_args
+            .iter()
+            .all(|arg| matches!(arg, RawArgument::Variable(_) | RawArgument::Integer(_))))?;
+
+        Ok(Box::new(AddFunction {
+            inputs: raw.positional_args,
+        }))
+    }
+}
+
+impl Function for AddFunction {
+    fn name(&self) -> &'static str {
+        "add"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+        let result = self.add(scope)?;
+
+        scope
+            .output(result.to_string())
+            .map_err(|e| self_token.traceback(e))?;
+
+        Ok(())
+    }
+
+    fn evaluate(&self, scope: &mut Scope) -> Result<Option<Value>, TracebackError<ProcessError>> {
+        self.add(scope).map(|result| Some(Value::Number(result)))
+    }
+}
+
+impl AddFunction {
+    /// Adds the inputs together, looking up any variables in the scope.
+    fn add(&self, scope: &Scope) -> Result<f64, TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+        let mut result = 0.0;
+
+        for input in &self.inputs {
+            result += match input {
+                RawArgument::Integer(i) => *i as f64,
+                RawArgument::Variable(variable_name) => scope
+                    .get_variable(variable_name)
+                    .ok_or_else(|| {
+                        self_token.traceback(ProcessError::UndefinedVariable(variable_name.clone()))
+                    })?
+                    .as_number()
+                    .ok_or_else(|| {
+                        self_token.traceback(ProcessError::InvalidDataType {
+                            variable: variable_name.clone(),
+                            expected: "number".to_string(),
+                            found: String::new(),
+                        })
+                    })?,
+                _ => unreachable!(),
+            };
+        }
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,141 @@
+use super::r#for::{
+    collect_source, comparable_string, parse_source, ForFunctionSourceType, Sort, SortOrder,
+};
+
+use crate::functions::{Function, FunctionParser, Signature};
+use crate::parse::{ParseError, RawArgument, RawFunction};
+use crate::process::stack::StackFrame;
+use crate::process::{ProcessError, Scope};
+use crate::TracebackError;
+
+use humphrey_json::Value;
+
+/// Parses the `adjacent` function.
+pub struct AdjacentParser;
+
+#[derive(Debug, Clone)]
+pub struct AdjacentFunction {
+    source: String,
+    source_type: ForFunctionSourceType,
+    key: String,
+    current: AdjacentCurrent,
+    sort: Sort,
+}
+
+/// The identifier of the current item, which is either given literally or read from a variable.
+#[derive(Debug, Clone)]
+enum AdjacentCurrent {
+    Literal(String),
+    Variable(String),
+}
+
+impl FunctionParser for AdjacentParser {
+    fn name(&self) -> &'static str {
+        "adjacent"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(3).with_named_args(&["sortby", "sortas", "order"]))
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        let (source, source_type) = parse_source(&raw.positional_args[0])?;
+
+        let key = raw.positional_args[1]
+            .as_variable()
+            .ok_or(ParseError::InvalidArgument)?;
+
+        let current = match &raw.positional_args[2] {
+            RawArgument::Variable(variable) => AdjacentCurrent::Variable(variable.to_string()),
+            RawArgument::String(string) => AdjacentCurrent::Literal(string.to_string()),
+            RawArgument::Integer(int) => AdjacentCurrent::Literal(int.to_string()),
+            _ => return Err(ParseError::InvalidArgument),
+        };
+
+        let mut sort = Sort::new();
+
+        for (name, arg) in &raw.named_args {
+            if !sort.parse_arg(name, arg)? {
+                return Err(ParseError::InvalidArgument);
+            }
+        }
+
+        sort.validate()?;
+
+        Ok(Box::new(AdjacentFunction {
+            source,
+            source_type,
+            key: key.to_string(),
+            current,
+            sort,
+        }))
+    }
+}
+
+impl Function for AdjacentFunction {
+    fn name(&self) -> &'static str {
+        "adjacent"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+        let body = scope
+            .tokens
+            .collect_until_end(self.name())
+            .map_err(|e| self_token.traceback(e))?;
+
+        let current = match &self.current {
+            AdjacentCurrent::Literal(current) => current.clone(),
+            AdjacentCurrent::Variable(variable_name) => {
+                let variable = scope.get_variable(variable_name).ok_or_else(|| {
+                    scope.error(ProcessError::UndefinedVariable(variable_name.clone()))
+                })?;
+
+                comparable_string(&variable).ok_or_else(|| {
+                    scope.error(ProcessError::InvalidDataType {
+                        variable: variable_name.clone(),
+                        expected: "string, number or boolean".to_string(),
+                        found: String::new(),
+                    })
+                })?
+            }
+        };
+
+        let mut variables = collect_source(&self.source, self.source_type, scope, &self_token)?;
+
+        self.sort.sort(&mut variables).map_err(|e| scope.error(e))?;
+
+        if let SortOrder::Desc = self.sort.order {
+            variables.reverse();
+        }
+
+        let indexes = self.key.split('.').skip(1).collect::<Vec<_>>();
+
+        let index = variables
+            .iter()
+            .position(|v| {
+                comparable_string(&crate::process::stack::get_value(&indexes, v))
+                    .is_some_and(|s| s == current)
+            })
+            .ok_or_else(|| {
+                scope.error(ProcessError::NotFound(format!(
+                    "{} = \"{}\" in {}",
+                    self.key, current, self.source
+                )))
+            })?;
+
+        let prev = index
+            .checked_sub(1)
+            .map_or(Value::Null, |i| variables[i].clone());
+        let next = variables.get(index + 1).cloned().unwrap_or(Value::Null);
+
+        let frame = {
+            let mut frame = StackFrame::new("adjacent");
+            frame.add_variable("prev", prev);
+            frame.add_variable("next", next);
+            frame
+        };
+
+        scope.process_block(&body, frame, &self_token)
+    }
+}
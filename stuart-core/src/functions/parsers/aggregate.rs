@@ -0,0 +1,81 @@
+macro_rules! aggregate_parsers {
+    ($($name:ident, $ty:ident, $aggregate:expr;)*) => {
+        $(
+            mod $name {
+                #[doc = concat!("Parses the `", stringify!($name), "` function.")]
+                pub struct Parser;
+
+                #[derive(Debug, Clone)]
+                pub struct Function {
+                    variable_name: String,
+                }
+
+                impl $crate::functions::FunctionParser for Parser {
+                    fn name(&self) -> &'static str {
+                        stringify!($name)
+                    }
+
+                    fn signature(&self) -> Option<$crate::functions::Signature> {
+                        Some($crate::functions::Signature::exactly(1))
+                    }
+
+                    fn parse(&self, raw: $crate::parse::RawFunction) -> Result<Box<dyn $crate::functions::Function>, $crate::parse::ParseError> {
+                        let variable_name = raw.positional_args[0]
+                            .as_variable()
+                            .ok_or($crate::parse::ParseError::InvalidArgument)?;
+
+                        Ok(Box::new(Function {
+                            variable_name: variable_name.to_string(),
+                        }))
+                    }
+                }
+
+                impl $crate::functions::Function for Function {
+                    fn name(&self) -> &'static str {
+                        stringify!($name)
+                    }
+
+                    fn execute(&self, scope: &mut $crate::process::Scope) -> Result<(), $crate::TracebackError<$crate::process::ProcessError>> {
+                        let self_token = scope.tokens.current().unwrap().clone();
+
+                        let variable = scope.get_variable(&self.variable_name).ok_or_else(|| {
+                            self_token.traceback($crate::process::ProcessError::UndefinedVariable(self.variable_name.clone()))
+                        })?;
+
+                        let numbers = variable
+                            .as_array()
+                            .ok_or_else(|| self_token.traceback($crate::process::ProcessError::NotJsonArray))?
+                            .iter()
+                            .map(|value| value.as_number())
+                            .collect::<Option<Vec<f64>>>()
+                            .ok_or_else(|| {
+                                self_token.traceback($crate::process::ProcessError::InvalidDataType {
+                                    variable: self.variable_name.clone(),
+                                    expected: "array of numbers".to_string(),
+                                    found: String::new(),
+                                })
+                            })?;
+
+                        let aggregate: fn(&[f64]) -> Option<f64> = $aggregate;
+
+                        let result = aggregate(&numbers).ok_or_else(|| {
+                            self_token.traceback($crate::process::ProcessError::InvalidDataType {
+                                variable: self.variable_name.clone(),
+                                expected: "non-empty array".to_string(),
+                                found: "empty array".to_string(),
+                            })
+                        })?;
+
+                        scope
+                            .output(result.to_string())
+                            .map_err(|e| self_token.traceback(e))?;
+
+                        Ok(())
+                    }
+                }
+            }
+
+            pub use $name::Parser as $ty;
+        )*
+    };
+}
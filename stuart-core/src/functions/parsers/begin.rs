@@ -20,6 +20,10 @@ impl FunctionParser for BeginParser {
     }
 
     fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        if raw.positional_args.first() == Some(&RawArgument::Ident("snippet".to_string())) {
+            return super::snippet::parse_definition(raw);
+        }
+
         quiet_assert!(raw.positional_args.len() == 1)?;
         quiet_assert!(raw.named_args.is_empty())?;
 
@@ -0,0 +1,65 @@
+use crate::functions::{Function, FunctionParser, Signature};
+use crate::parse::{ParseError, RawFunction};
+use crate::process::stack::StackFrame;
+use crate::process::{ProcessError, Scope};
+use crate::{quiet_assert, TracebackError};
+
+use humphrey_json::Value;
+
+/// Parses the `capture` function.
+pub struct CaptureParser;
+
+#[derive(Debug, Clone)]
+pub struct CaptureFunction {
+    variable_name: String,
+}
+
+impl FunctionParser for CaptureParser {
+    fn name(&self) -> &'static str {
+        "capture"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(1))
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        let variable_name = raw.positional_args[0]
+            .as_variable()
+            .ok_or(ParseError::InvalidArgument)?;
+
+        quiet_assert!(!variable_name.contains('.'))?;
+
+        Ok(Box::new(CaptureFunction {
+            variable_name: variable_name.to_string(),
+        }))
+    }
+}
+
+impl Function for CaptureFunction {
+    fn name(&self) -> &'static str {
+        "capture"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+        let body = scope
+            .tokens
+            .collect_until_end(self.name())
+            .map_err(|e| self_token.traceback(e))?;
+
+        // Unlike `set`, the output is always stored as it was rendered, even if the block returns a value.
+        let frame = StackFrame::new(format!("capture:{}", self.variable_name));
+        let output = scope.render_block(&body, frame, &self_token)?;
+        let output = String::from_utf8(output)
+            .map_err(|_| self_token.traceback(ProcessError::StackError))?;
+
+        scope
+            .stack
+            .last_mut()
+            .ok_or_else(|| self_token.traceback(ProcessError::StackError))?
+            .set_variable(&self.variable_name, Value::String(output));
+
+        Ok(())
+    }
+}
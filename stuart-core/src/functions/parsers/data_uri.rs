@@ -0,0 +1,116 @@
+use crate::functions::{Function, FunctionParser, Signature};
+use crate::parse::{ParseError, RawFunction};
+use crate::process::{ProcessError, Scope};
+use crate::TracebackError;
+
+/// The characters used for base64 encoding.
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Parses the `data_uri` function.
+pub struct DataUriParser;
+
+#[derive(Debug, Clone)]
+pub struct DataUriFunction {
+    file_name: String,
+}
+
+impl FunctionParser for DataUriParser {
+    fn name(&self) -> &'static str {
+        "data_uri"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(1))
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        let file_name = raw.positional_args[0]
+            .as_string()
+            .ok_or(ParseError::InvalidArgument)?
+            .to_string();
+
+        Ok(Box::new(DataUriFunction { file_name }))
+    }
+}
+
+impl Function for DataUriFunction {
+    fn name(&self) -> &'static str {
+        "data_uri"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+
+        let file = scope
+            .get_file(&self.file_name)
+            .map_err(|e| self_token.traceback(e))?;
+
+        let contents = file.contents().unwrap();
+
+        // Streamed files are never read into memory, and are always larger than the stream threshold.
+        if file.parsed_contents().is_streamed()
+            || contents.len() > scope.processor.config.data_uri_max_size
+        {
+            return Err(self_token.traceback(ProcessError::FileTooLarge(self.file_name.clone())));
+        }
+
+        let uri = format!(
+            "data:{};base64,{}",
+            mime_type(&self.file_name),
+            base64_encode(contents)
+        );
+
+        scope.output(uri).map_err(|e| self_token.traceback(e))?;
+
+        Ok(())
+    }
+}
+
+/// Returns the MIME type of a file based on its extension.
+fn mime_type(file_name: &str) -> &'static str {
+    let extension = file_name
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Encodes the given bytes as padded base64.
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(BASE64_ALPHABET[(n >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+
+    result
+}
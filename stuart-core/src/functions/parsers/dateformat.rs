@@ -1,7 +1,7 @@
-use crate::functions::{Function, FunctionParser};
+use crate::functions::{Function, FunctionParser, Signature};
 use crate::parse::{ParseError, RawFunction};
 use crate::process::{ProcessError, Scope};
-use crate::{quiet_assert, TracebackError};
+use crate::TracebackError;
 
 /// Parses the `dateformat` function.
 pub struct DateFormatParser;
@@ -18,10 +18,11 @@ impl FunctionParser for DateFormatParser {
         "dateformat"
     }
 
-    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
-        quiet_assert!(raw.positional_args.len() == 2)?;
-        quiet_assert!(raw.named_args.is_empty())?;
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(2))
+    }
 
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
         let variable_name = raw.positional_args[0]
             .as_variable()
             .ok_or(ParseError::InvalidArgument)?
@@ -46,41 +47,58 @@ impl Function for DateFormatFunction {
 
     #[cfg(feature = "date")]
     fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
-        use chrono::{NaiveTime, Utc};
-        use dateparser::parse_with;
-
-        let self_token = scope.tokens.current().unwrap().clone();
-
         let variable = scope.get_variable(&self.variable_name).ok_or_else(|| {
-            self_token.traceback(ProcessError::UndefinedVariable(self.variable_name.clone()))
+            scope.error(ProcessError::UndefinedVariable(self.variable_name.clone()))
         })?;
 
         let string = variable.as_str().ok_or_else(|| {
-            self_token.traceback(ProcessError::InvalidDataType {
+            scope.error(ProcessError::InvalidDataType {
                 variable: self.variable_name.clone(),
                 expected: "string".to_string(),
                 found: String::new(),
             })
         })?;
 
-        let date = std::panic::catch_unwind(|| {
-            parse_with(string, &Utc, NaiveTime::from_hms_opt(0, 0, 0).unwrap())
-                .ok()
-                .map(|d| d.format(&self.format).to_string())
-                .ok_or(ProcessError::InvalidDate)
-        })
-        .map_err(|_| self_token.traceback(ProcessError::InvalidDate))?
-        .map_err(|_| self_token.traceback(ProcessError::InvalidDate))?;
+        let date = parse_date(string)
+            .map(|d| d.format(&self.format).to_string())
+            .ok_or_else(|| scope.error(ProcessError::InvalidDate))?;
 
-        scope.output(date).map_err(|e| self_token.traceback(e))?;
+        scope.output(date).map_err(|e| scope.error(e))?;
 
         Ok(())
     }
 
     #[cfg(not(feature = "date"))]
     fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
-        let self_token = scope.tokens.current().unwrap();
-
-        Err(self_token.traceback(ProcessError::FeatureNotEnabled("date".to_string())))
+        Err(scope.error(ProcessError::FeatureNotEnabled("date".to_string())))
     }
 }
+
+/// Parses a date in any format supported by `dateparser`, such as `2024-01-12` or `12 Jan 2024`, returning `None`
+///   if it cannot be parsed.
+///
+/// Dates without a time are taken to be at midnight UTC. `dateparser` can panic on some malformed input, so this
+///   is caught and treated as an unparseable date.
+#[cfg(feature = "date")]
+pub(super) fn parse_date(string: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    use chrono::{NaiveTime, Utc};
+    use dateparser::parse_with;
+
+    std::panic::catch_unwind(|| {
+        parse_with(string, &Utc, NaiveTime::from_hms_opt(0, 0, 0).unwrap()).ok()
+    })
+    .ok()
+    .flatten()
+}
+
+/// Parses a date like [`parse_date`], returning its Unix timestamp.
+#[cfg(feature = "date")]
+pub(super) fn parse_timestamp(string: &str) -> Option<i64> {
+    parse_date(string).map(|d| d.timestamp())
+}
+
+/// Dates cannot be parsed without the `date` feature, so callers must check for it first.
+#[cfg(not(feature = "date"))]
+pub(super) fn parse_timestamp(_: &str) -> Option<i64> {
+    None
+}
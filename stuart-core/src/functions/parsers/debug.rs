@@ -0,0 +1,105 @@
+use crate::functions::{Function, FunctionParser, Signature};
+use crate::parse::{ParseError, RawFunction};
+use crate::process::{ProcessError, Scope};
+use crate::TracebackError;
+
+use humphrey_json::Value;
+
+/// Parses the `debug` function.
+pub struct DebugParser;
+
+#[derive(Debug, Clone)]
+pub struct DebugFunction;
+
+impl FunctionParser for DebugParser {
+    fn name(&self) -> &'static str {
+        "debug"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(0))
+    }
+
+    fn parse(&self, _raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        Ok(Box::new(DebugFunction))
+    }
+}
+
+impl Function for DebugFunction {
+    fn name(&self) -> &'static str {
+        "debug"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+
+        // Debug output could leak sensitive variables, so it is only emitted during development.
+        match scope.get_variable("env.STUART_ENV") {
+            Some(Value::String(env)) if env == "development" => (),
+            env if scope.processor.config.strict() => {
+                return Err(self_token.traceback(ProcessError::FeatureDisabledInMode {
+                    feature: "debug".to_string(),
+                    mode: env
+                        .and_then(|env| env.as_str().map(|env| env.to_string()))
+                        .unwrap_or_default(),
+                }))
+            }
+            _ => return Ok(()),
+        }
+
+        let mut variables = scope
+            .all_variables()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect::<Vec<_>>();
+
+        variables.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut json = String::new();
+        pretty_print(&Value::Object(variables), 0, &mut json);
+
+        // `--` is escaped so that string values cannot terminate the comment early.
+        let json = json.replace("--", "-\\u002d");
+
+        scope
+            .output(format!("<!-- stuart debug: {} -->", json))
+            .map_err(|e| self_token.traceback(e))?;
+
+        Ok(())
+    }
+}
+
+/// Serializes the value as indented JSON, appending it to the output.
+fn pretty_print(value: &Value, indent: usize, output: &mut String) {
+    let padding = "  ".repeat(indent + 1);
+
+    match value {
+        Value::Array(array) if !array.is_empty() => {
+            output.push_str("[\n");
+
+            for (i, item) in array.iter().enumerate() {
+                output.push_str(&padding);
+                pretty_print(item, indent + 1, output);
+                output.push_str(if i + 1 < array.len() { ",\n" } else { "\n" });
+            }
+
+            output.push_str(&"  ".repeat(indent));
+            output.push(']');
+        }
+        Value::Object(object) if !object.is_empty() => {
+            output.push_str("{\n");
+
+            for (i, (key, item)) in object.iter().enumerate() {
+                output.push_str(&padding);
+                output.push_str(&Value::String(key.clone()).serialize());
+                output.push_str(": ");
+                pretty_print(item, indent + 1, output);
+                output.push_str(if i + 1 < object.len() { ",\n" } else { "\n" });
+            }
+
+            output.push_str(&"  ".repeat(indent));
+            output.push('}');
+        }
+        value => output.push_str(&value.serialize()),
+    }
+}
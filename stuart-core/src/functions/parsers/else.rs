@@ -1,24 +1,30 @@
-use crate::functions::{Function, FunctionParser};
+use crate::functions::parsers::r#if::{frame_label, parse_conditional_args};
+use crate::functions::{is_conditional, Function, FunctionParser, Signature};
 use crate::parse::{ParseError, RawFunction};
 use crate::process::{ProcessError, Scope};
-use crate::{quiet_assert, TracebackError};
+use crate::TracebackError;
 
 /// Parses the `else` function.
 pub struct ElseParser;
 
 #[derive(Debug, Clone)]
-pub struct ElseFunction;
+pub struct ElseFunction {
+    label: Option<String>,
+}
 
 impl FunctionParser for ElseParser {
     fn name(&self) -> &'static str {
         "else"
     }
 
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(0).with_named_args(&["label"]))
+    }
+
     fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
-        quiet_assert!(raw.positional_args.is_empty())?;
-        quiet_assert!(raw.named_args.is_empty())?;
+        let (_, label) = parse_conditional_args(&raw.named_args)?;
 
-        Ok(Box::new(ElseFunction))
+        Ok(Box::new(ElseFunction { label }))
     }
 }
 
@@ -36,10 +42,26 @@ impl Function for ElseFunction {
             .ok_or_else(|| self_token.traceback(ProcessError::ElseWithoutIf))?
             .name;
 
-        if !name.starts_with("if") {
+        // The name of the frame of a conditional starts with the name of its function, as given by `frame_name`.
+        if !name.split([':', '#']).next().is_some_and(is_conditional) {
             return Err(self_token.traceback(ProcessError::ElseWithoutIf));
         }
 
+        if let Some(label) = &self.label {
+            let expected = frame_label(name);
+
+            if expected != Some(label.as_str()) {
+                return Err(self_token.traceback(ProcessError::MismatchedLabel {
+                    label: label.clone(),
+                    expected: expected.map(|label| label.to_string()),
+                }));
+            }
+        }
+
         Ok(())
     }
+
+    fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
 }
@@ -1,4 +1,5 @@
-use crate::functions::{Function, FunctionParser};
+use crate::functions::parsers::r#if::parse_conditional_args;
+use crate::functions::{is_conditional, Function, FunctionParser, Signature};
 use crate::parse::{ParseError, RawArgument, RawFunction};
 use crate::process::{ProcessError, Scope};
 use crate::{quiet_assert, TracebackError};
@@ -10,6 +11,7 @@ pub struct EndParser;
 pub struct EndFunction {
     label: String,
     custom: bool,
+    block_label: Option<String>,
 }
 
 impl FunctionParser for EndParser {
@@ -17,19 +19,38 @@ impl FunctionParser for EndParser {
         "end"
     }
 
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(1).with_named_args(&["label"]))
+    }
+
     fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
-        quiet_assert!(raw.positional_args.len() == 1)?;
-        quiet_assert!(raw.named_args.is_empty())?;
+        let (_, block_label) = parse_conditional_args(&raw.named_args)?;
 
         match &raw.positional_args[0] {
-            RawArgument::String(label) => Ok(Box::new(EndFunction {
-                label: label.to_string(),
-                custom: true,
-            })),
-            RawArgument::Ident(label) => Ok(Box::new(EndFunction {
-                label: label.to_string(),
-                custom: false,
-            })),
+            RawArgument::String(label) => {
+                // Only conditionals can be labelled, since custom sections are already named.
+                quiet_assert!(block_label.is_none())?;
+
+                Ok(Box::new(EndFunction {
+                    label: label.to_string(),
+                    custom: true,
+                    block_label,
+                }))
+            }
+            // Plugin functions which consume blocks are ended with their qualified name, such as
+            //   `end(my_plugin::wrap)`, but are known by their unqualified name while processing.
+            RawArgument::Ident(label) => {
+                quiet_assert!(block_label.is_none() || is_conditional(label))?;
+
+                Ok(Box::new(EndFunction {
+                    label: label
+                        .split_once("::")
+                        .map_or(label.as_str(), |(_, name)| name)
+                        .to_string(),
+                    custom: false,
+                    block_label,
+                }))
+            }
             _ => Err(ParseError::InvalidArgument),
         }
     }
@@ -78,4 +99,15 @@ impl Function for EndFunction {
 
         Ok(())
     }
+
+    fn label(&self) -> Option<&str> {
+        self.block_label.as_deref()
+    }
+
+    fn ends_block(&self) -> Option<&str> {
+        match self.custom {
+            true => None,
+            false => Some(&self.label),
+        }
+    }
 }
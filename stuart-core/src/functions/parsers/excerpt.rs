@@ -1,7 +1,7 @@
-use crate::functions::{Function, FunctionParser};
+use crate::functions::{Function, FunctionParser, Signature};
 use crate::parse::{ParseError, RawFunction};
-use crate::process::{ProcessError, Scope};
-use crate::{quiet_assert, TracebackError};
+use crate::process::{text_chars, ProcessError, Scope};
+use crate::TracebackError;
 
 /// Parses the `excerpt` function.
 pub struct ExcerptParser;
@@ -17,10 +17,11 @@ impl FunctionParser for ExcerptParser {
         "excerpt"
     }
 
-    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
-        quiet_assert!(raw.positional_args.len() == 2)?;
-        quiet_assert!(raw.named_args.is_empty())?;
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(2))
+    }
 
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
         let variable_name = raw.positional_args[0]
             .as_variable()
             .ok_or(ParseError::InvalidArgument)?;
@@ -44,46 +45,34 @@ impl Function for ExcerptFunction {
     }
 
     fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
-        let self_token = scope.tokens.current().unwrap().clone();
-
         let variable = scope.get_variable(&self.variable_name).ok_or_else(|| {
-            self_token.traceback(ProcessError::UndefinedVariable(self.variable_name.clone()))
+            scope.error(ProcessError::UndefinedVariable(self.variable_name.clone()))
         })?;
 
         let string = variable.as_str().ok_or_else(|| {
-            self_token.traceback(ProcessError::InvalidDataType {
+            scope.error(ProcessError::InvalidDataType {
                 variable: self.variable_name.clone(),
                 expected: "string".to_string(),
                 found: String::new(),
             })
         })?;
 
-        let mut chars = string.chars();
+        let mut chars = text_chars(string);
         let mut excerpt = String::with_capacity(self.length + 3);
-        let mut tag = false;
-        let mut total_chars: usize = 0;
 
         while excerpt.len() < self.length {
             if let Some(ch) = chars.next() {
-                if ch == '<' {
-                    tag = true;
-                } else if ch == '>' {
-                    tag = false;
-                } else if !tag {
-                    excerpt.push(ch);
-                }
-
-                total_chars += 1;
+                excerpt.push(ch);
             } else {
                 break;
             }
         }
 
-        if total_chars < string.len() {
+        if chars.next().is_some() {
             excerpt.push_str("...");
         }
 
-        scope.output(excerpt).map_err(|e| self_token.traceback(e))?;
+        scope.output(excerpt).map_err(|e| scope.error(e))?;
 
         Ok(())
     }
@@ -1,13 +1,13 @@
 use crate::fs::ParsedContents;
-use crate::functions::{Function, FunctionParser};
-use crate::parse::{ParseError, RawArgument, RawFunction};
+use crate::functions::{Function, FunctionParser, Signature};
+use crate::parse::{LocatableToken, ParseError, RawArgument, RawFunction};
 use crate::process::stack::StackFrame;
 use crate::process::{ProcessError, Scope};
 use crate::{quiet_assert, TracebackError};
 
 use humphrey_json::Value;
 
-use std::path::PathBuf;
+use std::path::Path;
 
 /// Parses the `for` function.
 pub struct ForParser;
@@ -17,109 +17,147 @@ pub struct ForFunction {
     variable_name: String,
     source: String,
     source_type: ForFunctionSourceType,
-    skip: Option<usize>,
-    limit: Option<usize>,
-    sort_variable: Option<String>,
-    sort_order: SortOrder,
+    skip: Option<ForCount>,
+    limit: Option<ForCount>,
+    sort: Sort,
+    filter: Option<(String, String)>,
+    shuffle: bool,
 }
 
 #[derive(Clone, Copy, Debug)]
 pub enum ForFunctionSourceType {
     MarkdownDirectory,
     JSONFile,
+    JsonlFile,
     JSONObject,
 }
 
+/// A `skip` or `limit` argument, which is either given literally or read from a variable when the loop runs.
+#[derive(Clone, Debug)]
+pub enum ForCount {
+    Literal(usize),
+    Variable(String),
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum SortOrder {
     Asc,
     Desc,
 }
 
+/// The sorting of a `for`-like function, set with the `sortby`, `sortas` and `order` arguments.
+#[derive(Clone, Debug)]
+pub(super) struct Sort {
+    variables: Vec<String>,
+    pub(super) order: SortOrder,
+    sort_as: Option<SortAs>,
+}
+
+/// How the values of the sort keys are compared, set with the `sortas` argument.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortAs {
+    String,
+    Date,
+}
+
+/// The value of a sort key for one item.
+///
+/// Dates which cannot be parsed are given a flag so that they sort last whatever the order of the loop.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum SortKey {
+    String(String),
+    Date(bool, i64),
+}
+
 impl FunctionParser for ForParser {
     fn name(&self) -> &'static str {
         "for"
     }
 
-    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
-        quiet_assert!(raw.positional_args.len() == 2)?;
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(2).with_named_args(&[
+            "skip",
+            "limit",
+            "sortby",
+            "sortas",
+            "order",
+            "filter_by",
+            "filter_value",
+            "shuffle",
+        ]))
+    }
 
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
         let variable_name = raw.positional_args[0]
             .as_variable()
             .ok_or(ParseError::InvalidArgument)?;
 
-        let (source, is_file) = match &raw.positional_args[1] {
-            RawArgument::String(source) => Ok((source.to_string(), true)),
-            RawArgument::Variable(source) => Ok((source.to_string(), false)),
-            _ => return Err(ParseError::InvalidArgument),
-        }?;
-
-        let source_type = if is_file {
-            if source.ends_with(".json") {
-                Ok(ForFunctionSourceType::JSONFile)
-            } else if source.ends_with('/') {
-                Ok(ForFunctionSourceType::MarkdownDirectory)
-            } else {
-                Err(ParseError::InvalidArgument)
-            }?
-        } else {
-            ForFunctionSourceType::JSONObject
-        };
+        let (source, source_type) = parse_source(&raw.positional_args[1])?;
 
         let mut skip = None;
         let mut limit = None;
-        let mut sort_variable = None;
-        let mut sort_order = SortOrder::Asc;
+        let mut sort = Sort::new();
+        let mut filter_variable = None;
+        let mut filter_value = None;
+        let mut shuffle = false;
 
         for (name, arg) in &raw.named_args {
+            if sort.parse_arg(name, arg)? {
+                continue;
+            }
+
             match name.as_str() {
                 "skip" => {
-                    quiet_assert!(arg.as_integer().is_some())?;
                     quiet_assert!(skip.is_none())?;
 
-                    skip = Some(
-                        arg.as_integer()
-                            .unwrap()
-                            .try_into()
-                            .map_err(|_| ParseError::InvalidArgument)?,
-                    );
+                    skip = Some(ForCount::parse(arg)?);
                 }
                 "limit" => {
-                    quiet_assert!(arg.as_integer().is_some())?;
                     quiet_assert!(limit.is_none())?;
 
-                    limit = Some(
-                        arg.as_integer()
-                            .unwrap()
-                            .try_into()
-                            .map_err(|_| ParseError::InvalidArgument)?,
-                    );
+                    limit = Some(ForCount::parse(arg)?);
                 }
-                "sortby" => {
+                "filter_by" => {
                     quiet_assert!(arg.as_variable().is_some())?;
-                    quiet_assert!(sort_variable.is_none())?;
+                    quiet_assert!(filter_variable.is_none())?;
 
-                    sort_variable = Some(arg.as_variable().unwrap().to_string());
+                    filter_variable = Some(arg.as_variable().unwrap().to_string());
                 }
-                "order" => {
-                    sort_order = match arg.as_string() {
-                        Some("asc") => SortOrder::Asc,
-                        Some("desc") => SortOrder::Desc,
+                "filter_value" => {
+                    quiet_assert!(filter_value.is_none())?;
+
+                    filter_value = Some(match arg {
+                        RawArgument::String(s) => s.to_string(),
+                        RawArgument::Integer(i) => i.to_string(),
+                        RawArgument::Bool(b) => b.to_string(),
                         _ => return Err(ParseError::InvalidArgument),
-                    };
+                    });
+                }
+                "shuffle" => {
+                    shuffle = arg.as_bool().ok_or(ParseError::InvalidArgument)?;
                 }
                 _ => return Err(ParseError::InvalidArgument),
             }
         }
 
+        sort.validate()?;
+        quiet_assert!(!shuffle || sort.variables.is_empty())?;
+
+        let filter = match (filter_variable, filter_value) {
+            (Some(variable), Some(value)) => Some((variable, value)),
+            (None, None) => None,
+            _ => return Err(ParseError::InvalidArgument),
+        };
+
         Ok(Box::new(ForFunction {
             variable_name: variable_name.to_string(),
             source,
             source_type,
             skip,
             limit,
-            sort_variable,
-            sort_order,
+            sort,
+            filter,
+            shuffle,
         }))
     }
 }
@@ -130,125 +168,375 @@ impl Function for ForFunction {
     }
 
     fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
-        let waypoint = scope.tokens.waypoint();
         let self_token = scope.tokens.current().unwrap().clone();
-
-        let mut variables: Vec<Value> = match self.source_type {
-            ForFunctionSourceType::MarkdownDirectory => {
-                let directory = scope
-                    .processor
-                    .input
-                    .as_ref()
-                    .unwrap()
-                    .get_at_path(&PathBuf::from(self.source.clone()))
-                    .ok_or_else(|| {
-                        self_token.traceback(ProcessError::NotFound(self.source.clone()))
-                    })?;
-
-                if !directory.is_dir() {
-                    return Err(self_token.traceback(ProcessError::NotFound(self.source.clone())));
-                }
-
-                directory
-                    .children()
-                    .unwrap()
-                    .iter()
-                    .filter_map(|n| match n.parsed_contents() {
-                        ParsedContents::Markdown(md) => Some(md.to_value()),
-                        _ => None,
-                    })
-                    .collect()
-            }
-            ForFunctionSourceType::JSONFile => {
-                let file = scope
-                    .processor
-                    .input
-                    .as_ref()
-                    .unwrap()
-                    .get_at_path(&PathBuf::from(self.source.clone()))
-                    .ok_or_else(|| {
-                        self_token.traceback(ProcessError::NotFound(self.source.clone()))
-                    })?;
-
-                if !file.is_file() {
-                    return Err(self_token.traceback(ProcessError::NotFound(self.source.clone())));
-                }
-
-                match file.parsed_contents() {
-                    ParsedContents::Json(json) => json.as_array().map(|a| a.iter().cloned()),
-                    _ => None,
-                }
-                .ok_or_else(|| self_token.traceback(ProcessError::NotJsonArray))?
-                .collect()
-            }
-            ForFunctionSourceType::JSONObject => {
-                let mut variable_iter = self.source.split('.');
-                let variable_name = variable_iter.next().unwrap();
-                let variable_indexes = variable_iter.collect::<Vec<_>>();
-
-                let mut variable = None;
-
-                for frame in scope.stack.iter().rev() {
-                    if let Some(value) = frame
-                        .get_variable(variable_name)
-                        .map(|v| crate::process::stack::get_value(&variable_indexes, v))
-                    {
-                        variable = Some(value);
-                        break;
-                    }
-                }
-
-                variable
-                    .and_then(|v| v.as_array().map(|a| a.to_vec()))
-                    .ok_or_else(|| self_token.traceback(ProcessError::NotJsonArray))?
-            }
-        };
-
-        if let Some(key) = &self.sort_variable {
+        let body = scope
+            .tokens
+            .collect_until_end(self.name())
+            .map_err(|e| self_token.traceback(e))?;
+
+        let skip = self
+            .skip
+            .as_ref()
+            .map(|skip| skip.resolve(scope, &self_token))
+            .transpose()?;
+        let limit = self
+            .limit
+            .as_ref()
+            .map(|limit| limit.resolve(scope, &self_token))
+            .transpose()?;
+
+        let mut variables = collect_source(&self.source, self.source_type, scope, &self_token)?;
+
+        // The totals are exposed through `$loop` so templates can show how many items were left out.
+        let total = variables.len();
+
+        if let Some((key, expected)) = &self.filter {
             let indexes = key.split('.').skip(1).collect::<Vec<_>>();
 
-            variables.sort_by_cached_key(|v| {
-                crate::process::stack::get_value(&indexes, v)
-                    .as_str()
-                    .unwrap_or("")
-                    .to_string()
+            // Values are compared by their string representation, so `filter_value=true` matches both
+            //   `true` and `"true"`.
+            variables.retain(|v| {
+                comparable_string(&crate::process::stack::get_value(&indexes, v))
+                    .map(|s| &s == expected)
+                    .unwrap_or(false)
             });
         }
 
-        let mut variable_iter: Box<dyn Iterator<Item = Value>> = match self.sort_order {
+        self.sort
+            .sort(&mut variables)
+            .map_err(|e| self_token.traceback(e))?;
+
+        if self.shuffle {
+            shuffle(&mut variables, scope.processor.config.shuffle_seed);
+        }
+
+        let filtered_total = variables.len().saturating_sub(skip.unwrap_or(0));
+        let count = limit.map_or(filtered_total, |limit| filtered_total.min(limit));
+
+        let mut variable_iter: Box<dyn Iterator<Item = Value>> = match self.sort.order {
             SortOrder::Asc => Box::new(variables.into_iter()),
             SortOrder::Desc => Box::new(variables.into_iter().rev()),
         };
 
-        if let Some(s) = self.skip {
+        if let Some(s) = skip {
             variable_iter = Box::new(variable_iter.skip(s));
         }
 
-        if let Some(l) = self.limit {
+        if let Some(l) = limit {
             variable_iter = Box::new(variable_iter.take(l));
         }
 
-        for variable in variable_iter {
-            scope.tokens.rewind_to(waypoint);
-
+        for (index, variable) in variable_iter.enumerate() {
             let frame = {
                 let mut frame = StackFrame::new(format!("for:{}", self.variable_name));
                 frame.add_variable(&self.variable_name, variable);
+                frame.add_variable(
+                    "loop",
+                    Value::Object(vec![
+                        ("index".to_string(), Value::Number(index as f64)),
+                        ("count".to_string(), Value::Number(count as f64)),
+                        ("total".to_string(), Value::Number(total as f64)),
+                        (
+                            "filtered_total".to_string(),
+                            Value::Number(filtered_total as f64),
+                        ),
+                    ]),
+                );
                 frame
             };
 
-            let stack_height = scope.stack.len();
-            scope.stack.push(frame);
+            scope.process_block(&body, frame, &self_token)?;
+        }
 
-            while scope.stack.len() > stack_height {
-                let token = scope
-                    .tokens
-                    .next()
-                    .ok_or_else(|| self_token.traceback(ProcessError::UnexpectedEndOfFile))?;
-                token.process(scope)?;
+        Ok(())
+    }
+}
+
+impl Sort {
+    /// Creates a sort which keeps the original order.
+    pub(super) fn new() -> Self {
+        Self {
+            variables: Vec::new(),
+            order: SortOrder::Asc,
+            sort_as: None,
+        }
+    }
+
+    /// Parses a named argument into the sort, returning `false` if it is not a sorting argument.
+    pub(super) fn parse_arg(&mut self, name: &str, arg: &RawArgument) -> Result<bool, ParseError> {
+        match name {
+            "sortby" => {
+                quiet_assert!(self.variables.is_empty())?;
+
+                self.variables = match arg {
+                    RawArgument::Variable(variable) => vec![variable.to_string()],
+                    RawArgument::String(keys) => keys
+                        .split(',')
+                        .map(|key| {
+                            key.trim()
+                                .strip_prefix('$')
+                                .filter(|key| !key.is_empty())
+                                .map(|key| key.to_string())
+                                .ok_or(ParseError::InvalidArgument)
+                        })
+                        .collect::<Result<_, _>>()?,
+                    _ => return Err(ParseError::InvalidArgument),
+                };
+            }
+            "sortas" => {
+                self.sort_as = match arg.as_string() {
+                    Some("string") => Some(SortAs::String),
+                    Some("date") => Some(SortAs::Date),
+                    _ => return Err(ParseError::InvalidArgument),
+                };
+            }
+            "order" => {
+                self.order = match arg.as_string() {
+                    Some("asc") => SortOrder::Asc,
+                    Some("desc") => SortOrder::Desc,
+                    _ => return Err(ParseError::InvalidArgument),
+                };
             }
+            _ => return Ok(false),
         }
 
+        Ok(true)
+    }
+
+    /// Checks that the sorting arguments are consistent once they have all been parsed.
+    pub(super) fn validate(&self) -> Result<(), ParseError> {
+        quiet_assert!(self.sort_as.is_none() || !self.variables.is_empty())
+    }
+
+    /// Sorts the items by the sort keys in ascending order.
+    ///
+    /// The items are not reversed for descending order, so this must be done by the caller.
+    pub(super) fn sort(&self, items: &mut [Value]) -> Result<(), ProcessError> {
+        if self.variables.is_empty() {
+            return Ok(());
+        }
+
+        let keys = self
+            .variables
+            .iter()
+            .map(|key| key.split('.').skip(1).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        #[cfg(not(feature = "date"))]
+        if self.sort_as == Some(SortAs::Date) {
+            return Err(ProcessError::FeatureNotEnabled("date".to_string()));
+        }
+
+        // Keys are compared lexicographically, so later keys only break ties between earlier ones.
+        items.sort_by_cached_key(|v| {
+            keys.iter()
+                .map(|indexes| {
+                    let value = crate::process::stack::get_value(indexes, v);
+                    self.sort_key(value.as_str().unwrap_or(""))
+                })
+                .collect::<Vec<_>>()
+        });
+
         Ok(())
     }
+
+    /// Returns the sort key of a value, parsing it as a date if the items are sorted by date.
+    fn sort_key(&self, value: &str) -> SortKey {
+        match self.sort_as.unwrap_or(SortAs::String) {
+            SortAs::String => SortKey::String(value.to_string()),
+            SortAs::Date => {
+                let timestamp = super::dateformat::parse_timestamp(value);
+
+                // The items are reversed after sorting for descending order, so the flag is inverted to keep
+                //   unparseable dates last.
+                let unparseable = timestamp.is_none() != matches!(self.order, SortOrder::Desc);
+
+                SortKey::Date(unparseable, timestamp.unwrap_or(0))
+            }
+        }
+    }
+}
+
+impl ForCount {
+    /// Parses a `skip` or `limit` argument, which must be a non-negative integer or a variable.
+    fn parse(arg: &RawArgument) -> Result<Self, ParseError> {
+        match arg {
+            RawArgument::Integer(int) => Ok(Self::Literal(
+                (*int).try_into().map_err(|_| ParseError::InvalidArgument)?,
+            )),
+            RawArgument::Variable(variable_name) => Ok(Self::Variable(variable_name.to_string())),
+            _ => Err(ParseError::InvalidArgument),
+        }
+    }
+
+    /// Resolves the count, returning an error if a variable is undefined or is not a non-negative integer.
+    fn resolve(
+        &self,
+        scope: &Scope,
+        self_token: &LocatableToken,
+    ) -> Result<usize, TracebackError<ProcessError>> {
+        match self {
+            Self::Literal(count) => Ok(*count),
+            Self::Variable(variable_name) => {
+                let variable = scope.get_variable(variable_name).ok_or_else(|| {
+                    self_token.traceback(ProcessError::UndefinedVariable(variable_name.clone()))
+                })?;
+
+                match variable.as_number() {
+                    Some(n) if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+                    _ => Err(self_token.traceback(ProcessError::InvalidDataType {
+                        variable: variable_name.clone(),
+                        expected: "non-negative integer".to_string(),
+                        found: String::new(),
+                    })),
+                }
+            }
+        }
+    }
+}
+
+/// Parses the source argument of a `for`-like function, returning the source and its type.
+pub(super) fn parse_source(
+    arg: &RawArgument,
+) -> Result<(String, ForFunctionSourceType), ParseError> {
+    let (source, is_file) = match arg {
+        RawArgument::String(source) => (source.to_string(), true),
+        RawArgument::Variable(source) => (source.to_string(), false),
+        _ => return Err(ParseError::InvalidArgument),
+    };
+
+    let source_type = if is_file {
+        if source.ends_with(".json") {
+            Ok(ForFunctionSourceType::JSONFile)
+        } else if source.ends_with(".jsonl") {
+            Ok(ForFunctionSourceType::JsonlFile)
+        } else if source.ends_with('/') {
+            Ok(ForFunctionSourceType::MarkdownDirectory)
+        } else {
+            Err(ParseError::InvalidArgument)
+        }?
+    } else {
+        ForFunctionSourceType::JSONObject
+    };
+
+    Ok((source, source_type))
+}
+
+/// Collects the items of the given source, in their original order.
+pub(super) fn collect_source(
+    source: &str,
+    source_type: ForFunctionSourceType,
+    scope: &Scope,
+    self_token: &LocatableToken,
+) -> Result<Vec<Value>, TracebackError<ProcessError>> {
+    let variables = match source_type {
+        ForFunctionSourceType::MarkdownDirectory => {
+            let directory = scope
+                .processor
+                .input
+                .as_ref()
+                .ok_or_else(|| self_token.traceback(ProcessError::NotFound(source.to_string())))?
+                .get_at_path_result(Path::new(source))
+                .map_err(|e| self_token.traceback(e.into()))?;
+
+            if !directory.is_dir() {
+                return Err(
+                    self_token.traceback(ProcessError::ExpectedDirectory(source.to_string()))
+                );
+            }
+
+            directory
+                .children()
+                .unwrap()
+                .iter()
+                .filter_map(|n| match n.parsed_contents() {
+                    ParsedContents::Markdown(md) => Some(md.to_value()),
+                    _ => None,
+                })
+                .collect()
+        }
+        ForFunctionSourceType::JSONFile => scope
+            .get_file_json(source)
+            .map_err(|e| self_token.traceback(e))?
+            .as_array()
+            .ok_or_else(|| self_token.traceback(ProcessError::NotJsonArray))?
+            .to_vec(),
+        ForFunctionSourceType::JsonlFile => {
+            let file = scope
+                .get_file(source)
+                .map_err(|e| self_token.traceback(e))?;
+
+            let contents = file
+                .contents()
+                .and_then(|contents| std::str::from_utf8(contents).ok())
+                .ok_or_else(|| self_token.traceback(ProcessError::NotFound(source.to_string())))?;
+
+            // Each non-empty line is a separate value, so errors point to the offending line of the file.
+            contents
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(i, line)| {
+                    humphrey_json::from_str(line).map_err(|_| TracebackError {
+                        path: file.source().to_path_buf(),
+                        line: i as u32 + 1,
+                        column: 0,
+                        kind: ProcessError::InvalidJson,
+                    })
+                })
+                .collect::<Result<_, _>>()?
+        }
+        ForFunctionSourceType::JSONObject => {
+            let mut variable_iter = source.split('.');
+            let variable_name = variable_iter.next().unwrap();
+            let variable_indexes = variable_iter.collect::<Vec<_>>();
+
+            let mut variable = None;
+
+            for frame in scope.stack.iter().rev() {
+                if let Some(value) = frame
+                    .get_variable(variable_name)
+                    .map(|v| crate::process::stack::get_value(&variable_indexes, v))
+                {
+                    variable = Some(value);
+                    break;
+                }
+            }
+
+            variable
+                .and_then(|v| v.as_array().map(|a| a.to_vec()))
+                .ok_or_else(|| self_token.traceback(ProcessError::NotJsonArray))?
+        }
+    };
+
+    Ok(variables)
+}
+
+/// Returns the string representation of a string, boolean or number, so that values can be compared whatever
+///   their type.
+pub(super) fn comparable_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Deterministically shuffles the items using the given seed.
+///
+/// This uses a Fisher-Yates shuffle driven by SplitMix64, so the same seed always gives the same order.
+fn shuffle(items: &mut [Value], seed: u64) {
+    let mut state = seed;
+
+    for i in (1..items.len()).rev() {
+        state = state.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+
+        items.swap(i, (z % (i as u64 + 1)) as usize);
+    }
 }
@@ -0,0 +1,110 @@
+use crate::functions::{Function, FunctionParser, Signature};
+use crate::parse::{ParseError, RawArgument, RawFunction};
+use crate::process::{ProcessError, Scope};
+use crate::TracebackError;
+
+/// Parses the `frontmatter` function.
+pub struct FrontmatterParser;
+
+#[derive(Debug, Clone)]
+pub struct FrontmatterFunction {
+    file_name: String,
+    target: FrontmatterTarget,
+}
+
+/// What to do with the frontmatter once it has been read.
+#[derive(Debug, Clone)]
+enum FrontmatterTarget {
+    /// Output the value of the given key.
+    Key(String),
+    /// Add the whole frontmatter to the current stack frame as the given variable.
+    Variable(String),
+}
+
+impl FunctionParser for FrontmatterParser {
+    fn name(&self) -> &'static str {
+        "frontmatter"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(2))
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        let (file_name, target) = match (&raw.positional_args[0], &raw.positional_args[1]) {
+            (RawArgument::String(file_name), RawArgument::String(key)) => {
+                (file_name, FrontmatterTarget::Key(key.to_string()))
+            }
+            (RawArgument::Variable(variable_name), RawArgument::String(file_name)) => (
+                file_name,
+                FrontmatterTarget::Variable(variable_name.to_string()),
+            ),
+            _ => return Err(ParseError::InvalidArgument),
+        };
+
+        Ok(Box::new(FrontmatterFunction {
+            file_name: file_name.to_string(),
+            target,
+        }))
+    }
+}
+
+impl Function for FrontmatterFunction {
+    fn name(&self) -> &'static str {
+        "frontmatter"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+
+        let md = scope
+            .get_file_markdown(&self.file_name)
+            .map_err(|e| self_token.traceback(e))?;
+
+        match &self.target {
+            FrontmatterTarget::Key(key) => {
+                let value = md
+                    .frontmatter
+                    .iter()
+                    .find(|(k, _)| k == key)
+                    .map(|(_, v)| v)
+                    .ok_or_else(|| {
+                        self_token.traceback(ProcessError::MissingFrontmatterKey {
+                            file: self.file_name.clone(),
+                            key: key.clone(),
+                        })
+                    })?
+                    .as_str()
+                    .ok_or_else(|| {
+                        self_token.traceback(ProcessError::InvalidDataType {
+                            variable: key.clone(),
+                            expected: "string".to_string(),
+                            found: "array".to_string(),
+                        })
+                    })?
+                    .to_string();
+
+                scope.output(value).map_err(|e| self_token.traceback(e))?;
+            }
+            FrontmatterTarget::Variable(variable_name) => {
+                let frame = scope
+                    .stack
+                    .last_mut()
+                    .ok_or_else(|| self_token.traceback(ProcessError::StackError))?;
+
+                if frame.get_variable(variable_name).is_some() {
+                    return Err(self_token
+                        .traceback(ProcessError::VariableAlreadyExists(variable_name.clone())));
+                }
+
+                frame.add_variable(variable_name, md.frontmatter_to_value());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn referenced_file(&self) -> Option<&str> {
+        Some(&self.file_name)
+    }
+}
@@ -0,0 +1,81 @@
+use super::r#for::{collect_source, parse_source, ForFunctionSourceType};
+
+use crate::functions::{Function, FunctionParser, Signature};
+use crate::parse::{ParseError, RawFunction};
+use crate::process::stack::StackFrame;
+use crate::process::{ProcessError, Scope};
+use crate::TracebackError;
+
+/// Parses the `get` function.
+pub struct GetParser;
+
+#[derive(Debug, Clone)]
+pub struct GetFunction {
+    variable_name: String,
+    source: String,
+    source_type: ForFunctionSourceType,
+    index: usize,
+}
+
+impl FunctionParser for GetParser {
+    fn name(&self) -> &'static str {
+        "get"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(3))
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        let variable_name = raw.positional_args[0]
+            .as_variable()
+            .ok_or(ParseError::InvalidArgument)?;
+
+        let (source, source_type) = parse_source(&raw.positional_args[1])?;
+
+        let index = raw.positional_args[2]
+            .as_integer()
+            .ok_or(ParseError::InvalidArgument)?
+            .try_into()
+            .map_err(|_| ParseError::InvalidArgument)?;
+
+        Ok(Box::new(GetFunction {
+            variable_name: variable_name.to_string(),
+            source,
+            source_type,
+            index,
+        }))
+    }
+}
+
+impl Function for GetFunction {
+    fn name(&self) -> &'static str {
+        "get"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+        let body = scope
+            .tokens
+            .collect_until_end(self.name())
+            .map_err(|e| self_token.traceback(e))?;
+
+        let variable = collect_source(&self.source, self.source_type, scope, &self_token)?
+            .into_iter()
+            .nth(self.index)
+            .ok_or_else(|| {
+                self_token.traceback(ProcessError::NotFound(format!(
+                    "{}[{}]",
+                    self.source, self.index
+                )))
+            })?;
+
+        let frame = {
+            let mut frame = StackFrame::new(format!("get:{}", self.variable_name));
+            frame.add_variable(&self.variable_name, variable);
+            frame
+        };
+
+        scope.process_block(&body, frame, &self_token)
+    }
+}
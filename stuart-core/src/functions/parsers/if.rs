@@ -9,6 +9,8 @@ macro_rules! if_parsers {
                 pub struct Function {
                     input_1: $crate::functions::Input,
                     input_2: $crate::functions::Input,
+                    negate: bool,
+                    label: Option<String>,
                 }
 
                 impl $crate::functions::FunctionParser for Parser {
@@ -16,9 +18,16 @@ macro_rules! if_parsers {
                         stringify!($name)
                     }
 
+                    fn signature(&self) -> Option<$crate::functions::Signature> {
+                        Some($crate::functions::Signature::exactly(2).with_named_args(&["not", "label"]))
+                    }
+
+                    fn is_conditional(&self) -> bool {
+                        true
+                    }
+
                     fn parse(&self, mut raw: $crate::parse::RawFunction) -> Result<Box<dyn $crate::functions::Function>, $crate::parse::ParseError> {
-                        $crate::quiet_assert!(raw.positional_args.len() == 2)?;
-                        $crate::quiet_assert!(raw.named_args.is_empty())?;
+                        let (negate, label) = $crate::functions::parsers::r#if::parse_conditional_args(&raw.named_args)?;
 
                         let input_2 = match raw.positional_args.pop().unwrap() {
                             $crate::parse::RawArgument::Variable(v) => $crate::functions::Input::Variable(v),
@@ -34,7 +43,7 @@ macro_rules! if_parsers {
                             _ => return Err($crate::parse::ParseError::InvalidArgument),
                         };
 
-                        Ok(Box::new(Function { input_1, input_2 }))
+                        Ok(Box::new(Function { input_1, input_2, negate, label }))
                     }
                 }
 
@@ -54,40 +63,26 @@ macro_rules! if_parsers {
                             self_token.traceback($crate::process::ProcessError::UndefinedVariable(self.input_2.to_string()))
                         })?;
 
-                        let mut condition = input_1 $cond input_2;
+                        let condition = (input_1 $cond input_2) != self.negate;
+
+                        let details = format!("{}:{}", self.input_1, self.input_2);
 
-                        let frame = $crate::process::stack::StackFrame::new(format!(
-                            "{}:{}:{}",
+                        $crate::functions::parsers::r#if::process_conditional(
+                            scope,
                             stringify!($name),
-                            self.input_1.to_string(),
-                            self.input_2.to_string()
-                        ));
-
-                        let stack_height = scope.stack.len();
-                        scope.stack.push(frame);
-
-                        while scope.stack.len() > stack_height {
-                            let token = scope
-                                .tokens
-                                .next()
-                                .ok_or_else(|| self_token.traceback($crate::process::ProcessError::UnexpectedEndOfFile))?;
-
-                            let function_name = token.as_function().map(|f| f.name().to_string());
-
-                            if condition
-                                || ((function_name == Some("end".to_string())
-                                    || function_name == Some("else".to_string()))
-                                    && scope.stack.len() == stack_height + 1)
-                            {
-                                token.process(scope)?;
-
-                                if function_name == Some("else".to_string()) {
-                                    condition = !condition;
-                                }
-                            }
-                        }
-
-                        Ok(())
+                            self.label.as_deref(),
+                            &details,
+                            condition,
+                            &self_token,
+                        )
+                    }
+
+                    fn label(&self) -> Option<&str> {
+                        self.label.as_deref()
+                    }
+
+                    fn is_conditional(&self) -> bool {
+                        true
                     }
                 }
             }
@@ -96,3 +91,222 @@ macro_rules! if_parsers {
         )*
     }
 }
+
+use crate::functions::{is_conditional, Function, FunctionParser, Signature};
+use crate::parse::{LocatableToken, ParseError, RawArgument, RawFunction};
+use crate::process::stack::StackFrame;
+use crate::process::{ProcessError, Scope};
+use crate::{quiet_assert, TracebackError};
+
+use humphrey_json::Value;
+
+/// Parses the `if` function.
+pub struct IfParser;
+
+#[derive(Debug, Clone)]
+pub struct IfFunction {
+    variable_name: String,
+    negate: bool,
+    label: Option<String>,
+}
+
+impl FunctionParser for IfParser {
+    fn name(&self) -> &'static str {
+        "if"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(1).with_named_args(&["not", "label"]))
+    }
+
+    fn is_conditional(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        let (negate, label) = parse_conditional_args(&raw.named_args)?;
+
+        let variable_name = raw.positional_args[0]
+            .as_variable()
+            .ok_or(ParseError::InvalidArgument)?;
+
+        Ok(Box::new(IfFunction {
+            variable_name: variable_name.to_string(),
+            negate,
+            label,
+        }))
+    }
+}
+
+impl Function for IfFunction {
+    fn name(&self) -> &'static str {
+        "if"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+
+        let condition = scope
+            .get_variable(&self.variable_name)
+            .map(|v| is_truthy(&v) != self.negate)
+            .ok_or_else(|| {
+                self_token.traceback(ProcessError::UndefinedVariable(self.variable_name.clone()))
+            })?;
+
+        process_conditional(
+            scope,
+            self.name(),
+            self.label.as_deref(),
+            &self.variable_name,
+            condition,
+            &self_token,
+        )
+    }
+
+    fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    fn is_conditional(&self) -> bool {
+        true
+    }
+}
+
+/// Collects the block of a conditional function and processes the branch chosen by the condition in a new stack
+///   frame, named after the function, its label and the given details.
+///
+/// The block is split at its `else`, if it has one. The `else` functions of nested conditionals are skipped by
+///   counting how deeply conditionals are nested. If the `else` or `end` of the block is labelled, its label must
+///   match that of the conditional.
+pub(super) fn process_conditional(
+    scope: &mut Scope,
+    fn_name: &str,
+    label: Option<&str>,
+    details: &str,
+    condition: bool,
+    self_token: &LocatableToken,
+) -> Result<(), TracebackError<ProcessError>> {
+    let body = scope
+        .tokens
+        .collect_until_end(fn_name)
+        .map_err(|e| self_token.traceback(e))?;
+
+    if let Some(end) = scope.tokens.current() {
+        check_label(end, label)?;
+    }
+
+    let mut depth = 0;
+    let else_index = body.iter().position(|token| {
+        let function = match token.as_function() {
+            Some(function) => function,
+            None => return false,
+        };
+
+        if function.is_conditional() {
+            depth += 1;
+        } else if function.ends_block().is_some_and(is_conditional) {
+            depth -= 1;
+        }
+
+        depth == 0 && function.name() == "else"
+    });
+
+    if let Some(i) = else_index {
+        check_label(&body[i], label)?;
+    }
+
+    let frame = StackFrame::new(frame_name(fn_name, label, details));
+
+    let branch = match (condition, else_index) {
+        (true, Some(i)) => &body[..i],
+        (true, None) => &body[..],
+        (false, Some(i)) => &body[i + 1..],
+        (false, None) => &[],
+    };
+
+    scope.process_block(branch, frame, self_token)
+}
+
+/// Returns the name of the stack frame of a conditional, such as `ifeq#outer:$a:1` for
+///   `ifeq($a, 1, label="outer")`, which allows `else` to find the label of the conditional it is in.
+fn frame_name(fn_name: &str, label: Option<&str>, details: &str) -> String {
+    match label {
+        Some(label) => format!("{}#{}:{}", fn_name, label, details),
+        None => format!("{}:{}", fn_name, details),
+    }
+}
+
+/// Returns the label of a conditional from the name of its stack frame, if it has one.
+pub(super) fn frame_label(frame_name: &str) -> Option<&str> {
+    frame_name
+        .split(':')
+        .next()
+        .and_then(|name| name.split_once('#'))
+        .map(|(_, label)| label)
+}
+
+/// Checks that the label of an `else` or `end` function, if it has one, matches the label of its conditional.
+fn check_label(
+    token: &LocatableToken,
+    expected: Option<&str>,
+) -> Result<(), TracebackError<ProcessError>> {
+    let function = match token.as_function() {
+        Some(function) => function,
+        None => return Ok(()),
+    };
+
+    match function.label() {
+        Some(label) if Some(label) != expected => {
+            Err(token.traceback(ProcessError::MismatchedLabel {
+                label: label.to_string(),
+                expected: expected.map(|label| label.to_string()),
+            }))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Parses the named arguments of a conditional function, returning whether the condition should be negated and
+///   the label of the conditional, if it has one.
+///
+/// The accepted named arguments are `not`, which must be a boolean, for example `ifeq($a, $b, not=true)`, and
+///   `label`, which must be a string, for example `ifeq($a, $b, label="outer")`. The names of the arguments are
+///   checked against the signature of the function before this is called.
+pub(super) fn parse_conditional_args(
+    named_args: &[(String, RawArgument)],
+) -> Result<(bool, Option<String>), ParseError> {
+    let mut negate = false;
+    let mut label = None;
+
+    for (name, arg) in named_args {
+        match name.as_str() {
+            "not" => negate = arg.as_bool().ok_or(ParseError::InvalidArgument)?,
+            _ => {
+                let value = arg.as_string().ok_or(ParseError::InvalidArgument)?;
+                quiet_assert!(is_valid_label(value))?;
+                label = Some(value.to_string());
+            }
+        }
+    }
+
+    Ok((negate, label))
+}
+
+/// Returns whether the string can be used as the label of a conditional.
+pub(super) fn is_valid_label(label: &str) -> bool {
+    !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Returns whether the value is truthy.
+///
+/// `true`, non-zero numbers, and non-empty strings, arrays and objects are truthy. Everything else is not.
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => *n != 0.0,
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
@@ -1,8 +1,8 @@
-use crate::functions::{Function, FunctionParser};
+use crate::functions::parsers::r#if::{parse_conditional_args, process_conditional};
+use crate::functions::{Function, FunctionParser, Signature};
 use crate::parse::{ParseError, RawFunction};
-use crate::process::stack::StackFrame;
 use crate::process::{ProcessError, Scope};
-use crate::{quiet_assert, TracebackError};
+use crate::TracebackError;
 
 use humphrey_json::Value;
 
@@ -12,6 +12,7 @@ pub struct IfDefinedParser;
 #[derive(Debug, Clone)]
 pub struct IfDefinedFunction {
     variable_name: String,
+    label: Option<String>,
 }
 
 impl FunctionParser for IfDefinedParser {
@@ -19,9 +20,16 @@ impl FunctionParser for IfDefinedParser {
         "ifdefined"
     }
 
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(1).with_named_args(&["label"]))
+    }
+
+    fn is_conditional(&self) -> bool {
+        true
+    }
+
     fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
-        quiet_assert!(raw.positional_args.len() == 1)?;
-        quiet_assert!(raw.named_args.is_empty())?;
+        let (_, label) = parse_conditional_args(&raw.named_args)?;
 
         let variable_name = raw.positional_args[0]
             .as_variable()
@@ -29,6 +37,7 @@ impl FunctionParser for IfDefinedParser {
 
         Ok(Box::new(IfDefinedFunction {
             variable_name: variable_name.to_string(),
+            label,
         }))
     }
 }
@@ -41,37 +50,26 @@ impl Function for IfDefinedFunction {
     fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
         let self_token = scope.tokens.current().unwrap().clone();
 
-        let mut defined = scope
+        let defined = scope
             .get_variable(&self.variable_name)
             .map(|v| !matches!(v, Value::Null))
             .unwrap_or(false);
 
-        let frame = StackFrame::new(format!("ifdefined:{}", self.variable_name));
-
-        let stack_height = scope.stack.len();
-        scope.stack.push(frame);
-
-        while scope.stack.len() > stack_height {
-            let token = scope
-                .tokens
-                .next()
-                .ok_or_else(|| self_token.traceback(ProcessError::UnexpectedEndOfFile))?;
-
-            let function_name = token.as_function().map(|f| f.name().to_string());
-
-            if defined
-                || ((function_name == Some("end".to_string())
-                    || function_name == Some("else".to_string()))
-                    && scope.stack.len() == stack_height + 1)
-            {
-                token.process(scope)?;
+        process_conditional(
+            scope,
+            self.name(),
+            self.label.as_deref(),
+            &self.variable_name,
+            defined,
+            &self_token,
+        )
+    }
 
-                if function_name == Some("else".to_string()) {
-                    defined = !defined;
-                }
-            }
-        }
+    fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
 
-        Ok(())
+    fn is_conditional(&self) -> bool {
+        true
     }
 }
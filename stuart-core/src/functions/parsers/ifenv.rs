@@ -0,0 +1,81 @@
+use crate::functions::parsers::r#if::{parse_conditional_args, process_conditional};
+use crate::functions::{Function, FunctionParser, Signature};
+use crate::parse::{ParseError, RawFunction};
+use crate::process::{ProcessError, Scope};
+use crate::TracebackError;
+
+use humphrey_json::Value;
+
+/// Parses the `ifenv` function.
+pub struct IfEnvParser;
+
+#[derive(Debug, Clone)]
+pub struct IfEnvFunction {
+    environments: Vec<String>,
+    label: Option<String>,
+}
+
+impl FunctionParser for IfEnvParser {
+    fn name(&self) -> &'static str {
+        "ifenv"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::at_least(1).with_named_args(&["label"]))
+    }
+
+    fn is_conditional(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        let (_, label) = parse_conditional_args(&raw.named_args)?;
+
+        let environments = raw
+            .positional_args
+            .iter()
+            .map(|arg| {
+                arg.as_string()
+                    .map(|s| s.to_string())
+                    .ok_or(ParseError::InvalidArgument)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Box::new(IfEnvFunction {
+            environments,
+            label,
+        }))
+    }
+}
+
+impl Function for IfEnvFunction {
+    fn name(&self) -> &'static str {
+        "ifenv"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+
+        let condition = match scope.get_variable("env.STUART_ENV") {
+            Some(Value::String(env)) => self.environments.contains(&env),
+            _ => false,
+        };
+
+        process_conditional(
+            scope,
+            self.name(),
+            self.label.as_deref(),
+            &self.environments.join(","),
+            condition,
+            &self_token,
+        )
+    }
+
+    fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    fn is_conditional(&self) -> bool {
+        true
+    }
+}
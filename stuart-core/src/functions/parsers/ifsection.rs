@@ -0,0 +1,75 @@
+use crate::functions::parsers::r#if::{parse_conditional_args, process_conditional};
+use crate::functions::{Function, FunctionParser, Signature};
+use crate::parse::{ParseError, RawFunction};
+use crate::process::{ProcessError, Scope};
+use crate::TracebackError;
+
+/// Parses the `ifsection` function.
+pub struct IfSectionParser;
+
+#[derive(Debug, Clone)]
+pub struct IfSectionFunction {
+    section_name: String,
+    negate: bool,
+    label: Option<String>,
+}
+
+impl FunctionParser for IfSectionParser {
+    fn name(&self) -> &'static str {
+        "ifsection"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(1).with_named_args(&["not", "label"]))
+    }
+
+    fn is_conditional(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        let (negate, label) = parse_conditional_args(&raw.named_args)?;
+
+        let section_name = raw.positional_args[0]
+            .as_string()
+            .ok_or(ParseError::InvalidArgument)?;
+
+        Ok(Box::new(IfSectionFunction {
+            section_name: section_name.to_string(),
+            negate,
+            label,
+        }))
+    }
+}
+
+impl Function for IfSectionFunction {
+    fn name(&self) -> &'static str {
+        "ifsection"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+
+        let defined = scope
+            .sections
+            .iter()
+            .any(|(name, _)| name == &self.section_name);
+
+        process_conditional(
+            scope,
+            self.name(),
+            self.label.as_deref(),
+            &self.section_name,
+            defined != self.negate,
+            &self_token,
+        )
+    }
+
+    fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    fn is_conditional(&self) -> bool {
+        true
+    }
+}
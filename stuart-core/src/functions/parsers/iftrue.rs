@@ -0,0 +1,138 @@
+use crate::functions::parsers::r#if::{parse_conditional_args, process_conditional};
+use crate::functions::{Function, FunctionParser, Signature};
+use crate::parse::{ParseError, RawFunction};
+use crate::process::{ProcessError, Scope};
+use crate::TracebackError;
+
+use humphrey_json::Value;
+
+/// Parses the `iftrue` function.
+pub struct IfTrueParser;
+
+/// Parses the `iffalse` function.
+pub struct IfFalseParser;
+
+#[derive(Debug, Clone)]
+pub struct IfBoolFunction {
+    variable_name: String,
+    expected: bool,
+    negate: bool,
+    label: Option<String>,
+}
+
+impl FunctionParser for IfTrueParser {
+    fn name(&self) -> &'static str {
+        "iftrue"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(1).with_named_args(&["not", "label"]))
+    }
+
+    fn is_conditional(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        parse(raw, true)
+    }
+}
+
+impl FunctionParser for IfFalseParser {
+    fn name(&self) -> &'static str {
+        "iffalse"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(1).with_named_args(&["not", "label"]))
+    }
+
+    fn is_conditional(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        parse(raw, false)
+    }
+}
+
+/// Parses a conditional which checks whether a variable is the expected boolean.
+fn parse(raw: RawFunction, expected: bool) -> Result<Box<dyn Function>, ParseError> {
+    let (negate, label) = parse_conditional_args(&raw.named_args)?;
+
+    let variable_name = raw.positional_args[0]
+        .as_variable()
+        .ok_or(ParseError::InvalidArgument)?;
+
+    Ok(Box::new(IfBoolFunction {
+        variable_name: variable_name.to_string(),
+        expected,
+        negate,
+        label,
+    }))
+}
+
+impl Function for IfBoolFunction {
+    fn name(&self) -> &'static str {
+        match self.expected {
+            true => "iftrue",
+            false => "iffalse",
+        }
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+
+        let value = scope.get_variable(&self.variable_name).ok_or_else(|| {
+            self_token.traceback(ProcessError::UndefinedVariable(self.variable_name.clone()))
+        })?;
+
+        let condition = (as_bool(&value).ok_or_else(|| {
+            self_token.traceback(ProcessError::InvalidDataType {
+                variable: self.variable_name.clone(),
+                expected: "boolean".to_string(),
+                found: match value {
+                    Value::String(s) => format!("\"{}\"", s),
+                    Value::Array(_) => "array".to_string(),
+                    _ => "object".to_string(),
+                },
+            })
+        })? == self.expected)
+            != self.negate;
+
+        process_conditional(
+            scope,
+            self.name(),
+            self.label.as_deref(),
+            &self.variable_name,
+            condition,
+            &self_token,
+        )
+    }
+
+    fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    fn is_conditional(&self) -> bool {
+        true
+    }
+}
+
+/// Interprets the value as a boolean, returning `None` if it does not represent one.
+///
+/// Strings such as `"yes"` and `"0"` are accepted as well as booleans and numbers, since frontmatter values are
+///   always strings. Null is treated as `false`.
+fn as_bool(value: &Value) -> Option<bool> {
+    match value {
+        Value::Null => Some(false),
+        Value::Bool(b) => Some(*b),
+        Value::Number(n) => Some(*n != 0.0),
+        Value::String(s) => match s.trim().to_ascii_lowercase().as_str() {
+            "true" | "yes" | "1" => Some(true),
+            "false" | "no" | "0" | "" => Some(false),
+            _ => None,
+        },
+        Value::Array(_) | Value::Object(_) => None,
+    }
+}
@@ -1,17 +1,20 @@
 use crate::fs::ParsedContents;
-use crate::functions::{Function, FunctionParser};
+use crate::functions::{Function, FunctionParser, Signature};
 use crate::parse::{ParseError, RawFunction};
 use crate::process::{ProcessError, Scope};
 use crate::{quiet_assert, TracebackError};
 
-use std::path::PathBuf;
+use humphrey_json::Value;
+
+use std::path::{Component, Path};
 
 /// Parses the `import` function.
 pub struct ImportParser;
 
 #[derive(Debug, Clone)]
 pub struct ImportFunction {
-    variable_name: String,
+    /// The variable to import JSON into, or `None` if snippets are being imported from an HTML file.
+    variable_name: Option<String>,
     file_name: String,
 }
 
@@ -20,20 +23,33 @@ impl FunctionParser for ImportParser {
         "import"
     }
 
-    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
-        quiet_assert!(raw.positional_args.len() == 2)?;
-        quiet_assert!(raw.named_args.is_empty())?;
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::between(1, 2))
+    }
 
-        let variable_name = raw.positional_args[0]
-            .as_variable()
-            .ok_or(ParseError::InvalidArgument)?
-            .to_string();
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        let variable_name = match raw.positional_args.len() {
+            2 => Some(
+                raw.positional_args[0]
+                    .as_variable()
+                    .ok_or(ParseError::InvalidArgument)?
+                    .to_string(),
+            ),
+            _ => None,
+        };
 
-        let file_name = raw.positional_args[1]
+        let file_name = raw
+            .positional_args
+            .last()
+            .unwrap()
             .as_string()
             .ok_or(ParseError::InvalidArgument)?
             .to_string();
 
+        quiet_assert!(
+            variable_name.is_some() || (file_name.ends_with(".html") && !is_glob(&file_name))
+        )?;
+
         Ok(Box::new(ImportFunction {
             variable_name,
             file_name,
@@ -47,45 +63,149 @@ impl Function for ImportFunction {
     }
 
     fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
-        let self_token = scope.tokens.current().unwrap().clone();
+        let variable_name = match &self.variable_name {
+            Some(variable_name) => variable_name,
+            None => return self.import_snippets(scope),
+        };
+
+        let json = if is_glob(&self.file_name) {
+            self.import_glob(scope)
+        } else {
+            self.import_file(scope)?
+        };
+
+        if scope
+            .stack
+            .last()
+            .ok_or_else(|| scope.error(ProcessError::StackError))?
+            .get_variable(variable_name)
+            .is_some()
+        {
+            return Err(scope.error(ProcessError::VariableAlreadyExists(variable_name.clone())));
+        }
 
-        let file = scope
-            .processor
-            .input
-            .as_ref()
+        scope
+            .stack
+            .last_mut()
             .unwrap()
-            .get_at_path(&PathBuf::from(self.file_name.clone()))
-            .ok_or_else(|| self_token.traceback(ProcessError::NotFound(self.file_name.clone())))?;
+            .add_variable(variable_name, json);
 
-        if !file.is_file() {
-            return Err(self_token.traceback(ProcessError::NotFound(self.file_name.clone())));
-        }
+        Ok(())
+    }
 
-        let json = match file.parsed_contents() {
-            ParsedContents::Json(json) => Some(json.clone()),
-            _ => None,
-        }
-        .ok_or_else(|| {
-            self_token.traceback(ProcessError::InvalidDataType {
-                variable: "<file>".to_string(),
-                expected: "json".to_string(),
-                found: String::new(),
-            })
-        })?;
+    fn referenced_file(&self) -> Option<&str> {
+        Some(self.file_name.as_str()).filter(|file_name| !is_glob(file_name))
+    }
+}
+
+impl ImportFunction {
+    /// Imports the single JSON file given by the file name.
+    fn import_file(&self, scope: &Scope) -> Result<Value, TracebackError<ProcessError>> {
+        scope
+            .get_file_json(&self.file_name)
+            .cloned()
+            .map_err(|e| scope.error(e))
+    }
+
+    /// Imports the snippets defined in the HTML file given by the file name into the current stack frame.
+    fn import_snippets(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+
+        let tokens = scope
+            .get_file(&self.file_name)
+            .map_err(|e| scope.error(e))?
+            .parsed_contents()
+            .tokens()
+            .ok_or_else(|| {
+                scope.error(ProcessError::InvalidDataType {
+                    variable: "<file>".to_string(),
+                    expected: "html".to_string(),
+                    found: String::new(),
+                })
+            })?;
+
+        let snippets = super::snippet::collect_snippets(tokens, scope, &self_token)?.snippets;
 
         let frame = scope
             .stack
             .last_mut()
             .ok_or_else(|| self_token.traceback(ProcessError::StackError))?;
 
-        if frame.get_variable(&self.variable_name).is_some() {
-            return Err(self_token.traceback(ProcessError::VariableAlreadyExists(
-                self.variable_name.clone(),
-            )));
+        for (name, body) in snippets {
+            frame.add_snippet(name, body);
         }
 
-        frame.add_variable(self.variable_name.clone(), json);
-
         Ok(())
     }
+
+    /// Imports all the JSON files matching the glob pattern given by the file name as an array, ordered by path.
+    ///
+    /// If no files match, the array is empty.
+    fn import_glob(&self, scope: &Scope) -> Value {
+        let pattern = components(Path::new(&self.file_name));
+
+        let mut files = scope
+            .processor
+            .input
+            .iter()
+            .flat_map(|input| input.iter_files())
+            .filter(|(path, _)| glob_matches(&pattern, &components(path)))
+            .filter_map(|(path, file)| match file.parsed_contents() {
+                ParsedContents::Json(json) => Some((path, json.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        Value::Array(files.into_iter().map(|(_, json)| json).collect())
+    }
+}
+
+/// Returns `true` if the file name is a glob pattern rather than a path.
+fn is_glob(file_name: &str) -> bool {
+    file_name.contains(['*', '?'])
+}
+
+/// Splits a path into its normal components, ignoring `.` components.
+fn components(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns `true` if the path matches the glob pattern.
+///
+/// `**` matches any number of directories, `*` matches any characters within a file or directory name, and `?`
+///   matches a single character.
+fn glob_matches(pattern: &[String], path: &[String]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| glob_matches(rest, &path[skip..]))
+        }
+        Some((first, rest)) => match path.split_first() {
+            Some((name, path_rest)) => {
+                segment_matches(first.as_bytes(), name.as_bytes()) && glob_matches(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Returns `true` if the file or directory name matches the pattern segment.
+fn segment_matches(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            segment_matches(&pattern[1..], name)
+                || (!name.is_empty() && segment_matches(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => segment_matches(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => segment_matches(&pattern[1..], &name[1..]),
+        _ => false,
+    }
 }
@@ -1,7 +1,7 @@
-use crate::functions::{Function, FunctionParser};
+use crate::functions::{Function, FunctionParser, Signature};
 use crate::parse::{ParseError, RawFunction};
 use crate::process::{ProcessError, Scope};
-use crate::{quiet_assert, TracebackError};
+use crate::TracebackError;
 
 /// Parses the `insert` function.
 pub struct InsertParser;
@@ -16,10 +16,11 @@ impl FunctionParser for InsertParser {
         "insert"
     }
 
-    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
-        quiet_assert!(raw.positional_args.len() == 1)?;
-        quiet_assert!(raw.named_args.is_empty())?;
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(1))
+    }
 
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
         let string = raw.positional_args[0]
             .as_string()
             .ok_or(ParseError::InvalidArgument)?;
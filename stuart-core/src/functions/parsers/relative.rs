@@ -0,0 +1,104 @@
+use crate::functions::{Function, FunctionParser, Input, Signature};
+use crate::parse::{ParseError, RawArgument, RawFunction};
+use crate::process::{ProcessError, Scope};
+use crate::TracebackError;
+
+/// Parses the `relative` function.
+pub struct RelativeParser;
+
+#[derive(Debug, Clone)]
+pub struct RelativeFunction {
+    target: Input,
+}
+
+impl FunctionParser for RelativeParser {
+    fn name(&self) -> &'static str {
+        "relative"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(1))
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        let target = match &raw.positional_args[0] {
+            RawArgument::String(s) if s.starts_with('/') => Input::String(s.clone()),
+            RawArgument::Variable(v) => Input::Variable(v.clone()),
+            _ => return Err(ParseError::InvalidArgument),
+        };
+
+        Ok(Box::new(RelativeFunction { target }))
+    }
+}
+
+impl Function for RelativeFunction {
+    fn name(&self) -> &'static str {
+        "relative"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+
+        let target = match self.target.evaluate_variable(scope) {
+            Some(Input::String(s)) if s.starts_with('/') => s,
+            _ => {
+                return Err(self_token.traceback(ProcessError::InvalidDataType {
+                    variable: self.target.to_string(),
+                    expected: "absolute path".to_string(),
+                    found: String::new(),
+                }))
+            }
+        };
+
+        let page = scope
+            .get_variable("page.url")
+            .and_then(|url| url.as_str().map(|s| s.to_string()))
+            .ok_or_else(|| {
+                self_token.traceback(ProcessError::UndefinedVariable("page.url".to_string()))
+            })?;
+
+        scope
+            .output(relative_path(&page, &target))
+            .map_err(|e| self_token.traceback(e))?;
+
+        Ok(())
+    }
+}
+
+/// Computes the path of the absolute `target` relative to the page at the absolute URL `page`.
+fn relative_path(page: &str, target: &str) -> String {
+    // Everything after the last slash of the page is the file name, which does not affect the result.
+    let page_dir = &page[..page.rfind('/').unwrap_or(0)];
+    let page_segments = page_dir
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+
+    let (target_dir, target_file) = target.split_at(target.rfind('/').unwrap_or(0));
+    let target_segments = target_dir
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    let target_file = target_file.trim_start_matches('/');
+
+    let common = page_segments
+        .iter()
+        .zip(&target_segments)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut result = "../".repeat(page_segments.len() - common);
+
+    for segment in &target_segments[common..] {
+        result.push_str(segment);
+        result.push('/');
+    }
+
+    result.push_str(target_file);
+
+    if result.is_empty() {
+        "./".to_string()
+    } else {
+        result
+    }
+}
@@ -0,0 +1,61 @@
+use crate::functions::{Function, FunctionParser, Signature};
+use crate::parse::{ParseError, RawFunction};
+use crate::process::stack::StackFrame;
+use crate::process::{ProcessError, Scope};
+use crate::{quiet_assert, TracebackError};
+
+/// Parses the `set` function.
+pub struct SetParser;
+
+#[derive(Debug, Clone)]
+pub struct SetFunction {
+    variable_name: String,
+}
+
+impl FunctionParser for SetParser {
+    fn name(&self) -> &'static str {
+        "set"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(1))
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        let variable_name = raw.positional_args[0]
+            .as_variable()
+            .ok_or(ParseError::InvalidArgument)?;
+
+        // Variables are set as a whole, so properties of existing variables cannot be set.
+        quiet_assert!(!variable_name.contains('.'))?;
+
+        Ok(Box::new(SetFunction {
+            variable_name: variable_name.to_string(),
+        }))
+    }
+}
+
+impl Function for SetFunction {
+    fn name(&self) -> &'static str {
+        "set"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+        let body = scope
+            .tokens
+            .collect_until_end(self.name())
+            .map_err(|e| self_token.traceback(e))?;
+
+        let frame = StackFrame::new(format!("set:{}", self.variable_name));
+        let value = scope.evaluate_block(&body, frame, &self_token)?;
+
+        scope
+            .stack
+            .last_mut()
+            .ok_or_else(|| self_token.traceback(ProcessError::StackError))?
+            .set_variable(&self.variable_name, value);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use crate::functions::{Function, FunctionParser};
+use crate::parse::{LocatableToken, ParseError, RawArgument, RawFunction};
+use crate::process::iter::TokenIter;
+use crate::process::stack::StackFrame;
+use crate::process::{ProcessError, Scope};
+use crate::{quiet_assert, TracebackError};
+
+use humphrey_json::Value;
+
+/// The maximum number of snippet calls which can be nested within each other, which stops a snippet which calls
+///   itself from recursing forever.
+const MAX_SNIPPET_DEPTH: usize = 64;
+
+/// Parses the `snippet` function, as well as calls to snippets by name with `snippet::name`.
+pub struct SnippetParser;
+
+/// Defines a snippet with `begin(snippet, "name")`, collecting the tokens up to `end(snippet)` as its body.
+#[derive(Debug, Clone)]
+pub struct SnippetDefinitionFunction {
+    name: String,
+}
+
+/// Calls a snippet, either with `snippet("name", ...)` or with `snippet::name(...)`.
+#[derive(Debug, Clone)]
+pub struct SnippetFunction {
+    name: String,
+    args: Vec<(String, RawArgument)>,
+}
+
+impl FunctionParser for SnippetParser {
+    fn name(&self) -> &'static str {
+        "snippet"
+    }
+
+    fn can_parse(&self, raw: &RawFunction) -> bool {
+        raw.name == "snippet" || raw.name.starts_with("snippet::")
+    }
+
+    /// Parses a call to a snippet.
+    ///
+    /// This is also used for calls of the form `snippet::name`, which only have named arguments.
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        let name = match raw.name.strip_prefix("snippet::") {
+            Some(name) => {
+                quiet_assert!(raw.positional_args.is_empty())?;
+
+                name.to_string()
+            }
+            None => {
+                quiet_assert!(raw.positional_args.len() == 1)?;
+
+                raw.positional_args[0]
+                    .as_string()
+                    .ok_or(ParseError::InvalidArgument)?
+                    .to_string()
+            }
+        };
+
+        quiet_assert!(raw
+            .named_args
+            .iter()
+            .all(|(_, arg)| !matches!(arg, RawArgument::Ident(_))))?;
+
+        Ok(Box::new(SnippetFunction {
+            name,
+            args: raw.named_args,
+        }))
+    }
+}
+
+/// Parses a snippet definition of the form `begin(snippet, "name")`.
+pub(super) fn parse_definition(raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+    quiet_assert!(raw.positional_args.len() == 2)?;
+    quiet_assert!(raw.named_args.is_empty())?;
+
+    let name = raw.positional_args[1]
+        .as_string()
+        .ok_or(ParseError::InvalidArgument)?;
+
+    quiet_assert!(!name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_'))?;
+
+    Ok(Box::new(SnippetDefinitionFunction {
+        name: name.to_string(),
+    }))
+}
+
+impl Function for SnippetDefinitionFunction {
+    fn name(&self) -> &'static str {
+        "begin"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+
+        // The body is collected without being executed, so snippets cannot be nested.
+        let mut body = Vec::new();
+
+        loop {
+            let token = scope
+                .tokens
+                .next()
+                .ok_or_else(|| self_token.traceback(ProcessError::UnexpectedEndOfFile))?;
+
+            if token
+                .as_function()
+                .and_then(|f| f.ends_block().map(|label| label == "snippet"))
+                .unwrap_or(false)
+            {
+                break;
+            }
+
+            body.push(token.clone());
+        }
+
+        scope
+            .stack
+            .last_mut()
+            .ok_or_else(|| self_token.traceback(ProcessError::StackError))?
+            .add_snippet(&self.name, body.into());
+
+        Ok(())
+    }
+}
+
+impl Function for SnippetFunction {
+    fn name(&self) -> &'static str {
+        "snippet"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+
+        let body = scope
+            .stack
+            .iter()
+            .rev()
+            .find_map(|frame| frame.get_snippet(&self.name))
+            .cloned()
+            .ok_or_else(|| {
+                self_token.traceback(ProcessError::UndefinedSnippet(self.name.clone()))
+            })?;
+
+        let depth = scope
+            .stack
+            .iter()
+            .filter(|frame| frame.name.starts_with("snippet:"))
+            .count();
+
+        if depth >= MAX_SNIPPET_DEPTH {
+            return Err(self_token.traceback(ProcessError::SnippetTooDeep(self.name.clone())));
+        }
+
+        let mut frame = StackFrame::new(format!("snippet:{}", self.name));
+
+        for (name, arg) in &self.args {
+            let value = match arg {
+                RawArgument::Variable(variable) => {
+                    scope.get_variable(variable).ok_or_else(|| {
+                        self_token.traceback(ProcessError::UndefinedVariable(variable.clone()))
+                    })?
+                }
+                RawArgument::String(s) => Value::String(s.clone()),
+                RawArgument::Integer(i) => Value::Number(*i as f64),
+                RawArgument::Bool(b) => Value::Bool(*b),
+                RawArgument::Ident(_) => unreachable!(),
+            };
+
+            frame.add_variable(name, value);
+        }
+
+        // The body is run with its own token iterator, but shares the stack and sections of the calling file.
+        scope.process_block(&body, frame, &self_token)
+    }
+}
+
+/// Executes the given tokens, such as those of a shared snippet file, in a separate stack frame and returns it.
+///
+/// The snippets defined by the tokens are available in the returned frame, and any output is discarded.
+pub(super) fn collect_snippets(
+    tokens: &[LocatableToken],
+    scope: &mut Scope,
+    self_token: &LocatableToken,
+) -> Result<StackFrame, TracebackError<ProcessError>> {
+    let stack_height = scope.stack.len();
+    scope.stack.push(StackFrame::new("snippets"));
+
+    let mut sections = Vec::new();
+    let mut token_iter = TokenIter::new(tokens);
+    let mut file_scope = Scope {
+        tokens: &mut token_iter,
+        stack: &mut *scope.stack,
+        processor: scope.processor,
+        sections: &mut sections,
+    };
+
+    while let Some(token) = file_scope.tokens.next() {
+        token.process(&mut file_scope)?;
+    }
+
+    scope
+        .stack
+        .pop()
+        .filter(|_| scope.stack.len() == stack_height)
+        .ok_or_else(|| self_token.traceback(ProcessError::UnexpectedEndOfFile))
+}
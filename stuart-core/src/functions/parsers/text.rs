@@ -0,0 +1,67 @@
+use crate::functions::{Function, FunctionParser, Signature};
+use crate::parse::{ParseError, RawFunction};
+use crate::process::{ProcessError, Scope};
+use crate::TracebackError;
+
+use humphrey_json::Value;
+
+/// Parses the `text` function.
+pub struct TextParser;
+
+#[derive(Debug, Clone)]
+pub struct TextFunction {
+    variable_name: String,
+}
+
+impl FunctionParser for TextParser {
+    fn name(&self) -> &'static str {
+        "text"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(1))
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        let variable_name = raw.positional_args[0]
+            .as_variable()
+            .ok_or(ParseError::InvalidArgument)?;
+
+        Ok(Box::new(TextFunction {
+            variable_name: variable_name.to_string(),
+        }))
+    }
+}
+
+impl Function for TextFunction {
+    fn name(&self) -> &'static str {
+        "text"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+
+        let variable = scope.get_variable(&self.variable_name).ok_or_else(|| {
+            self_token.traceback(ProcessError::UndefinedVariable(self.variable_name.clone()))
+        })?;
+
+        let text = match variable {
+            Value::String(s) => s,
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => {
+                return Err(self_token.traceback(ProcessError::InvalidDataType {
+                    variable: self.variable_name.clone(),
+                    expected: "string".to_string(),
+                    found: String::new(),
+                }))
+            }
+        };
+
+        scope
+            .output_escaped(text)
+            .map_err(|e| self_token.traceback(e))?;
+
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
-use crate::functions::{Function, FunctionParser};
+use crate::functions::{Function, FunctionParser, Signature};
 use crate::parse::{ParseError, RawFunction};
 use crate::process::{ProcessError, Scope};
-use crate::{quiet_assert, TracebackError};
+use crate::TracebackError;
 
 static WORDS_PER_MINUTE: usize = 200;
 
@@ -18,10 +18,11 @@ impl FunctionParser for TimeToReadParser {
         "timetoread"
     }
 
-    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
-        quiet_assert!(raw.positional_args.len() == 1)?;
-        quiet_assert!(raw.named_args.is_empty())?;
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(1))
+    }
 
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
         let variable_name = raw.positional_args[0]
             .as_variable()
             .ok_or(ParseError::InvalidArgument)?;
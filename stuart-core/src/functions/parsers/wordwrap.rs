@@ -0,0 +1,109 @@
+use crate::functions::{Function, FunctionParser, Signature};
+use crate::parse::{ParseError, RawArgument, RawFunction};
+use crate::process::{ProcessError, Scope};
+use crate::{quiet_assert, TracebackError};
+
+use humphrey_json::Value;
+
+/// Parses the `wordwrap` function.
+pub struct WordWrapParser;
+
+#[derive(Debug, Clone)]
+pub struct WordWrapFunction {
+    input: RawArgument,
+    width: usize,
+}
+
+impl FunctionParser for WordWrapParser {
+    fn name(&self) -> &'static str {
+        "wordwrap"
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        Some(Signature::exactly(2))
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        let input = raw.positional_args[0].clone();
+        quiet_assert!(matches!(
+            input,
+            RawArgument::Variable(_) | RawArgument::String(_)
+        ))?;
+
+        let width = match raw.positional_args[1] {
+            RawArgument::Integer(width) if width > 0 => width as usize,
+            _ => return Err(ParseError::InvalidArgument),
+        };
+
+        Ok(Box::new(WordWrapFunction { input, width }))
+    }
+}
+
+impl Function for WordWrapFunction {
+    fn name(&self) -> &'static str {
+        "wordwrap"
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+
+        let text = match &self.input {
+            RawArgument::String(s) => s.clone(),
+            RawArgument::Variable(variable_name) => {
+                let variable = scope.get_variable(variable_name).ok_or_else(|| {
+                    self_token.traceback(ProcessError::UndefinedVariable(variable_name.clone()))
+                })?;
+
+                match variable {
+                    Value::String(s) => s,
+                    _ => {
+                        return Err(self_token.traceback(ProcessError::InvalidDataType {
+                            variable: variable_name.clone(),
+                            expected: "string".to_string(),
+                            found: String::new(),
+                        }))
+                    }
+                }
+            }
+            _ => unreachable!(),
+        };
+
+        scope
+            .output(wrap(&text, self.width))
+            .map_err(|e| self_token.traceback(e))?;
+
+        Ok(())
+    }
+}
+
+/// Wraps each line of the text so that it is at most `width` characters long, breaking only at spaces.
+///
+/// Existing line breaks are kept, and words longer than the width are placed on their own line unbroken.
+fn wrap(text: &str, width: usize) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            result.push('\n');
+        }
+
+        let mut line_length = 0;
+
+        for word in line.split(' ').filter(|word| !word.is_empty()) {
+            let word_length = word.chars().count();
+
+            if line_length > 0 && line_length + 1 + word_length > width {
+                result.push('\n');
+                line_length = 0;
+            } else if line_length > 0 {
+                result.push(' ');
+                line_length += 1;
+            }
+
+            result.push_str(word);
+            line_length += word_length;
+        }
+    }
+
+    result
+}
@@ -9,6 +9,13 @@ pub mod fs;
 pub mod parse;
 pub mod plugins;
 pub mod process;
+pub mod validate;
+
+mod order;
+mod search;
+
+#[cfg(feature = "lint")]
+pub mod lint;
 
 #[macro_use]
 pub mod functions;
@@ -20,33 +27,64 @@ pub use config::Config;
 pub use error::{Error, TracebackError};
 pub use fs::Node;
 
+use crate::error::FsError;
 use crate::fs::ParsedContents;
-use crate::parse::LocatableToken;
+use crate::parse::{parse_html_with_delimiters, LocatableToken};
 use crate::plugins::Manager;
 use crate::process::stack::StackFrame;
+use crate::process::{process_tokens, ProcessError};
 
 use humphrey_json::{prelude::*, Value};
 
+use std::collections::HashMap;
 use std::fmt::Debug;
-use std::path::{Path, PathBuf};
+use std::fs::{create_dir, create_dir_all, remove_dir_all, write};
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// The version of Stuart's core library.
+///
+/// Plugins compiled against a different version may not be compatible.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 define_functions![
+    functions::parsers::Add,
+    functions::parsers::Adjacent,
     functions::parsers::Begin,
+    functions::parsers::Capture,
+    functions::parsers::DataUri,
     functions::parsers::DateFormat,
+    functions::parsers::Debug,
     functions::parsers::Else,
     functions::parsers::End,
     functions::parsers::Excerpt,
     functions::parsers::For,
+    functions::parsers::Frontmatter,
+    functions::parsers::Get,
+    functions::parsers::If,
     functions::parsers::IfDefined,
+    functions::parsers::IfEnv,
+    functions::parsers::IfSection,
+    functions::parsers::IfTrue,
+    functions::parsers::IfFalse,
     functions::parsers::Import,
     functions::parsers::Insert,
+    functions::parsers::Relative,
+    functions::parsers::Set,
+    functions::parsers::Snippet,
+    functions::parsers::Text,
     functions::parsers::TimeToRead,
+    functions::parsers::WordWrap,
     functions::parsers::IfEq,
     functions::parsers::IfNe,
     functions::parsers::IfGt,
     functions::parsers::IfGe,
     functions::parsers::IfLt,
     functions::parsers::IfLe,
+    functions::parsers::Sum,
+    functions::parsers::Min,
+    functions::parsers::Max,
 ];
 
 /// The project builder.
@@ -55,6 +93,8 @@ pub struct Stuart {
     pub dir: PathBuf,
     /// The input virtual filesystem tree.
     pub input: Option<Node>,
+    /// Virtual filesystem trees overlaid onto the input directory in order when it is loaded.
+    pub input_overlays: Vec<Node>,
     /// The output virtual filesystem tree.
     pub output: Option<Node>,
     /// The configuration of the project.
@@ -63,6 +103,61 @@ pub struct Stuart {
     pub base: Option<StackFrame>,
     /// The plugins to be used by Stuart.
     pub plugins: Option<Box<dyn Manager>>,
+    /// Data shared between plugins during the build.
+    ///
+    /// This is reset at the start of every build, and can be accessed with [`Stuart::get_plugin_data`]
+    ///   and [`Stuart::set_plugin_data`].
+    pub plugin_data: Arc<RwLock<HashMap<String, Value>>>,
+    /// The build lifecycle hooks, called in the order they were added.
+    pub hooks: Vec<Box<dyn StuartBuildHook>>,
+}
+
+/// Provides callbacks at points in the build lifecycle, allowing library users to integrate Stuart
+///   into larger applications.
+///
+/// All methods have default no-op implementations, so only the relevant ones need to be implemented.
+pub trait StuartBuildHook {
+    /// Called at the start of the build, once the input has been loaded and preprocessed.
+    fn on_build_start(&self, _input: &Node) {}
+
+    /// Called once the build has completed successfully, before anything is saved.
+    fn on_build_complete(&self, _output: &Node) {}
+
+    /// Called after each input file has been processed into an output file.
+    fn on_file_processed(&self, _input: &Node, _output: &Node) {}
+
+    /// Transforms the HTML rendered from a markdown file, before it is wrapped in the markdown and root templates.
+    ///
+    /// This is called with the source path of the markdown file, and can be used to rewrite the HTML, for example
+    ///   to lazy-load images. When several hooks are added, each is given the result of the previous one.
+    fn transform_markdown(&self, _source: &Path, html: String) -> String {
+        html
+    }
+
+    /// Renames an output file as it is saved, given the source path of the file and its current name.
+    ///
+    /// This applies to every saved file, including static files merged into the output, so it can be used to
+    ///   change extensions, for example from `.scss` to `.css`. When several hooks are added, each is given the
+    ///   result of the previous one.
+    fn rename_output(&self, _source: &Path, name: String) -> String {
+        name
+    }
+}
+
+/// An event emitted during a streaming build by [`Stuart::build_streaming`].
+///
+/// Events are emitted in depth-first order, so every file belongs to the most recently entered directory
+///   which has not yet been exited.
+#[derive(Clone, Debug)]
+pub enum OutputEvent {
+    /// A file has been processed and saved, with the given name and contents.
+    ///
+    /// The contents of files larger than `stream_threshold` which are copied from their source are empty.
+    File(String, Vec<u8>),
+    /// A directory with the given name has been entered.
+    EnterDirectory(String),
+    /// The current directory has been exited.
+    ExitDirectory,
 }
 
 /// The environment of the build.
@@ -74,6 +169,21 @@ pub struct Environment<'a> {
     pub root: Option<&'a [LocatableToken]>,
     /// The root markdown HTML file.
     pub md: Option<&'a [LocatableToken]>,
+    /// The template used to generate index pages for directories of markdown files.
+    pub md_index: Option<&'a [LocatableToken]>,
+}
+
+/// A builder for an [`Environment`], where any fields which are not set are left empty.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct EnvironmentBuilder<'a> {
+    /// The environment variables.
+    vars: &'a [(String, String)],
+    /// The root HTML file.
+    root: Option<&'a [LocatableToken]>,
+    /// The root markdown HTML file.
+    md: Option<&'a [LocatableToken]>,
+    /// The template used to generate index pages for directories of markdown files.
+    md_index: Option<&'a [LocatableToken]>,
 }
 
 impl Stuart {
@@ -82,34 +192,42 @@ impl Stuart {
         Self {
             dir: dir.as_ref().to_path_buf(),
             input: None,
+            input_overlays: Vec::new(),
             output: None,
             config: Config::default(),
             base: None,
             plugins: None,
+            plugin_data: Arc::new(RwLock::new(HashMap::new())),
+            hooks: Vec::new(),
         }
     }
 
     /// Creates a new builder from a virtual filesystem tree. (for tests)
-    pub fn new_from_node(mut node: Node) -> Self {
-        let mut stuart = Self {
+    pub fn new_from_node(node: Node) -> Self {
+        let stuart = Self {
             dir: node.source().to_path_buf(),
-            input: Some(node.clone()),
+            input: Some(node),
+            input_overlays: Vec::new(),
             output: None,
             config: Config::default(),
             base: Some(StackFrame::new("base")),
             plugins: None,
+            plugin_data: Arc::new(RwLock::new(HashMap::new())),
+            hooks: Vec::new(),
         };
 
-        stuart.preprocess_markdown_node(&mut node).unwrap();
-
-        stuart.input = Some(node);
+        stuart
+            .preprocess_markdown_node(stuart.input.as_ref().unwrap())
+            .unwrap();
 
         stuart
     }
 
     /// Sets the configuration to use.
+    ///
+    /// The defaults of any optional settings which are not set are filled in with [`Config::resolve`].
     pub fn with_config(mut self, config: Config) -> Self {
-        self.config = config;
+        self.config = config.resolve();
         self
     }
 
@@ -122,18 +240,137 @@ impl Stuart {
         self
     }
 
+    /// Adds a build lifecycle hook.
+    pub fn with_hook<H>(mut self, hook: H) -> Self
+    where
+        H: StuartBuildHook + 'static,
+    {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
     /// Attempts to build the project.
     pub fn build(&mut self, stuart_env: String) -> Result<(), Error> {
-        let mut input = match self.plugins {
-            Some(ref plugins) => Node::new_with_plugins(&self.dir, true, plugins.as_ref())?,
-            None => Node::new(&self.dir, true)?,
+        let vars = self.prepare(stuart_env)?;
+
+        let env = Environment::builder()
+            .with_vars(&vars)
+            .build()
+            .update_from_children(
+                self.input.as_ref().unwrap().children().unwrap(),
+                &self.config,
+            );
+
+        for hook in &self.hooks {
+            hook.on_build_start(self.input.as_ref().unwrap());
+        }
+
+        let mut output = if self.config.dependency_order() {
+            self.build_node_ordered(self.input.as_ref().unwrap(), env)?
+        } else {
+            self.build_node(self.input.as_ref().unwrap(), env)?
+        };
+
+        output.apply_config(&self.config);
+
+        for hook in &self.hooks {
+            hook.on_build_complete(&output);
+        }
+
+        self.output = Some(output);
+
+        Ok(())
+    }
+
+    /// Attempts to build the project, saving each file to the given directory as soon as it is processed.
+    ///
+    /// Unlike [`Stuart::build`], the output is never held in memory as a whole, which greatly reduces memory usage
+    ///   for large sites. As a result, [`Stuart::output`] is left empty, directory listings are not generated, and
+    ///   the `on_build_complete` hook is not called. The callback is called with an event for each directory and
+    ///   file in the output. If `search_index` is configured, the search index is saved once every file has been
+    ///   built, without an event.
+    pub fn build_streaming(
+        &mut self,
+        stuart_env: String,
+        path: impl AsRef<Path>,
+        callback: &mut dyn FnMut(OutputEvent),
+    ) -> Result<(), Error> {
+        let vars = self.prepare(stuart_env)?;
+        self.output = None;
+
+        let input = self.input.as_ref().unwrap();
+        let env = Environment::builder()
+            .with_vars(&vars)
+            .build()
+            .update_from_children(input.children().unwrap(), &self.config);
+
+        for hook in &self.hooks {
+            hook.on_build_start(input);
+        }
+
+        fs::create_output_dir(path.as_ref())?;
+
+        self.build_children_streaming(input, env, path.as_ref(), callback)?;
+        self.save_search_index(path.as_ref())
+    }
+
+    /// Attempts to build a single file or directory of the project, given by its path relative to the input directory.
+    ///
+    /// The whole project is still loaded so that templates and data can be resolved as usual, but only the given
+    ///   path is processed, so the output contains just that path and its parent directories.
+    /// The output should be saved with [`Stuart::save_merged`] to avoid removing the rest of the previous build.
+    pub fn build_only(&mut self, stuart_env: String, path: impl AsRef<Path>) -> Result<(), Error> {
+        let vars = self.prepare(stuart_env)?;
+
+        let env = Environment::builder().with_vars(&vars).build();
+
+        let not_found = || {
+            Error::Fs(FsError::NotFound(
+                path.as_ref().to_string_lossy().to_string(),
+            ))
         };
 
-        // This needs some explaining...
-        // We have to clone the input node here so that we can have an immutable copy in case
-        // something tries to change it during the markdown preprocessing stage.
-        // I hate this as much as you, TODO: come up with a better solution.
-        self.input = Some(input.clone());
+        // Like `Node::get_at_path`, paths which could refer to something outside the input directory are rejected.
+        let components = path
+            .as_ref()
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(Ok(name.to_string_lossy().to_string())),
+                Component::CurDir => None,
+                _ => Some(Err(not_found())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for hook in &self.hooks {
+            hook.on_build_start(self.input.as_ref().unwrap());
+        }
+
+        let mut output = self
+            .build_path(self.input.as_ref().unwrap(), &components, env)
+            .map_err(|e| match e {
+                Error::Fs(FsError::NotFound(_)) => not_found(),
+                e => e,
+            })?;
+
+        output.apply_config(&self.config);
+
+        for hook in &self.hooks {
+            hook.on_build_complete(&output);
+        }
+
+        self.output = Some(output);
+
+        Ok(())
+    }
+
+    /// Loads and preprocesses the input ready for building, returning the environment variables for the build.
+    fn prepare(&mut self, stuart_env: String) -> Result<Vec<(String, String)>, Error> {
+        self.config
+            .delimiters
+            .validate()
+            .map_err(|_| Error::InvalidConfig("invalid template delimiters".to_string()))?;
+
+        self.input = Some(self.load_input()?);
 
         let vars = {
             let mut env = std::env::vars().collect::<Vec<_>>();
@@ -152,19 +389,144 @@ impl Stuart {
 
         self.base = Some(base);
 
-        self.preprocess_markdown_node(&mut input)?;
-        self.input = Some(input);
+        {
+            let mut plugin_data = self.plugin_data.write().unwrap();
+            plugin_data.clear();
 
-        let env = Environment {
-            vars: &vars,
-            md: None,
-            root: None,
+            if let Some(plugins) = &self.plugins {
+                plugins.on_build_start(&mut plugin_data);
+            }
         }
-        .update_from_children(self.input.as_ref().unwrap().children().unwrap());
 
-        self.output = Some(self.build_node(self.input.as_ref().unwrap(), env)?);
+        self.preprocess_markdown_node(self.input.as_ref().unwrap())?;
 
-        Ok(())
+        Ok(vars)
+    }
+
+    /// Checks the input for problems without building it, returning warnings about any non-fatal problems.
+    ///
+    /// Every file is parsed, so unknown functions and other syntax errors are returned as errors, as are missing
+    ///   root and markdown templates. Functions which reference missing input files, such as `import`, produce
+    ///   warnings since they only cause the build to fail if they are reached.
+    pub fn validate(&self) -> Result<Vec<validate::Warning>, Error> {
+        self.config
+            .delimiters
+            .validate()
+            .map_err(|_| Error::InvalidConfig("invalid template delimiters".to_string()))?;
+
+        let parsed;
+        let input = match &self.input {
+            Some(input) => input,
+            None => {
+                parsed = self.load_input()?;
+                &parsed
+            }
+        };
+
+        let mut warnings = Vec::new();
+        validate::validate_input(input, &self.config, &mut warnings)?;
+
+        Ok(warnings)
+    }
+
+    /// Loads and parses the input directory, overlaying any trees added with [`Stuart::merge_input`].
+    ///
+    /// This does not build anything, so can be used to inspect the input, for example with [`Node::to_tree_json`].
+    pub fn load_input(&self) -> Result<Node, Error> {
+        let mut input =
+            Node::new_with_options(&self.dir, true, self.plugins.as_deref(), &self.config)?;
+
+        for overlay in &self.input_overlays {
+            input.overlay(overlay.clone());
+        }
+
+        Ok(input)
+    }
+
+    /// Renders a template string with the given context, without loading or building the project.
+    ///
+    /// The context must be a JSON object, whose fields are available to the template as variables along with those
+    ///   of the base stack frame, if one has been set. Functions which read input files, such as `import` or `for`
+    ///   over a markdown directory, can only find them if the input has been loaded. Since there is no root
+    ///   template, any sections are discarded.
+    pub fn render_str(&self, template: &str, context: Value) -> Result<String, Error> {
+        self.config
+            .delimiters
+            .validate()
+            .map_err(|_| Error::InvalidConfig("invalid template delimiters".to_string()))?;
+
+        let path = Path::new("<template>");
+        let error = |kind| {
+            Error::Process(TracebackError {
+                path: path.to_path_buf(),
+                line: 0,
+                column: 0,
+                kind,
+            })
+        };
+
+        let tokens = parse_html_with_delimiters(
+            template,
+            path,
+            self.plugins.as_deref(),
+            &self.config.delimiters,
+            self.config.column_mode(),
+        )
+        .map_err(Error::Parse)?;
+
+        let variables = match context {
+            Value::Object(variables) => variables,
+            _ => {
+                return Err(error(ProcessError::InvalidDataType {
+                    variable: "<context>".to_string(),
+                    expected: "object".to_string(),
+                    found: String::new(),
+                }))
+            }
+        };
+
+        let mut base = self.base.clone().unwrap_or_else(|| StackFrame::new("base"));
+
+        for (name, value) in variables {
+            base.add_variable(name, value);
+        }
+
+        let frame = process_tokens(&tokens, base, self, path).map_err(Error::Process)?;
+
+        String::from_utf8(frame.output).map_err(|_| error(ProcessError::StackError))
+    }
+
+    /// Returns the names of the functions available to templates, with the built-in functions first.
+    ///
+    /// Plugin functions are given with the plugin name as a prefix, such as `plugin_name::function_name`.
+    pub fn function_names(&self) -> Vec<String> {
+        let builtin = FUNCTION_PARSERS.iter().map(|f| f.name().to_string());
+
+        let plugins = self.plugins.iter().flat_map(|plugins| {
+            plugins.plugins().iter().flat_map(|plugin| {
+                plugin
+                    .functions
+                    .iter()
+                    .map(move |f| format!("{}::{}", plugin.name, f.name()))
+            })
+        });
+
+        builtin.chain(plugins).collect()
+    }
+
+    /// Gets a value from the data shared between plugins.
+    pub fn get_plugin_data(&self, key: &str) -> Option<Value> {
+        self.plugin_data.read().unwrap().get(key).cloned()
+    }
+
+    /// Sets a value in the data shared between plugins, returning the previous value if there was one.
+    ///
+    /// This only requires an immutable reference to the builder, so it can be called by functions during the build.
+    pub fn set_plugin_data(&self, key: impl AsRef<str>, value: Value) -> Option<Value> {
+        self.plugin_data
+            .write()
+            .unwrap()
+            .insert(key.as_ref().to_string(), value)
     }
 
     /// Merges an output node with the built result.
@@ -177,18 +539,101 @@ impl Stuart {
             .and_then(|out| out.merge(node))
     }
 
+    /// Overlays an input tree onto the input directory for all subsequent builds.
+    ///
+    /// Files in the given tree replace files of the same path in the input directory, allowing a project to
+    ///   override parts of a shared base, such as a theme. Trees are overlaid in the order they were added.
+    pub fn merge_input(&mut self, node: Node) {
+        self.input_overlays.push(node);
+    }
+
     /// Saves the build output to a directory.
+    ///
+    /// If `search_index` is configured, the search index is also saved at its path in the directory.
     pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
         if let Some(out) = &self.output {
-            out.save(&path, &self.config)
+            out.save_with_hooks(&path, &self.config, &self.hooks)?;
+            self.save_search_index(path.as_ref())
+        } else {
+            Err(Error::NotBuilt)
+        }
+    }
+
+    /// Saves the search index at its path in the given directory, if `search_index` is configured.
+    fn save_search_index(&self, path: &Path) -> Result<(), Error> {
+        if let Some(search_index) = &self.config.search_index {
+            let index_path = path.join(search_index);
+
+            if let Some(parent) = index_path.parent() {
+                create_dir_all(parent).map_err(|_| Error::Fs(FsError::Write))?;
+            }
+
+            write(index_path, self.search_index()?.serialize())
+                .map_err(|_| Error::Fs(FsError::Write))?;
+        }
+
+        Ok(())
+    }
+
+    /// Generates a search index of the markdown pages in the input, for use by client-side search.
+    ///
+    /// This is a JSON array with the `title`, `url` and plain-text `body` of each page.
+    pub fn search_index(&self) -> Result<Value, Error> {
+        let input = self.input.as_ref().ok_or(Error::NotBuilt)?;
+
+        Ok(search::search_index(input, self))
+    }
+
+    /// Lints the HTML files in the build output, returning warnings about problems such as unclosed tags
+    ///   and duplicate `id` attributes.
+    #[cfg(feature = "lint")]
+    pub fn lint_output(&self) -> Result<Vec<validate::Warning>, Error> {
+        let out = self.output.as_ref().ok_or(Error::NotBuilt)?;
+
+        let mut warnings = Vec::new();
+        lint::lint_node(out, &self.config, &mut warnings);
+
+        Ok(warnings)
+    }
+
+    /// Saves the build output into a directory without removing its existing contents.
+    ///
+    /// This is used to save the output of [`Stuart::build_only`] over the output of a previous full build.
+    ///   If `search_index` is configured, the search index is also saved, since the built page may have changed.
+    pub fn save_merged(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        if let Some(out) = &self.output {
+            out.save_merged_with_hooks(&path, &self.config, &self.hooks)?;
+            self.save_search_index(path.as_ref())
         } else {
             Err(Error::NotBuilt)
         }
     }
 
+    /// Removes a previous build output from the given directory.
+    ///
+    /// To guard against accidentally deleting source files, this refuses to remove the input directory,
+    ///   any directory containing it, any directory inside it, or anything which is not a directory.
+    ///   If the output directory does not exist, this does nothing.
+    pub fn clean_output(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let output = path.canonicalize().map_err(|_| Error::Fs(FsError::Read))?;
+        let input = self.dir.canonicalize().unwrap_or_else(|_| self.dir.clone());
+
+        if !output.is_dir() || input.starts_with(&output) || output.starts_with(&input) {
+            return Err(Error::Fs(FsError::UnsafeRemove(path.to_path_buf())));
+        }
+
+        remove_dir_all(&output).map_err(|_| Error::Fs(FsError::Write))
+    }
+
     /// Saves the build metadata to a file.
     pub fn save_metadata(&self, path: impl AsRef<Path>) -> Result<(), Error> {
-        if !self.config.save_metadata {
+        if !self.config.save_metadata() {
             return Err(Error::MetadataNotEnabled);
         }
 
@@ -212,28 +657,294 @@ impl Stuart {
                 children,
                 source,
             } => {
-                let env = env.update_from_children(children);
-                let children = children
+                let env = env.update_from_children(children, &self.config);
+                let mut output_children = children
                     .iter()
                     .map(|n| self.build_node(n, env))
                     .collect::<Result<Vec<_>, Error>>()?;
 
+                if let Some(index) = self.build_markdown_index(source, children, env)? {
+                    output_children.push(index);
+                }
+
+                Ok(Node::Directory {
+                    name: name.clone(),
+                    children: output_children,
+                    source: source.clone(),
+                })
+            }
+            Node::File { .. } => {
+                let output = node.process(self, env)?;
+
+                for hook in &self.hooks {
+                    hook.on_file_processed(node, &output);
+                }
+
+                Ok(output)
+            }
+        }
+    }
+
+    /// Builds an input node and its descendants like [`Stuart::build_node`], but processes every file after the
+    ///   files it references.
+    fn build_node_ordered(&self, node: &Node, env: Environment) -> Result<Node, Error> {
+        let mut outputs = HashMap::new();
+
+        for path in order::dependency_order(node)? {
+            let mut dir = node;
+            let mut file_env = env.update_from_children(node.children().unwrap(), &self.config);
+
+            for component in path.parent().unwrap().components() {
+                dir = dir.get_at_path(Path::new(&component)).unwrap();
+                file_env = file_env.update_from_children(dir.children().unwrap(), &self.config);
+            }
+
+            let file = node.get_at_path(&path).unwrap();
+            let output = file.process(self, file_env)?;
+
+            for hook in &self.hooks {
+                hook.on_file_processed(file, &output);
+            }
+
+            outputs.insert(path, output);
+        }
+
+        self.assemble_output(node, Path::new(""), env, &mut outputs)
+    }
+
+    /// Recursively assembles the already-processed output files below an input node into an output node.
+    fn assemble_output(
+        &self,
+        node: &Node,
+        path: &Path,
+        env: Environment,
+        outputs: &mut HashMap<PathBuf, Node>,
+    ) -> Result<Node, Error> {
+        match node {
+            Node::Directory {
+                name,
+                children,
+                source,
+            } => {
+                let env = env.update_from_children(children, &self.config);
+                let mut output_children = children
+                    .iter()
+                    .map(|n| self.assemble_output(n, &path.join(n.name()), env, outputs))
+                    .collect::<Result<Vec<_>, Error>>()?;
+
+                if let Some(index) = self.build_markdown_index(source, children, env)? {
+                    output_children.push(index);
+                }
+
+                Ok(Node::Directory {
+                    name: name.clone(),
+                    children: output_children,
+                    source: source.clone(),
+                })
+            }
+            Node::File { .. } => Ok(outputs.remove(path).unwrap()),
+        }
+    }
+
+    /// Recursively builds an input node and its descendants, saving each output file into the given directory.
+    fn build_node_streaming(
+        &self,
+        node: &Node,
+        env: Environment,
+        save_path: &Path,
+        callback: &mut dyn FnMut(OutputEvent),
+    ) -> Result<(), Error> {
+        match node {
+            Node::Directory { name, children, .. } => {
+                // Nothing in the data directory is ever saved.
+                if !node.is_output(&self.config) {
+                    return Ok(());
+                }
+
+                let dir = save_path.join(name);
+
+                // The directory may already exist if a file of the same name had its extension stripped.
+                match create_dir(&dir) {
+                    Ok(_) => (),
+                    Err(e) if e.kind() == ErrorKind::AlreadyExists => (),
+                    Err(_) => return Err(Error::Fs(FsError::Write)),
+                };
+
+                callback(OutputEvent::EnterDirectory(name.clone()));
+
+                let env = env.update_from_children(children, &self.config);
+                self.build_children_streaming(node, env, &dir, callback)?;
+
+                callback(OutputEvent::ExitDirectory);
+            }
+            Node::File { .. } => {
+                let output = node.process(self, env)?;
+
+                for hook in &self.hooks {
+                    hook.on_file_processed(node, &output);
+                }
+
+                self.save_streamed(output, save_path, callback)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds the children of a directory node, including its markdown index, saving them into the given directory.
+    fn build_children_streaming(
+        &self,
+        node: &Node,
+        env: Environment,
+        save_path: &Path,
+        callback: &mut dyn FnMut(OutputEvent),
+    ) -> Result<(), Error> {
+        let children = node.children().unwrap();
+
+        for child in children {
+            self.build_node_streaming(child, env, save_path, callback)?;
+        }
+
+        if let Some(index) = self.build_markdown_index(node.source(), children, env)? {
+            self.save_streamed(index, save_path, callback)?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves a processed output file into the given directory and emits an event for it, unless it is not part of
+    ///   the output, such as a template or a data file, in which case it is skipped like [`Node::apply_config`] would.
+    fn save_streamed(
+        &self,
+        output: Node,
+        save_path: &Path,
+        callback: &mut dyn FnMut(OutputEvent),
+    ) -> Result<(), Error> {
+        if !output.is_output(&self.config) {
+            return Ok(());
+        }
+
+        output.save_recur(save_path, &self.config, &self.hooks)?;
+
+        let saved_name = output.output_name(&self.hooks);
+
+        if let Node::File { contents, .. } = output {
+            callback(OutputEvent::File(saved_name, contents));
+        }
+
+        Ok(())
+    }
+
+    /// Builds only the node at the given path below the given node, keeping the directories along the path
+    ///   so that templates are resolved in the same way as a full build.
+    fn build_path(&self, node: &Node, path: &[String], env: Environment) -> Result<Node, Error> {
+        let (first, rest) = match path.split_first() {
+            Some(parts) => parts,
+            None => return self.build_node(node, env),
+        };
+
+        match node {
+            Node::Directory {
+                name,
+                children,
+                source,
+            } => {
+                let env = env.update_from_children(children, &self.config);
+
+                let child = children
+                    .iter()
+                    .find(|child| child.name() == first)
+                    .ok_or_else(|| Error::Fs(FsError::NotFound(first.clone())))?;
+
                 Ok(Node::Directory {
                     name: name.clone(),
-                    children,
+                    children: vec![self.build_path(child, rest, env)?],
                     source: source.clone(),
                 })
             }
-            Node::File { .. } => node.process(self, env),
+            Node::File { .. } => Err(Error::Fs(FsError::NotFound(first.clone()))),
         }
     }
 
+    /// Generates an index page for a directory containing markdown files but no index page of its own,
+    ///   if a markdown index template is configured and available in the environment.
+    ///
+    /// The template is processed like an HTML page, with the directory's markdown pages available as `$pages`.
+    fn build_markdown_index(
+        &self,
+        source: &Path,
+        children: &[Node],
+        env: Environment,
+    ) -> Result<Option<Node>, Error> {
+        let template = match env.md_index {
+            Some(template) => template,
+            None => return Ok(None),
+        };
+
+        if children
+            .iter()
+            .any(|child| child.name() == "index.html" || child.name() == "index.md")
+        {
+            return Ok(None);
+        }
+
+        let mut pages = children
+            .iter()
+            .filter_map(|child| {
+                child
+                    .parsed_contents()
+                    .markdown()
+                    .map(|md| (child.name(), md))
+            })
+            .collect::<Vec<_>>();
+
+        if pages.is_empty() {
+            return Ok(None);
+        }
+
+        pages.sort_by_key(|(name, _)| *name);
+
+        let pages = pages
+            .into_iter()
+            .map(|(name, md)| {
+                let stem = name.strip_suffix(".md").unwrap();
+                let mut page = md.frontmatter_to_value();
+                page["name"] = Value::String(stem.to_string());
+                page["url"] = Value::String(if self.config.strip_extensions() {
+                    format!("{}/", stem)
+                } else {
+                    format!("{}.html", stem)
+                });
+                page
+            })
+            .collect();
+
+        let index = Node::File {
+            name: "index.html".to_string(),
+            contents: Vec::new(),
+            parsed_contents: ParsedContents::Html(template.to_vec()),
+            metadata: None,
+            source: source.join("index.html"),
+        };
+
+        let base = self
+            .base
+            .as_ref()
+            .unwrap()
+            .clone()
+            .with_variable("pages", Value::Array(pages));
+
+        index
+            .process_html_with_base(template, base, self, env)
+            .map(Some)
+    }
+
     /// Preprocess the given markdown node and its descendants, executing functions
     /// and adding the result to the node's metadata in place.
-    fn preprocess_markdown_node(&mut self, node: &mut Node) -> Result<(), Error> {
+    fn preprocess_markdown_node(&self, node: &Node) -> Result<(), Error> {
         match node {
             Node::Directory { children, .. } => {
-                for child in children.iter_mut() {
+                for child in children {
                     self.preprocess_markdown_node(child)?;
                 }
 
@@ -249,28 +960,70 @@ impl Stuart {
 }
 
 impl<'a> Environment<'a> {
+    /// Returns a builder for an environment.
+    pub fn builder() -> EnvironmentBuilder<'a> {
+        EnvironmentBuilder::default()
+    }
+
     /// Updates the environment from a list of children, adding the closest root HTML files.
-    fn update_from_children(&self, children: &'a [Node]) -> Self {
+    pub fn update_from_children(&self, children: &'a [Node], config: &Config) -> Self {
         let mut env = *self;
 
         for child in children {
-            match child.name() {
-                "root.html" => {
-                    env.root = match child.parsed_contents() {
-                        ParsedContents::Html(tokens) => Some(tokens),
-                        _ => None,
-                    }
+            if config.markdown_index.as_deref() == Some(child.name()) {
+                env.md_index = child.parsed_contents().tokens();
+                continue;
+            }
+
+            if child.name() == config.root_template {
+                env.root = match child.parsed_contents() {
+                    ParsedContents::Html(tokens) => Some(tokens),
+                    _ => None,
                 }
-                "md.html" => {
-                    env.md = match child.parsed_contents() {
-                        ParsedContents::Html(tokens) => Some(tokens),
-                        _ => None,
-                    }
+            } else if child.name() == config.md_template {
+                env.md = match child.parsed_contents() {
+                    ParsedContents::Html(tokens) => Some(tokens),
+                    _ => None,
                 }
-                _ => (),
             }
         }
 
         env
     }
 }
+
+impl<'a> EnvironmentBuilder<'a> {
+    /// Sets the environment variables.
+    pub fn with_vars(mut self, vars: &'a [(String, String)]) -> Self {
+        self.vars = vars;
+        self
+    }
+
+    /// Sets the root HTML file.
+    pub fn with_root(mut self, tokens: &'a [LocatableToken]) -> Self {
+        self.root = Some(tokens);
+        self
+    }
+
+    /// Sets the root markdown HTML file.
+    pub fn with_md(mut self, tokens: &'a [LocatableToken]) -> Self {
+        self.md = Some(tokens);
+        self
+    }
+
+    /// Sets the template used to generate index pages for directories of markdown files.
+    pub fn with_md_index(mut self, tokens: &'a [LocatableToken]) -> Self {
+        self.md_index = Some(tokens);
+        self
+    }
+
+    /// Builds the environment.
+    pub fn build(self) -> Environment<'a> {
+        Environment {
+            vars: self.vars,
+            root: self.root,
+            md: self.md,
+            md_index: self.md_index,
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Provides linting of the HTML build output.
+
+use crate::fs::Node;
+use crate::validate::Warning;
+use crate::Config;
+
+use html5ever::tendril::TendrilSink;
+use html5ever::{parse_document, ParseOpts};
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+
+use std::collections::HashMap;
+
+/// Lints all the HTML files in the given output tree which will be saved, adding any warnings to the list.
+pub(crate) fn lint_node(node: &Node, config: &Config, warnings: &mut Vec<Warning>) {
+    match node {
+        Node::File {
+            name,
+            contents,
+            source,
+            ..
+        } if name.ends_with(".html")
+            && name != &config.root_template
+            && name != &config.md_template
+            && config.markdown_index.as_ref() != Some(name) =>
+        {
+            warnings.extend(lint_html(contents).into_iter().map(|message| Warning {
+                source: source.clone(),
+                line: 0,
+                message,
+            }));
+        }
+        Node::Directory { name, children, .. } if config.data_dir.as_ref() != Some(name) => {
+            for child in children {
+                lint_node(child, config, warnings);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Lints a single HTML document, returning a description of each problem found.
+///
+/// The document is parsed leniently, so parse errors such as unclosed or mismatched tags are reported as
+///   warnings rather than preventing the document from being checked for duplicate `id` attributes.
+pub fn lint_html(contents: &[u8]) -> Vec<String> {
+    let dom = parse_document(RcDom::default(), ParseOpts::default())
+        .from_utf8()
+        .one(contents);
+
+    let mut warnings: Vec<String> = Vec::new();
+    let mut errors = dom.errors.iter().peekable();
+
+    let has_doctype = dom
+        .document
+        .children
+        .borrow()
+        .iter()
+        .any(|child| matches!(child.data, NodeData::Doctype { .. }));
+
+    // Without a doctype, the parser reports a vague error for the first token of the document.
+    if !has_doctype {
+        errors.next_if(|error| *error == "Unexpected token");
+        warnings.push("missing `<!DOCTYPE html>` declaration".to_string());
+    }
+
+    for error in errors {
+        if !warnings.iter().any(|w| w == error) {
+            warnings.push(error.to_string());
+        }
+    }
+
+    let mut ids = HashMap::new();
+    find_ids(&dom.document, &mut ids);
+
+    let mut duplicates = ids
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .collect::<Vec<_>>();
+
+    duplicates.sort();
+
+    for (id, count) in duplicates {
+        warnings.push(format!("duplicate id `{}` ({} occurrences)", id, count));
+    }
+
+    warnings
+}
+
+/// Recursively counts the occurrences of each `id` attribute in the tree.
+fn find_ids(handle: &Handle, ids: &mut HashMap<String, usize>) {
+    if let NodeData::Element { attrs, .. } = &handle.data {
+        for attr in attrs.borrow().iter() {
+            if &*attr.name.local == "id" {
+                *ids.entry(attr.value.to_string()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    for child in handle.children.borrow().iter() {
+        find_ids(child, ids);
+    }
+}
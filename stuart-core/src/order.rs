@@ -0,0 +1,120 @@
+//! Provides ordering of input files by the files they reference.
+
+use crate::error::Error;
+use crate::fs::{Node, ParsedContents};
+use crate::parse::LocatableToken;
+use crate::process::ProcessError;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The state of a file during the depth-first search.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// The file has not yet been reached.
+    Unvisited,
+    /// The file's dependencies are being visited, so reaching it again means there is a cycle.
+    Visiting,
+    /// The file and all its dependencies have been ordered.
+    Done,
+}
+
+/// A file below the input node, with the indexes of the files it references and the tokens referencing them.
+struct File<'a> {
+    /// The path of the file relative to the input node.
+    path: PathBuf,
+    /// The files which this file references.
+    dependencies: Vec<(usize, &'a LocatableToken)>,
+}
+
+/// Returns the paths of the files below the input node, relative to it, ordered so that every file comes after the
+///   files it references, such as with `import`.
+///
+/// Files are otherwise kept in their stored order. Returns an error if files reference each other in a cycle.
+pub(crate) fn dependency_order(input: &Node) -> Result<Vec<PathBuf>, Error> {
+    let nodes = input.iter_files().collect::<Vec<_>>();
+    let indexes = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, (_, node))| (*node as *const Node, i))
+        .collect::<HashMap<_, _>>();
+
+    let files = nodes
+        .iter()
+        .map(|(path, node)| {
+            let tokens = match node.parsed_contents() {
+                ParsedContents::Html(tokens) | ParsedContents::Text(tokens) => tokens.as_slice(),
+                ParsedContents::Markdown(md) => md.markdown.as_slice(),
+                _ => &[],
+            };
+
+            let dependencies = tokens
+                .iter()
+                .filter_map(|token| {
+                    let function = token.inner.as_function()?;
+                    let dependency = input.get_at_path(Path::new(function.referenced_file()?))?;
+
+                    indexes
+                        .get(&(dependency as *const Node))
+                        .map(|&i| (i, token))
+                })
+                .collect();
+
+            File {
+                path: path.clone(),
+                dependencies,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut states = vec![State::Unvisited; files.len()];
+    let mut stack = Vec::new();
+    let mut order = Vec::with_capacity(files.len());
+
+    for i in 0..files.len() {
+        visit(i, &files, &mut states, &mut stack, &mut order)?;
+    }
+
+    Ok(order)
+}
+
+/// Visits a file, adding it to the order after its dependencies.
+///
+/// The stack contains the files currently being visited, so that cycles can be reported in full.
+fn visit(
+    i: usize,
+    files: &[File],
+    states: &mut [State],
+    stack: &mut Vec<usize>,
+    order: &mut Vec<PathBuf>,
+) -> Result<(), Error> {
+    if states[i] == State::Done {
+        return Ok(());
+    }
+
+    states[i] = State::Visiting;
+    stack.push(i);
+
+    for &(dependency, token) in &files[i].dependencies {
+        if states[dependency] == State::Visiting {
+            let start = stack.iter().position(|&j| j == dependency).unwrap();
+            let cycle = stack[start..]
+                .iter()
+                .chain(std::iter::once(&dependency))
+                .map(|&j| files[j].path.to_string_lossy().to_string())
+                .collect();
+
+            return Err(Error::Process(
+                token.traceback(ProcessError::DependencyCycle(cycle)),
+            ));
+        }
+
+        visit(dependency, files, states, stack, order)?;
+    }
+
+    stack.pop();
+    states[i] = State::Done;
+    order.push(files[i].path.clone());
+
+    Ok(())
+}
@@ -14,6 +14,8 @@ use std::rc::Rc;
 pub enum ParsedContents {
     /// An HTML file, parsed into template tokens.
     Html(Vec<LocatableToken>),
+    /// A plain-text file with a configured text extension, parsed into template tokens.
+    Text(Vec<LocatableToken>),
     /// A markdown file, parsed into frontmatter and HTML.
     Markdown(ParsedMarkdown),
     /// A JSON file.
@@ -24,13 +26,17 @@ pub enum ParsedContents {
     None,
     /// The file was not parsed because it was ignored.
     Ignored,
+    /// The file was too large to be held in memory, so is copied from its source when saved.
+    ///
+    /// The contents of a streamed file are always empty.
+    Streamed,
 }
 
 impl ParsedContents {
     /// Returns the template tokens of the parsed contents, if applicable.
     pub fn tokens(&self) -> Option<&[LocatableToken]> {
         match self {
-            Self::Html(tokens) => Some(tokens),
+            Self::Html(tokens) | Self::Text(tokens) => Some(tokens),
             _ => None,
         }
     }
@@ -43,17 +49,51 @@ impl ParsedContents {
         }
     }
 
+    /// Returns the plugin-parsed contents as the concrete processor type `T`, if applicable.
+    ///
+    /// Returns `None` if the contents were not parsed by a plugin, or if they were parsed into a different type.
+    pub fn downcast_custom<T: NodeProcessor + 'static>(&self) -> Option<&T> {
+        match self {
+            Self::Custom(custom) => {
+                let processor: &dyn NodeProcessor = custom.as_ref().as_ref();
+                processor.as_any().downcast_ref::<T>()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the file is copied from its source when saved rather than held in memory.
+    pub fn is_streamed(&self) -> bool {
+        matches!(self, Self::Streamed)
+    }
+
     /// Returns `true` if the contents were ignored.
     pub fn is_ignored(&self) -> bool {
         matches!(self, Self::Ignored)
     }
 
+    /// Returns the name of the kind of parsed contents, such as `html` or `markdown`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ParsedContents::Html(_) => "html",
+            ParsedContents::Text(_) => "text",
+            ParsedContents::Markdown(_) => "markdown",
+            ParsedContents::Json(_) => "json",
+            ParsedContents::Custom(_) => "custom",
+            ParsedContents::None => "none",
+            ParsedContents::Ignored => "ignored",
+            ParsedContents::Streamed => "streamed",
+        }
+    }
+
     /// Converts the parsed contents to a JSON value, if applicable.
     pub fn to_json(&self) -> Option<Value> {
         match self {
             ParsedContents::Html(_) => None,
+            ParsedContents::Text(_) => None,
             ParsedContents::None => None,
             ParsedContents::Ignored => None,
+            ParsedContents::Streamed => None,
 
             ParsedContents::Markdown(md) => Some(json!({
                 "type": "markdown",
@@ -74,11 +114,13 @@ impl Debug for ParsedContents {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Html(arg0) => f.debug_tuple("Html").field(arg0).finish(),
+            Self::Text(arg0) => f.debug_tuple("Text").field(arg0).finish(),
             Self::Markdown(arg0) => f.debug_tuple("Markdown").field(arg0).finish(),
             Self::Json(arg0) => f.debug_tuple("Json").field(arg0).finish(),
             Self::Custom(_) => f.debug_tuple("Custom").finish(),
             Self::None => write!(f, "None"),
             Self::Ignored => write!(f, "Ignored"),
+            Self::Streamed => write!(f, "Streamed"),
         }
     }
 }
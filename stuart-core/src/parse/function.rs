@@ -1,6 +1,6 @@
 //! Provides functionality for parsing raw functions and arguments.
 
-use crate::functions::is_ident;
+use crate::functions::{is_ident, is_plugin_ident};
 use crate::parse::ParseError;
 
 /// Represents a raw function.
@@ -24,10 +24,12 @@ pub enum RawArgument {
     Variable(String),
     /// A string literal.
     String(String),
-    /// An identifier, such as a function name.
+    /// An identifier, such as a function name or a plugin function name like `my_plugin::my_function`.
     Ident(String),
     /// A number literal. (floats are not yet supported)
     Integer(i32),
+    /// A boolean literal.
+    Bool(bool),
 }
 
 impl RawArgument {
@@ -38,15 +40,16 @@ impl RawArgument {
 
             let variable_name = arg.strip_prefix('$').unwrap();
 
-            if !variable_name
-                .chars()
-                .all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+            if variable_name.is_empty()
+                || !variable_name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '.')
             {
                 return Err(ParseError::InvalidVariableName(variable_name.to_string()));
             }
 
             Ok(Self::Variable(variable_name.to_string()))
-        } else if arg.starts_with('"') && arg.ends_with('"') {
+        } else if arg.len() >= 2 && arg.starts_with('"') && arg.ends_with('"') {
             // Parse a positional string argument.
 
             let string = arg.strip_prefix('"').unwrap().strip_suffix('"').unwrap();
@@ -60,7 +63,11 @@ impl RawArgument {
             // Parse an integer argument.
 
             Ok(Self::Integer(int))
-        } else if is_ident(arg) {
+        } else if let Ok(boolean) = arg.parse::<bool>() {
+            // Parse a boolean argument.
+
+            Ok(Self::Bool(boolean))
+        } else if is_ident(arg) || is_plugin_ident(arg) {
             // Parse an identifier argument.
 
             Ok(Self::Ident(arg.to_string()))
@@ -102,4 +109,12 @@ impl RawArgument {
             _ => None,
         }
     }
+
+    /// Returns the argument as a boolean, if it is a boolean.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(boolean) => Some(*boolean),
+            _ => None,
+        }
+    }
 }
@@ -2,30 +2,61 @@
 
 use crate::plugins::Manager;
 
-use super::{parse_html, LocatableToken, ParseError, TracebackError};
+use super::{
+    parse_html_with_delimiters, ColumnMode, Delimiters, LocatableToken, ParseError, TracebackError,
+};
 
 use humphrey_json::Value;
 
 use std::path::Path;
+use std::sync::OnceLock;
 
 /// Represents the parsed contents of a markdown file.
 #[derive(Clone, Debug)]
 pub struct ParsedMarkdown {
-    /// The frontmatter of the file.
-    pub(crate) frontmatter: Vec<(String, String)>,
+    /// The frontmatter of the file, where each value is either a string or an array.
+    pub(crate) frontmatter: Vec<(String, Value)>,
     /// The raw markdown body of the file.
     pub(crate) markdown: Vec<LocatableToken>,
     /// The raw markdown body of the file as a string.
     pub(crate) markdown_string: String,
     /// The final processed HTML body of the file.
-    pub(crate) html: Option<String>,
+    ///
+    /// This is set once during markdown preprocessing, which only has shared access to the input tree.
+    pub(crate) html: OnceLock<String>,
+    /// The length of the excerpt at the start of the HTML body, which ends before the first [`EXCERPT_MARKER`],
+    ///   or the length of the whole HTML body if there is no marker.
+    ///
+    /// This is set along with the HTML body during markdown preprocessing.
+    pub(crate) excerpt_length: OnceLock<usize>,
 }
 
+/// The HTML comment which separates the excerpt of a markdown file from the rest of its content.
+pub const EXCERPT_MARKER: &str = "<!-- more -->";
+
 /// Attempts to parse a markdown file into a [`ParsedMarkdown`] struct.
 pub fn parse_markdown(
     input: String,
     path: &Path,
     plugins: Option<&dyn Manager>,
+) -> Result<ParsedMarkdown, TracebackError<ParseError>> {
+    parse_markdown_with_delimiters(
+        input,
+        path,
+        plugins,
+        &Delimiters::default(),
+        ColumnMode::default(),
+    )
+}
+
+/// Attempts to parse a markdown file into a [`ParsedMarkdown`] struct, using the given delimiters and counting
+///   the columns of errors in the given unit.
+pub fn parse_markdown_with_delimiters(
+    input: String,
+    path: &Path,
+    plugins: Option<&dyn Manager>,
+    delimiters: &Delimiters,
+    column_mode: ColumnMode,
 ) -> Result<ParsedMarkdown, TracebackError<ParseError>> {
     let (lines_to_skip, frontmatter) = if input.starts_with("---\n") || input.starts_with("---\r\n")
     {
@@ -56,14 +87,23 @@ pub fn parse_markdown(
                 let mut parts = line.splitn(2, ':');
                 let key = parts.next().ok_or_else(e)?.trim().to_string();
 
-                let value = parts
-                    .next()
-                    .ok_or_else(e)?
-                    .trim()
-                    .strip_prefix('"')
-                    .and_then(|v| v.strip_suffix('"'))
-                    .ok_or_else(e)?
-                    .to_string();
+                let value = parts.next().ok_or_else(e)?.trim();
+
+                // Arrays are written as JSON, such as `styles: ["/a.css", "/b.css"]`.
+                let value = if value.starts_with('[') {
+                    humphrey_json::from_str(value)
+                        .ok()
+                        .filter(|value: &Value| value.as_array().is_some())
+                        .ok_or_else(e)?
+                } else {
+                    Value::String(
+                        value
+                            .strip_prefix('"')
+                            .and_then(|v| v.strip_suffix('"'))
+                            .ok_or_else(e)?
+                            .to_string(),
+                    )
+                };
 
                 frontmatter.push((key, value));
             }
@@ -89,13 +129,15 @@ pub fn parse_markdown(
         .collect::<Vec<_>>()
         .join("\n");
 
-    let markdown = parse_html(&raw_markdown, path, plugins)?;
+    let markdown =
+        parse_html_with_delimiters(&raw_markdown, path, plugins, delimiters, column_mode)?;
 
     Ok(ParsedMarkdown {
         frontmatter,
         markdown,
         markdown_string: raw_markdown,
-        html: None,
+        html: OnceLock::new(),
+        excerpt_length: OnceLock::new(),
     })
 }
 
@@ -104,19 +146,36 @@ impl ParsedMarkdown {
     ///
     /// **Warning:** this function also returns the body of the file as an HTML string. This can be very large, so if the contents
     ///   is not required, consider using [`ParsedMarkdown::to_json`], which does the same thing without returning the contents.
+    ///
+    /// The content and excerpt are empty if the file has not yet been preprocessed. An `excerpt` in the frontmatter
+    ///   is kept instead of the excerpt of the content.
     pub fn to_value(&self) -> Value {
         let mut v = self.frontmatter_to_value();
-        v["content"] = Value::String(self.html.as_ref().unwrap().clone());
+        v["content"] = Value::String(self.html.get().cloned().unwrap_or_default());
+        if v.get("excerpt").is_none() {
+            v["excerpt"] = Value::String(self.excerpt().unwrap_or_default().to_string());
+        }
         v["markdown"] = Value::String(self.markdown_string.clone());
         v
     }
 
+    /// Returns the processed HTML before the first [`EXCERPT_MARKER`], or the whole HTML body if there is no
+    ///   marker.
+    ///
+    /// Returns `None` if the file has not yet been preprocessed.
+    pub fn excerpt(&self) -> Option<&str> {
+        let html = self.html.get()?;
+        let length = self.excerpt_length.get().copied().unwrap_or(html.len());
+
+        Some(&html[..length])
+    }
+
     /// Converts the markdown frontmatter into a JSON object.
     pub fn frontmatter_to_value(&self) -> Value {
         let children = self
             .frontmatter
             .iter()
-            .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+            .map(|(key, value)| (key.clone(), value.clone()))
             .collect::<Vec<_>>();
 
         Value::Object(children)
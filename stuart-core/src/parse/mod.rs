@@ -10,8 +10,10 @@ use crate::plugins::Manager;
 
 pub use self::contents::ParsedContents;
 pub use self::function::{RawArgument, RawFunction};
-pub use self::markdown::{parse_markdown, ParsedMarkdown};
-pub use self::parser::Parser;
+pub use self::markdown::{
+    parse_markdown, parse_markdown_with_delimiters, ParsedMarkdown, EXCERPT_MARKER,
+};
+pub use self::parser::{ColumnMode, Parser};
 
 pub use crate::error::{ParseError, TracebackError};
 
@@ -90,19 +92,78 @@ impl Token {
     }
 }
 
+/// The delimiters which surround functions and variables in templates.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Delimiters {
+    /// The opening delimiter, `{{` by default.
+    pub open: String,
+    /// The closing delimiter, `}}` by default.
+    pub close: String,
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Self {
+            open: "{{".to_string(),
+            close: "}}".to_string(),
+        }
+    }
+}
+
+impl Delimiters {
+    /// Creates a new set of delimiters, returning an error if they are invalid.
+    pub fn new(open: impl AsRef<str>, close: impl AsRef<str>) -> Result<Self, ParseError> {
+        let delimiters = Self {
+            open: open.as_ref().to_string(),
+            close: close.as_ref().to_string(),
+        };
+
+        delimiters.validate()?;
+
+        Ok(delimiters)
+    }
+
+    /// Checks that the delimiters are non-empty and distinct.
+    pub fn validate(&self) -> Result<(), ParseError> {
+        if self.open.is_empty() || self.close.is_empty() || self.open == self.close {
+            Err(ParseError::InvalidDelimiters)
+        } else {
+            Ok(())
+        }
+    }
+}
+
 /// Attempts to parse a file at the given path into a list of tokens.
 pub fn parse_html(
     input: &str,
     path: &Path,
     plugins: Option<&dyn Manager>,
+) -> Result<Vec<LocatableToken>, TracebackError<ParseError>> {
+    parse_html_with_delimiters(
+        input,
+        path,
+        plugins,
+        &Delimiters::default(),
+        ColumnMode::default(),
+    )
+}
+
+/// Attempts to parse a file at the given path into a list of tokens, using the given delimiters and counting the
+///   columns of errors in the given unit.
+pub fn parse_html_with_delimiters(
+    input: &str,
+    path: &Path,
+    plugins: Option<&dyn Manager>,
+    delimiters: &Delimiters,
+    column_mode: ColumnMode,
 ) -> Result<Vec<LocatableToken>, TracebackError<ParseError>> {
     let chars = input.chars();
-    let mut parser = Parser::new(chars, path);
+    let mut parser = Parser::new_with_column_mode(chars, path, column_mode);
     let mut tokens = Vec::new();
 
     let (mut line, mut column) = parser.location();
 
-    while let Some(raw) = parser.extract_until("{{", true) {
+    while let Some(raw) = parser.extract_until(&delimiters.open, true) {
         if !raw.is_empty() {
             tokens.push(LocatableToken {
                 inner: Token::Raw(raw),
@@ -130,12 +191,14 @@ pub fn parse_html(
         });
 
         parser.ignore_while(|c| c.is_whitespace());
-        parser.expect("}}")?;
+        parser.expect(&delimiters.close)?;
 
         (line, column) = parser.location();
     }
 
-    let remaining = parser.extract_remaining(true);
+    let remaining = parser
+        .extract_remaining(false)
+        .replace(&format!("\\{}", delimiters.open), &delimiters.open);
     if !remaining.is_empty() {
         tokens.push(LocatableToken {
             inner: Token::Raw(remaining),
@@ -245,6 +308,12 @@ fn parse_function(
 
     for function_parser in &*crate::FUNCTION_PARSERS {
         if function_parser.can_parse(&raw_function) {
+            if let Some(signature) = function_parser.signature() {
+                signature
+                    .check(&raw_function)
+                    .map_err(|e| parser.traceback(e))?;
+            }
+
             return Ok(Token::Function(Rc::new(
                 function_parser
                     .parse(raw_function)
@@ -259,6 +328,12 @@ fn parse_function(
                 if function_name == function.name()
                     || function_name == format!("{}::{}", plugin.name, function.name())
                 {
+                    if let Some(signature) = function.signature() {
+                        signature
+                            .check(&raw_function)
+                            .map_err(|e| parser.traceback(e))?;
+                    }
+
                     return Ok(Token::Function(Rc::new(
                         function
                             .parse(raw_function)
@@ -17,11 +17,29 @@ pub struct Parser<'a> {
     column: u32,
     next_line: u32,
     next_column: u32,
+    column_mode: ColumnMode,
+}
+
+/// The unit in which the parser counts columns, which affects the columns reported in errors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColumnMode {
+    /// Columns are counted in bytes of UTF-8.
+    ByteOffset,
+    /// Columns are counted in Unicode scalar values.
+    #[default]
+    CharOffset,
+    /// Columns are counted in UTF-16 code units, as used by many editors.
+    Utf16Offset,
 }
 
 impl<'a> Parser<'a> {
-    /// Creates a new parser for characters at the given path.
+    /// Creates a new parser for characters at the given path, counting columns in Unicode scalar values.
     pub fn new(chars: Chars<'a>, path: &'a Path) -> Self {
+        Self::new_with_column_mode(chars, path, ColumnMode::default())
+    }
+
+    /// Creates a new parser for characters at the given path, counting columns in the given unit.
+    pub fn new_with_column_mode(chars: Chars<'a>, path: &'a Path, column_mode: ColumnMode) -> Self {
         Self {
             chars: chars.peekable(),
             path,
@@ -29,6 +47,7 @@ impl<'a> Parser<'a> {
             column: 1,
             next_line: 1,
             next_column: 1,
+            column_mode,
         }
     }
 
@@ -53,7 +72,11 @@ impl<'a> Parser<'a> {
                 self.next_line += 1;
                 self.next_column = 0;
             } else if c != '\r' {
-                self.next_column += 1;
+                self.next_column += match self.column_mode {
+                    ColumnMode::ByteOffset => c.len_utf8() as u32,
+                    ColumnMode::CharOffset => 1,
+                    ColumnMode::Utf16Offset => c.len_utf16() as u32,
+                };
             }
 
             Ok(c)
@@ -7,6 +7,8 @@ use crate::{Environment, Stuart};
 use humphrey_json::prelude::*;
 use humphrey_json::Value;
 
+use std::any::Any;
+use std::collections::HashMap;
 use std::path::Path;
 
 /// Represents a type that can manage plugins.
@@ -19,6 +21,12 @@ use std::path::Path;
 pub trait Manager {
     /// Returns the plugins loaded by the plugin manager.
     fn plugins(&self) -> &[Plugin];
+
+    /// Called at the start of every build, before any files are processed.
+    ///
+    /// The data store is shared between all plugins for the duration of the build, and can later be accessed
+    ///   with [`Stuart::get_plugin_data`] and [`Stuart::set_plugin_data`].
+    fn on_build_start(&self, _data: &mut HashMap<String, Value>) {}
 }
 
 /// Represents a plugin.
@@ -40,12 +48,48 @@ pub trait NodeParser {
     /// Returns the file extensions that this parser can parse.
     fn extensions(&self) -> Vec<&'static str>;
 
+    /// Returns the priority of the parser, which decides which parser is used when several claim the same extension.
+    ///
+    /// The parser with the highest priority is used, and if several share the highest priority, the parser of the
+    ///   plugin which comes first in the plugin manager is used. This defaults to `0`.
+    fn priority(&self) -> i32 {
+        0
+    }
+
     /// Parses the node, returning the parsed contents within a type that implements `NodeProcessor` so they can then be processed.
     fn parse(&self, contents: &[u8], path: &Path) -> Result<Box<dyn NodeProcessor>, String>;
+
+    /// Parses the node like [`NodeParser::parse`], or passes new contents on to be parsed again.
+    ///
+    /// This allows parsers to be chained, for example by preprocessing a file into markdown to be parsed by Stuart.
+    ///   Each parser is used at most once for a file, so a parser can also fall through to the next parser for the
+    ///   same extension by continuing with the same name. The default implementation calls [`NodeParser::parse`],
+    ///   so parsers which only ever continue can return an error from it.
+    fn parse_step(&self, contents: &[u8], path: &Path) -> Result<ParseStep, String> {
+        self.parse(contents, path).map(ParseStep::Done)
+    }
+}
+
+/// The result of one parser in a chain of parsers, returned by [`NodeParser::parse_step`].
+pub enum ParseStep {
+    /// The node has been parsed, and its parsed contents will be processed by the given processor.
+    Done(Box<dyn NodeProcessor>),
+    /// The node should be parsed again as if it were a file with the given name and contents.
+    ///
+    /// The node takes the new name and contents, so the name also decides the name of the node in the output.
+    Continue {
+        /// The new name of the node.
+        name: String,
+        /// The new contents of the node.
+        contents: Vec<u8>,
+    },
 }
 
 /// Represents a type that contains the parsed contents of a node, which can be processed.
-pub trait NodeProcessor {
+///
+/// Types implementing this trait can be recovered from [`ParsedContents::Custom`](crate::fs::ParsedContents::Custom)
+///   with [`ParsedContents::downcast_custom`](crate::fs::ParsedContents::downcast_custom).
+pub trait NodeProcessor: NodeProcessorAny {
     /// Processes the parsed contents in the given environment, retuning the processed output.
     fn process(&self, processor: &Stuart, env: Environment) -> Result<ProcessOutput, String>;
 
@@ -55,6 +99,20 @@ pub trait NodeProcessor {
     }
 }
 
+/// Allows a [`NodeProcessor`] trait object to be converted to [`Any`] so it can be downcast to its concrete type.
+///
+/// This is automatically implemented for all types, so should not be implemented manually.
+pub trait NodeProcessorAny {
+    /// Returns the processor as [`Any`].
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Any> NodeProcessorAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
 impl<T> Manager for T
 where
     T: AsRef<[Plugin]>,
@@ -1,6 +1,7 @@
 //! Provides the [`TokenIter`] iterator over tokens, which is able to be rewound to a [`TokenIterWaypoint`].
 
 use crate::parse::LocatableToken;
+use crate::process::ProcessError;
 
 /// Represents an iterator over tokens.
 pub struct TokenIter<'a> {
@@ -40,6 +41,44 @@ impl<'a> TokenIter<'a> {
             None
         }
     }
+
+    /// Returns the next token to be returned without consuming it.
+    pub fn peek(&self) -> Option<&LocatableToken> {
+        self.tokens.get(self.index)
+    }
+
+    /// Consumes tokens up to and including the `end` function which closes the block of the given function,
+    ///   returning the tokens in between.
+    ///
+    /// Blocks of the same function nested within it are collected along with their `end` functions, so only the
+    ///   matching `end` closes the block. This allows functions such as `for` to collect their body before
+    ///   executing it. Returns `ProcessError::UnexpectedEndOfFile` if the block is never closed.
+    pub fn collect_until_end(
+        &mut self,
+        fn_name: &str,
+    ) -> Result<Vec<LocatableToken>, ProcessError> {
+        let start = self.index;
+        let mut depth = 0;
+
+        while let Some(token) = self.next() {
+            let function = match token.as_function() {
+                Some(function) => function,
+                None => continue,
+            };
+
+            if function.name() == fn_name {
+                depth += 1;
+            } else if function.ends_block() == Some(fn_name) {
+                if depth == 0 {
+                    return Ok(self.tokens[start..self.index - 1].to_vec());
+                }
+
+                depth -= 1;
+            }
+        }
+
+        Err(ProcessError::UnexpectedEndOfFile)
+    }
 }
 
 impl<'a> Iterator for TokenIter<'a> {
@@ -10,12 +10,15 @@ use self::iter::TokenIter;
 use self::stack::StackFrame;
 
 use crate::fs::{Node, ParsedContents};
-use crate::parse::{LocatableToken, ParsedMarkdown, Token};
+use crate::parse::{LocatableToken, ParsedMarkdown, Token, EXCERPT_MARKER};
 use crate::{Environment, Error, Stuart};
 
 use humphrey_json::Value;
 use pulldown_cmark::{html, Options, Parser};
 
+use std::collections::HashMap;
+use std::path::Path;
+
 /// Represents the scope of a function execution.
 pub struct Scope<'a> {
     /// The token iterator.
@@ -49,14 +52,111 @@ pub struct ProcessOutput {
     pub new_name: Option<String>,
 }
 
+/// Returns the error for a file whose contents are not of the expected type.
+fn invalid_file_type(expected: &str) -> ProcessError {
+    ProcessError::InvalidDataType {
+        variable: "<file>".to_string(),
+        expected: expected.to_string(),
+        found: String::new(),
+    }
+}
+
+/// Returns the `$sections` variable for the root template, containing the names of the sections defined by the
+///   page in the order they were defined.
+fn sections_value(sections: &[(String, Vec<u8>)]) -> Value {
+    Value::Array(
+        sections
+            .iter()
+            .map(|(name, _)| Value::String(name.clone()))
+            .collect(),
+    )
+}
+
+/// Processes the tokens in a new stack containing only the given base frame, returning the base frame with its
+///   output once every token has been processed.
+///
+/// Any sections are discarded. If a block is left open, so the base frame is not the only one left, a stack
+///   error is returned at the given path.
+pub(crate) fn process_tokens(
+    tokens: &[LocatableToken],
+    base: StackFrame,
+    processor: &Stuart,
+    path: &Path,
+) -> Result<StackFrame, TracebackError<ProcessError>> {
+    let mut token_iter = TokenIter::new(tokens);
+    let mut stack = vec![base];
+    let mut sections = Vec::new();
+    let mut scope = Scope {
+        tokens: &mut token_iter,
+        stack: &mut stack,
+        processor,
+        sections: &mut sections,
+    };
+
+    while let Some(token) = scope.tokens.next() {
+        token.process(&mut scope)?;
+    }
+
+    stack
+        .pop()
+        .filter(|frame| frame.name == "base" && stack.is_empty())
+        .ok_or(TracebackError {
+            path: path.to_path_buf(),
+            line: 0,
+            column: 0,
+            kind: ProcessError::StackError,
+        })
+}
+
+/// Escapes the characters of the text which have special meaning in HTML.
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            ch => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+/// Returns an iterator over the characters of the HTML which are not part of a tag.
+pub(crate) fn text_chars(html: &str) -> impl Iterator<Item = char> + '_ {
+    let mut tag = false;
+
+    html.chars().filter(move |&ch| match ch {
+        '<' => {
+            tag = true;
+            false
+        }
+        '>' => {
+            tag = false;
+            false
+        }
+        _ => !tag,
+    })
+}
+
 impl Node {
     /// Processes a node, returning an output node.
     pub fn process(&self, processor: &Stuart, env: Environment) -> Result<Node, Error> {
-        let output = if self.name() != "root.html" && self.name() != "md.html" {
+        let output = if self.name() != processor.config.root_template
+            && self.name() != processor.config.md_template
+            && processor.config.markdown_index.as_deref() != Some(self.name())
+        {
             match self.parsed_contents() {
                 ParsedContents::Html(tokens) => self
                     .process_html(tokens, processor, env)
                     .map_err(Error::Process)?,
+                ParsedContents::Text(tokens) => self
+                    .process_text(tokens, processor)
+                    .map_err(Error::Process)?,
                 ParsedContents::Markdown(md) => self
                     .process_markdown(md, processor, env)
                     .map_err(Error::Process)?,
@@ -74,8 +174,12 @@ impl Node {
             contents: output
                 .new_contents
                 .unwrap_or_else(|| self.contents().unwrap().to_vec()),
-            parsed_contents: ParsedContents::None,
-            metadata: if processor.config.save_metadata {
+            // Streamed files are still copied from their source when the output is saved.
+            parsed_contents: match self.parsed_contents() {
+                ParsedContents::Streamed => ParsedContents::Streamed,
+                _ => ParsedContents::None,
+            },
+            metadata: if processor.config.save_metadata() {
                 self.parsed_contents().to_json()
             } else {
                 None
@@ -84,12 +188,51 @@ impl Node {
         })
     }
 
+    /// Processes an HTML node with the given base stack frame, returning an output node.
+    ///
+    /// This is used for generated pages which need extra variables in scope.
+    pub(crate) fn process_html_with_base(
+        &self,
+        tokens: &[LocatableToken],
+        base: StackFrame,
+        processor: &Stuart,
+        env: Environment,
+    ) -> Result<Node, Error> {
+        let output = self
+            .process_html_inner(tokens, base, processor, env)
+            .map_err(Error::Process)?;
+
+        Ok(Node::File {
+            name: self.name().to_string(),
+            contents: output.new_contents.unwrap_or_default(),
+            parsed_contents: ParsedContents::None,
+            metadata: None,
+            source: self.source().to_path_buf(),
+        })
+    }
+
     /// Processes an HTML node, returning the processed output.
     fn process_html(
         &self,
         tokens: &[LocatableToken],
         processor: &Stuart,
         env: Environment,
+    ) -> Result<ProcessOutput, TracebackError<ProcessError>> {
+        self.process_html_inner(
+            tokens,
+            processor.base.as_ref().unwrap().clone(),
+            processor,
+            env,
+        )
+    }
+
+    /// Processes an HTML node with the given base stack frame, returning the processed output.
+    fn process_html_inner(
+        &self,
+        tokens: &[LocatableToken],
+        base: StackFrame,
+        processor: &Stuart,
+        env: Environment,
     ) -> Result<ProcessOutput, TracebackError<ProcessError>> {
         let root = env.root.ok_or(TracebackError {
             path: self.source().to_path_buf(),
@@ -98,8 +241,10 @@ impl Node {
             kind: ProcessError::MissingHtmlRoot,
         })?;
 
+        let page = self.page_value(processor);
+
         let mut token_iter = TokenIter::new(tokens);
-        let mut stack: Vec<StackFrame> = vec![processor.base.as_ref().unwrap().clone()];
+        let mut stack: Vec<StackFrame> = vec![base.with_variable("page", page.clone())];
         let mut sections: Vec<(String, Vec<u8>)> = Vec::new();
         let mut scope = Scope {
             tokens: &mut token_iter,
@@ -128,7 +273,15 @@ impl Node {
 
         let mut token_iter = TokenIter::new(root);
 
-        scope.stack.push(processor.base.as_ref().unwrap().clone());
+        scope.stack.push(
+            processor
+                .base
+                .as_ref()
+                .unwrap()
+                .clone()
+                .with_variable("page", page)
+                .with_variable("sections", sections_value(scope.sections)),
+        );
         scope.tokens = &mut token_iter;
 
         while let Some(token) = scope.tokens.next() {
@@ -141,6 +294,30 @@ impl Node {
         })
     }
 
+    /// Processes a plain-text node, returning the processed output.
+    ///
+    /// Unlike HTML pages, the output is not wrapped by the root template, so any sections are discarded.
+    fn process_text(
+        &self,
+        tokens: &[LocatableToken],
+        processor: &Stuart,
+    ) -> Result<ProcessOutput, TracebackError<ProcessError>> {
+        let page = self.page_value(processor);
+        let base = processor
+            .base
+            .as_ref()
+            .unwrap()
+            .clone()
+            .with_variable("page", page);
+
+        let frame = process_tokens(tokens, base, processor, self.source())?;
+
+        Ok(ProcessOutput {
+            new_contents: Some(frame.output),
+            new_name: None,
+        })
+    }
+
     /// Processes a markdown node, returning the processed output.
     fn process_markdown(
         &self,
@@ -162,6 +339,9 @@ impl Node {
             kind: ProcessError::MissingMarkdownRoot,
         })?;
 
+        let page = self.page_value(processor);
+        let value = md.to_value();
+
         let mut token_iter = TokenIter::new(md_tokens);
 
         let mut stack: Vec<StackFrame> = vec![processor
@@ -169,7 +349,8 @@ impl Node {
             .as_ref()
             .unwrap()
             .clone()
-            .with_variable("self", md.to_value())];
+            .with_variable("self", value.clone())
+            .with_variable("page", page.clone())];
 
         let mut sections: Vec<(String, Vec<u8>)> = Vec::new();
         let mut scope = Scope {
@@ -199,7 +380,17 @@ impl Node {
 
         let mut token_iter = TokenIter::new(root);
 
-        scope.stack.push(processor.base.as_ref().unwrap().clone());
+        // The root template can also read the page's frontmatter, such as to include per-page stylesheets.
+        scope.stack.push(
+            processor
+                .base
+                .as_ref()
+                .unwrap()
+                .clone()
+                .with_variable("self", value)
+                .with_variable("page", page)
+                .with_variable("sections", sections_value(scope.sections)),
+        );
         scope.tokens = &mut token_iter;
 
         while let Some(token) = scope.tokens.next() {
@@ -214,59 +405,81 @@ impl Node {
         })
     }
 
+    /// Returns the `$page` variable for this node, containing the URL at which it will be served.
+    pub(crate) fn page_value(&self, processor: &Stuart) -> Value {
+        let relative_path = processor
+            .input
+            .as_ref()
+            .and_then(|input| self.source().strip_prefix(input.source()).ok())
+            .and_then(|path| path.parent())
+            .map(|path| {
+                path.components()
+                    .map(|c| format!("{}/", c.as_os_str().to_string_lossy()))
+                    .collect::<String>()
+            })
+            .unwrap_or_default();
+
+        let name = match self.name().strip_suffix(".md") {
+            Some(stem) => format!("{}.html", stem),
+            None => self.name().to_string(),
+        };
+
+        let url = if name == "index.html" {
+            format!("/{}", relative_path)
+        } else if processor.config.strip_extensions() && name.ends_with(".html") {
+            format!("/{}{}/", relative_path, name.strip_suffix(".html").unwrap())
+        } else {
+            format!("/{}{}", relative_path, name)
+        };
+
+        Value::Object(vec![("url".to_string(), Value::String(url))])
+    }
+
     /// Preprocess the markdown node, executing functions within the raw markdown and
-    /// converting it to HTML. The implementation of this is currently quite dodgy but
-    /// it works for the time being.
+    /// converting it to HTML.
+    ///
+    /// The node is usually part of the processor's input, so the HTML is stored through
+    /// shared access and is visible to any later reads of the input.
     pub(crate) fn preprocess_markdown(
-        &mut self,
+        &self,
         processor: &Stuart,
     ) -> Result<(), TracebackError<ProcessError>> {
         let source = self.source().to_path_buf();
 
-        let md = match self.parsed_contents_mut() {
+        let md = match self.parsed_contents() {
             ParsedContents::Markdown(md) => md,
             _ => return Ok(()),
         };
 
-        let mut token_iter = TokenIter::new(&md.markdown);
-        let mut stack: Vec<StackFrame> = vec![processor.base.as_ref().unwrap().clone()];
-        let mut sections: Vec<(String, Vec<u8>)> = Vec::new();
-        let mut scope = Scope {
-            tokens: &mut token_iter,
-            stack: &mut stack,
-            processor,
-            sections: &mut sections,
-        };
-
-        while let Some(token) = scope.tokens.next() {
-            token.process(&mut scope)?;
-        }
+        let base = processor.base.as_ref().unwrap().clone();
+        let frame = process_tokens(&md.markdown, base, processor, &source)?;
 
-        if let Some(frame) = scope.stack.pop() {
-            if frame.name == "base" {
-                let processed_markdown =
-                    String::from_utf8(frame.output).map_err(|_| TracebackError {
-                        path: source.clone(),
-                        line: 0,
-                        column: 0,
-                        kind: ProcessError::StackError,
-                    })?;
-
-                let parser = Parser::new_ext(&processed_markdown, Options::all());
-                let mut processed_html = String::new();
-                html::push_html(&mut processed_html, parser);
-
-                md.html = Some(processed_html);
-                return Ok(());
-            }
-        }
-
-        Err(TracebackError {
-            path: self.source().to_path_buf(),
+        let processed_markdown = String::from_utf8(frame.output).map_err(|_| TracebackError {
+            path: source.clone(),
             line: 0,
             column: 0,
             kind: ProcessError::StackError,
-        })
+        })?;
+
+        let parser = Parser::new_ext(&processed_markdown, Options::all());
+        let mut processed_html = String::new();
+        html::push_html(&mut processed_html, parser);
+
+        let processed_html = processor.hooks.iter().fold(processed_html, |html, hook| {
+            hook.transform_markdown(&source, html)
+        });
+
+        let excerpt_length = match processed_html.find(EXCERPT_MARKER) {
+            Some(index) => processed_html[..index].trim_end().len(),
+            None => processed_html.len(),
+        };
+
+        // The HTML is already set if the node has been preprocessed before, in which case it is kept.
+        if md.html.set(processed_html).is_ok() {
+            md.excerpt_length.set(excerpt_length).ok();
+        }
+
+        Ok(())
     }
 }
 
@@ -360,7 +573,31 @@ impl<'a> Scope<'a> {
         variable
     }
 
+    /// Returns all the variables in scope, with variables in inner frames shadowing those in outer frames.
+    pub fn all_variables(&self) -> HashMap<&str, &Value> {
+        crate::process::stack::all_variables_in_scope(self.stack)
+    }
+
+    /// Creates an error located at the current token, which is the function being executed.
+    ///
+    /// If no token has been consumed yet, the error is located at the next token instead, and if there are no
+    ///   tokens at all, it has no location.
+    pub fn error(&self, e: ProcessError) -> TracebackError<ProcessError> {
+        match self.tokens.current().or_else(|| self.tokens.peek()) {
+            Some(token) => token.traceback(e),
+            None => TracebackError {
+                path: Default::default(),
+                line: 0,
+                column: 0,
+                kind: e,
+            },
+        }
+    }
+
     /// Adds to the output of the current stack frame.
+    ///
+    /// The output is added as-is, so this should be used for markup generated by the function itself and for
+    ///   content which is already HTML, such as rendered markdown.
     pub fn output(&mut self, output: impl AsRef<[u8]>) -> Result<(), ProcessError> {
         self.stack
             .last_mut()
@@ -370,4 +607,148 @@ impl<'a> Scope<'a> {
 
         Ok(())
     }
+
+    /// Adds text to the output of the current stack frame, escaping any characters with special meaning in HTML.
+    ///
+    /// This should be used for plain text which may come from user data, such as frontmatter or JSON values,
+    ///   so that it is displayed as written rather than being interpreted as markup.
+    pub fn output_escaped(&mut self, text: impl AsRef<str>) -> Result<(), ProcessError> {
+        self.output(escape_html(text.as_ref()))
+    }
+
+    /// Processes the tokens of a block, such as those collected with [`TokenIter::collect_until_end`], in the
+    ///   given stack frame, then adds the frame's output to the current stack frame.
+    ///
+    /// The tokens share the stack and sections of this scope, so they can use its variables and define sections.
+    pub fn process_block(
+        &mut self,
+        tokens: &[LocatableToken],
+        frame: StackFrame,
+        self_token: &LocatableToken,
+    ) -> Result<(), TracebackError<ProcessError>> {
+        let output = self.render_block(tokens, frame, self_token)?;
+
+        self.output(output).map_err(|e| self_token.traceback(e))
+    }
+
+    /// Processes the tokens of a block in the given stack frame like [`Scope::process_block`], but returns the
+    ///   frame's output instead of adding it to the output.
+    ///
+    /// This allows functions to transform the output of their block, such as plugin functions which wrap it.
+    pub fn render_block(
+        &mut self,
+        tokens: &[LocatableToken],
+        frame: StackFrame,
+        self_token: &LocatableToken,
+    ) -> Result<Vec<u8>, TracebackError<ProcessError>> {
+        let frame = self.run_block(tokens, frame, self_token, |token, scope| {
+            token.process(scope)
+        })?;
+
+        Ok(frame.output)
+    }
+
+    /// Processes the tokens of a block in the given stack frame like [`Scope::process_block`], but returns its
+    ///   result instead of adding it to the output.
+    ///
+    /// The result is the value returned by the last function in the block which returns one, or the trimmed output
+    ///   of the block as a string if none do.
+    pub fn evaluate_block(
+        &mut self,
+        tokens: &[LocatableToken],
+        frame: StackFrame,
+        self_token: &LocatableToken,
+    ) -> Result<Value, TracebackError<ProcessError>> {
+        let mut value = None;
+
+        let frame = self.run_block(tokens, frame, self_token, |token, scope| {
+            match token.as_function() {
+                Some(function) => {
+                    if let Some(result) = function.evaluate(scope)? {
+                        value = Some(result);
+                    }
+                }
+                None => token.process(scope)?,
+            }
+
+            Ok(())
+        })?;
+
+        match value {
+            Some(value) => Ok(value),
+            None => String::from_utf8(frame.output)
+                .map(|output| Value::String(output.trim().to_string()))
+                .map_err(|_| self_token.traceback(ProcessError::StackError)),
+        }
+    }
+
+    /// Runs each token of a block with the given function in the given stack frame, then returns the frame.
+    ///
+    /// The tokens share the stack and sections of this scope, and the frame must be the only one left on the stack
+    ///   when they have all been run, otherwise the block is unterminated.
+    fn run_block(
+        &mut self,
+        tokens: &[LocatableToken],
+        frame: StackFrame,
+        self_token: &LocatableToken,
+        mut run: impl FnMut(&LocatableToken, &mut Scope) -> Result<(), TracebackError<ProcessError>>,
+    ) -> Result<StackFrame, TracebackError<ProcessError>> {
+        let stack_height = self.stack.len();
+        self.stack.push(frame);
+
+        let mut token_iter = TokenIter::new(tokens);
+        let mut block_scope = Scope {
+            tokens: &mut token_iter,
+            stack: &mut *self.stack,
+            processor: self.processor,
+            sections: &mut *self.sections,
+        };
+
+        while let Some(token) = block_scope.tokens.next() {
+            run(token, &mut block_scope)?;
+        }
+
+        self.stack
+            .pop()
+            .filter(|_| self.stack.len() == stack_height)
+            .ok_or_else(|| self_token.traceback(ProcessError::UnexpectedEndOfFile))
+    }
+
+    /// Gets the file at the given path, relative to the input directory.
+    ///
+    /// Returns `ProcessError::NotFound` if there is nothing at the path, or `ProcessError::ExpectedFile` if the path
+    ///   is a directory.
+    pub fn get_file(&self, path: &str) -> Result<&'a Node, ProcessError> {
+        let node = self
+            .processor
+            .input
+            .as_ref()
+            .ok_or_else(|| ProcessError::NotFound(path.to_string()))?
+            .get_at_path_result(Path::new(path))?;
+
+        match node.is_file() {
+            true => Ok(node),
+            false => Err(ProcessError::ExpectedFile(path.to_string())),
+        }
+    }
+
+    /// Gets the parsed JSON contents of the file at the given path, relative to the input directory.
+    ///
+    /// Returns `ProcessError::InvalidDataType` if the file exists but is not a JSON file.
+    pub fn get_file_json(&self, path: &str) -> Result<&'a Value, ProcessError> {
+        match self.get_file(path)?.parsed_contents() {
+            ParsedContents::Json(json) => Ok(json),
+            _ => Err(invalid_file_type("json")),
+        }
+    }
+
+    /// Gets the parsed markdown contents of the file at the given path, relative to the input directory.
+    ///
+    /// Returns `ProcessError::InvalidDataType` if the file exists but is not a markdown file.
+    pub fn get_file_markdown(&self, path: &str) -> Result<&'a ParsedMarkdown, ProcessError> {
+        self.get_file(path)?
+            .parsed_contents()
+            .markdown()
+            .ok_or_else(|| invalid_file_type("markdown"))
+    }
 }
@@ -1,7 +1,12 @@
 //! Provides a basic call stack implementation.
 
+use crate::parse::LocatableToken;
+
 use humphrey_json::Value;
 
+use std::collections::HashMap;
+use std::rc::Rc;
+
 /// Represents a stack frame.
 ///
 /// When the stack frame is popped, the output of the frame is appended to the output of the frame below it.
@@ -13,6 +18,8 @@ pub struct StackFrame {
     pub variables: Vec<(String, Value)>,
     /// The output of the stack frame.
     pub output: Vec<u8>,
+    /// Snippets defined in the stack frame, with the tokens of their bodies.
+    pub snippets: Vec<(String, Rc<[LocatableToken]>)>,
 }
 
 impl StackFrame {
@@ -22,6 +29,7 @@ impl StackFrame {
             name: name.as_ref().to_string(),
             variables: Vec::new(),
             output: Vec::new(),
+            snippets: Vec::new(),
         }
     }
 
@@ -30,6 +38,12 @@ impl StackFrame {
         self.variables.push((name.as_ref().to_string(), value));
     }
 
+    /// Sets a variable in the stack frame, replacing any existing variable with the same name.
+    pub fn set_variable(&mut self, name: impl AsRef<str>, value: Value) {
+        self.variables.retain(|(n, _)| n != name.as_ref());
+        self.add_variable(name, value);
+    }
+
     /// Adds a variable to the stack frame.
     pub fn with_variable(mut self, name: impl AsRef<str>, value: Value) -> Self {
         self.add_variable(name, value);
@@ -43,6 +57,45 @@ impl StackFrame {
             .find(|(n, _)| n == name)
             .map(|(_, v)| v)
     }
+
+    /// Adds a snippet to the stack frame, replacing any existing snippet with the same name.
+    pub fn add_snippet(&mut self, name: impl AsRef<str>, tokens: Rc<[LocatableToken]>) {
+        self.snippets.retain(|(n, _)| n != name.as_ref());
+        self.snippets.push((name.as_ref().to_string(), tokens));
+    }
+
+    /// Returns the tokens of the snippet with the given name.
+    pub fn get_snippet(&self, name: &str) -> Option<&Rc<[LocatableToken]>> {
+        self.snippets
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, tokens)| tokens)
+    }
+
+    /// Returns the variables in the stack frame, in the order they were added.
+    pub fn variables(&self) -> &[(String, Value)] {
+        &self.variables
+    }
+}
+
+/// Merges the variables of all the given stack frames into a single map.
+///
+/// Variables in later frames shadow those with the same name in earlier frames, matching the behaviour of
+///   variable lookup during processing.
+pub fn all_variables_in_scope(stack: &[StackFrame]) -> HashMap<&str, &Value> {
+    let mut variables = HashMap::new();
+
+    for frame in stack {
+        let mut frame_variables: HashMap<&str, &Value> = HashMap::new();
+
+        for (name, value) in frame.variables() {
+            frame_variables.entry(name.as_str()).or_insert(value);
+        }
+
+        variables.extend(frame_variables);
+    }
+
+    variables
 }
 
 /// Gets a value from inside a JSON object.
@@ -0,0 +1,52 @@
+//! Provides generation of a search index of the markdown pages.
+
+use crate::fs::{Node, ParsedContents};
+use crate::process::text_chars;
+use crate::Stuart;
+
+use humphrey_json::Value;
+
+/// Returns a JSON array describing every markdown page below the input node, in filesystem order.
+///
+/// Each entry has the `title` from the page's frontmatter, the `url` at which it is served, and its `body` as plain
+///   text with tags removed and whitespace collapsed. Pages in the data directory are not included since they are
+///   never saved.
+pub(crate) fn search_index(input: &Node, processor: &Stuart) -> Value {
+    let entries = input
+        .iter_files()
+        .filter(|(path, _)| {
+            !path.components().any(|component| {
+                processor.config.data_dir.as_deref()
+                    == Some(component.as_os_str().to_string_lossy().as_ref())
+            })
+        })
+        .filter_map(|(_, node)| match node.parsed_contents() {
+            ParsedContents::Markdown(md) => Some((node, md)),
+            _ => None,
+        })
+        .map(|(node, md)| {
+            let frontmatter = md.frontmatter_to_value();
+            let html = md.html.get().map(String::as_str).unwrap_or_default();
+            let body = text_chars(html).collect::<String>();
+
+            Value::Object(vec![
+                (
+                    "title".to_string(),
+                    Value::String(
+                        frontmatter["title"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                    ),
+                ),
+                ("url".to_string(), node.page_value(processor)["url"].clone()),
+                (
+                    "body".to_string(),
+                    Value::String(body.split_whitespace().collect::<Vec<_>>().join(" ")),
+                ),
+            ])
+        })
+        .collect();
+
+    Value::Array(entries)
+}
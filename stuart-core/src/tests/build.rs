@@ -0,0 +1,1199 @@
+use crate::config::LineEndings;
+use crate::error::{FsError, ProcessError};
+use crate::fs::ParsedContents;
+use crate::functions::{Function, FunctionParser};
+use crate::parse::{ParseError, RawFunction};
+use crate::plugins::{Manager, Plugin};
+use crate::process::stack::StackFrame;
+use crate::process::Scope;
+use crate::{Config, Error, Node, OutputEvent, Stuart, StuartBuildHook, TracebackError};
+
+use humphrey_json::Value;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{
+    create_dir_all, metadata, read_dir, read_to_string, remove_dir_all, remove_file, write,
+};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Builds the project with the given name from the `projects` directory, saving it to a temporary directory.
+fn build_project(name: &str, config: Config) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/tests/projects")
+        .join(name);
+
+    let output = std::env::temp_dir().join(format!("stuart-test-{}", name));
+
+    let mut stuart = Stuart::new(path).with_config(config);
+    stuart.build("production".to_string()).unwrap();
+    stuart.save(&output).unwrap();
+
+    output
+}
+
+#[test]
+fn data_dir() {
+    let config = Config {
+        save_data_files: Some(true),
+        data_dir: Some("_data".to_string()),
+        ..Config::default()
+    };
+
+    let output = build_project("data_dir", config);
+    let index = read_to_string(output.join("index.html")).unwrap();
+    let data_dir_exists = output.join("_data").exists();
+    remove_dir_all(&output).unwrap();
+
+    assert!(index.contains("<h1>Data Directory</h1>"));
+    assert!(!data_dir_exists);
+}
+
+#[test]
+fn apply_config() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/data_dir");
+    let config = Config {
+        data_dir: Some("_data".to_string()),
+        ..Config::default()
+    };
+
+    let mut stuart = Stuart::new(path).with_config(config.clone());
+    stuart.build("production".to_string()).unwrap();
+
+    let out = stuart.output.take().unwrap();
+    let names = out.iter_files().map(|(path, _)| path).collect::<Vec<_>>();
+
+    assert_eq!(names, vec![PathBuf::from("index.html")]);
+    assert_eq!(out.count_files(&config), 1);
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/text_files");
+    let config = Config {
+        save_data_files: Some(true),
+        text_extensions: vec!["txt".to_string()],
+        ..Config::default()
+    };
+
+    let mut stuart = Stuart::new(path).with_config(config);
+    stuart.build("production".to_string()).unwrap();
+
+    let out = stuart.output.take().unwrap();
+
+    assert!(out.get_at_path(Path::new("root.html")).is_none());
+    assert!(matches!(
+        out.get_at_path(Path::new("team.json")),
+        Some(Node::File { metadata: Some(metadata), .. }) if metadata["type"].as_str() == Some("data")
+    ));
+}
+
+#[test]
+fn markdown_index() {
+    let config = Config {
+        strip_extensions: Some(false),
+        markdown_index: Some("_index.html".to_string()),
+        ..Config::default()
+    };
+
+    let output = build_project("markdown_index", config);
+    let index = read_to_string(output.join("docs/index.html")).unwrap();
+    let template_exists = output.join("_index.html").exists();
+    remove_dir_all(&output).unwrap();
+
+    assert!(index.contains("<li><a href=\"a.html\">First Page</a></li>"));
+    assert!(index.contains("<li><a href=\"b.html\">Second Page</a></li>"));
+    assert!(index.find("First Page") < index.find("Second Page"));
+    assert!(!template_exists);
+}
+
+#[test]
+fn custom_templates() {
+    let config = Config {
+        strip_extensions: Some(false),
+        root_template: "_layout.html".to_string(),
+        ..Config::default()
+    };
+
+    let output = build_project("templates", config);
+    let index = read_to_string(output.join("index.html")).unwrap();
+    let root = read_to_string(output.join("root.html")).unwrap();
+    let template_exists = output.join("_layout.html").exists();
+    remove_dir_all(&output).unwrap();
+
+    assert!(index.contains("<body>"));
+    assert!(index.contains("<h1>Home</h1>"));
+    assert!(root.contains("<body>"));
+    assert!(root.contains("<h1>Root Vegetables</h1>"));
+    assert!(!template_exists);
+}
+
+#[test]
+fn output_summary() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/markdown_index");
+    let output = std::env::temp_dir().join("stuart-test-output_summary");
+
+    let mut stuart = Stuart::new(path).with_config(Config {
+        strip_extensions: Some(false),
+        markdown_index: Some("_index.html".to_string()),
+        ..Config::default()
+    });
+    stuart.build("production".to_string()).unwrap();
+    stuart.save(&output).unwrap();
+
+    let saved_size: u64 = [
+        "index.html",
+        "docs/index.html",
+        "docs/a.html",
+        "docs/b.html",
+    ]
+    .iter()
+    .map(|file| metadata(output.join(file)).unwrap().len())
+    .sum();
+
+    remove_dir_all(&output).unwrap();
+
+    let out = stuart.output.as_ref().unwrap();
+    assert_eq!(out.count_files(&stuart.config), 4);
+    assert_eq!(out.total_size(&stuart.config), saved_size);
+}
+
+#[test]
+fn directory_listings() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/partial");
+    let output = std::env::temp_dir().join("stuart-test-directory_listings");
+
+    let mut stuart = Stuart::new(path).with_config(Config {
+        strip_extensions: Some(false),
+        directory_listings: Some(true),
+        ..Config::default()
+    });
+    stuart.build("production".to_string()).unwrap();
+    stuart.save(&output).unwrap();
+
+    let index = read_to_string(output.join("index.html")).unwrap();
+    let listing = read_to_string(output.join("blog/index.html")).unwrap();
+    remove_dir_all(&output).unwrap();
+
+    assert!(!index.contains("Index of"));
+    assert!(listing.contains("<title>Index of /blog/</title>"));
+    assert!(listing.contains("<a href=\"../\">"));
+    assert!(listing.contains("<a href=\"other.html\">other.html</a>"));
+    assert!(listing.contains("<a href=\"post.html\">post.html</a>"));
+}
+
+#[test]
+fn search_index() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/partial");
+    let output = std::env::temp_dir().join("stuart-test-search_index");
+
+    let new = || {
+        Stuart::new(&path).with_config(Config {
+            search_index: Some("assets/search.json".to_string()),
+            ..Config::default()
+        })
+    };
+
+    // The index is saved by partial and streaming builds as well as full builds.
+    let mut stuart = new();
+    stuart
+        .build_only("production".to_string(), "blog/post.md")
+        .unwrap();
+    stuart.save_merged(&output).unwrap();
+    assert!(output.join("assets/search.json").exists());
+    remove_dir_all(&output).unwrap();
+
+    new()
+        .build_streaming("production".to_string(), &output, &mut |_| ())
+        .unwrap();
+    assert!(output.join("assets/search.json").exists());
+    remove_dir_all(&output).unwrap();
+
+    let mut stuart = new();
+    stuart.build("production".to_string()).unwrap();
+    stuart.save(&output).unwrap();
+
+    let index = read_to_string(output.join("assets/search.json")).unwrap();
+    remove_dir_all(&output).unwrap();
+
+    let index: Value = humphrey_json::from_str(&index).unwrap();
+    let mut entries = index
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            (
+                entry["title"].as_str().unwrap().to_string(),
+                entry["url"].as_str().unwrap().to_string(),
+                entry["body"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    assert_eq!(
+        entries,
+        vec![
+            (
+                "Other Post".to_string(),
+                "/blog/other/".to_string(),
+                "This is another blog post.".to_string()
+            ),
+            (
+                "Post".to_string(),
+                "/blog/post/".to_string(),
+                "This is a blog post.".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn merge_input() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/partial");
+    let file = |name: &str, contents: &str| Node::from_bytes(name, contents, name, None).unwrap();
+
+    let mut stuart = Stuart::new(path).with_config(Config {
+        strip_extensions: Some(false),
+        ..Config::default()
+    });
+    stuart.merge_input(Node::Directory {
+        name: "overrides".to_string(),
+        children: vec![
+            file(
+                "index.html",
+                "{{ begin(\"main\") }}<h1>Override</h1>{{ end(\"main\") }}",
+            ),
+            Node::Directory {
+                name: "blog".to_string(),
+                children: vec![file("new.md", "---\ntitle: \"New Post\"\n---\n\nNew.")],
+                source: PathBuf::from("overrides/blog"),
+            },
+        ],
+        source: PathBuf::from("overrides"),
+    });
+    stuart.build("production".to_string()).unwrap();
+
+    let out = stuart.output.as_ref().unwrap();
+    let contents = |path: &str| {
+        String::from_utf8(
+            out.get_at_path(&PathBuf::from(path))
+                .unwrap()
+                .contents()
+                .unwrap()
+                .to_vec(),
+        )
+        .unwrap()
+    };
+
+    assert!(contents("index.html").contains("<h1>Override</h1>"));
+    assert!(!contents("index.html").contains("<h1>Home</h1>"));
+    assert!(contents("blog/post.html").contains("<h1>Post</h1>"));
+    assert!(contents("blog/new.html").contains("<h1>New Post</h1>"));
+}
+
+#[test]
+fn merge_output_metadata() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/partial");
+    let output = std::env::temp_dir().join("stuart-test-merge-output-metadata.json");
+    let file = |name: &str, metadata: Option<Value>| Node::File {
+        name: name.to_string(),
+        contents: Vec::new(),
+        parsed_contents: ParsedContents::None,
+        metadata,
+        source: PathBuf::from("static").join(name),
+    };
+
+    let mut stuart = Stuart::new(path).with_config(Config {
+        save_metadata: Some(true),
+        ..Config::default()
+    });
+    stuart.build("production".to_string()).unwrap();
+    stuart
+        .merge_output(Node::Directory {
+            name: String::new(),
+            children: vec![
+                file("favicon.ico", None),
+                file("notes.txt", Some(Value::String("draft".to_string()))),
+                Node::Directory {
+                    name: "blog".to_string(),
+                    children: vec![file(
+                        "cover.png",
+                        Some(Value::Object(vec![
+                            ("type".to_string(), Value::String("image".to_string())),
+                            ("width".to_string(), Value::Number(640.0)),
+                        ])),
+                    )],
+                    source: PathBuf::from("static/blog"),
+                },
+            ],
+            source: PathBuf::from("static"),
+        })
+        .unwrap();
+    stuart.save_metadata(&output).unwrap();
+
+    let metadata: Value = humphrey_json::from_str(read_to_string(&output).unwrap()).unwrap();
+    remove_file(&output).unwrap();
+
+    let entries = match &metadata["data"] {
+        Value::Array(entries) => entries.clone(),
+        _ => panic!("metadata has no data"),
+    };
+    let find = |entries: &[Value], name: &str| {
+        entries
+            .iter()
+            .find(|entry| entry["name"].as_str() == Some(name))
+            .cloned()
+            .unwrap_or_else(|| panic!("no metadata for `{}`", name))
+    };
+
+    assert_eq!(find(&entries, "favicon.ico")["type"].as_str(), Some("file"));
+    assert_eq!(find(&entries, "notes.txt")["type"].as_str(), Some("file"));
+    assert_eq!(find(&entries, "notes.txt")["value"].as_str(), Some("draft"));
+
+    let blog = find(&entries, "blog");
+    let blog_children = match &blog["children"] {
+        Value::Array(children) => children.clone(),
+        _ => panic!("blog has no children"),
+    };
+    let cover = find(&blog_children, "cover.png");
+
+    assert_eq!(blog["type"].as_str(), Some("directory"));
+    assert_eq!(cover["type"].as_str(), Some("image"));
+    assert_eq!(cover["width"].as_number(), Some(640.0));
+    assert_eq!(
+        find(&blog_children, "post.html")["type"].as_str(),
+        Some("markdown")
+    );
+}
+
+#[test]
+fn build_streaming() {
+    /// Returns the paths of the files below the given directory, relative to it, in sorted order.
+    fn list_files(dir: &Path) -> Vec<String> {
+        let mut files = Vec::new();
+
+        for entry in read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+
+            if path.is_dir() {
+                files.extend(
+                    list_files(&path)
+                        .into_iter()
+                        .map(|file| format!("{}/{}", name, file)),
+                );
+            } else {
+                files.push(name);
+            }
+        }
+
+        files.sort();
+        files
+    }
+
+    /// Collects the names of the files below the given node.
+    fn file_names(node: &Node, names: &mut Vec<String>) {
+        match node.children() {
+            Some(children) => children.iter().for_each(|child| file_names(child, names)),
+            None => names.push(node.name().to_string()),
+        }
+    }
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/streaming");
+    let output = std::env::temp_dir().join("stuart-test-build_streaming");
+    let config = Config {
+        data_dir: Some("_data".to_string()),
+        ..Config::default()
+    };
+
+    let mut events = Vec::new();
+    let mut stuart = Stuart::new(&path).with_config(config.clone());
+    stuart
+        .build_streaming("production".to_string(), &output, &mut |event| {
+            events.push(match event {
+                OutputEvent::File(name, _) => name,
+                OutputEvent::EnterDirectory(name) => format!("{}/", name),
+                OutputEvent::ExitDirectory => "..".to_string(),
+            })
+        })
+        .unwrap();
+
+    let post = read_to_string(output.join("blog/post/index.html")).unwrap();
+    let index = read_to_string(output.join("index.html")).unwrap();
+    let files = list_files(&output);
+    let has_root = output.join("root").exists();
+    let has_md = output.join("md").exists();
+    let has_data_dir = output.join("_data").exists();
+    remove_dir_all(&output).unwrap();
+
+    let mut expected = Stuart::new(&path).with_config(config);
+    expected.build("production".to_string()).unwrap();
+    expected.save(&output).unwrap();
+    let expected_post = read_to_string(output.join("blog/post/index.html")).unwrap();
+    let expected_files = list_files(&output);
+    remove_dir_all(&output).unwrap();
+
+    let mut file_events = events
+        .iter()
+        .filter(|event| !event.ends_with('/') && *event != "..")
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut expected_file_events = Vec::new();
+    file_names(expected.output.as_ref().unwrap(), &mut expected_file_events);
+    file_events.sort();
+    expected_file_events.sort();
+
+    assert!(stuart.output.is_none());
+    assert_eq!(events, ["blog/", "post.html", "..", "index.html"]);
+    assert_eq!(files, ["blog/post/index.html", "index.html"]);
+    assert!(!has_root && !has_md && !has_data_dir);
+    assert_eq!(files, expected_files);
+    assert_eq!(file_events, expected_file_events);
+    assert_eq!(post, expected_post);
+    assert!(index.contains("<h1>Streaming</h1>"));
+}
+
+#[test]
+fn dependency_order() {
+    struct RecordingHook(Rc<RefCell<Vec<String>>>);
+
+    impl StuartBuildHook for RecordingHook {
+        fn on_file_processed(&self, input: &Node, _output: &Node) {
+            self.0.borrow_mut().push(input.name().to_string());
+        }
+    }
+
+    /// Outputs the names of the files which have been processed so far, as recorded by the hook.
+    #[derive(Debug)]
+    struct ProcessedFunction(Rc<RefCell<Vec<String>>>);
+
+    impl FunctionParser for ProcessedFunction {
+        fn name(&self) -> &str {
+            "processed"
+        }
+
+        fn parse(&self, _: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+            Ok(Box::new(ProcessedFunction(self.0.clone())))
+        }
+    }
+
+    impl Function for ProcessedFunction {
+        fn name(&self) -> &str {
+            "processed"
+        }
+
+        fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+            let processed = self.0.borrow().join(",");
+            scope.output(processed).map_err(|e| scope.error(e))
+        }
+    }
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/dependencies");
+
+    let build_order = |dependency_order| {
+        let events = Rc::new(RefCell::new(Vec::new()));
+
+        let plugins = vec![Plugin {
+            name: "test".to_string(),
+            version: "0.1.0".to_string(),
+            functions: vec![Box::new(ProcessedFunction(events.clone()))],
+            parsers: Vec::new(),
+        }];
+
+        let mut stuart = Stuart::new(&path)
+            .with_config(Config {
+                dependency_order: Some(dependency_order),
+                ..Config::default()
+            })
+            .with_plugins(plugins)
+            .with_hook(RecordingHook(events.clone()));
+        stuart.build("production".to_string()).unwrap();
+
+        let a = stuart
+            .output
+            .as_ref()
+            .and_then(|out| out.get_at_path(&PathBuf::from("a.html")))
+            .and_then(|a| a.contents())
+            .unwrap()
+            .to_vec();
+        let a = String::from_utf8(a).unwrap();
+
+        // `b.html` has only been processed when `a.html` is if the build is in dependency order.
+        let expected = if dependency_order { "b.html" } else { "" };
+        assert!(a.contains(&format!("<p>Processed: {}</p>", expected)));
+
+        let events = events.borrow();
+        let position = |name: &str| events.iter().position(|e| e == name).unwrap();
+
+        position("a.html") < position("b.html")
+    };
+
+    assert!(build_order(false));
+    assert!(!build_order(true));
+
+    let file = |name: &str, contents: &str| Node::from_bytes(name, contents, name, None).unwrap();
+    let cyclic = Node::Directory {
+        name: "content".to_string(),
+        children: vec![
+            file("a.html", "{{ import(\"b.html\") }}"),
+            file("b.html", "{{ import(\"a.html\") }}"),
+        ],
+        source: PathBuf::from("content"),
+    };
+
+    assert!(matches!(
+        crate::order::dependency_order(&cyclic),
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::DependencyCycle(ref cycle),
+            ..
+        })) if cycle == &["a.html", "b.html", "a.html"]
+    ));
+}
+
+#[test]
+fn text_files() {
+    let config = Config {
+        text_extensions: vec!["txt".to_string()],
+        ..Config::default()
+    };
+
+    let output = build_project("text_files", config);
+    let humans = read_to_string(output.join("humans.txt")).unwrap();
+    let notes = read_to_string(output.join("notes.log")).unwrap();
+    remove_dir_all(&output).unwrap();
+
+    assert_eq!(
+        humans,
+        "/* TEAM */\nName: Jane Doe\n\n\
+         Jane writes templates\nfor static sites and\noccasionally\ndocumentation.\n\n\
+         She lives by the sea.\n"
+    );
+    assert_eq!(notes, "Not a {{ template }}\n");
+}
+
+#[test]
+fn template_files() {
+    let config = Config {
+        template_files: vec!["_redirects".to_string()],
+        ..Config::default()
+    };
+
+    let output = build_project("template_files", config);
+    let redirects = read_to_string(output.join("_redirects")).unwrap();
+    let license = read_to_string(output.join("LICENSE")).unwrap();
+    remove_dir_all(&output).unwrap();
+
+    assert_eq!(redirects, "/old  /new  301\n/staging/*  /  302\n");
+    assert_eq!(license, "Copyright {{ $year }} Jane Doe\n");
+}
+
+#[test]
+fn build_only() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/partial");
+    let output = build_project("partial", Config::default());
+
+    write(output.join("index.html"), "untouched").unwrap();
+    write(output.join("blog/other/index.html"), "untouched").unwrap();
+    write(output.join("blog/post/index.html"), "stale").unwrap();
+
+    let mut stuart = Stuart::new(&path);
+    stuart
+        .build_only("production".to_string(), "blog/post.md")
+        .unwrap();
+    stuart.save_merged(&output).unwrap();
+
+    let index = read_to_string(output.join("index.html")).unwrap();
+    let other = read_to_string(output.join("blog/other/index.html")).unwrap();
+    let post = read_to_string(output.join("blog/post/index.html")).unwrap();
+
+    let missing = Stuart::new(&path).build_only("production".to_string(), "blog/missing.md");
+    let traversal = Stuart::new(&path).build_only("production".to_string(), "blog/../index.html");
+
+    remove_dir_all(&output).unwrap();
+
+    assert_eq!(index, "untouched");
+    assert_eq!(other, "untouched");
+    assert!(post.contains("<h1>Post</h1>"));
+    assert!(post.contains("This is a blog post."));
+    assert!(post.contains("<html>"));
+    assert!(missing.is_err());
+    assert!(matches!(
+        traversal,
+        Err(Error::Fs(FsError::NotFound(ref file))) if file == "blog/../index.html"
+    ));
+}
+
+#[test]
+fn clean_output() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/data_dir");
+    let output = std::env::temp_dir().join("stuart-test-clean_output");
+    create_dir_all(output.join("nested")).unwrap();
+    write(output.join("nested/index.html"), "<h1>Output</h1>").unwrap();
+
+    let stuart = Stuart::new(&path);
+
+    assert!(stuart.clean_output(&path).is_err());
+    assert!(stuart.clean_output(path.parent().unwrap()).is_err());
+    assert!(stuart.clean_output(path.join("_data")).is_err());
+    assert!(path.join("_data/site.json").exists());
+
+    stuart.clean_output(&output).unwrap();
+    assert!(!output.exists());
+}
+
+#[test]
+fn line_endings() {
+    let path = std::env::temp_dir().join("stuart-test-line_endings");
+    let output = std::env::temp_dir().join("stuart-test-line_endings-out");
+
+    remove_dir_all(&path).ok();
+    create_dir_all(&path).unwrap();
+    write(
+        path.join("root.html"),
+        "<html>\r\n{{ insert(\"main\") }}\n</html>",
+    )
+    .unwrap();
+    write(
+        path.join("index.html"),
+        "{{ begin(\"main\") }}<p>a</p>\r\n<p>b</p>\n{{ end(\"main\") }}",
+    )
+    .unwrap();
+    write(path.join("humans.txt"), "a\r\nb\n").unwrap();
+    write(path.join("image.bin"), b"a\r\nb\n").unwrap();
+
+    let build = |line_endings| {
+        let config = Config {
+            text_extensions: vec!["txt".to_string()],
+            line_endings,
+            ..Default::default()
+        };
+
+        let mut stuart = Stuart::new(&path).with_config(config);
+        stuart.build("production".to_string()).unwrap();
+        stuart.save(&output).unwrap();
+
+        let files = ["index.html", "humans.txt", "image.bin"]
+            .map(|file| read_to_string(output.join(file)).unwrap());
+
+        remove_dir_all(&output).unwrap();
+
+        files
+    };
+
+    let preserved = build(None);
+    let lf = build(Some(LineEndings::Lf));
+    let crlf = build(Some(LineEndings::Crlf));
+
+    remove_dir_all(&path).unwrap();
+
+    assert_eq!(
+        preserved,
+        [
+            "<html>\r\n<p>a</p>\r\n<p>b</p>\n\n</html>",
+            "a\r\nb\n",
+            "a\r\nb\n"
+        ]
+    );
+    assert_eq!(
+        lf,
+        [
+            "<html>\n<p>a</p>\n<p>b</p>\n\n</html>",
+            "a\nb\n",
+            "a\r\nb\n"
+        ]
+    );
+    assert_eq!(
+        crlf,
+        [
+            "<html>\r\n<p>a</p>\r\n<p>b</p>\r\n\r\n</html>",
+            "a\r\nb\r\n",
+            "a\r\nb\n"
+        ]
+    );
+}
+
+#[test]
+fn preprocessed_markdown_in_input() {
+    let path = std::env::temp_dir().join("stuart-test-preprocessed_markdown_in_input");
+    let output = std::env::temp_dir().join("stuart-test-preprocessed_markdown_in_input-out");
+
+    remove_dir_all(&path).ok();
+    create_dir_all(path.join("posts")).unwrap();
+    write(
+        path.join("root.html"),
+        "<html>{{ insert(\"main\") }}</html>",
+    )
+    .unwrap();
+    write(
+        path.join("md.html"),
+        "{{ begin(\"main\") }}{{ $self.content }}{{ end(\"main\") }}",
+    )
+    .unwrap();
+    write(
+        path.join("index.html"),
+        "{{ begin(\"main\") }}{{ for($post, \"posts/\") }}{{ $post.content }}{{ end(for) }}{{ end(\"main\") }}",
+    )
+    .unwrap();
+    write(
+        path.join("posts/post.md"),
+        "---\ntitle: \"Post\"\n---\nBuilt for {{ $env.STUART_ENV }}",
+    )
+    .unwrap();
+
+    let mut stuart = Stuart::new(&path);
+    stuart.build("production".to_string()).unwrap();
+    stuart.save(&output).unwrap();
+
+    let input = stuart.input.as_ref().unwrap();
+    let post = match input
+        .get_at_path(Path::new("posts/post.md"))
+        .unwrap()
+        .parsed_contents()
+    {
+        ParsedContents::Markdown(md) => md.to_value(),
+        _ => panic!("Expected a markdown file"),
+    };
+
+    let index = read_to_string(output.join("index.html")).unwrap();
+
+    remove_dir_all(&path).unwrap();
+    remove_dir_all(&output).unwrap();
+
+    assert_eq!(
+        post["content"].as_str(),
+        Some("<p>Built for production</p>\n")
+    );
+    assert_eq!(index, "<html><p>Built for production</p>\n</html>");
+}
+
+#[test]
+fn stream_large_files() {
+    let path = std::env::temp_dir().join("stuart-test-stream_large_files");
+    let output = std::env::temp_dir().join("stuart-test-stream_large_files-out");
+    let binary = (0..1024 * 1024)
+        .map(|i| (i % 251) as u8)
+        .collect::<Vec<_>>();
+
+    remove_dir_all(&path).ok();
+    create_dir_all(path.join("static")).unwrap();
+    write(
+        path.join("root.html"),
+        "<html>{{ insert(\"main\") }}</html>",
+    )
+    .unwrap();
+    write(
+        path.join("index.html"),
+        "{{ begin(\"main\") }}<h1>Home</h1>{{ end(\"main\") }}",
+    )
+    .unwrap();
+    write(path.join("video.bin"), &binary).unwrap();
+    write(path.join("static/archive.bin"), &binary).unwrap();
+
+    let config = Config {
+        stream_threshold: Some(1024),
+        ..Default::default()
+    };
+
+    let mut stuart = Stuart::new(&path).with_config(config);
+    stuart.build("production".to_string()).unwrap();
+    stuart.save(&output).unwrap();
+
+    let input = stuart.load_input().unwrap();
+    let video = input.get_at_path(Path::new("video.bin")).unwrap();
+    let index = input.get_at_path(Path::new("index.html")).unwrap();
+
+    let ignored = Node::new_with_options(path.join("static"), false, None, &stuart.config).unwrap();
+    let archive = ignored.get_at_path(Path::new("archive.bin")).unwrap();
+
+    let total_size = stuart.output.as_ref().unwrap().total_size(&stuart.config);
+    let saved = std::fs::read(output.join("video.bin")).unwrap();
+
+    remove_dir_all(&path).unwrap();
+    remove_dir_all(&output).unwrap();
+
+    assert!(video.parsed_contents().is_streamed());
+    assert!(video.contents().unwrap().is_empty());
+    assert!(!index.parsed_contents().is_streamed());
+    assert!(archive.parsed_contents().is_streamed());
+    assert!(archive.contents().unwrap().is_empty());
+    assert_eq!(saved, binary);
+    assert!(total_size > binary.len() as u64);
+}
+
+#[test]
+fn stream_threshold_jsonl() {
+    let path = std::env::temp_dir().join("stuart-test-stream_threshold_jsonl");
+    let events = (0..100)
+        .map(|i| format!("{{\"title\": \"Event {}\"}}\n", i))
+        .collect::<String>();
+
+    remove_dir_all(&path).ok();
+    create_dir_all(&path).unwrap();
+    write(
+        path.join("root.html"),
+        "<html>{{ insert(\"main\") }}</html>",
+    )
+    .unwrap();
+    write(
+        path.join("index.html"),
+        "{{ begin(\"main\") }}{{ for($event, \"events.jsonl\") }}<p>{{ $event.title }}</p>{{ end(for) }}{{ end(\"main\") }}",
+    )
+    .unwrap();
+    write(path.join("events.jsonl"), &events).unwrap();
+
+    let config = Config {
+        stream_threshold: Some(64),
+        ..Default::default()
+    };
+
+    let mut stuart = Stuart::new(&path).with_config(config);
+    let result = stuart.build("production".to_string());
+
+    let input = stuart.load_input().unwrap();
+    let jsonl = input.get_at_path(Path::new("events.jsonl")).unwrap();
+    let is_streamed = jsonl.parsed_contents().is_streamed();
+
+    remove_dir_all(&path).unwrap();
+    result.unwrap();
+
+    let index = stuart
+        .output
+        .as_ref()
+        .and_then(|out| out.get_at_path(Path::new("index.html")))
+        .and_then(|index| index.contents())
+        .map(|contents| String::from_utf8(contents.to_vec()).unwrap())
+        .unwrap();
+
+    assert!(events.len() > 64);
+    assert!(!is_streamed);
+    assert_eq!(index.matches("<p>").count(), 100);
+    assert!(index.contains("<p>Event 99</p>"));
+}
+
+#[test]
+fn save_output_is_file() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/minify");
+    let output = std::env::temp_dir().join("stuart-test-save_output_is_file");
+    write(&output, "not a directory").unwrap();
+
+    let mut stuart = Stuart::new(path);
+    stuart.build("production".to_string()).unwrap();
+
+    let result = stuart.save(&output);
+    let merged_result = stuart.save_merged(&output);
+    let contents = read_to_string(&output).unwrap();
+    remove_file(&output).unwrap();
+
+    assert!(matches!(
+        result,
+        Err(Error::Fs(FsError::OutputIsFile(ref file))) if file == &output
+    ));
+    assert!(matches!(
+        merged_result,
+        Err(Error::Fs(FsError::OutputIsFile(_)))
+    ));
+    assert_eq!(contents, "not a directory");
+}
+
+#[test]
+#[cfg(feature = "minify")]
+fn minify_html() {
+    let config = Config {
+        minify_html: Some(true),
+        ..Config::default()
+    };
+
+    let output = build_project("minify", config);
+    let index = read_to_string(output.join("index.html")).unwrap();
+    remove_dir_all(&output).unwrap();
+
+    assert!(index.contains("<h1>Minified</h1>"));
+    assert!(!index.contains("<!--"));
+    assert!(!index.contains('\n'));
+}
+
+#[test]
+#[cfg(feature = "lint")]
+fn lint_output() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/lint");
+
+    let mut stuart = Stuart::new(path);
+    stuart.build("production".to_string()).unwrap();
+
+    let warnings = stuart.lint_output().unwrap();
+
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].source.ends_with("index.html"));
+    assert_eq!(warnings[0].message, "duplicate id `intro` (2 occurrences)");
+
+    assert!(
+        !crate::lint::lint_html(b"<!DOCTYPE html><html><body><div>Unclosed</body></html>")
+            .is_empty()
+    );
+}
+
+#[test]
+fn build_hooks() {
+    struct RecordingHook(Rc<RefCell<Vec<String>>>);
+
+    impl StuartBuildHook for RecordingHook {
+        fn on_build_start(&self, input: &Node) {
+            self.0.borrow_mut().push(format!("start:{}", input.name()));
+        }
+
+        fn on_build_complete(&self, output: &Node) {
+            self.0
+                .borrow_mut()
+                .push(format!("complete:{}", output.name()));
+        }
+
+        fn on_file_processed(&self, input: &Node, output: &Node) {
+            assert_eq!(input.name(), output.name());
+            self.0.borrow_mut().push(format!("file:{}", input.name()));
+        }
+    }
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/data_dir");
+
+    let mut stuart = Stuart::new(path).with_hook(RecordingHook(events.clone()));
+    stuart.build("production".to_string()).unwrap();
+
+    let events = events.borrow();
+
+    assert_eq!(events.first().unwrap(), "start:data_dir");
+    assert_eq!(events.last().unwrap(), "complete:data_dir");
+    assert!(events.contains(&"file:index.html".to_string()));
+    assert!(events.contains(&"file:site.json".to_string()));
+}
+
+#[test]
+fn plugin_build_start() {
+    struct SeedingManager(Vec<Plugin>);
+
+    impl Manager for SeedingManager {
+        fn plugins(&self) -> &[Plugin] {
+            &self.0
+        }
+
+        fn on_build_start(&self, data: &mut HashMap<String, Value>) {
+            assert!(data.is_empty());
+            data.insert("seeded".to_string(), Value::Bool(true));
+        }
+    }
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/data_dir");
+
+    let mut stuart = Stuart::new(path).with_plugins(SeedingManager(Vec::new()));
+    stuart.set_plugin_data("stale", Value::Bool(true));
+    stuart.build("production".to_string()).unwrap();
+
+    // The data store is cleared before the plugins are called, so nothing is left over from earlier builds.
+    assert_eq!(stuart.get_plugin_data("seeded"), Some(Value::Bool(true)));
+    assert!(stuart.get_plugin_data("stale").is_none());
+}
+
+#[test]
+fn transform_markdown_hook() {
+    struct LazyImages;
+
+    impl StuartBuildHook for LazyImages {
+        fn transform_markdown(&self, _source: &Path, html: String) -> String {
+            html.replace("<img ", "<img loading=\"lazy\" ")
+        }
+    }
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/projects/partial");
+    let image = Node::from_bytes(
+        "image.md",
+        "---\ntitle: \"Image\"\n---\n\n![A cat](cat.png)",
+        "image.md",
+        None,
+    )
+    .unwrap();
+
+    let mut stuart = Stuart::new(path)
+        .with_config(Config {
+            strip_extensions: Some(false),
+            ..Config::default()
+        })
+        .with_hook(LazyImages);
+    stuart.merge_input(Node::Directory {
+        name: "overrides".to_string(),
+        children: vec![Node::Directory {
+            name: "blog".to_string(),
+            children: vec![image],
+            source: PathBuf::from("overrides/blog"),
+        }],
+        source: PathBuf::from("overrides"),
+    });
+    stuart.build("production".to_string()).unwrap();
+
+    let out = stuart.output.as_ref().unwrap();
+    let contents = out
+        .get_at_path(&PathBuf::from("blog/image.html"))
+        .unwrap()
+        .contents()
+        .unwrap();
+    let contents = std::str::from_utf8(contents).unwrap();
+
+    assert!(contents.contains("<img loading=\"lazy\" src=\"cat.png\" alt=\"A cat\" />"));
+    assert!(contents.contains("<h1>Image</h1>"));
+}
+
+#[test]
+fn rename_output_hook() {
+    struct ScssToCss;
+
+    impl StuartBuildHook for ScssToCss {
+        fn rename_output(&self, _source: &Path, name: String) -> String {
+            match name.strip_suffix(".scss") {
+                Some(stem) => format!("{}.css", stem),
+                None => name,
+            }
+        }
+    }
+
+    let path = std::env::temp_dir().join("stuart-test-rename_output_hook");
+    let output = std::env::temp_dir().join("stuart-test-rename_output_hook-out");
+
+    remove_dir_all(&path).ok();
+    create_dir_all(path.join("styles")).unwrap();
+    write(
+        path.join("root.html"),
+        "<html>{{ insert(\"main\") }}</html>",
+    )
+    .unwrap();
+    write(
+        path.join("index.html"),
+        "{{ begin(\"main\") }}<h1>Home</h1>{{ end(\"main\") }}",
+    )
+    .unwrap();
+    write(path.join("styles/main.scss"), "body { color: red; }").unwrap();
+
+    let config = Config {
+        directory_listings: Some(true),
+        ..Default::default()
+    };
+
+    let mut stuart = Stuart::new(&path).with_config(config).with_hook(ScssToCss);
+    stuart.build("production".to_string()).unwrap();
+    stuart
+        .merge_output(Node::Directory {
+            name: "static".to_string(),
+            children: vec![
+                Node::from_bytes("extra.scss", "a {}", "static/extra.scss", None).unwrap(),
+            ],
+            source: PathBuf::from("static"),
+        })
+        .unwrap();
+    stuart.save(&output).unwrap();
+
+    let css = read_to_string(output.join("styles/main.css")).unwrap();
+    let scss_exists = output.join("styles/main.scss").exists();
+    let extra_exists = output.join("extra.css").exists();
+    let listing = read_to_string(output.join("styles/index.html")).unwrap();
+
+    remove_dir_all(&path).unwrap();
+    remove_dir_all(&output).unwrap();
+
+    assert_eq!(css, "body { color: red; }");
+    assert!(!scss_exists);
+    assert!(extra_exists);
+    assert!(listing.contains("<a href=\"main.css\">main.css</a>"));
+}
+
+#[test]
+fn plugin_block_function() {
+    struct WrapParser;
+
+    #[derive(Debug)]
+    struct WrapFunction;
+
+    impl FunctionParser for WrapParser {
+        fn name(&self) -> &str {
+            "wrap"
+        }
+
+        fn parse(&self, _: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+            Ok(Box::new(WrapFunction))
+        }
+    }
+
+    impl Function for WrapFunction {
+        fn name(&self) -> &str {
+            "wrap"
+        }
+
+        fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+            let self_token = scope.tokens.current().unwrap().clone();
+            let tokens = scope
+                .tokens
+                .collect_until_end(self.name())
+                .map_err(|e| self_token.traceback(e))?;
+            let output = scope.render_block(&tokens, StackFrame::new("wrap"), &self_token)?;
+
+            scope
+                .output(format!("<b>{}</b>", String::from_utf8_lossy(&output)))
+                .map_err(|e| self_token.traceback(e))
+        }
+    }
+
+    let path = std::env::temp_dir().join("stuart-test-plugin_block_function");
+
+    remove_dir_all(&path).ok();
+    create_dir_all(&path).unwrap();
+    write(path.join("root.html"), "{{ insert(\"main\") }}").unwrap();
+    write(
+        path.join("index.html"),
+        "{{ begin(\"main\") }}{{ test::wrap() }}{{ test::wrap() }}Hi{{ end(test::wrap) }}!{{ end(test::wrap) }}{{ end(\"main\") }}",
+    )
+    .unwrap();
+
+    let plugins = vec![Plugin {
+        name: "test".to_string(),
+        version: "0.1.0".to_string(),
+        functions: vec![Box::new(WrapParser)],
+        parsers: Vec::new(),
+    }];
+
+    let mut stuart = Stuart::new(&path).with_plugins(plugins);
+    let result = stuart.build("production".to_string());
+    remove_dir_all(&path).unwrap();
+    result.unwrap();
+
+    let index = stuart
+        .output
+        .unwrap()
+        .get_at_path(Path::new("index.html"))
+        .unwrap()
+        .contents()
+        .unwrap()
+        .to_vec();
+
+    assert_eq!(String::from_utf8(index).unwrap(), "<b><b>Hi</b>!</b>");
+}
+
+#[test]
+fn relative_links() {
+    let config = Config {
+        strip_extensions: Some(false),
+        ..Config::default()
+    };
+
+    let output = build_project("relative", config);
+    let index = read_to_string(output.join("index.html")).unwrap();
+    let post = read_to_string(output.join("blog/post.html")).unwrap();
+    let page = read_to_string(output.join("blog/deep/page.html")).unwrap();
+    remove_dir_all(&output).unwrap();
+
+    assert!(index.contains("href=\"style.css\""));
+    assert!(index.contains("<a href=\"blog/post/\">Post</a>"));
+
+    assert!(post.contains("href=\"../style.css\""));
+    assert!(post.contains("<a href=\"other.html\">Sibling</a>"));
+    assert!(post.contains("<a href=\"deep/page.html\">Deeper</a>"));
+    assert!(post.contains("<a href=\"../\">Home</a>"));
+
+    assert!(page.contains("href=\"../../style.css\""));
+    assert!(page.contains("<a href=\"./\">Here</a>"));
+}
@@ -1,23 +1,60 @@
 #[macro_use]
 mod r#macro;
 
+mod build;
+mod parse;
+
+use crate::error::FsError;
+use crate::fs::ParsedContents;
+use crate::parse::{
+    parse_html, parse_html_with_delimiters, ColumnMode, Delimiters, ParseError, Parser,
+};
+use crate::plugins::{NodeParser, NodeProcessor, ParseStep, Plugin};
+use crate::process::iter::TokenIter;
 use crate::process::stack::StackFrame;
-use crate::{Environment, Node, Stuart};
+use crate::process::{process_tokens, ProcessError, ProcessOutput, Scope};
+use crate::{Config, Environment, Error, Node, Stuart, TracebackError};
+
+use humphrey_json::Value;
 
-use std::path::PathBuf;
+use std::fs::{create_dir_all, remove_dir_all, write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 define_testcases![
     for_loop_markdown,
     for_loop_json_file,
+    for_loop_jsonl,
     for_loop_json_object,
     for_loop_nested,
     for_loop_skip_limit,
+    for_loop_sort_multiple,
+    for_loop_sort_date,
+    for_loop_filter,
+    for_loop_totals,
+    frontmatter,
+    nested_blocks,
+    get,
+    adjacent,
+    import_glob,
     dateformat,
+    data_uri,
     excerpt,
+    excerpt_marker,
+    if_truthy,
+    if_negation,
+    if_labels,
     ifdefined,
+    ifdefined_else,
     conditionals,
     markdown_functions,
-    escape
+    snippets,
+    escape,
+    text,
+    aggregate,
+    set,
+    capture,
+    iftrue
 ];
 
 pub struct Testcase {
@@ -36,11 +73,14 @@ impl Testcase {
         let mut context = load_base();
 
         // Merge with the specific context for this testcase.
-        let specific_context = Node::create_from_dir(&path, true, None).unwrap();
+        let specific_context =
+            Node::create_from_dir(&path, true, None, &Config::default()).unwrap();
         context.merge(specific_context).unwrap();
 
-        let input = Node::create_from_file(path.join("in.html"), true, None).unwrap();
-        let output = Node::create_from_file(path.join("out"), false, None).unwrap();
+        let input =
+            Node::create_from_file(path.join("in.html"), true, None, &Config::default()).unwrap();
+        let output =
+            Node::create_from_file(path.join("out"), false, None, &Config::default()).unwrap();
 
         // Add the input to the base context.
         match context {
@@ -58,55 +98,1567 @@ impl Testcase {
     }
 
     pub fn run(&self) {
+        self.run_with_base(StackFrame::new("base"));
+    }
+
+    pub fn run_with_env(&self, stuart_env: &str) {
+        let env = Value::Object(vec![(
+            "STUART_ENV".to_string(),
+            Value::String(stuart_env.to_string()),
+        )]);
+
+        self.run_with_base(StackFrame::new("base").with_variable("env", env));
+    }
+
+    pub fn run_with_base(&self, base: StackFrame) {
+        let expected = match &self.output {
+            Node::File { contents, .. } => std::str::from_utf8(contents)
+                .unwrap()
+                .replace(['\n', '\r'], ""),
+            _ => panic!("Expected output is not a file"),
+        };
+
+        // Check the two outputs match.
+        assert_eq!(self.render(base, Config::default()), expected);
+    }
+
+    pub fn output_with_config(&self, config: Config) -> String {
+        self.render(StackFrame::new("base"), config)
+    }
+
+    fn render(&self, base: StackFrame, config: Config) -> String {
+        let out = self.process(base, config).unwrap();
+
+        // Newlines and carriage returns are removed since Stuart (currently) makes no guarantees about how it outputs them.
+        // The arrays are converted to strings purely so the error messages are easier to read; it has no effect on the actual comparison.
+        match &out {
+            Node::File { contents, .. } => std::str::from_utf8(contents)
+                .unwrap()
+                .replace(['\n', '\r'], ""),
+            _ => panic!("Output is not a file"),
+        }
+    }
+
+    fn process(&self, base: StackFrame, config: Config) -> Result<Node, Error> {
         // Create a mock processing scenario.
         let mut stuart = Stuart::new_from_node(self.context.clone());
-        stuart.base = Some(StackFrame::new("base"));
+        stuart.base = Some(base);
+        stuart.config = config;
+
+        let env = Environment::builder()
+            .with_root(
+                self.context
+                    .get_at_path(&PathBuf::from("root.html"))
+                    .unwrap()
+                    .parsed_contents()
+                    .tokens()
+                    .unwrap(),
+            )
+            .with_md(
+                self.context
+                    .get_at_path(&PathBuf::from("md.html"))
+                    .unwrap()
+                    .parsed_contents()
+                    .tokens()
+                    .unwrap(),
+            )
+            .build();
+
+        // Process the input node.
+        self.input.process(&stuart, env)
+    }
+}
+
+#[test]
+fn for_loop_shuffle() {
+    let testcase = Testcase::new("for_loop_shuffle");
+    testcase.run();
+
+    let with_seed = |shuffle_seed| Config {
+        shuffle_seed,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        testcase.output_with_config(with_seed(1)),
+        testcase.output_with_config(with_seed(1))
+    );
+    assert_ne!(
+        testcase.output_with_config(with_seed(1)),
+        testcase.output_with_config(with_seed(2))
+    );
+}
+
+#[test]
+fn for_loop_variable_limit() {
+    let page = |skip: f64, size: f64| {
+        StackFrame::new("base").with_variable(
+            "page",
+            Value::Object(vec![
+                ("skip".to_string(), Value::Number(skip)),
+                ("size".to_string(), Value::Number(size)),
+            ]),
+        )
+    };
+
+    Testcase::new("for_loop_variable_limit").run_with_base(page(1.0, 2.0));
+}
+
+#[test]
+fn ifenv() {
+    Testcase::new("ifenv").run_with_env("production");
+}
+
+#[test]
+fn debug() {
+    Testcase::new("debug").run_with_env("development");
+}
+
+#[test]
+fn debug_production() {
+    Testcase::new("debug_production").run_with_env("production");
+}
+
+#[test]
+fn frontmatter_styles() {
+    let root = Node::from_bytes(
+        "root.html",
+        "<head>{{ ifdefined($self.styles) }}{{ for($style, $self.styles) }}<link rel=\"stylesheet\" href=\"{{ $style }}\">{{ end(for) }}{{ end(ifdefined) }}</head>{{ insert(\"main\") }}",
+        "root.html",
+        None,
+    )
+    .unwrap();
+    let md = Node::from_bytes(
+        "md.html",
+        "{{ begin(\"main\") }}<h1>{{ $self.title }}</h1>{{ end(\"main\") }}",
+        "md.html",
+        None,
+    )
+    .unwrap();
+    let page = Node::from_bytes(
+        "page.md",
+        "---\ntitle: \"Styled\"\nstyles: [\"/a.css\", \"/b.css\"]\n---\n\nContent.",
+        "page.md",
+        None,
+    )
+    .unwrap();
+
+    let stuart = Stuart::new_from_node(Node::Directory {
+        name: "content".to_string(),
+        children: vec![root.clone(), md.clone(), page],
+        source: PathBuf::from("content"),
+    });
 
-        let env = Environment {
-            vars: &[],
-            root: self
-                .context
+    let env = Environment::builder()
+        .with_root(root.parsed_contents().tokens().unwrap())
+        .with_md(md.parsed_contents().tokens().unwrap())
+        .build();
+
+    let out = stuart
+        .input
+        .as_ref()
+        .unwrap()
+        .get_at_path(Path::new("page.md"))
+        .unwrap()
+        .process(&stuart, env)
+        .unwrap();
+
+    assert_eq!(
+        std::str::from_utf8(out.contents().unwrap()).unwrap(),
+        "<head><link rel=\"stylesheet\" href=\"/a.css\"><link rel=\"stylesheet\" href=\"/b.css\"></head><h1>Styled</h1>"
+    );
+
+    assert!(Node::from_bytes("bad.md", "---\nstyles: [\"/a.css\"\n---\n", "bad.md", None).is_err());
+}
+
+#[test]
+fn debug_strict() {
+    let root = Node::from_bytes("root.html", "{{ insert(\"main\") }}", "root.html", None).unwrap();
+
+    let index = Node::from_bytes(
+        "index.html",
+        "{{ begin(\"main\") }}{{ debug() }}{{ end(\"main\") }}",
+        "index.html",
+        None,
+    )
+    .unwrap();
+
+    let mut stuart = Stuart::new_from_node(Node::Directory {
+        name: "content".to_string(),
+        children: vec![root.clone(), index.clone()],
+        source: PathBuf::from("content"),
+    });
+    stuart.base = Some(StackFrame::new("base").with_variable(
+        "env",
+        Value::Object(vec![(
+            "STUART_ENV".to_string(),
+            Value::String("production".to_string()),
+        )]),
+    ));
+    stuart.config.strict = Some(true);
+
+    let env = Environment::builder()
+        .with_root(root.parsed_contents().tokens().unwrap())
+        .build();
+
+    match index.process(&stuart, env) {
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::FeatureDisabledInMode { feature, mode },
+            ..
+        })) => {
+            assert_eq!(feature, "debug");
+            assert_eq!(mode, "production");
+        }
+        _ => panic!("Expected a feature disabled error"),
+    }
+}
+
+#[test]
+fn node_from_bytes() {
+    let root = Node::from_bytes(
+        "root.html",
+        "<html>{{ insert(\"main\") }}</html>",
+        "root.html",
+        None,
+    )
+    .unwrap();
+
+    let index = Node::from_bytes(
+        "index.html",
+        "{{ begin(\"main\") }}<p>Hello, world!</p>{{ end(\"main\") }}",
+        "index.html",
+        None,
+    )
+    .unwrap();
+
+    assert!(index.parsed_contents().tokens().is_some());
+
+    let stuart = Stuart::new_from_node(Node::Directory {
+        name: "content".to_string(),
+        children: vec![root.clone(), index.clone()],
+        source: PathBuf::from("content"),
+    });
+
+    let env = Environment::builder()
+        .with_root(root.parsed_contents().tokens().unwrap())
+        .build();
+
+    let out = index.process(&stuart, env).unwrap();
+
+    assert_eq!(
+        out.contents().unwrap(),
+        b"<html><p>Hello, world!</p></html>"
+    );
+}
+
+#[test]
+fn scope_output_escaped() {
+    let stuart = Stuart::new_from_node(load_base());
+
+    with_scope(&stuart, |scope| {
+        scope.output("<b>").unwrap();
+        scope.output_escaped("<i>Fish & \"Chips\"</i>").unwrap();
+        scope.output("</b>").unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&scope.stack[0].output).unwrap(),
+            "<b>&lt;i&gt;Fish &amp; &quot;Chips&quot;&lt;/i&gt;</b>"
+        );
+    });
+}
+
+#[test]
+fn scope_get_file() {
+    let stuart = Stuart::new_from_node(load_base());
+
+    with_scope(&stuart, |scope| {
+        assert!(scope
+            .get_file_json("data.json")
+            .unwrap()
+            .as_array()
+            .is_some());
+        assert!(scope.get_file_markdown("posts/post_1.md").is_ok());
+
+        assert!(matches!(
+            scope.get_file_json("missing.json"),
+            Err(ProcessError::NotFound(_))
+        ));
+        assert!(matches!(
+            scope.get_file_markdown("posts"),
+            Err(ProcessError::ExpectedFile(ref path)) if path == "posts"
+        ));
+        assert!(matches!(
+            scope.get_file_json("posts/post_1.md"),
+            Err(ProcessError::InvalidDataType { .. })
+        ));
+        assert!(matches!(
+            scope.get_file_markdown("data.json"),
+            Err(ProcessError::InvalidDataType { .. })
+        ));
+    });
+}
+
+#[test]
+fn get_at_path_result() {
+    let base = load_base();
+
+    assert!(base
+        .get_at_path_result(Path::new("posts"))
+        .unwrap()
+        .is_dir());
+
+    assert!(matches!(
+        base.get_at_path_result(Path::new("missing.json")),
+        Err(FsError::NotFound(ref path)) if path == "missing.json"
+    ));
+
+    assert!(matches!(
+        ProcessError::from(FsError::NotFound("missing.json".to_string())),
+        ProcessError::NotFound(ref path) if path == "missing.json"
+    ));
+    assert!(matches!(
+        ProcessError::from(FsError::Read),
+        ProcessError::Fs(FsError::Read)
+    ));
+}
+
+#[test]
+fn path_traversal() {
+    let context = load_base();
+    let stuart = Stuart::new_from_node(context.clone());
+
+    for path in ["../data.json", "/data.json", "posts/../data.json"] {
+        assert!(context.get_at_path(Path::new(path)).is_none());
+        assert!(matches!(
+            context.get_at_path_result(Path::new(path)),
+            Err(FsError::NotFound(ref p)) if p == path
+        ));
+    }
+
+    let env = Environment::builder()
+        .with_root(
+            context
                 .get_at_path(&PathBuf::from("root.html"))
                 .unwrap()
                 .parsed_contents()
-                .tokens(),
-            md: self
-                .context
-                .get_at_path(&PathBuf::from("md.html"))
+                .tokens()
+                .unwrap(),
+        )
+        .build();
+
+    let process = |body: &str| {
+        Node::from_bytes(
+            "index.html",
+            format!("{{{{ begin(\"main\") }}}}{}{{{{ end(\"main\") }}}}", body),
+            "index.html",
+            None,
+        )
+        .unwrap()
+        .process(&stuart, env)
+    };
+
+    for body in [
+        "{{ import($data, \"../data.json\") }}",
+        "{{ import($data, \"/data.json\") }}",
+        "{{ for($post, \"../posts/\") }}{{ end(for) }}",
+        "{{ for($post, \"/posts/\") }}{{ end(for) }}",
+        "{{ frontmatter(\"posts/../posts/post_1.md\", \"title\") }}",
+        "{{ data_uri(\"../../etc/passwd\") }}",
+    ] {
+        assert!(
+            matches!(
+                process(body),
+                Err(Error::Process(TracebackError {
+                    kind: ProcessError::NotFound(_),
+                    ..
+                }))
+            ),
+            "{}",
+            body
+        );
+    }
+}
+
+#[test]
+fn data_uri_max_size() {
+    let testcase = Testcase::new("data_uri");
+    let size = testcase
+        .context
+        .get_at_path(Path::new("icons/logo.svg"))
+        .unwrap()
+        .contents()
+        .unwrap()
+        .len();
+
+    let with_max_size = |data_uri_max_size| Config {
+        data_uri_max_size,
+        ..Default::default()
+    };
+
+    assert!(testcase
+        .process(StackFrame::new("base"), with_max_size(size))
+        .is_ok());
+    assert!(matches!(
+        testcase.process(StackFrame::new("base"), with_max_size(size - 1)),
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::FileTooLarge(ref file),
+            ..
+        })) if file == "icons/logo.svg"
+    ));
+}
+
+#[test]
+fn expected_file_or_directory() {
+    let context = load_base();
+    let stuart = Stuart::new_from_node(context.clone());
+
+    let env = Environment::builder()
+        .with_root(
+            context
+                .get_at_path(&PathBuf::from("root.html"))
                 .unwrap()
                 .parsed_contents()
-                .tokens(),
-        };
+                .tokens()
+                .unwrap(),
+        )
+        .build();
 
-        // Process the input node.
-        let out = self.input.process(&stuart, env).unwrap();
+    let process = |body: &str| {
+        Node::from_bytes(
+            "index.html",
+            format!("{{{{ begin(\"main\") }}}}{}{{{{ end(\"main\") }}}}", body),
+            "index.html",
+            None,
+        )
+        .unwrap()
+        .process(&stuart, env)
+    };
 
-        match (&out, &self.output) {
-            (
-                Node::File { contents, .. },
-                Node::File {
-                    contents: expected_contents,
-                    ..
-                },
-            ) => {
-                // Check the two outputs match.
-                // Newlines and carriage returns are removed since Stuart (currently) makes no guarantees about how it outputs them.
-                // The arrays are converted to strings purely so the error messages are easier to read; it has no effect on the actual comparison.
-                assert_eq!(
-                    std::str::from_utf8(contents)
-                        .unwrap()
-                        .replace(['\n', '\r'], ""),
-                    std::str::from_utf8(expected_contents)
-                        .unwrap()
-                        .replace(['\n', '\r'], "")
-                );
-            }
-            _ => panic!("Not both files"),
+    assert!(matches!(
+        process("{{ import($posts, \"posts\") }}"),
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::ExpectedFile(ref path),
+            ..
+        })) if path == "posts"
+    ));
+    assert!(matches!(
+        process("{{ for($post, \"posts/post_1.md/\") }}{{ end(for) }}"),
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::ExpectedDirectory(ref path),
+            ..
+        })) if path == "posts/post_1.md/"
+    ));
+}
+
+#[test]
+fn config_merge() {
+    let base = Config {
+        name: "Base".to_string(),
+        strip_extensions: Some(false),
+        save_metadata: Some(true),
+        data_dir: Some("_data".to_string()),
+        root_template: "layout.html".to_string(),
+        ..Config::default()
+    };
+
+    let overlay = Config {
+        name: "Overlay".to_string(),
+        save_metadata: Some(false),
+        minify_html: Some(true),
+        ..Config::default()
+    };
+
+    let merged = base.merge(overlay);
+
+    assert_eq!(merged.name, "Overlay");
+    assert_eq!(merged.root_template, "layout.html");
+    assert_eq!(merged.strip_extensions, Some(false));
+    assert_eq!(merged.save_metadata, Some(false));
+    assert_eq!(merged.minify_html, Some(true));
+    assert_eq!(merged.data_dir.as_deref(), Some("_data"));
+    assert_eq!(merged.lint_html, None);
+
+    let resolved = merged.resolve();
+
+    assert_eq!(resolved.strip_extensions, Some(false));
+    assert_eq!(resolved.lint_html, Some(false));
+    assert!(!Config::default().lint_html());
+    assert!(Config::default().strip_extensions());
+    assert_eq!(Config::default_resolved().strip_extensions, Some(true));
+}
+
+#[test]
+fn config_json() {
+    let config = Config {
+        name: "Site".to_string(),
+        minify_html: Some(true),
+        ..Config::default()
+    };
+
+    let json = config.to_json();
+
+    assert_eq!(json["name"].as_str(), Some("Site"));
+    assert_eq!(json["minify_html"].as_bool(), Some(true));
+    assert_eq!(json["strip_extensions"].as_bool(), Some(true));
+    assert_eq!(json["delimiters"]["open"].as_str(), Some("{{"));
+    assert_eq!(json["author"], Value::Null);
+}
+
+#[test]
+fn sort_children() {
+    let file = |name: &str, contents: &str| Node::from_bytes(name, contents, name, None).unwrap();
+
+    let mut node = Node::Directory {
+        name: "content".to_string(),
+        children: vec![
+            file("b.txt", "1"),
+            Node::Directory {
+                name: "a".to_string(),
+                children: vec![file("z.txt", "22"), file("y.txt", "333")],
+                source: PathBuf::from("a"),
+            },
+            file("c.txt", "4444"),
+        ],
+        source: PathBuf::from("content"),
+    };
+
+    let names = |node: &Node| {
+        node.iter_files()
+            .map(|(path, _)| path.to_string_lossy().replace('\\', "/"))
+            .collect::<Vec<_>>()
+    };
+
+    node.sort_children_by_name();
+    assert_eq!(names(&node), ["a/y.txt", "a/z.txt", "b.txt", "c.txt"]);
+
+    node.sort_children_by(|node| std::cmp::Reverse(node.contents().map(|c| c.len())));
+    assert_eq!(names(&node), ["c.txt", "b.txt", "a/y.txt", "a/z.txt"]);
+
+    let base = load_base();
+    let posts = base.get_at_path(&PathBuf::from("posts")).unwrap();
+    let post_names = posts
+        .children()
+        .unwrap()
+        .iter()
+        .map(|n| n.name())
+        .collect::<Vec<_>>();
+    assert_eq!(post_names, ["post_1.md", "post_2.md"]);
+}
+
+#[test]
+fn downcast_custom() {
+    struct TestProcessor(u8);
+    struct OtherProcessor;
+
+    impl NodeProcessor for TestProcessor {
+        fn process(&self, _: &Stuart, _: Environment) -> Result<ProcessOutput, String> {
+            Ok(ProcessOutput::default())
+        }
+    }
+
+    impl NodeProcessor for OtherProcessor {
+        fn process(&self, _: &Stuart, _: Environment) -> Result<ProcessOutput, String> {
+            Ok(ProcessOutput::default())
         }
     }
+
+    let contents = ParsedContents::Custom(Rc::new(Box::new(TestProcessor(42))));
+
+    assert_eq!(contents.downcast_custom::<TestProcessor>().unwrap().0, 42);
+    assert!(contents.downcast_custom::<OtherProcessor>().is_none());
+    assert!(ParsedContents::None
+        .downcast_custom::<TestProcessor>()
+        .is_none());
+}
+
+#[test]
+fn parser_priority() {
+    struct TestParser(&'static str, i32);
+    struct TestProcessor(&'static str);
+
+    impl NodeParser for TestParser {
+        fn extensions(&self) -> Vec<&'static str> {
+            vec!["test"]
+        }
+
+        fn priority(&self) -> i32 {
+            self.1
+        }
+
+        fn parse(&self, _: &[u8], _: &Path) -> Result<Box<dyn NodeProcessor>, String> {
+            Ok(Box::new(TestProcessor(self.0)))
+        }
+    }
+
+    impl NodeProcessor for TestProcessor {
+        fn process(&self, _: &Stuart, _: Environment) -> Result<ProcessOutput, String> {
+            Ok(ProcessOutput::default())
+        }
+    }
+
+    let plugin = |name: &'static str, priority: i32| Plugin {
+        name: name.to_string(),
+        version: "0.1.0".to_string(),
+        functions: Vec::new(),
+        parsers: vec![Box::new(TestParser(name, priority))],
+    };
+
+    let parsed_by = |plugins: Vec<Plugin>| {
+        let node = Node::from_bytes("file.test", "", "file.test", Some(&plugins)).unwrap();
+
+        node.parsed_contents()
+            .downcast_custom::<TestProcessor>()
+            .unwrap()
+            .0
+    };
+
+    // The parser with the highest priority wins wherever its plugin is loaded.
+    assert_eq!(
+        parsed_by(vec![plugin("first", 0), plugin("second", 1)]),
+        "second"
+    );
+    assert_eq!(
+        parsed_by(vec![plugin("second", 1), plugin("first", 0)]),
+        "second"
+    );
+
+    // Ties go to the plugin which was loaded first.
+    assert_eq!(
+        parsed_by(vec![plugin("first", 0), plugin("second", 0)]),
+        "first"
+    );
+    assert_eq!(
+        parsed_by(vec![plugin("second", 0), plugin("first", 0)]),
+        "second"
+    );
+}
+
+#[test]
+fn parser_chain() {
+    /// Uppercases the contents, passing them on as a file with the given extension.
+    struct UpperParser(&'static str);
+    /// Wraps the contents in brackets.
+    struct WrapParser;
+    struct WrapProcessor(String);
+
+    impl NodeParser for UpperParser {
+        fn extensions(&self) -> Vec<&'static str> {
+            vec!["upper"]
+        }
+
+        fn parse(&self, _: &[u8], _: &Path) -> Result<Box<dyn NodeProcessor>, String> {
+            Err("the upper parser only continues".to_string())
+        }
+
+        fn parse_step(&self, contents: &[u8], path: &Path) -> Result<ParseStep, String> {
+            Ok(ParseStep::Continue {
+                name: path
+                    .with_extension(self.0)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string(),
+                contents: contents.to_ascii_uppercase(),
+            })
+        }
+    }
+
+    impl NodeParser for WrapParser {
+        fn extensions(&self) -> Vec<&'static str> {
+            vec!["wrap"]
+        }
+
+        fn parse(&self, contents: &[u8], _: &Path) -> Result<Box<dyn NodeProcessor>, String> {
+            Ok(Box::new(WrapProcessor(format!(
+                "[{}]",
+                String::from_utf8_lossy(contents)
+            ))))
+        }
+    }
+
+    impl NodeProcessor for WrapProcessor {
+        fn process(&self, _: &Stuart, _: Environment) -> Result<ProcessOutput, String> {
+            Ok(ProcessOutput::default())
+        }
+    }
+
+    let plugins = |extension: &'static str| {
+        vec![Plugin {
+            name: "chain".to_string(),
+            version: "0.1.0".to_string(),
+            functions: Vec::new(),
+            parsers: vec![Box::new(UpperParser(extension)), Box::new(WrapParser)],
+        }]
+    };
+
+    // The first parser passes its output on to the second.
+    let plugins_wrap = plugins("wrap");
+    let node = Node::from_bytes("file.upper", "hello", "file.upper", Some(&plugins_wrap)).unwrap();
+    assert_eq!(node.name(), "file.wrap");
+    assert_eq!(node.contents().unwrap(), b"HELLO");
+    assert_eq!(
+        node.parsed_contents()
+            .downcast_custom::<WrapProcessor>()
+            .unwrap()
+            .0,
+        "[HELLO]"
+    );
+
+    // Plugin parsers can also pass their output on to Stuart.
+    let plugins_html = plugins("html");
+    let node = Node::from_bytes("file.upper", "hi", "file.upper", Some(&plugins_html)).unwrap();
+    assert_eq!(node.name(), "file.html");
+    assert!(matches!(node.parsed_contents(), ParsedContents::Html(_)));
+
+    // Each parser is only used once, so continuing with the same extension doesn't loop forever.
+    let plugins_upper = plugins("upper");
+    let node = Node::from_bytes("file.upper", "hi", "file.upper", Some(&plugins_upper)).unwrap();
+    assert_eq!(node.contents().unwrap(), b"HI");
+    assert!(matches!(node.parsed_contents(), ParsedContents::None));
 }
 
 fn load_base() -> Node {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/tests/testcases/_base");
-    Node::create_from_dir(path, true, None).unwrap()
+    Node::create_from_dir(path, true, None, &Config::default()).unwrap()
+}
+
+/// Runs the closure with a scope over no tokens, whose stack contains only an empty base frame.
+fn with_scope<T>(stuart: &Stuart, f: impl FnOnce(&mut Scope) -> T) -> T {
+    let mut tokens = TokenIter::new(&[]);
+    let mut stack = vec![StackFrame::new("base")];
+    let mut sections = Vec::new();
+
+    f(&mut Scope {
+        tokens: &mut tokens,
+        stack: &mut stack,
+        processor: stuart,
+        sections: &mut sections,
+    })
+}
+
+/// Parses the input as `index.html` and processes it in the given base frame without a root template, returning
+///   the output.
+fn process_str(
+    stuart: &Stuart,
+    base: StackFrame,
+    input: &str,
+) -> Result<String, TracebackError<ProcessError>> {
+    let path = Path::new("index.html");
+    let tokens = parse_html(input, path, None).unwrap();
+    let frame = process_tokens(&tokens, base, stuart, path)?;
+
+    Ok(String::from_utf8(frame.output).unwrap())
+}
+
+#[test]
+fn plugin_data() {
+    let stuart = Stuart::new_from_node(load_base());
+
+    assert!(stuart.get_plugin_data("key").is_none());
+    assert!(stuart
+        .set_plugin_data("key", Value::String("value".into()))
+        .is_none());
+    assert_eq!(
+        stuart.get_plugin_data("key"),
+        Some(Value::String("value".into()))
+    );
+}
+
+#[test]
+fn aggregate_invalid() {
+    let context = load_base();
+    let mut stuart = Stuart::new_from_node(context.clone());
+    stuart.base = Some(
+        StackFrame::new("base")
+            .with_variable(
+                "mixed",
+                Value::Array(vec![Value::Number(1.0), Value::String("2".into())]),
+            )
+            .with_variable("number", Value::Number(1.0)),
+    );
+
+    let env = Environment::builder()
+        .with_root(
+            context
+                .get_at_path(&PathBuf::from("root.html"))
+                .unwrap()
+                .parsed_contents()
+                .tokens()
+                .unwrap(),
+        )
+        .build();
+
+    for (function, variable) in ["sum", "min", "max"]
+        .iter()
+        .flat_map(|function| [(function, "mixed"), (function, "number")])
+    {
+        let index = Node::from_bytes(
+            "index.html",
+            format!(
+                "{{{{ begin(\"main\") }}}}{{{{ {}(${}) }}}}{{{{ end(\"main\") }}}}",
+                function, variable
+            ),
+            "index.html",
+            None,
+        )
+        .unwrap();
+
+        let result = index.process(&stuart, env);
+
+        match variable {
+            "mixed" => assert!(matches!(
+                result,
+                Err(Error::Process(TracebackError {
+                    kind: ProcessError::InvalidDataType { .. },
+                    ..
+                }))
+            )),
+            _ => assert!(matches!(
+                result,
+                Err(Error::Process(TracebackError {
+                    kind: ProcessError::NotJsonArray,
+                    ..
+                }))
+            )),
+        }
+    }
+}
+
+#[test]
+fn frontmatter_invalid() {
+    let context = load_base();
+    let stuart = Stuart::new_from_node(context.clone());
+
+    let env = Environment::builder()
+        .with_root(
+            context
+                .get_at_path(&PathBuf::from("root.html"))
+                .unwrap()
+                .parsed_contents()
+                .tokens()
+                .unwrap(),
+        )
+        .build();
+
+    let process = |call: &str| {
+        Node::from_bytes(
+            "index.html",
+            format!(
+                "{{{{ begin(\"main\") }}}}{{{{ {} }}}}{{{{ end(\"main\") }}}}",
+                call
+            ),
+            "index.html",
+            None,
+        )
+        .unwrap()
+        .process(&stuart, env)
+    };
+
+    assert!(matches!(
+        process("frontmatter(\"posts/post_1.md\", \"subtitle\")"),
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::MissingFrontmatterKey { ref key, .. },
+            ..
+        })) if key == "subtitle"
+    ));
+    assert!(matches!(
+        process("frontmatter(\"data.json\", \"title\")"),
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::InvalidDataType { .. },
+            ..
+        }))
+    ));
+    assert!(matches!(
+        process("frontmatter(\"posts/missing.md\", \"title\")"),
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::NotFound(_),
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn tree_json() {
+    let tree = load_base().to_tree_json();
+
+    assert_eq!(tree["type"], Value::String("directory".to_string()));
+    assert_eq!(tree["path"], Value::String(String::new()));
+
+    let children = tree["children"].as_array().unwrap();
+    let summary = children
+        .iter()
+        .map(|c| {
+            (
+                c["name"].as_str().unwrap(),
+                c["type"].as_str().unwrap(),
+                c["kind"].as_str().unwrap_or_default(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        summary,
+        vec![
+            ("data.json", "file", "json"),
+            ("md.html", "file", "html"),
+            ("posts", "directory", ""),
+            ("root.html", "file", "html"),
+        ]
+    );
+
+    let post = &children[2]["children"][0];
+    assert_eq!(post["path"], Value::String("posts/post_1.md".to_string()));
+    assert_eq!(post["kind"], Value::String("markdown".to_string()));
+    assert_eq!(
+        post["frontmatter"]["title"],
+        Value::String("Post 1".to_string())
+    );
+    assert_eq!(children[0]["frontmatter"], Value::Null);
+}
+
+#[test]
+fn collect_until_end() {
+    let tokens = parse_html_with_delimiters(
+        "{{ for($a, $b) }}{{ for($c, $a) }}{{ $c }}{{ end(for) }}{{ end(for) }}after",
+        &PathBuf::from("index.html"),
+        None,
+        &Delimiters::default(),
+        ColumnMode::default(),
+    )
+    .unwrap();
+
+    let mut iter = TokenIter::new(&tokens);
+    iter.next();
+
+    let body = iter.collect_until_end("for").unwrap();
+    assert_eq!(body.len(), 3);
+    assert_eq!(iter.current().unwrap().as_function().unwrap().name(), "end");
+    assert_eq!(iter.next().unwrap().as_raw(), Some("after"));
+
+    let mut iter = TokenIter::new(&tokens);
+    iter.next();
+    iter.next();
+    iter.next();
+    iter.next();
+
+    assert!(matches!(
+        iter.collect_until_end("if"),
+        Err(ProcessError::UnexpectedEndOfFile)
+    ));
+}
+
+#[test]
+fn wordwrap_invalid() {
+    let root = Node::from_bytes("root.html", "{{ insert(\"main\") }}", "root.html", None).unwrap();
+    let stuart = Stuart::new_from_node(Node::Directory {
+        name: "base".to_string(),
+        children: vec![root.clone()],
+        source: PathBuf::from("base"),
+    });
+
+    let env = Environment::builder()
+        .with_root(root.parsed_contents().tokens().unwrap())
+        .build();
+
+    let index = Node::from_bytes(
+        "index.html",
+        "{{ begin(\"main\") }}{{ wordwrap($page, 10) }}{{ end(\"main\") }}",
+        "index.html",
+        None,
+    )
+    .unwrap();
+
+    assert!(matches!(
+        index.process(&stuart, env),
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::InvalidDataType { .. },
+            ..
+        }))
+    ));
+
+    assert!(Node::from_bytes("index.html", "{{ wordwrap($bio, 0) }}", "index.html", None).is_err());
+}
+
+#[test]
+fn iftrue_invalid() {
+    let root = Node::from_bytes("root.html", "{{ insert(\"main\") }}", "root.html", None).unwrap();
+    let stuart = Stuart::new_from_node(Node::Directory {
+        name: "base".to_string(),
+        children: vec![root.clone()],
+        source: PathBuf::from("base"),
+    });
+
+    let env = Environment::builder()
+        .with_root(root.parsed_contents().tokens().unwrap())
+        .build();
+
+    for call in ["iftrue($page)", "iffalse($page.url)"] {
+        let index = Node::from_bytes(
+            "index.html",
+            format!(
+                "{{{{ begin(\"main\") }}}}{{{{ {} }}}}{{{{ end({}) }}}}{{{{ end(\"main\") }}}}",
+                call,
+                call.split('(').next().unwrap()
+            ),
+            "index.html",
+            None,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            index.process(&stuart, env),
+            Err(Error::Process(TracebackError {
+                kind: ProcessError::InvalidDataType { ref expected, .. },
+                ..
+            })) if expected == "boolean"
+        ));
+    }
+}
+
+#[test]
+fn for_loop_jsonl_invalid() {
+    let root = Node::from_bytes("root.html", "{{ insert(\"main\") }}", "root.html", None).unwrap();
+
+    let events = Node::from_bytes(
+        "events.jsonl",
+        "{\"title\": \"Launch party\"}\n\n{\"title\": \"Workshop\"\n",
+        "events.jsonl",
+        None,
+    )
+    .unwrap();
+
+    let index = Node::from_bytes(
+        "index.html",
+        "{{ begin(\"main\") }}{{ for($event, \"events.jsonl\") }}{{ end(for) }}{{ end(\"main\") }}",
+        "index.html",
+        None,
+    )
+    .unwrap();
+
+    let stuart = Stuart::new_from_node(Node::Directory {
+        name: "content".to_string(),
+        children: vec![root.clone(), events, index.clone()],
+        source: PathBuf::from("content"),
+    });
+
+    let env = Environment::builder()
+        .with_root(root.parsed_contents().tokens().unwrap())
+        .build();
+
+    match index.process(&stuart, env) {
+        Err(Error::Process(TracebackError {
+            path,
+            line,
+            kind: ProcessError::InvalidJson,
+            ..
+        })) => {
+            assert_eq!(path, PathBuf::from("events.jsonl"));
+            assert_eq!(line, 3);
+        }
+        _ => panic!("Expected an invalid JSON error"),
+    }
+}
+
+#[test]
+fn for_loop_variable_limit_invalid() {
+    let root = Node::from_bytes("root.html", "{{ insert(\"main\") }}", "root.html", None).unwrap();
+    let data = Node::from_bytes("data.json", "[1, 2, 3]", "data.json", None).unwrap();
+
+    let index = Node::from_bytes(
+        "index.html",
+        "{{ begin(\"main\") }}{{ for($n, \"data.json\", limit=$size) }}{{ end(for) }}{{ end(\"main\") }}",
+        "index.html",
+        None,
+    )
+    .unwrap();
+
+    let mut stuart = Stuart::new_from_node(Node::Directory {
+        name: "content".to_string(),
+        children: vec![root.clone(), data, index.clone()],
+        source: PathBuf::from("content"),
+    });
+
+    let env = Environment::builder()
+        .with_root(root.parsed_contents().tokens().unwrap())
+        .build();
+
+    for size in [
+        Value::Number(-1.0),
+        Value::Number(1.5),
+        Value::String("2".into()),
+    ] {
+        stuart.base = Some(StackFrame::new("base").with_variable("size", size));
+
+        assert!(matches!(
+            index.process(&stuart, env),
+            Err(Error::Process(TracebackError {
+                kind: ProcessError::InvalidDataType { .. },
+                ..
+            }))
+        ));
+    }
+
+    stuart.base = Some(StackFrame::new("base"));
+
+    assert!(matches!(
+        index.process(&stuart, env),
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::UndefinedVariable(_),
+            ..
+        }))
+    ));
+
+    assert!(Node::from_bytes(
+        "index.html",
+        "{{ for($n, \"data.json\", skip=-1) }}",
+        "index.html",
+        None
+    )
+    .is_err());
+}
+
+#[test]
+fn snippet_undefined() {
+    let root = Node::from_bytes("root.html", "{{ insert(\"main\") }}", "root.html", None).unwrap();
+
+    let index = Node::from_bytes(
+        "index.html",
+        "{{ begin(\"main\") }}{{ snippet::card(title=\"Missing\") }}{{ end(\"main\") }}",
+        "index.html",
+        None,
+    )
+    .unwrap();
+
+    let stuart = Stuart::new_from_node(Node::Directory {
+        name: "content".to_string(),
+        children: vec![root.clone(), index.clone()],
+        source: PathBuf::from("content"),
+    });
+
+    let env = Environment::builder()
+        .with_root(root.parsed_contents().tokens().unwrap())
+        .build();
+
+    assert!(matches!(
+        index.process(&stuart, env),
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::UndefinedSnippet(ref name),
+            ..
+        })) if name == "card"
+    ));
+
+    assert!(Node::from_bytes(
+        "index.html",
+        "{{ snippet::card(\"Title\") }}",
+        "index.html",
+        None
+    )
+    .is_err());
+
+    assert!(matches!(
+        Node::from_bytes("index.html", "{{ lenght() }}", "index.html", None),
+        Err(Error::Parse(TracebackError {
+            kind: ParseError::NonexistentFunction(ref name),
+            ..
+        })) if name == "lenght"
+    ));
+}
+
+#[test]
+fn snippet_recursion() {
+    let root = Node::from_bytes("root.html", "{{ insert(\"main\") }}", "root.html", None).unwrap();
+
+    let index = Node::from_bytes(
+        "index.html",
+        "{{ begin(snippet, \"loop\") }}{{ snippet::loop() }}{{ end(snippet) }}{{ begin(\"main\") }}{{ snippet::loop() }}{{ end(\"main\") }}",
+        "index.html",
+        None,
+    )
+    .unwrap();
+
+    let stuart = Stuart::new_from_node(Node::Directory {
+        name: "content".to_string(),
+        children: vec![root.clone(), index.clone()],
+        source: PathBuf::from("content"),
+    });
+
+    let env = Environment::builder()
+        .with_root(root.parsed_contents().tokens().unwrap())
+        .build();
+
+    assert!(matches!(
+        index.process(&stuart, env),
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::SnippetTooDeep(ref name),
+            ..
+        })) if name == "loop"
+    ));
+}
+
+#[test]
+fn validate() {
+    let file = |name: &str, contents: &str| Node::from_bytes(name, contents, name, None).unwrap();
+    let directory = |children: Vec<Node>| Node::Directory {
+        name: "content".to_string(),
+        children,
+        source: PathBuf::from("content"),
+    };
+
+    let root = file("root.html", "{{ insert(\"main\") }}");
+    let index = file(
+        "index.html",
+        "{{ import($data, \"data.json\") }}{{ import($missing, \"missing.json\") }}",
+    );
+    let post = file("post.md", "# Post");
+
+    let stuart = Stuart::new_from_node(directory(vec![
+        root.clone(),
+        index.clone(),
+        file("data.json", "[]"),
+    ]));
+    let warnings = stuart.validate().unwrap();
+
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].source, PathBuf::from("index.html"));
+    assert!(warnings[0].message.contains("missing.json"));
+
+    let stuart = Stuart::new_from_node(directory(vec![index.clone()]));
+    assert!(matches!(
+        stuart.validate(),
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::MissingHtmlRoot,
+            ..
+        }))
+    ));
+
+    let stuart = Stuart::new_from_node(directory(vec![root.clone(), post.clone()]));
+    assert!(matches!(
+        stuart.validate(),
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::MissingMarkdownRoot,
+            ..
+        }))
+    ));
+
+    let stuart = Stuart::new_from_node(directory(vec![
+        root,
+        post,
+        file("md.html", "{{ $self.content }}"),
+    ]));
+    assert!(stuart.validate().unwrap().is_empty());
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn watch() {
+    let dir = std::env::temp_dir().join("stuart-test-watch");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("posts")).unwrap();
+
+    let mut changes = Node::watch(&dir).unwrap();
+    std::fs::write(dir.join("posts/post.md"), "# Post").unwrap();
+
+    let mut change = None;
+
+    for _ in 0..50 {
+        change = changes.try_next();
+
+        if change.is_some() {
+            break;
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+
+    assert_eq!(change, Some(PathBuf::from("posts/post.md")));
+    assert!(Node::watch(dir.join("missing")).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn parser_column_mode() {
+    let path = PathBuf::from("index.html");
+
+    let column = |column_mode| {
+        let mut parser = Parser::new_with_column_mode("é😀x".chars(), &path, column_mode);
+
+        for _ in 0..3 {
+            parser.next().unwrap();
+        }
+
+        parser.traceback(ParseError::UnexpectedEOF).column
+    };
+
+    assert_eq!(column(ColumnMode::CharOffset), 3);
+    assert_eq!(column(ColumnMode::ByteOffset), 7);
+    assert_eq!(column(ColumnMode::Utf16Offset), 4);
+}
+
+#[test]
+fn config_column_mode() {
+    let path = std::env::temp_dir().join("stuart-test-config_column_mode");
+    remove_dir_all(&path).ok();
+    create_dir_all(&path).unwrap();
+    write(path.join("index.html"), "<p>é😀</p>{{ end( }}").unwrap();
+
+    let column = |column_mode| {
+        let config = Config {
+            column_mode,
+            ..Config::default()
+        };
+
+        match Node::new_with_options(&path, true, None, &config) {
+            Err(Error::Parse(e)) => e.column,
+            _ => panic!("Expected a parse error"),
+        }
+    };
+
+    let default_column = column(None);
+    let char_column = column(Some(ColumnMode::CharOffset));
+    let byte_column = column(Some(ColumnMode::ByteOffset));
+    let utf16_column = column(Some(ColumnMode::Utf16Offset));
+    remove_dir_all(&path).unwrap();
+
+    assert_eq!(default_column, char_column);
+    assert_eq!(byte_column, char_column + 4);
+    assert_eq!(utf16_column, char_column + 1);
+}
+
+#[test]
+fn custom_delimiters() {
+    let delimiters = Delimiters::new("<%", "%>").unwrap();
+
+    let tokens = parse_html_with_delimiters(
+        "<p><% $self.title %></p><div>{{ vue }}</div>\\<% <% end(for) %>",
+        &PathBuf::from("index.html"),
+        None,
+        &delimiters,
+        ColumnMode::default(),
+    )
+    .unwrap();
+
+    assert_eq!(tokens.len(), 4);
+    assert_eq!(tokens[0].as_raw(), Some("<p>"));
+    assert_eq!(tokens[1].as_variable(), Some("self.title"));
+    assert_eq!(tokens[2].as_raw(), Some("</p><div>{{ vue }}</div><% "));
+    assert_eq!(tokens[3].as_function().unwrap().name(), "end");
+
+    assert!(matches!(
+        Delimiters::new("", "%>"),
+        Err(ParseError::InvalidDelimiters)
+    ));
+    assert!(matches!(
+        Delimiters::new("%%", "%%"),
+        Err(ParseError::InvalidDelimiters)
+    ));
+}
+
+#[test]
+fn function_signatures() {
+    let parse = |input: &str| {
+        parse_html(input, &PathBuf::from("index.html"), None)
+            .map(|_| ())
+            .map_err(|e| e.kind)
+    };
+
+    assert!(matches!(
+        parse("{{ for($post, \"posts/\", $extra) }}"),
+        Err(ParseError::WrongArgumentCount {
+            ref function,
+            min: 2,
+            max: Some(2),
+            found: 3,
+        }) if function == "for"
+    ));
+    assert!(matches!(
+        parse("{{ add($a) }}"),
+        Err(ParseError::WrongArgumentCount {
+            min: 2,
+            max: None,
+            found: 1,
+            ..
+        })
+    ));
+    assert!(matches!(
+        parse("{{ for($post, \"posts/\", sort=\"date\") }}"),
+        Err(ParseError::UnknownNamedArgument {
+            ref function,
+            ref name,
+            ref expected,
+        }) if function == "for" && name == "sort" && expected.contains(&"sortby".to_string())
+    ));
+    assert!(matches!(
+        parse("{{ ifeq($a, $b, not=true, not=false) }}"),
+        Err(ParseError::DuplicateNamedArgument(ref name)) if name == "not"
+    ));
+    assert!(parse("{{ for($post, \"posts/\", limit=2, order=\"desc\") }}{{ end(for) }}").is_ok());
+}
+
+#[test]
+fn get_index_out_of_bounds() {
+    let stuart = Stuart::new_from_node(load_base());
+
+    assert!(matches!(
+        process_str(&stuart, StackFrame::new("base"), "{{ get($item, \"data.json\", 99) }}{{ end(get) }}"),
+        Err(TracebackError {
+            kind: ProcessError::NotFound(ref name),
+            ..
+        }) if name == "data.json[99]"
+    ));
+    assert!(matches!(
+        parse_html(
+            "{{ get($item, \"data.json\", -1) }}{{ end(get) }}",
+            Path::new("index.html"),
+            None
+        ),
+        Err(TracebackError {
+            kind: ParseError::InvalidArgument,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn if_label_mismatch() {
+    let stuart = Stuart::new_from_node(load_base());
+
+    let process = |input: &str| process_str(&stuart, StackFrame::new("base"), input);
+
+    assert!(matches!(
+        process("{{ ifeq(1, 1, label=\"outer\") }}{{ ifeq(2, 2, label=\"inner\") }}a{{ else(label=\"outer\") }}b{{ end(ifeq) }}{{ end(ifeq) }}"),
+        Err(TracebackError {
+            kind: ProcessError::MismatchedLabel { ref label, expected: Some(ref expected) },
+            ..
+        }) if label == "outer" && expected == "inner"
+    ));
+    assert!(matches!(
+        process("{{ ifeq(1, 1) }}a{{ end(ifeq, label=\"outer\") }}"),
+        Err(TracebackError {
+            kind: ProcessError::MismatchedLabel { expected: None, .. },
+            ..
+        })
+    ));
+    assert!(process(
+        "{{ ifeq(1, 1, label=\"outer\") }}a{{ else() }}b{{ end(ifeq, label=\"outer\") }}"
+    )
+    .is_ok());
+}
+
+#[test]
+fn scope_error() {
+    let stuart = Stuart::new_from_node(load_base());
+
+    let process = |input: &str| {
+        let base = StackFrame::new("base").with_variable("date", Value::from("fish"));
+        process_str(&stuart, base, input)
+    };
+
+    let location = |e: TracebackError<ProcessError>| (e.path, e.line, e.column);
+    let expected = |line, column| (PathBuf::from("index.html"), line, column);
+
+    assert_eq!(
+        process("<p>\n  {{ excerpt($missing, 10) }}\n</p>").map_err(location),
+        Err(expected(2, 4))
+    );
+    assert_eq!(
+        process("<p>\n</p>\n{{ import($data, \"missing.json\") }}").map_err(location),
+        Err(expected(3, 2))
+    );
+    #[cfg(feature = "date")]
+    assert_eq!(
+        process("<p>{{ $date }}</p> {{ dateformat($date, \"%Y\") }}").map_err(location),
+        Err(expected(1, 22))
+    );
+
+    with_scope(&stuart, |scope| {
+        assert_eq!(scope.error(ProcessError::StackError).line, 0);
+    });
+}
+
+#[test]
+fn render_str() {
+    let stuart = Stuart::new("content");
+
+    let context = Value::Object(vec![
+        ("name".to_string(), Value::String("Stuart".to_string())),
+        ("count".to_string(), Value::Number(3.0)),
+        (
+            "tags".to_string(),
+            Value::Array(vec![
+                Value::String("fast".to_string()),
+                Value::String("<static>".to_string()),
+            ]),
+        ),
+    ]);
+
+    assert_eq!(
+        stuart
+            .render_str(
+                "<h1>{{ $name }}</h1>{{ ifgt($count, 2) }}<p>many</p>{{ else() }}<p>few</p>{{ end(ifgt) }}",
+                context.clone()
+            )
+            .unwrap(),
+        "<h1>Stuart</h1><p>many</p>"
+    );
+    assert_eq!(
+        stuart
+            .render_str(
+                "{{ for($tag, $tags) }}<li>{{ text($tag) }}</li>{{ end(for) }}",
+                context.clone()
+            )
+            .unwrap(),
+        "<li>fast</li><li>&lt;static&gt;</li>"
+    );
+
+    assert!(matches!(
+        stuart.render_str(
+            "{{ for($post, \"posts/\") }}{{ end(for) }}",
+            context.clone()
+        ),
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::NotFound(_),
+            ..
+        }))
+    ));
+    assert!(matches!(
+        stuart.render_str("{{ $name }}", Value::Null),
+        Err(Error::Process(TracebackError {
+            kind: ProcessError::InvalidDataType { .. },
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn ifsection() {
+    let stuart = Stuart::new_from_node(load_base());
+
+    let root = parse_html(
+        "{{ ifsection(\"sidebar\") }}<aside>{{ insert(\"sidebar\") }}</aside>{{ else() }}<hr>{{ end(ifsection) }}\
+         {{ ifsection(\"footer\", not=true) }}<p>no footer</p>{{ end(ifsection) }}\
+         {{ for($section, $sections) }}[{{ $section }}]{{ end(for) }}",
+        &PathBuf::from("root.html"),
+        None,
+    )
+    .unwrap();
+    let env = Environment::builder().with_root(&root).build();
+
+    let process = |body: &str| {
+        let out = Node::from_bytes("index.html", body.to_string(), "index.html", None)
+            .unwrap()
+            .process(&stuart, env)
+            .unwrap();
+
+        String::from_utf8(out.contents().unwrap().to_vec()).unwrap()
+    };
+
+    assert_eq!(
+        process("{{ begin(\"main\") }}a{{ end(\"main\") }}{{ begin(\"sidebar\") }}b{{ end(\"sidebar\") }}"),
+        "<aside>b</aside><p>no footer</p>[main][sidebar]"
+    );
+    assert_eq!(
+        process("{{ begin(\"main\") }}a{{ end(\"main\") }}"),
+        "<hr><p>no footer</p>[main]"
+    );
 }
@@ -0,0 +1,89 @@
+use crate::parse::RawArgument;
+use crate::FUNCTION_PARSERS;
+
+use proptest::prelude::*;
+
+/// Generates arbitrary arguments which can be written in a template.
+fn raw_argument() -> impl Strategy<Value = RawArgument> {
+    let idents = FUNCTION_PARSERS
+        .iter()
+        .map(|parser| parser.name().to_string())
+        .collect::<Vec<_>>();
+
+    prop_oneof![
+        "[A-Za-z0-9_.]{1,16}".prop_map(RawArgument::Variable),
+        "[^\"]{0,16}".prop_map(RawArgument::String),
+        prop::sample::select(idents).prop_map(RawArgument::Ident),
+        "[a-z_]{1,8}::[a-z_]{1,8}".prop_map(RawArgument::Ident),
+        any::<i32>().prop_map(RawArgument::Integer),
+        any::<bool>().prop_map(RawArgument::Bool),
+    ]
+}
+
+/// Writes the argument as it would appear in a template.
+fn serialize(arg: &RawArgument) -> String {
+    match arg {
+        RawArgument::Variable(name) => format!("${}", name),
+        RawArgument::String(string) => format!("\"{}\"", string),
+        RawArgument::Ident(ident) => ident.clone(),
+        RawArgument::Integer(int) => int.to_string(),
+        RawArgument::Bool(boolean) => boolean.to_string(),
+    }
+}
+
+#[test]
+fn raw_argument_edge_cases() {
+    assert!(RawArgument::parse("$").is_err());
+    assert!(RawArgument::parse("\"").is_err());
+    assert!(RawArgument::parse("\"unclosed").is_err());
+    assert!(RawArgument::parse("2147483648").is_err());
+    assert!(RawArgument::parse("::wrap").is_err());
+    assert!(RawArgument::parse("my_plugin::").is_err());
+    assert!(RawArgument::parse("my_plugin::wrap::inner").is_err());
+
+    assert_eq!(
+        RawArgument::parse("\"\"").ok(),
+        Some(RawArgument::String(String::new()))
+    );
+    assert_eq!(
+        RawArgument::parse("-2147483648").ok(),
+        Some(RawArgument::Integer(i32::MIN))
+    );
+    assert_eq!(
+        RawArgument::parse("my_plugin::wrap").ok(),
+        Some(RawArgument::Ident("my_plugin::wrap".to_string()))
+    );
+}
+
+proptest! {
+    #[test]
+    fn raw_argument_never_panics(arg in any::<String>()) {
+        if let Ok(parsed) = RawArgument::parse(&arg) {
+            match &parsed {
+                RawArgument::Variable(name) => {
+                    prop_assert_eq!(parsed.as_variable(), Some(name.as_str()));
+                    prop_assert_eq!(arg.strip_prefix('$'), Some(name.as_str()));
+                }
+                RawArgument::String(string) => {
+                    prop_assert_eq!(parsed.as_string(), Some(string.as_str()));
+                    prop_assert!(!string.contains('"'));
+                }
+                RawArgument::Ident(ident) => {
+                    prop_assert_eq!(parsed.as_ident(), Some(ident.as_str()));
+                    prop_assert_eq!(ident, &arg);
+                }
+                RawArgument::Integer(int) => {
+                    prop_assert_eq!(parsed.as_integer(), Some(*int));
+                }
+                RawArgument::Bool(boolean) => {
+                    prop_assert_eq!(parsed.as_bool(), Some(*boolean));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn raw_argument_round_trip(arg in raw_argument()) {
+        prop_assert_eq!(RawArgument::parse(&serialize(&arg)).ok(), Some(arg));
+    }
+}
@@ -0,0 +1,108 @@
+//! Provides validation of the project input without building it.
+
+use crate::error::{Error, TracebackError};
+use crate::fs::{Node, ParsedContents};
+use crate::parse::LocatableToken;
+use crate::process::ProcessError;
+use crate::Config;
+
+use std::path::{Path, PathBuf};
+
+/// A warning about a potential problem in an input file, or in the output it produces.
+///
+/// Warnings are returned by [`Stuart::validate`](crate::Stuart::validate), about problems which may cause the build
+///   to fail, and by [`Stuart::lint_output`](crate::Stuart::lint_output), about problems in the output HTML.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    /// The filesystem source of the file containing the problem, or which produced the output containing it.
+    pub source: PathBuf,
+    /// The line number of the problem, or `0` if it is not known, such as for problems in the output.
+    pub line: u32,
+    /// A description of the problem.
+    pub message: String,
+}
+
+/// Validates the given parsed input, returning an error for any problem which will always cause the build to fail.
+///
+/// Problems which only cause the build to fail if the offending code is reached are added to the warnings.
+pub(crate) fn validate_input(
+    input: &Node,
+    config: &Config,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), Error> {
+    let children = input.children().unwrap_or_default();
+
+    if !children.iter().any(|n| n.name() == config.root_template) {
+        return Err(Error::Process(TracebackError {
+            path: input.source().to_path_buf(),
+            line: 0,
+            column: 0,
+            kind: ProcessError::MissingHtmlRoot,
+        }));
+    }
+
+    validate_node(input, input, false, config, warnings)
+}
+
+/// Recursively validates a node, where `has_md` is whether a markdown template has been found in an ancestor.
+fn validate_node(
+    node: &Node,
+    input: &Node,
+    has_md: bool,
+    config: &Config,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), Error> {
+    match node {
+        Node::Directory { children, .. } => {
+            let has_md = has_md || children.iter().any(|n| n.name() == config.md_template);
+
+            for child in children {
+                validate_node(child, input, has_md, config, warnings)?;
+            }
+        }
+        Node::File { .. } => match node.parsed_contents() {
+            ParsedContents::Html(tokens) | ParsedContents::Text(tokens) => {
+                validate_tokens(tokens, input, warnings)
+            }
+            ParsedContents::Markdown(md) => {
+                if !has_md {
+                    return Err(Error::Process(TracebackError {
+                        path: node.source().to_path_buf(),
+                        line: 0,
+                        column: 0,
+                        kind: ProcessError::MissingMarkdownRoot,
+                    }));
+                }
+
+                validate_tokens(&md.markdown, input, warnings);
+            }
+            _ => (),
+        },
+    }
+
+    Ok(())
+}
+
+/// Adds a warning for every function in the tokens which references an input file that does not exist.
+fn validate_tokens(tokens: &[LocatableToken], input: &Node, warnings: &mut Vec<Warning>) {
+    for token in tokens {
+        let function = match token.inner.as_function() {
+            Some(function) => function,
+            None => continue,
+        };
+
+        if let Some(file) = function.referenced_file() {
+            if !input
+                .get_at_path(Path::new(file))
+                .map(|n| n.is_file())
+                .unwrap_or(false)
+            {
+                warnings.push(Warning {
+                    source: token.path.clone(),
+                    line: token.line,
+                    message: format!("`{}` references missing file `{}`", function.name(), file),
+                });
+            }
+        }
+    }
+}
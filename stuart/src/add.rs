@@ -0,0 +1,140 @@
+//! Provides the `stuart add` functionality.
+
+use crate::build::manifest_file;
+use crate::config;
+use crate::error::StuartError;
+use crate::plugins;
+
+use clap::ArgMatches;
+
+use std::collections::HashMap;
+use std::fs::{read_to_string, write};
+use std::path::Path;
+
+/// Runs the add command with the given arguments.
+pub fn add(args: &ArgMatches) -> Result<(), Box<dyn StuartError>> {
+    let name: &str = args.value_of("name").unwrap();
+    let source: &str = args.value_of("source").unwrap();
+    let manifest_path: &str = args.value_of("manifest-path").unwrap();
+
+    let path = manifest_file(manifest_path)?;
+    let root = path.parent().unwrap();
+
+    if args.is_present("load") {
+        let fetch = read_to_string(&path)
+            .ok()
+            .and_then(|manifest| config::load(&manifest).ok())
+            .and_then(|config| config.fetch);
+        let dependencies = HashMap::from([(name.to_string(), source.to_string())]);
+
+        plugins::load(
+            &Some(dependencies),
+            root,
+            &plugins::LoadOptions {
+                retry: plugins::Retry::from(fetch.as_ref()),
+                ..Default::default()
+            },
+        )?;
+    } else if !plugins::source_exists(source, root) {
+        return Err(format!("invalid source for plugin `{}`", name).into());
+    }
+
+    add_dependency(&path, name, source)?;
+
+    log!("Added", "plugin `{}` from `{}`", name, source);
+
+    Ok(())
+}
+
+/// Adds a dependency on the plugin with the given name and source to the manifest at the given path.
+///
+/// The entry is added to the end of the `[dependencies]` table, which is created if it doesn't exist, and the
+///   rest of the manifest is left untouched.
+pub fn add_dependency(
+    manifest_path: &Path,
+    name: &str,
+    source: &str,
+) -> Result<(), Box<dyn StuartError>> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(format!("invalid plugin name `{}`", name).into());
+    }
+
+    let manifest =
+        read_to_string(manifest_path).map_err(|e| format!("failed to read manifest:\n  {}", e))?;
+
+    let dependencies = config::load(&manifest)
+        .map_err(|e| format!("failed to parse manifest:\n  {}", e))?
+        .dependencies
+        .unwrap_or_default();
+
+    if dependencies.contains_key(name) {
+        return Err(format!("plugin `{}` is already a dependency", name).into());
+    }
+
+    let entry = format!("{} = {}", name, toml::Value::String(source.to_string()));
+    let manifest = with_dependency(&manifest, &entry);
+
+    // Check the new manifest before writing it, so that a manifest which can't be edited safely is never broken.
+    let added = config::load(&manifest)
+        .ok()
+        .and_then(|config| config.dependencies)
+        .and_then(|dependencies| dependencies.get(name).cloned());
+
+    if added.as_deref() != Some(source) {
+        return Err("failed to add the dependency to the manifest".into());
+    }
+
+    write(manifest_path, manifest).map_err(|e| format!("failed to write manifest:\n  {}", e))?;
+
+    Ok(())
+}
+
+/// Inserts the entry after the last entry of the manifest's `[dependencies]` table, or appends the table if the
+///   manifest doesn't have one.
+fn with_dependency(manifest: &str, entry: &str) -> String {
+    let mut offset = 0;
+    let mut in_table = false;
+    let mut insert_at = None;
+
+    for line in manifest.split_inclusive('\n') {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with('[') {
+            if in_table {
+                break;
+            }
+
+            in_table = trimmed.starts_with("[dependencies]");
+
+            if in_table {
+                insert_at = Some(offset + line.len());
+            }
+        } else if in_table && !trimmed.is_empty() {
+            insert_at = Some(offset + line.len());
+        }
+
+        offset += line.len();
+    }
+
+    match insert_at {
+        Some(i) => {
+            let (before, after) = manifest.split_at(i);
+            let separator = if before.ends_with('\n') { "" } else { "\n" };
+
+            format!("{}{}{}\n{}", before, separator, entry, after)
+        }
+        None => {
+            let separator = if manifest.is_empty() || manifest.ends_with('\n') {
+                ""
+            } else {
+                "\n"
+            };
+
+            format!("{}{}\n[dependencies]\n{}\n", manifest, separator, entry)
+        }
+    }
+}
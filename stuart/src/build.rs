@@ -2,12 +2,12 @@
 
 use crate::error::StuartError;
 use crate::scripts::Scripts;
-use crate::{config, plugins};
+use crate::{config, plugins, robots};
 
 use stuart_core::{Config, Node, Stuart, TracebackError};
 
 use std::fs::{read_to_string, remove_dir_all};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 /// Contains information about a successful build.
@@ -32,8 +32,51 @@ pub struct StuartContext {
     pub stuart_env: String,
     /// The project directory.
     pub project_dir: PathBuf,
-    /// The output directory, relative to the project directory.
+    /// The output directory, which is relative to the project directory unless it is absolute.
     pub output: String,
+    /// The only file to build, relative to the content directory, if the whole site should not be built.
+    pub only: Option<PathBuf>,
+    /// The contents of the `robots.txt` file to generate, if the manifest has a `[robots]` section.
+    pub robots: Option<String>,
+    /// The headers to add to every response from the development server.
+    pub dev_headers: Vec<(String, String)>,
+    /// Whether the build should fail if it produces any warnings, such as from linting or generating `robots.txt`.
+    ///
+    /// Only warnings logged with [`StuartContext::warn`] are counted, so warnings logged elsewhere with `warn!`,
+    ///   such as by the development server, never fail the build.
+    pub deny_warnings: bool,
+    /// The number of warnings logged during the last build.
+    pub warnings: usize,
+}
+
+/// Returns the canonical path of the manifest file from the `--manifest-path` argument.
+///
+/// The argument can either be the manifest file itself or the project directory, in which case the
+///   `stuart.toml` file inside it is used.
+pub fn manifest_file(manifest_path: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(manifest_path)
+        .canonicalize()
+        .map_err(|_| "invalid manifest path".to_string())?;
+
+    if path.is_dir() {
+        path.join("stuart.toml")
+            .canonicalize()
+            .map_err(|_| "no `stuart.toml` manifest found in the directory".to_string())
+    } else {
+        Ok(path)
+    }
+}
+
+/// Returns the path of the output directory, which is used as-is if absolute and is otherwise relative to the
+///   project directory.
+pub fn output_dir(project_dir: &Path, output: &str) -> PathBuf {
+    let output = Path::new(output);
+
+    if output.is_absolute() {
+        output.to_path_buf()
+    } else {
+        project_dir.join(output)
+    }
 }
 
 impl StuartContext {
@@ -43,10 +86,18 @@ impl StuartContext {
         output: &str,
         stuart_env: &str,
     ) -> Result<Self, Box<dyn StuartError>> {
-        let path = PathBuf::try_from(&manifest_path)
-            .ok()
-            .and_then(|path| path.canonicalize().ok())
-            .ok_or_else(|| "invalid manifest path".to_string())?;
+        Self::init_with_update(manifest_path, output, stuart_env, false)
+    }
+
+    /// Initialises the context like [`StuartContext::init`], updating Git and downloaded plugins to their latest
+    ///   versions instead of the versions in `stuart.lock` if `update` is set.
+    pub fn init_with_update(
+        manifest_path: &str,
+        output: &str,
+        stuart_env: &str,
+        update: bool,
+    ) -> Result<Self, Box<dyn StuartError>> {
+        let path = manifest_file(manifest_path)?;
 
         let manifest =
             read_to_string(&path).map_err(|e| format!("failed to read manifest:\n  {}", e))?;
@@ -66,7 +117,28 @@ impl StuartContext {
             },
         };
 
-        let plugins = plugins::load(&config.dependencies, path.parent().unwrap())?;
+        let plugins = plugins::load(
+            &config.dependencies,
+            path.parent().unwrap(),
+            &plugins::LoadOptions {
+                retry: plugins::Retry::from(config.fetch.as_ref()),
+                update,
+                save_lock: true,
+            },
+        )?;
+
+        let robots = config
+            .robots
+            .as_ref()
+            .map(|robots| robots::generate(robots, config.site.url.as_deref()))
+            .transpose()?;
+
+        let dev_headers = config
+            .dev
+            .as_ref()
+            .and_then(|dev| dev.headers.clone())
+            .map(|headers| headers.into_iter().collect())
+            .unwrap_or_default();
 
         let config: Config = config.into();
 
@@ -97,9 +169,7 @@ impl StuartContext {
                 ),
                 (
                     "STUART_OUT_DIR".into(),
-                    path.parent()
-                        .unwrap()
-                        .join(output)
+                    output_dir(path.parent().unwrap(), output)
                         .to_string_lossy()
                         .trim_start_matches("\\\\?\\")
                         .to_string(),
@@ -117,11 +187,23 @@ impl StuartContext {
             stuart_env: stuart_env.into(),
             project_dir: path.parent().unwrap().to_path_buf(),
             output: output.into(),
+            only: None,
+            robots,
+            dev_headers,
+            deny_warnings: false,
+            warnings: 0,
         })
     }
 
+    /// Returns the path of the output directory.
+    pub fn output_dir(&self) -> PathBuf {
+        output_dir(&self.project_dir, &self.output)
+    }
+
     /// Builds the site with the given configuration.
     pub fn build(&mut self) -> Result<BuildInfo, Box<dyn StuartError>> {
+        self.warnings = 0;
+
         let pre_build_start = Instant::now();
         self.scripts.execute_pre_build()?;
         let pre_build_duration = pre_build_start.elapsed().as_micros();
@@ -136,35 +218,153 @@ impl StuartContext {
         );
 
         let build_start = Instant::now();
-        self.stuart.build(self.stuart_env.to_string())?;
+
+        if let Some(only) = &self.only {
+            log!("Only", "building `{}`", only.display());
+
+            self.stuart.build_only(self.stuart_env.to_string(), only)?;
+        } else {
+            self.stuart.build(self.stuart_env.to_string())?;
+        }
+
         let build_duration = build_start.elapsed().as_micros();
 
+        // Partial builds only update the output of the built file, so static files, metadata and post-build
+        //   scripts are left as they were after the last full build.
+        if self.only.is_some() {
+            let save_start = Instant::now();
+            self.stuart.save_merged(self.output_dir())?;
+            let save_duration = save_start.elapsed().as_micros();
+
+            return Ok(self.finish(pre_build_duration, build_duration, save_duration, 0));
+        }
+
         for dir in ["static", "temp"] {
             let dir_path = self.project_dir.join(dir);
 
             if dir_path.exists() {
-                let node = Node::new(dir_path, false)?;
+                let node = Node::new_with_options(dir_path, false, None, &self.stuart.config)?;
                 self.stuart.merge_output(node)?;
             }
         }
 
         remove_dir_all(self.project_dir.join("temp")).ok();
 
+        if let Some(robots) = &self.robots {
+            self.add_robots(robots.clone())?;
+        }
+
         let save_start = Instant::now();
-        self.stuart.save(self.project_dir.join(&self.output))?;
+        self.stuart.save(self.output_dir())?;
         let save_duration = save_start.elapsed().as_micros();
 
-        if self.stuart.config.save_metadata {
+        if let Some(out) = &self.stuart.output {
+            let files = out.count_files(&self.stuart.config);
+
+            log!(
+                "Generated",
+                "{} file{} ({})",
+                files,
+                if files == 1 { "" } else { "s" },
+                format_size(out.total_size(&self.stuart.config))
+            );
+        }
+
+        if self.stuart.config.save_metadata() {
             log!("Exporting", "metadata to `metadata.json`");
 
             let metadata_path = self.project_dir.join("metadata.json");
             self.stuart.save_metadata(metadata_path)?;
         }
 
+        #[cfg(feature = "lint")]
+        if self.stuart.config.lint_html() {
+            for warning in self.stuart.lint_output()? {
+                self.warn(format!(
+                    "{}: {}",
+                    warning
+                        .source
+                        .strip_prefix(&self.project_dir)
+                        .unwrap_or(&warning.source)
+                        .to_string_lossy()
+                        .trim_start_matches("\\\\?\\"),
+                    warning.message
+                ));
+            }
+        }
+
         let post_build_start = Instant::now();
         self.scripts.execute_post_build()?;
         let post_build_duration = post_build_start.elapsed().as_micros();
 
+        if self.deny_warnings && self.warnings > 0 {
+            return Err(Box::new(format!(
+                "build produced {} warning{}, which are denied by `--deny-warnings`",
+                self.warnings,
+                if self.warnings == 1 { "" } else { "s" }
+            )));
+        }
+
+        Ok(self.finish(
+            pre_build_duration,
+            build_duration,
+            save_duration,
+            post_build_duration,
+        ))
+    }
+
+    /// Adds the generated `robots.txt` file to the build output, unless the output already has one.
+    fn add_robots(&mut self, robots: String) -> Result<(), Box<dyn StuartError>> {
+        let existing = self
+            .stuart
+            .output
+            .as_ref()
+            .and_then(|out| out.get_at_path(Path::new("robots.txt")));
+
+        if let Some(existing) = existing {
+            let message = format!(
+                "not generating `robots.txt` as `{}` already exists",
+                existing
+                    .source()
+                    .strip_prefix(&self.project_dir)
+                    .unwrap_or_else(|_| existing.source())
+                    .to_string_lossy()
+                    .trim_start_matches("\\\\?\\")
+            );
+
+            self.warn(message);
+
+            return Ok(());
+        }
+
+        log!("Generating", "`robots.txt`");
+
+        let source = self.project_dir.join("stuart.toml");
+        let file = Node::from_bytes("robots.txt", robots, &source, None)?;
+
+        self.stuart.merge_output(Node::Directory {
+            name: String::new(),
+            children: vec![file],
+            source,
+        })?;
+
+        Ok(())
+    }
+
+    /// Logs a warning, counting it so that the build can fail if warnings are denied.
+    fn warn(&mut self, message: String) {
+        warn!("{}", message);
+        self.warnings += 1;
+    }
+
+    /// Logs the durations of each stage of the build, returning information about the build.
+    fn finish(
+        &self,
+        pre_build_duration: u128,
+        build_duration: u128,
+        save_duration: u128,
+        post_build_duration: u128,
+    ) -> BuildInfo {
         let total_duration =
             ((pre_build_duration + build_duration + save_duration + post_build_duration) / 100)
                 as f64
@@ -182,11 +382,30 @@ impl StuartContext {
             fs_duration
         );
 
-        Ok(BuildInfo {
+        BuildInfo {
             total_duration,
             build_duration,
             scripts_duration,
             fs_duration,
-        })
+        }
     }
 }
+
+/// Formats a size in bytes as a human-readable string, such as `3.4 MB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
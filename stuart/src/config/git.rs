@@ -65,10 +65,42 @@ pub fn clone(url: &str, path: &str) -> bool {
 
 /// Attempts to pull the latest changes from the remote repository into the given directory.
 ///
+/// The latest commit is checked out directly rather than merged, so this also works after [`checkout`].
+///
 /// Returns `true` if the pull was successful, `false` otherwise.
 pub fn pull(path: &str) -> bool {
+    run(&["-C", path, "fetch", "--depth", "1", "origin", "HEAD"])
+        && run(&["-C", path, "checkout", "--detach", "FETCH_HEAD"])
+}
+
+/// Checks out the given commit in the repository in the given directory, fetching it from the remote
+///   repository if it isn't available locally.
+///
+/// Returns `true` if the checkout was successful, `false` otherwise.
+pub fn checkout(path: &str, commit: &str) -> bool {
+    run(&["-C", path, "checkout", "--detach", commit])
+        || (run(&["-C", path, "fetch", "--depth", "1", "origin", commit])
+            && run(&["-C", path, "checkout", "--detach", commit]))
+}
+
+/// Gets the commit which the repository in the given directory is checked out at.
+pub fn head(path: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["-C", path, "rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
+    }
+}
+
+/// Runs Git with the given arguments, returning `true` if it was successful.
+fn run(args: &[&str]) -> bool {
     Command::new("git")
-        .args(["-C", path, "pull"])
+        .args(args)
         .output()
         .map(|output| output.status.success())
         .unwrap_or(false)
@@ -2,7 +2,8 @@
 
 pub mod git;
 
-use stuart_core::Config;
+use stuart_core::parse::{self, Delimiters};
+use stuart_core::{config, Config};
 
 use serde_derive::Deserialize;
 
@@ -17,6 +18,12 @@ pub struct RawConfig {
     pub settings: Option<Settings>,
     /// Dependencies.
     pub dependencies: Option<HashMap<String, String>>,
+    /// `robots.txt` configuration.
+    pub robots: Option<Robots>,
+    /// Development server configuration.
+    pub dev: Option<Dev>,
+    /// Plugin fetching configuration.
+    pub fetch: Option<Fetch>,
 }
 
 /// Raw, unparsed site configuration information from the TOML file.
@@ -26,6 +33,69 @@ pub struct Site {
     pub name: String,
     /// The author of the site.
     pub author: Option<String>,
+    /// The URL at which the site is hosted, such as `https://example.com`.
+    pub url: Option<String>,
+}
+
+/// Raw, unparsed development server configuration information from the TOML file.
+#[derive(Clone, Deserialize)]
+pub struct Dev {
+    /// Headers to add to every response, such as `Content-Security-Policy`.
+    pub headers: Option<HashMap<String, String>>,
+}
+
+/// Raw, unparsed plugin fetching configuration information from the TOML file.
+#[derive(Clone, Deserialize)]
+pub struct Fetch {
+    /// The number of times to retry cloning, pulling or downloading a plugin after the first attempt fails.
+    pub retries: Option<u32>,
+    /// The delay in milliseconds before the first retry, which doubles after each retry.
+    pub retry_delay: Option<u64>,
+}
+
+/// Raw, unparsed `robots.txt` configuration information from the TOML file.
+#[derive(Clone, Deserialize)]
+pub struct Robots {
+    /// The groups of rules, each applying to a user agent.
+    #[serde(default)]
+    pub rules: Vec<RobotsRule>,
+    /// Whether to point crawlers to the site's `sitemap.xml`.
+    pub sitemap: Option<bool>,
+}
+
+/// A group of `robots.txt` rules for a user agent.
+#[derive(Clone, Deserialize)]
+pub struct RobotsRule {
+    /// The user agent the rules apply to, or `*` for all user agents.
+    pub user_agent: String,
+    /// The paths which the user agent may not crawl.
+    #[serde(default)]
+    pub disallow: Vec<String>,
+    /// The paths which the user agent may crawl, overriding less specific disallowed paths.
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+/// The line endings which text files in the output are converted to.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEndings {
+    /// Unix-style line endings.
+    Lf,
+    /// Windows-style line endings.
+    Crlf,
+}
+
+/// The unit in which the columns of parse errors are counted.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnMode {
+    /// Bytes of UTF-8.
+    Byte,
+    /// Unicode scalar values.
+    Char,
+    /// UTF-16 code units, as used by many editors.
+    Utf16,
 }
 
 /// Raw, unparsed settings configuration information from the TOML file.
@@ -37,6 +107,44 @@ pub struct Settings {
     pub save_data_files: Option<bool>,
     /// Whether to output the build metadata.
     pub save_metadata: Option<bool>,
+    /// The name of the template which wraps HTML pages.
+    pub root_template: Option<String>,
+    /// The name of the template which wraps markdown pages.
+    pub md_template: Option<String>,
+    /// The name of directories whose files can be used by templates but are never saved to the output.
+    pub data_dir: Option<String>,
+    /// The name of the template used to generate index pages for directories of markdown files without one.
+    pub markdown_index: Option<String>,
+    /// Whether to minify HTML files.
+    pub minify_html: Option<bool>,
+    /// Whether to keep comments when minifying HTML.
+    pub minify_html_keep_comments: Option<bool>,
+    /// Whether to keep optional closing tags when minifying HTML.
+    pub minify_html_keep_closing_tags: Option<bool>,
+    /// The maximum size in bytes of files which can be inlined with the `data_uri` function.
+    pub data_uri_max_size: Option<usize>,
+    /// The opening and closing delimiters for template functions and variables.
+    pub delimiters: Option<[String; 2]>,
+    /// The unit in which the columns of parse errors are counted.
+    pub column_mode: Option<ColumnMode>,
+    /// The seed used to shuffle `for` loops.
+    pub shuffle_seed: Option<u64>,
+    /// Whether to lint HTML files in the output.
+    pub lint_html: Option<bool>,
+    /// Whether to generate listings for output directories without an `index.html`.
+    pub directory_listings: Option<bool>,
+    /// The path in the output of a JSON search index of the markdown pages.
+    pub search_index: Option<String>,
+    /// The size in bytes above which unparsed files are copied from their source when saved.
+    pub stream_threshold: Option<u64>,
+    /// The line endings which HTML files and plain-text templates are converted to.
+    pub line_endings: Option<LineEndings>,
+    /// Whether to process files after the files they reference.
+    pub dependency_order: Option<bool>,
+    /// The extensions of plain-text files which are processed as templates.
+    pub text_extensions: Option<Vec<String>>,
+    /// The names of files without an extension which are processed as plain-text templates.
+    pub template_files: Option<Vec<String>>,
 }
 
 /// Attempts to load the configuration from the given TOML file.
@@ -54,18 +162,112 @@ impl From<RawConfig> for Config {
             strip_extensions: raw
                 .settings
                 .as_ref()
-                .and_then(|settings| settings.strip_extensions)
-                .unwrap_or(default.strip_extensions),
+                .and_then(|settings| settings.strip_extensions),
             save_data_files: raw
                 .settings
                 .as_ref()
-                .and_then(|settings| settings.save_data_files)
-                .unwrap_or(default.save_data_files),
+                .and_then(|settings| settings.save_data_files),
             save_metadata: raw
                 .settings
                 .as_ref()
-                .and_then(|settings| settings.save_metadata)
-                .unwrap_or(default.save_metadata),
+                .and_then(|settings| settings.save_metadata),
+            root_template: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.root_template.clone())
+                .unwrap_or(default.root_template),
+            md_template: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.md_template.clone())
+                .unwrap_or(default.md_template),
+            data_dir: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.data_dir.clone())
+                .or(default.data_dir),
+            markdown_index: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.markdown_index.clone())
+                .or(default.markdown_index),
+            minify_html: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.minify_html),
+            minify_html_keep_comments: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.minify_html_keep_comments),
+            minify_html_keep_closing_tags: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.minify_html_keep_closing_tags),
+            data_uri_max_size: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.data_uri_max_size)
+                .unwrap_or(default.data_uri_max_size),
+            delimiters: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.delimiters.clone())
+                .map(|[open, close]| Delimiters { open, close })
+                .unwrap_or(default.delimiters),
+            column_mode: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.column_mode)
+                .map(|column_mode| match column_mode {
+                    ColumnMode::Byte => parse::ColumnMode::ByteOffset,
+                    ColumnMode::Char => parse::ColumnMode::CharOffset,
+                    ColumnMode::Utf16 => parse::ColumnMode::Utf16Offset,
+                }),
+            shuffle_seed: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.shuffle_seed)
+                .unwrap_or(default.shuffle_seed),
+            text_extensions: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.text_extensions.clone())
+                .unwrap_or(default.text_extensions),
+            template_files: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.template_files.clone())
+                .unwrap_or(default.template_files),
+            lint_html: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.lint_html),
+            directory_listings: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.directory_listings),
+            search_index: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.search_index.clone()),
+            stream_threshold: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.stream_threshold),
+            line_endings: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.line_endings)
+                .map(|line_endings| match line_endings {
+                    LineEndings::Lf => config::LineEndings::Lf,
+                    LineEndings::Crlf => config::LineEndings::Crlf,
+                }),
+            dependency_order: raw
+                .settings
+                .as_ref()
+                .and_then(|settings| settings.dependency_order),
+            // Strict mode is enabled per build with the `--strict` flag rather than in the manifest.
+            strict: None,
         }
     }
 }
@@ -5,6 +5,7 @@ use crate::scripts::ScriptError;
 use stuart_core::error::{Error, FsError, ParseError, ProcessError, TracebackError};
 
 use termcolor::{Buffer, BufferWriter, Color, ColorChoice, ColorSpec, WriteColor};
+use unicode_width::UnicodeWidthChar;
 
 use std::env::current_dir;
 use std::fmt::Debug;
@@ -63,6 +64,7 @@ impl StuartError for Error {
             Error::MetadataNotEnabled => {
                 "metadata saving not enabled in configuration".display(buf)
             }
+            Error::InvalidConfig(e) => format!("invalid configuration: {}", e).display(buf),
         }
     }
 
@@ -77,10 +79,27 @@ impl StuartError for Error {
                 "enable metadata by adding `save_metadata = true` to your `stuart.toml`"
                     .to_string(),
             ),
+            Error::InvalidConfig(_) => None,
         }
     }
 }
 
+/// Returns the padding which places the error marker under the given column of the line, where columns are counted
+///   in characters from 1.
+///
+/// The marker starts one character before the column. Tabs are kept so that they expand to the same width as in the
+///   line, and other characters are replaced with as many spaces as they are wide when displayed, so wide characters
+///   such as `中` take two spaces.
+fn marker_padding(line: &str, column: u32) -> String {
+    line.chars()
+        .take((column as usize).saturating_sub(2))
+        .map(|c| match c {
+            '\t' => "\t".to_string(),
+            c => " ".repeat(c.width().unwrap_or(0)),
+        })
+        .collect()
+}
+
 impl<T: Clone + Debug + StuartError> StuartError for TracebackError<T> {
     fn display(&self, buf: &mut Buffer) {
         let relative_path = if let Ok(dir) = current_dir().and_then(std::fs::canonicalize) {
@@ -133,7 +152,7 @@ impl<T: Clone + Debug + StuartError> StuartError for TracebackError<T> {
             writeln!(
                 buf,
                 "{}^^^ error occurred here",
-                " ".repeat((self.column as i32 - 2).clamp(0, i32::MAX) as usize)
+                marker_padding(&line, self.column)
             )
             .unwrap();
             buf.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_intense(true))
@@ -191,6 +210,12 @@ impl StuartError for FsError {
                 )
                 .display(buf)
             }
+            FsError::UnsafeRemove(path) => {
+                format!("refusing to remove `{}`", path.display()).display(buf)
+            }
+            FsError::OutputIsFile(path) => {
+                format!("output path `{}` is a file", path.display()).display(buf)
+            }
         }
     }
 
@@ -208,6 +233,14 @@ impl StuartError for FsError {
                     .to_string(),
             ),
             FsError::Conflict(_, _) => None,
+            FsError::UnsafeRemove(_) => Some(
+                "the output directory must not contain or be inside the input directory"
+                    .to_string(),
+            ),
+            FsError::OutputIsFile(_) => Some(
+                "remove the file or choose a different output directory, since Stuart will not overwrite it"
+                    .to_string(),
+            ),
         }
     }
 }
@@ -236,6 +269,35 @@ impl StuartError for ParseError {
             ParseError::AssertionError(assertion) => {
                 format!("assertion failed: `{}`", assertion).display(buf)
             }
+            ParseError::InvalidDelimiters => "invalid template delimiters".display(buf),
+            ParseError::WrongArgumentCount {
+                function,
+                min,
+                max,
+                found,
+            } => {
+                let expected = match max {
+                    Some(max) if max == min => min.to_string(),
+                    Some(max) if *max == min + 1 => format!("{} or {}", min, max),
+                    Some(max) => format!("between {} and {}", min, max),
+                    None => format!("at least {}", min),
+                };
+
+                format!(
+                    "`{}` expects {} positional argument{}, got {}",
+                    function,
+                    expected,
+                    if max.unwrap_or(*min) == 1 { "" } else { "s" },
+                    found
+                )
+                .display(buf)
+            }
+            ParseError::UnknownNamedArgument { function, name, .. } => {
+                format!("`{}` has no named argument `{}`", function, name).display(buf)
+            }
+            ParseError::DuplicateNamedArgument(name) => {
+                format!("named argument `{}` given more than once", name).display(buf)
+            }
         }
     }
 
@@ -260,6 +322,25 @@ impl StuartError for ParseError {
             ParseError::InvalidFrontmatter => None,
             ParseError::InvalidJson => None,
             ParseError::AssertionError(_) => None,
+            ParseError::InvalidDelimiters => {
+                Some("delimiters must be non-empty and different from each other".to_string())
+            }
+            ParseError::WrongArgumentCount { .. } => None,
+            ParseError::UnknownNamedArgument {
+                function, expected, ..
+            } => Some(if expected.is_empty() {
+                format!("`{}` does not accept any named arguments", function)
+            } else {
+                format!(
+                    "expected one of {}",
+                    expected
+                        .iter()
+                        .map(|name| format!("`{}`", name))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }),
+            ParseError::DuplicateNamedArgument(_) => None,
         }
     }
 }
@@ -272,12 +353,19 @@ impl StuartError for ProcessError {
             ProcessError::StackError => "stack error".display(buf),
             ProcessError::EndWithoutBegin => "no matching `begin` for `end`".display(buf),
             ProcessError::ElseWithoutIf => "no matching `if` for `else`".display(buf),
+            ProcessError::MismatchedLabel { label, .. } => {
+                format!("label `{}` does not match the enclosing conditional", label).display(buf)
+            }
             ProcessError::NotJsonArray => "not a json array".display(buf),
+            ProcessError::InvalidJson => "invalid json".display(buf),
             ProcessError::InvalidDate => "invalid date".display(buf),
             ProcessError::UnexpectedEndOfFile => "unexpected end of file".display(buf),
             ProcessError::FeatureNotEnabled(feature) => {
                 format!("feature not enabled: `{}`", feature).display(buf)
             }
+            ProcessError::FeatureDisabledInMode { feature, mode } => {
+                format!("feature `{}` is disabled in `{}` mode", feature, mode).display(buf)
+            }
             ProcessError::VariableAlreadyExists(name) => {
                 format!("variable already exists: `{}`", name).display(buf)
             }
@@ -287,8 +375,29 @@ impl StuartError for ProcessError {
             ProcessError::UndefinedSection(name) => {
                 format!("undefined section: `{}`", name).display(buf)
             }
+            ProcessError::UndefinedSnippet(name) => {
+                format!("undefined snippet: `{}`", name).display(buf)
+            }
+            ProcessError::SnippetTooDeep(name) => {
+                format!("snippet calls nested too deeply: `{}`", name).display(buf)
+            }
             ProcessError::NullError(name) => format!("null error: `{}`", name).display(buf),
             ProcessError::NotFound(name) => format!("not found: `{}`", name).display(buf),
+            ProcessError::ExpectedFile(name) => {
+                format!("expected a file, found a directory: `{}`", name).display(buf)
+            }
+            ProcessError::ExpectedDirectory(name) => {
+                format!("expected a directory, found a file: `{}`", name).display(buf)
+            }
+            ProcessError::FileTooLarge(name) => format!("file too large: `{}`", name).display(buf),
+            ProcessError::MissingFrontmatterKey { file, key } => {
+                format!("frontmatter of `{}` has no key `{}`", file, key).display(buf)
+            }
+            ProcessError::DependencyCycle(cycle) => {
+                format!("dependency cycle: `{}`", cycle.join("` -> `")).display(buf)
+            }
+            ProcessError::PluginError(message) => format!("plugin error: {}", message).display(buf),
+            ProcessError::Fs(e) => e.display(buf),
             ProcessError::InvalidDataType {
                 variable,
                 expected,
@@ -311,19 +420,26 @@ impl StuartError for ProcessError {
     fn help(&self) -> Option<String> {
         match self {
             ProcessError::MissingHtmlRoot => {
-                Some("ensure that your `root.html` template exists and is accessible".to_string())
+                Some("ensure that your root template (`root.html` by default) exists and is accessible".to_string())
             }
             ProcessError::MissingMarkdownRoot => {
-                Some("ensure that your `md.html` template exists and is accessible".to_string())
+                Some("ensure that your markdown template (`md.html` by default) exists and is accessible".to_string())
             }
             ProcessError::StackError => {
                 Some("this shouldn't have happened, please open an issue!".to_string())
             }
             ProcessError::EndWithoutBegin => None,
             ProcessError::ElseWithoutIf => None,
+            ProcessError::MismatchedLabel { expected, .. } => Some(match expected {
+                Some(expected) => format!("the enclosing conditional is labelled `{}`", expected),
+                None => "the enclosing conditional has no label".to_string(),
+            }),
             ProcessError::NotJsonArray => {
                 Some("only arrays can be used in this context".to_string())
             }
+            ProcessError::InvalidJson => {
+                Some("each non-empty line of a `.jsonl` file must be valid json".to_string())
+            }
             ProcessError::InvalidDate => {
                 Some("ensure the date is valid and the format is correct".to_string())
             }
@@ -331,16 +447,45 @@ impl StuartError for ProcessError {
             ProcessError::FeatureNotEnabled(_) => {
                 Some("reinstall Stuart with the feature enabled".to_string())
             }
+            ProcessError::FeatureDisabledInMode { .. } => Some(
+                "switch to `development` mode with `stuart dev`, or remove the function call"
+                    .to_string(),
+            ),
             ProcessError::VariableAlreadyExists(_) => {
                 Some("variables in Stuart are immutable (for the time being)".to_string())
             }
             ProcessError::UndefinedVariable(_) => None,
             ProcessError::UndefinedSection(_) => None,
+            ProcessError::UndefinedSnippet(_) => Some(
+                "snippets must be defined with `begin(snippet, ...)` or imported before they are used"
+                    .to_string(),
+            ),
+            ProcessError::SnippetTooDeep(_) => {
+                Some("check whether the snippet calls itself without a condition".to_string())
+            }
             ProcessError::NullError(_) => Some(
                 "if the variable is sometimes null, consider using the `ifdefined` function"
                     .to_string(),
             ),
             ProcessError::NotFound(_) => None,
+            ProcessError::ExpectedFile(_) => {
+                Some("use the path of a file inside the directory instead".to_string())
+            }
+            ProcessError::ExpectedDirectory(_) => Some(
+                "paths ending in `/` are directories of markdown files, so remove the `/` to use a `.json` or `.jsonl` file"
+                    .to_string(),
+            ),
+            ProcessError::FileTooLarge(_) => Some(
+                "increase `data_uri_max_size` in `stuart.toml` or link to the file instead"
+                    .to_string(),
+            ),
+            ProcessError::MissingFrontmatterKey { .. } => None,
+            ProcessError::DependencyCycle(_) => Some(
+                "remove one of the references, or disable `dependency_order` in `stuart.toml`"
+                    .to_string(),
+            ),
+            ProcessError::PluginError(_) => None,
+            ProcessError::Fs(e) => e.help(),
             ProcessError::InvalidDataType { .. } => None,
         }
     }
@@ -0,0 +1,122 @@
+//! Provides the `stuart info` functionality.
+
+use crate::build::StuartContext;
+use crate::error::StuartError;
+
+use clap::ArgMatches;
+use humphrey_json::Value;
+
+use std::fmt::Write;
+
+/// The project directories which Stuart uses if they exist.
+const DIRECTORIES: [&str; 4] = ["content", "static", "scripts", "temp"];
+
+/// Runs the info command with the given arguments.
+pub fn info(args: &ArgMatches) -> Result<(), Box<dyn StuartError>> {
+    let manifest_path: &str = args.value_of("manifest-path").unwrap();
+    let stuart_env = args
+        .value_of("env")
+        .map(|env| env.to_string())
+        .or_else(|| std::env::var("STUART_ENV").ok())
+        .unwrap_or_else(|| "production".to_string());
+
+    let ctx = StuartContext::init(manifest_path, "dist", &stuart_env)?;
+
+    print!("{}", report(&ctx));
+
+    Ok(())
+}
+
+/// Describes the project, its resolved configuration and the functions available to its templates.
+pub fn report(ctx: &StuartContext) -> String {
+    let mut report = String::new();
+
+    let directories = DIRECTORIES
+        .iter()
+        .filter(|dir| ctx.project_dir.join(dir).is_dir())
+        .copied()
+        .collect::<Vec<_>>();
+
+    let scripts = ctx
+        .scripts
+        .pre_build()
+        .iter()
+        .chain(ctx.scripts.post_build())
+        .map(|path| path.file_name().unwrap_or_default().to_string_lossy())
+        .collect::<Vec<_>>();
+
+    let plugins = ctx
+        .stuart
+        .plugins
+        .as_ref()
+        .map(|plugins| {
+            plugins
+                .plugins()
+                .iter()
+                .map(|plugin| format!("{} {}", plugin.name, plugin.version))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let rows = [
+        (
+            "Project",
+            format!(
+                "{} ({})",
+                ctx.stuart.config.name,
+                ctx.project_dir
+                    .to_string_lossy()
+                    .trim_start_matches("\\\\?\\")
+            ),
+        ),
+        ("STUART_ENV", ctx.stuart_env.clone()),
+        ("Directories", list(&directories)),
+        ("Scripts", list(&scripts)),
+        ("Plugins", list(&plugins)),
+        ("Functions", list(&ctx.stuart.function_names())),
+    ];
+
+    for (label, value) in rows {
+        writeln!(report, "{:>12} {}", label, value).unwrap();
+    }
+
+    writeln!(report, "\nConfiguration:").unwrap();
+
+    if let Value::Object(settings) = ctx.stuart.config.to_json() {
+        let width = settings.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+
+        for (key, value) in settings {
+            writeln!(report, "  {:<width$} {}", key, plain(&value), width = width).unwrap();
+        }
+    }
+
+    report
+}
+
+/// Formats a resolved configuration value without JSON quoting, using `none` for settings which are not set.
+fn plain(value: &Value) -> String {
+    match value {
+        Value::Null => "none".to_string(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => list(&items.iter().map(plain).collect::<Vec<_>>()),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, plain(value)))
+            .collect::<Vec<_>>()
+            .join(", "),
+        value => value.serialize(),
+    }
+}
+
+/// Joins the items into a comma-separated list, or returns `none` if there are none.
+fn list(items: &[impl AsRef<str>]) -> String {
+    if items.is_empty() {
+        "none".to_string()
+    } else {
+        items
+            .iter()
+            .map(|item| item.as_ref())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
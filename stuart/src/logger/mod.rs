@@ -84,3 +84,38 @@ macro_rules! log {
         }
     };
 }
+
+/// Logs a warning.
+///
+/// The arguments are the same as in the `format!` macro. Warnings are logged in the same way as other messages,
+///   but with the verb "Warning" in yellow text.
+///
+/// Warnings logged with this macro are not counted towards `--deny-warnings`. Warnings produced by a build
+///   should be logged with `StuartContext::warn` instead.
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        if let Some(logger) = $crate::logger::LOGGER.get() {
+            if logger.enabled.load(::std::sync::atomic::Ordering::Relaxed)
+                && logger.level != $crate::logger::LogLevel::Quiet {
+                use ::termcolor::*;
+                use std::io::Write;
+
+                let writer = BufferWriter::stderr(ColorChoice::Always);
+                let mut buffer = writer.buffer();
+
+                buffer
+                    .set_color(ColorSpec::new().set_fg(Some(Color::Yellow)).set_intense(true))
+                    .unwrap();
+                write!(buffer, "{:>12} ", "Warning").unwrap();
+                buffer.reset().unwrap();
+
+                writeln!(buffer, $($arg)*).unwrap();
+
+                writer.print(&buffer).unwrap();
+
+                logger.has_logged.store(true, ::std::sync::atomic::Ordering::SeqCst);
+            }
+        }
+    };
+}
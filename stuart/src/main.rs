@@ -6,11 +6,14 @@
 #[macro_use]
 mod logger;
 
+mod add;
 mod build;
 mod config;
 mod error;
+mod info;
 mod new;
 mod plugins;
+mod robots;
 mod scripts;
 mod serve;
 
@@ -22,6 +25,8 @@ use crate::error::StuartError;
 use crate::logger::{LogLevel, Logger, Progress, LOGGER};
 
 use clap::{App, Arg, ArgMatches, Command};
+use humphrey_json::Value;
+use stuart_core::Stuart;
 
 use std::fs::{remove_dir_all, remove_file};
 use std::path::PathBuf;
@@ -46,13 +51,19 @@ fn app() -> App<'static> {
                 .long("verbose")
                 .help("Output verbose information"),
         )
+        .arg(
+            Arg::new("deny-warnings")
+                .long("deny-warnings")
+                .help("Fail if building or checking the site produces any warnings")
+                .global(true),
+        )
         .subcommand(
             Command::new("build")
                 .about("Builds the site")
                 .arg(
                     Arg::new("manifest-path")
                         .long("manifest-path")
-                        .help("Path to the manifest file")
+                        .help("Path to the manifest file, or the project directory containing it")
                         .default_value("stuart.toml"),
                 )
                 .arg(
@@ -61,6 +72,64 @@ fn app() -> App<'static> {
                         .short('o')
                         .help("Output directory (if relative, relative to the manifest file)")
                         .default_value("dist"),
+                )
+                .arg(
+                    Arg::new("only")
+                        .long("only")
+                        .help("Only build the given file, leaving the rest of the output untouched")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("strict").long("strict").help(
+                        "Fail on functions which are disabled in production, such as `debug`",
+                    ),
+                )
+                .arg(
+                    Arg::new("update")
+                        .long("update")
+                        .help("Update plugins to their latest versions, ignoring `stuart.lock`"),
+                ),
+        )
+        .subcommand(
+            Command::new("check")
+                .about("Checks the site for problems without building it")
+                .arg(
+                    Arg::new("manifest-path")
+                        .long("manifest-path")
+                        .help("Path to the manifest file, or the project directory containing it")
+                        .default_value("stuart.toml"),
+                ),
+        )
+        .subcommand(
+            Command::new("info")
+                .about("Shows the project structure and resolved configuration without building")
+                .arg(
+                    Arg::new("manifest-path")
+                        .long("manifest-path")
+                        .help("Path to the manifest file, or the project directory containing it")
+                        .default_value("stuart.toml"),
+                )
+                .arg(
+                    Arg::new("env")
+                        .long("env")
+                        .help("Value of `STUART_ENV` to report, which defaults to the current `STUART_ENV` or `production`")
+                        .takes_value(true)
+                        .possible_values(["production", "development"]),
+                ),
+        )
+        .subcommand(
+            Command::new("tree")
+                .about("Shows the parsed input files without building them")
+                .arg(
+                    Arg::new("manifest-path")
+                        .long("manifest-path")
+                        .help("Path to the manifest file, or the project directory containing it")
+                        .default_value("stuart.toml"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Output the tree as JSON for use by other tools"),
                 ),
         )
         .subcommand(
@@ -69,15 +138,20 @@ fn app() -> App<'static> {
                 .arg(
                     Arg::new("manifest-path")
                         .long("manifest-path")
-                        .help("Path to the manifest file")
+                        .help("Path to the manifest file, or the project directory containing it")
                         .default_value("stuart.toml"),
                 )
                 .arg(
                     Arg::new("output")
                         .long("output")
                         .short('o')
-                        .help("Output directory relative to the manifest file")
+                        .help("Output directory (if relative, relative to the manifest file)")
                         .default_value("dist"),
+                )
+                .arg(
+                    Arg::new("update")
+                        .long("update")
+                        .help("Update plugins to their latest versions, ignoring `stuart.lock`"),
                 ),
         )
         .subcommand(
@@ -88,6 +162,36 @@ fn app() -> App<'static> {
                     Arg::new("no-git")
                         .long("no-git")
                         .help("Don't initialize a Git repository"),
+                )
+                .arg(
+                    Arg::new("template")
+                        .short('t')
+                        .long("template")
+                        .help("Starter template to use")
+                        .takes_value(true)
+                        .possible_values(new::TEMPLATES)
+                        .default_value("minimal"),
+                ),
+        )
+        .subcommand(
+            Command::new("add")
+                .about("Adds a plugin to the project's dependencies")
+                .arg(Arg::new("name").help("Name of the plugin").required(true))
+                .arg(
+                    Arg::new("source")
+                        .help("Path, Git repository or URL of the plugin")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("manifest-path")
+                        .long("manifest-path")
+                        .help("Path to the manifest file, or the project directory containing it")
+                        .default_value("stuart.toml"),
+                )
+                .arg(
+                    Arg::new("load")
+                        .long("load")
+                        .help("Load the plugin, compiling it if necessary, before adding it"),
                 ),
         )
         .subcommand(
@@ -103,7 +207,15 @@ fn app() -> App<'static> {
                 ),
         )
         .subcommand(
-            Command::new("clean").about("Removes the output directory and generated metadata"),
+            Command::new("clean")
+                .about("Removes the output directory and generated metadata")
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .help("Output directory (if relative, relative to the project directory)")
+                        .default_value("dist"),
+                ),
         )
         .subcommand_required(true)
 }
@@ -124,10 +236,14 @@ fn main() {
     #[allow(clippy::unit_arg)]
     let result = match matches.subcommand() {
         Some(("build", args)) => build(args),
+        Some(("check", args)) => check(args),
+        Some(("info", args)) => info::info(args),
+        Some(("tree", args)) => tree(args),
         Some(("dev", args)) => serve::serve(args.clone()),
         Some(("new", args)) => new::new(args),
+        Some(("add", args)) => add::add(args),
         Some(("bench", args)) => bench(args),
-        Some(("clean", _)) => clean(),
+        Some(("clean", args)) => clean(args),
         _ => unreachable!(),
     };
 
@@ -146,11 +262,122 @@ fn build(args: &ArgMatches) -> Result<(), Box<dyn StuartError>> {
     let manifest_path: &str = args.value_of("manifest-path").unwrap();
     let output: &str = args.value_of("output").unwrap();
 
-    let mut ctx = StuartContext::init(manifest_path, output, "production")?;
+    let mut ctx = StuartContext::init_with_update(
+        manifest_path,
+        output,
+        "production",
+        args.is_present("update"),
+    )?;
+    if args.is_present("strict") {
+        ctx.stuart.config.strict = Some(true);
+    }
+
+    ctx.deny_warnings = args.is_present("deny-warnings");
+
+    if let Some(only) = args.value_of("only") {
+        let content_dir = ctx.project_dir.join("content");
+
+        let only = PathBuf::from(only)
+            .canonicalize()
+            .or_else(|_| ctx.project_dir.join(only).canonicalize())
+            .map_err(|_| format!("file not found: `{}`", only))?;
+
+        let only = only
+            .strip_prefix(&content_dir)
+            .map_err(|_| "the `--only` path must be inside the `content` directory".to_string())?;
+
+        ctx.only = Some(only.to_path_buf());
+    }
 
     ctx.build().map(|_| ())
 }
 
+/// Runs the check command with the given arguments.
+fn check(args: &ArgMatches) -> Result<(), Box<dyn StuartError>> {
+    let manifest_path: &str = args.value_of("manifest-path").unwrap();
+
+    let ctx = StuartContext::init(manifest_path, "dist", "production")?;
+
+    log!(
+        "Checking",
+        "{} ({})",
+        ctx.stuart.config.name,
+        ctx.project_dir
+            .to_string_lossy()
+            .trim_start_matches("\\\\?\\")
+    );
+
+    let warnings = ctx.stuart.validate()?;
+
+    for warning in &warnings {
+        warn!(
+            "{}:{}: {}",
+            warning
+                .source
+                .strip_prefix(&ctx.project_dir)
+                .unwrap_or(&warning.source)
+                .to_string_lossy()
+                .trim_start_matches("\\\\?\\"),
+            warning.line,
+            warning.message
+        );
+    }
+
+    log!(
+        "Finished",
+        "check with {} warning{}",
+        warnings.len(),
+        if warnings.len() == 1 { "" } else { "s" }
+    );
+
+    if args.is_present("deny-warnings") && !warnings.is_empty() {
+        return Err(Box::new(
+            "warnings are denied by `--deny-warnings`".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Runs the tree command with the given arguments.
+fn tree(args: &ArgMatches) -> Result<(), Box<dyn StuartError>> {
+    let manifest_path: &str = args.value_of("manifest-path").unwrap();
+
+    let ctx = StuartContext::init(manifest_path, "dist", "production")?;
+    let tree = ctx.stuart.load_input()?.to_tree_json();
+
+    if args.is_present("json") {
+        println!("{}", tree.serialize());
+    } else {
+        print_tree(&tree, 0);
+    }
+
+    Ok(())
+}
+
+/// Prints a tree produced by [`Node::to_tree_json`](stuart_core::Node::to_tree_json), indenting each node by
+///   its depth.
+fn print_tree(node: &Value, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let name = node["name"].as_str().unwrap_or_default();
+
+    match node["children"].as_array() {
+        Some(children) => {
+            println!("{}{}/", indent, name);
+
+            for child in children {
+                print_tree(child, depth + 1);
+            }
+        }
+        None => println!(
+            "{}{} ({})",
+            indent,
+            name,
+            node["kind"].as_str().unwrap_or_default()
+        ),
+    }
+}
+
 /// Runs the benchmark command with the given arguments.
 fn bench(args: &ArgMatches) -> Result<(), Box<dyn StuartError>> {
     let mut ctx = StuartContext::init("stuart.toml", "dist", "benchmark")?;
@@ -200,14 +427,14 @@ fn bench(args: &ArgMatches) -> Result<(), Box<dyn StuartError>> {
 }
 
 /// Removes the output directory and generated metadata.
-fn clean() -> Result<(), Box<dyn StuartError>> {
+fn clean(args: &ArgMatches) -> Result<(), Box<dyn StuartError>> {
+    let output: &str = args.value_of("output").unwrap();
+
     if !PathBuf::from("stuart.toml").exists() {
         return Err("current working directory is not a Stuart project".into());
     }
 
-    if PathBuf::from("dist").exists() {
-        remove_dir_all("dist").map_err(|_| "failed to remove output directory")?;
-    }
+    Stuart::new("content").clean_output(output)?;
 
     if PathBuf::from("_build").exists() {
         remove_dir_all("_build").map_err(|_| "failed to remove build directory")?;
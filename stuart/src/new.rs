@@ -8,18 +8,34 @@ use stuart_core::error::FsError;
 use clap::ArgMatches;
 use include_dir::{include_dir, Dir, DirEntry};
 
-use std::fs::{create_dir, write};
+use std::fs::{create_dir, create_dir_all, write};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
-/// The directory containing the default site template, built into the binary when compiled.
-static DEFAULT_PROJECT: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/tests/basic");
+/// The directory containing the minimal site template, built into the binary when compiled.
+static MINIMAL_TEMPLATE: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/tests/basic");
+/// The directory containing the blog site template, built into the binary when compiled.
+static BLOG_TEMPLATE: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/tests/blog");
+/// The directory containing the documentation site template, built into the binary when compiled.
+static DOCS_TEMPLATE: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/tests/docs");
+/// The directory containing the portfolio site template, built into the binary when compiled.
+static PORTFOLIO_TEMPLATE: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/tests/portfolio");
+
+/// The names of the available site templates.
+pub const TEMPLATES: [&str; 4] = ["minimal", "blog", "docs", "portfolio"];
 
 /// Creates a new site with the given arguments.
 pub fn new(args: &ArgMatches) -> Result<(), Box<dyn StuartError>> {
     let name = args.value_of("name").unwrap();
     let path = PathBuf::from(name);
     let no_git = args.is_present("no-git");
+    let template_name = args.value_of("template").unwrap();
+    let template = match template_name {
+        "blog" => &BLOG_TEMPLATE,
+        "docs" => &DOCS_TEMPLATE,
+        "portfolio" => &PORTFOLIO_TEMPLATE,
+        _ => &MINIMAL_TEMPLATE,
+    };
 
     let mut manifest: Vec<u8> = format!("[site]\nname = \"{}\"", name).as_bytes().to_vec();
 
@@ -31,12 +47,23 @@ pub fn new(args: &ArgMatches) -> Result<(), Box<dyn StuartError>> {
 
     manifest.push(b'\n');
 
+    if let Some(settings) = template
+        .get_file("stuart.toml")
+        .and_then(|file| file.contents_utf8())
+        .map(template_settings)
+    {
+        if !settings.is_empty() {
+            manifest.push(b'\n');
+            manifest.extend_from_slice(settings.as_bytes());
+        }
+    }
+
     create_dir(&path).map_err(|_| FsError::Write)?;
     create_dir(path.join("content")).map_err(|_| FsError::Write)?;
     create_dir(path.join("static")).map_err(|_| FsError::Write)?;
     write(path.join("stuart.toml"), manifest).map_err(|_| FsError::Write)?;
 
-    extract(&path, &DEFAULT_PROJECT)?;
+    extract(&path, template)?;
 
     if !no_git {
         git::init_repository(&format!("./{}", name));
@@ -47,14 +74,63 @@ pub fn new(args: &ArgMatches) -> Result<(), Box<dyn StuartError>> {
 
     log!("Created", "new Stuart website `{}`", name);
 
+    print!("{}", getting_started(name, template_name));
+
     Ok(())
 }
 
+/// Describes the next steps for working on a new site created from the given template.
+pub fn getting_started(name: &str, template: &str) -> String {
+    let edit = match template {
+        "blog" => "write posts as markdown files in `content/posts`",
+        "docs" => "write pages as markdown files in `content/docs`",
+        "portfolio" => "describe projects as markdown files in `content/projects`",
+        _ => "edit `content/index.html` and the root template `content/root.html`",
+    };
+
+    let steps = [
+        format!("`cd {}` to enter the site's directory", name),
+        "`stuart dev` to start the development server".to_string(),
+        edit.to_string(),
+        "`stuart build` to build the site into `dist`".to_string(),
+    ];
+
+    let mut message = String::from("\nGetting started:\n");
+
+    for (i, step) in steps.iter().enumerate() {
+        message.push_str(&format!("  {}. {}\n", i + 1, step));
+    }
+
+    message
+}
+
+/// Returns everything in a template's manifest except its `[site]` section, which is generated for each new site.
+fn template_settings(manifest: &str) -> String {
+    let mut in_site = false;
+    let mut settings = String::new();
+
+    for line in manifest.lines() {
+        if line.starts_with('[') {
+            in_site = line.trim() == "[site]";
+        }
+
+        if !in_site {
+            settings.push_str(line);
+            settings.push('\n');
+        }
+    }
+
+    settings
+}
+
 /// Extracts the embedded directory to the filesystem.
 fn extract(root: &Path, dir: &Dir) -> Result<(), FsError> {
     for child in dir.entries() {
         match child {
-            DirEntry::Dir(dir) => extract(root, dir)?,
+            DirEntry::Dir(dir) => {
+                create_dir_all(root.join(dir.path())).map_err(|_| FsError::Write)?;
+                extract(root, dir)?
+            }
             DirEntry::File(file) => {
                 if !file.path().ends_with("stuart.toml") {
                     write(root.join(file.path()), file.contents()).map_err(|_| FsError::Write)?
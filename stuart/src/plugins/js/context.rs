@@ -1,15 +1,50 @@
 //! Enables access to Stuart's execution context from JavaScript running within V8.
 
+use super::JsRuntime;
+
+use stuart_core::parse::LocatableToken;
+use stuart_core::process::stack::StackFrame;
 use stuart_core::process::Scope;
 
-/// Makes the Stuart scope accessible to `set_variable` and `get_variable` when they're called from JavaScript code.
-pub fn set_stuart_context(scope: &mut v8::HandleScope, context: &mut Scope) {
+/// Makes the Stuart scope accessible to the functions of the `STUART` object when they're called from JavaScript
+///   code during a call to the function with the given name.
+///
+/// The `STUART` object provides the following functions:
+/// - `get(name)` gets a variable.
+/// - `set(name, value)` sets a variable in the current stack frame.
+/// - `block()` processes the template block following the function call, up to `end(plugin::function)`, and
+///   returns its output as a string, so that the function can transform it.
+/// - `skip()` consumes the block following the function call without processing it.
+/// - `json(path)` gets the parsed contents of a JSON file, relative to the input directory.
+/// - `markdown(path)` gets the frontmatter and contents of a markdown file, relative to the input directory.
+/// - `config(key)` gets a setting of the project's configuration, or the whole configuration if `key` is omitted.
+///
+/// `json` and `markdown` return `undefined` if the file doesn't exist or is of a different type, and `config`
+///   returns `undefined` if there is no such setting.
+///
+/// `block` and `skip` throw an exception if the block has no `end`, and `block` also throws if processing it fails.
+///
+/// Returns the `STUART` object which was previously set, which should be restored with `restore_stuart_context`
+///   once the function returns.
+pub fn set_stuart_context<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    context: &mut Scope,
+    runtime: &JsRuntime,
+    function_name: &str,
+) -> v8::Local<'s, v8::Value> {
     let stuart_context = v8::Object::new(scope);
 
     let k_context = v8::String::new(scope, "STUART").unwrap();
     let k_set_variable = v8::String::new(scope, "set").unwrap();
     let k_get_variable = v8::String::new(scope, "get").unwrap();
+    let k_block = v8::String::new(scope, "block").unwrap();
+    let k_skip = v8::String::new(scope, "skip").unwrap();
+    let k_json = v8::String::new(scope, "json").unwrap();
+    let k_markdown = v8::String::new(scope, "markdown").unwrap();
+    let k_config = v8::String::new(scope, "config").unwrap();
     let k_external = v8::String::new(scope, "_ptr").unwrap();
+    let k_name = v8::String::new(scope, "_name").unwrap();
+    let k_runtime = v8::String::new(scope, "_runtime").unwrap();
 
     let set_variable = v8::FunctionTemplate::new(scope, set_variable)
         .get_function(scope)
@@ -17,16 +52,51 @@ pub fn set_stuart_context(scope: &mut v8::HandleScope, context: &mut Scope) {
     let get_variable = v8::FunctionTemplate::new(scope, get_variable)
         .get_function(scope)
         .unwrap();
+    let block = v8::FunctionTemplate::new(scope, block)
+        .get_function(scope)
+        .unwrap();
+    let skip = v8::FunctionTemplate::new(scope, skip)
+        .get_function(scope)
+        .unwrap();
+    let json = v8::FunctionTemplate::new(scope, json)
+        .get_function(scope)
+        .unwrap();
+    let markdown = v8::FunctionTemplate::new(scope, markdown)
+        .get_function(scope)
+        .unwrap();
+    let config = v8::FunctionTemplate::new(scope, config)
+        .get_function(scope)
+        .unwrap();
     let external = v8::External::new(scope, context as *mut _ as *mut std::ffi::c_void);
+    let name = v8::String::new(scope, function_name).unwrap();
+    let runtime = v8::External::new(scope, runtime as *const _ as *mut std::ffi::c_void);
 
     stuart_context.set(scope, k_set_variable.into(), set_variable.into());
     stuart_context.set(scope, k_get_variable.into(), get_variable.into());
+    stuart_context.set(scope, k_block.into(), block.into());
+    stuart_context.set(scope, k_skip.into(), skip.into());
+    stuart_context.set(scope, k_json.into(), json.into());
+    stuart_context.set(scope, k_markdown.into(), markdown.into());
+    stuart_context.set(scope, k_config.into(), config.into());
     stuart_context.set(scope, k_external.into(), external.into());
+    stuart_context.set(scope, k_name.into(), name.into());
+    stuart_context.set(scope, k_runtime.into(), runtime.into());
+
+    let global = scope.get_current_context().global(scope);
+    let previous = global.get(scope, k_context.into()).unwrap();
+    global.set(scope, k_context.into(), stuart_context.into());
+
+    previous
+}
+
+/// Restores the `STUART` object returned by `set_stuart_context`.
+pub fn restore_stuart_context(scope: &mut v8::HandleScope, previous: v8::Local<'_, v8::Value>) {
+    let k_context = v8::String::new(scope, "STUART").unwrap();
 
     scope
         .get_current_context()
         .global(scope)
-        .set(scope, k_context.into(), stuart_context.into());
+        .set(scope, k_context.into(), previous);
 }
 
 /// Gets the Stuart context from V8.
@@ -46,6 +116,23 @@ unsafe fn get_stuart_context<'s>(
         .unwrap()
 }
 
+/// Gets the JavaScript runtime from V8.
+///
+/// # Safety
+/// This function is only safe if `set_stuart_context` has previously been called with the same `scope`.
+unsafe fn get_runtime<'s>(
+    scope: &mut v8::HandleScope,
+    obj: v8::Local<'_, v8::Object>,
+) -> &'s JsRuntime {
+    let k_runtime = v8::String::new(scope, "_runtime").unwrap();
+
+    (v8::Local::<v8::External>::try_from(obj.get(scope, k_runtime.into()).unwrap())
+        .unwrap()
+        .value() as *const JsRuntime)
+        .as_ref()
+        .unwrap()
+}
+
 /// Sets a variable in the current Stuart scope.
 pub fn set_variable<'s>(
     scope: &mut v8::HandleScope<'s>,
@@ -85,3 +172,129 @@ pub fn get_variable<'s>(
 
     ret.set(v8_value);
 }
+
+/// Processes the block following the current function and returns its output.
+pub fn block<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    args: v8::FunctionCallbackArguments<'s>,
+    mut ret: v8::ReturnValue,
+) {
+    let stuart_scope = unsafe { get_stuart_context(scope, args.this()) };
+    let self_token = stuart_scope.tokens.current().unwrap().clone();
+
+    let (name, tokens) = match collect_block(scope, args.this(), stuart_scope) {
+        Some(block) => block,
+        None => return,
+    };
+
+    // JavaScript functions called from within the block re-enter the isolate through this callback's scope.
+    let runtime = unsafe { get_runtime(scope, args.this()) };
+    let parent: *mut v8::HandleScope<()> = &mut **scope;
+    let previous_scope = runtime.active_scope.replace(parent.cast());
+    let result = stuart_scope.render_block(&tokens, StackFrame::new(name), &self_token);
+    runtime.active_scope.set(previous_scope);
+
+    match result {
+        Ok(output) => {
+            let output = v8::String::new(scope, &String::from_utf8_lossy(&output)).unwrap();
+            ret.set(output.into());
+        }
+        Err(e) => {
+            throw(scope, &format!("failed to process block: {:?}", e.kind));
+            runtime.block_error.replace(Some(e));
+        }
+    }
+}
+
+/// Consumes the block following the current function without processing it.
+pub fn skip<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    args: v8::FunctionCallbackArguments<'s>,
+    _ret: v8::ReturnValue,
+) {
+    let stuart_scope = unsafe { get_stuart_context(scope, args.this()) };
+
+    collect_block(scope, args.this(), stuart_scope);
+}
+
+/// Gets the parsed contents of a JSON file.
+pub fn json<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    args: v8::FunctionCallbackArguments<'s>,
+    mut ret: v8::ReturnValue,
+) {
+    let stuart_scope = unsafe { get_stuart_context(scope, args.this()) };
+    let path = args.get(0).to_rust_string_lossy(scope);
+    let value = stuart_scope.get_file_json(&path).ok().cloned();
+    let v8_value = super::json::json_to_js(value, scope);
+
+    ret.set(v8_value);
+}
+
+/// Gets the frontmatter and contents of a markdown file.
+pub fn markdown<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    args: v8::FunctionCallbackArguments<'s>,
+    mut ret: v8::ReturnValue,
+) {
+    let stuart_scope = unsafe { get_stuart_context(scope, args.this()) };
+    let path = args.get(0).to_rust_string_lossy(scope);
+    let value = stuart_scope
+        .get_file_markdown(&path)
+        .ok()
+        .map(|md| md.to_value());
+    let v8_value = super::json::json_to_js(value, scope);
+
+    ret.set(v8_value);
+}
+
+/// Gets a setting of the project's configuration, or the whole configuration if no key is given.
+pub fn config<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    args: v8::FunctionCallbackArguments<'s>,
+    mut ret: v8::ReturnValue,
+) {
+    let stuart_scope = unsafe { get_stuart_context(scope, args.this()) };
+    let config = stuart_scope.processor.config.to_json();
+
+    let value = if args.length() == 0 || args.get(0).is_undefined() {
+        Some(config)
+    } else {
+        let key = args.get(0).to_rust_string_lossy(scope);
+        config.get(key.as_str()).cloned()
+    };
+
+    let v8_value = super::json::json_to_js(value, scope);
+
+    ret.set(v8_value);
+}
+
+/// Collects the tokens of the block following the current function, returning them with the function's name.
+///
+/// Throws an exception and returns `None` if the block has no `end`.
+fn collect_block(
+    scope: &mut v8::HandleScope,
+    obj: v8::Local<'_, v8::Object>,
+    stuart_scope: &mut Scope,
+) -> Option<(String, Vec<LocatableToken>)> {
+    let k_name = v8::String::new(scope, "_name").unwrap();
+    let name = obj
+        .get(scope, k_name.into())
+        .unwrap()
+        .to_rust_string_lossy(scope);
+
+    match stuart_scope.tokens.collect_until_end(&name) {
+        Ok(tokens) => Some((name, tokens)),
+        Err(_) => {
+            throw(scope, &format!("no matching `end` for `{}`", name));
+            None
+        }
+    }
+}
+
+/// Throws a JavaScript error with the given message.
+fn throw(scope: &mut v8::HandleScope, message: &str) {
+    let message = v8::String::new(scope, message).unwrap();
+    let exception = v8::Exception::error(scope, message);
+    scope.throw_exception(exception);
+}
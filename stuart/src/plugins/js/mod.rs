@@ -9,15 +9,33 @@ use stuart_core::plugins::Plugin;
 use stuart_core::process::{ProcessError, Scope};
 use stuart_core::TracebackError;
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::path::Path;
+use std::rc::Rc;
 use std::sync::Once;
 
 /// Ensures that V8 is initialised exactly once.
 static INIT: Once = Once::new();
 
-thread_local! {
-    static ISOLATE: RefCell<v8::OwnedIsolate> = RefCell::new(v8::Isolate::new(Default::default()));
+/// Owns the V8 isolate shared by every JavaScript plugin loaded by a plugin manager.
+///
+/// A runtime is created by the plugin manager when it loads its first JavaScript plugin, and each plugin gets its
+///   own context within the isolate so that plugins can't see each other's state.
+///
+/// V8 isolates must only be used by the thread which created them, so the runtime is shared with `Rc`. This makes
+///   the plugins and functions holding it `!Send`, so the compiler ensures they never leave the thread which loaded
+///   them, and the isolate is borrowed through a `RefCell` rather than locked. Function calls only overlap on that
+///   thread when a block processed from JavaScript calls another JavaScript function, in which case the nested call
+///   re-enters the isolate through the handle scope of the running callback instead of borrowing it again. Isolates
+///   must also be dropped in the reverse order to which they were created on a thread, which holds as long as each
+///   runtime lives for the whole build.
+pub struct JsRuntime {
+    /// The V8 isolate.
+    isolate: RefCell<v8::OwnedIsolate>,
+    /// The handle scope of the callback currently processing a block, or null if there is none.
+    active_scope: Cell<*mut v8::HandleScope<'static, ()>>,
+    /// The error which caused the block being processed from JavaScript to fail, if any.
+    block_error: RefCell<Option<TracebackError<ProcessError>>>,
 }
 
 /// A parser for JavaScript functions.
@@ -29,131 +47,143 @@ pub struct JSFunctionParser {
     name: String,
     /// The V8 context for this plugin.
     context: v8::Global<v8::Context>,
+    /// The runtime owning the context, which must outlive it.
+    runtime: Rc<JsRuntime>,
 }
 
 /// A Stuart function that executes JavaScript code.
-#[derive(Debug)]
 pub struct JSFunction {
     /// The name of the function.
     name: String,
     /// The V8 context for this plugin.
     context: v8::Global<v8::Context>,
+    /// The runtime owning the context, which must outlive it.
+    runtime: Rc<JsRuntime>,
     /// The function's arguments.
     args: Vec<RawArgument>,
 }
 
-/// Attempts to load a JavaScript plugin from the given path, creating a new V8 context for it.
-pub fn load_js_plugin(path: impl AsRef<Path>) -> Result<Plugin, String> {
-    INIT.call_once(|| {
-        v8::V8::initialize_platform(v8::new_default_platform(0, false).make_shared());
-        v8::V8::initialize();
-    });
+impl JsRuntime {
+    /// Creates a new runtime, initialising V8 if this is the first runtime to be created.
+    pub fn new() -> Self {
+        INIT.call_once(|| {
+            v8::V8::initialize_platform(v8::new_default_platform(0, false).make_shared());
+            v8::V8::initialize();
+        });
+
+        Self {
+            isolate: RefCell::new(v8::Isolate::new(Default::default())),
+            active_scope: Cell::new(std::ptr::null_mut()),
+            block_error: RefCell::new(None),
+        }
+    }
+}
 
-    ISOLATE.with(|isolate| {
-        let mut isolate = isolate.borrow_mut();
-        let global_context;
+/// Attempts to load a JavaScript plugin from the given path, creating a new V8 context for it within the runtime.
+pub fn load_js_plugin(path: impl AsRef<Path>, runtime: &Rc<JsRuntime>) -> Result<Plugin, String> {
+    let mut isolate = runtime.isolate.borrow_mut();
+    let global_context;
 
-        let (name, version, functions) = {
-            let handle_scope = &mut v8::HandleScope::new(&mut *isolate);
-            let context = v8::Context::new(handle_scope);
-            global_context = v8::Global::new(handle_scope, context);
-            let scope = &mut v8::ContextScope::new(handle_scope, context);
-
-            let name: v8::Local<'_, v8::Value> =
-                v8::String::new(scope, &path.as_ref().to_string_lossy())
-                    .unwrap()
-                    .into();
-            let origin =
-                v8::ScriptOrigin::new(scope, name, 0, 0, false, 0, name, false, false, true);
-            let source_string = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
-            let source = v8::String::new(scope, &source_string).unwrap();
-            let compile_source = v8::script_compiler::Source::new(source, Some(&origin));
-            let module = v8::script_compiler::compile_module(scope, compile_source)
-                .ok_or("failed to compile module")?;
-
-            module
-                .instantiate_module(scope, |_, _, _, m| Some(m))
-                .ok_or("failed to instantiate module")?;
-            module.evaluate(scope).ok_or("failed to evaluate module")?;
-
-            let key = v8::String::new(scope, "default").unwrap();
-            let default = module
-                .get_module_namespace()
-                .to_object(scope)
+    let (name, version, functions) = {
+        let handle_scope = &mut v8::HandleScope::new(&mut *isolate);
+        let context = v8::Context::new(handle_scope);
+        global_context = v8::Global::new(handle_scope, context);
+        let scope = &mut v8::ContextScope::new(handle_scope, context);
+
+        let name: v8::Local<'_, v8::Value> =
+            v8::String::new(scope, &path.as_ref().to_string_lossy())
                 .unwrap()
-                .get(scope, key.into())
-                .ok_or("failed to get default export")?
+                .into();
+        let origin = v8::ScriptOrigin::new(scope, name, 0, 0, false, 0, name, false, false, true);
+        let source_string = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let source = v8::String::new(scope, &source_string).unwrap();
+        let compile_source = v8::script_compiler::Source::new(source, Some(&origin));
+        let module = v8::script_compiler::compile_module(scope, compile_source)
+            .ok_or("failed to compile module")?;
+
+        module
+            .instantiate_module(scope, |_, _, _, m| Some(m))
+            .ok_or("failed to instantiate module")?;
+        module.evaluate(scope).ok_or("failed to evaluate module")?;
+
+        let key = v8::String::new(scope, "default").unwrap();
+        let default = module
+            .get_module_namespace()
+            .to_object(scope)
+            .unwrap()
+            .get(scope, key.into())
+            .ok_or("failed to get default export")?
+            .to_object(scope)
+            .ok_or("failed to get default export")?;
+
+        let key = v8::String::new(scope, "name").unwrap();
+        let plugin_name = default
+            .get(scope, key.into())
+            .ok_or("missing plugin name")?
+            .to_rust_string_lossy(scope);
+
+        let key = v8::String::new(scope, "version").unwrap();
+        let plugin_version = default
+            .get(scope, key.into())
+            .ok_or("missing plugin version")?
+            .to_rust_string_lossy(scope);
+
+        let key = v8::String::new(scope, "functions").unwrap();
+        let functions = default
+            .get(scope, key.into())
+            .ok_or("missing plugin functions")?
+            .to_object(scope)
+            .ok_or("missing plugin functions")?;
+        let key = v8::String::new(scope, "length").unwrap();
+        let length = functions
+            .get(scope, key.into())
+            .ok_or("missing plugin functions")?
+            .uint32_value(scope)
+            .unwrap();
+
+        let mut functions_vec = Vec::with_capacity(length as usize);
+
+        for i in 0..length {
+            let function_object = functions
+                .get_index(scope, i)
+                .ok_or_else(|| format!("missing function at index {}", i))?
                 .to_object(scope)
-                .ok_or("failed to get default export")?;
+                .ok_or_else(|| format!("missing function at index {}", i))?;
 
             let key = v8::String::new(scope, "name").unwrap();
-            let plugin_name = default
+            let function_name = function_object
                 .get(scope, key.into())
-                .ok_or("missing plugin name")?
+                .ok_or_else(|| format!("invalid function at index {}", i))?
                 .to_rust_string_lossy(scope);
 
-            let key = v8::String::new(scope, "version").unwrap();
-            let plugin_version = default
+            let key = v8::String::new(scope, "fn").unwrap();
+            let function_fn = function_object
                 .get(scope, key.into())
-                .ok_or("missing plugin version")?
-                .to_rust_string_lossy(scope);
-
-            let key = v8::String::new(scope, "functions").unwrap();
-            let functions = default
-                .get(scope, key.into())
-                .ok_or("missing plugin functions")?
-                .to_object(scope)
-                .ok_or("missing plugin functions")?;
-            let key = v8::String::new(scope, "length").unwrap();
-            let length = functions
-                .get(scope, key.into())
-                .ok_or("missing plugin functions")?
-                .uint32_value(scope)
-                .unwrap();
-
-            let mut functions_vec = Vec::with_capacity(length as usize);
-
-            for i in 0..length {
-                let function_object = functions
-                    .get_index(scope, i)
-                    .ok_or_else(|| format!("missing function at index {}", i))?
-                    .to_object(scope)
-                    .ok_or_else(|| format!("missing function at index {}", i))?;
-
-                let key = v8::String::new(scope, "name").unwrap();
-                let function_name = function_object
-                    .get(scope, key.into())
-                    .ok_or_else(|| format!("invalid function at index {}", i))?
-                    .to_rust_string_lossy(scope);
-
-                let key = v8::String::new(scope, "fn").unwrap();
-                let function_fn = function_object
-                    .get(scope, key.into())
-                    .ok_or_else(|| format!("invalid function at index {}", i))?;
-
-                let key = v8::String::new(scope, &format!("_stuart_{}", function_name)).unwrap();
-                context.global(scope).set(scope, key.into(), function_fn);
-
-                functions_vec.push(function_name);
-            }
+                .ok_or_else(|| format!("invalid function at index {}", i))?;
 
-            (plugin_name, plugin_version, functions_vec)
-        };
+            let key = v8::String::new(scope, &format!("_stuart_{}", function_name)).unwrap();
+            context.global(scope).set(scope, key.into(), function_fn);
 
-        let mut function_parsers = Vec::with_capacity(functions.len());
-        for function in &functions {
-            function_parsers.push(Box::new(JSFunctionParser {
-                name: function.clone(),
-                context: global_context.clone(),
-            }) as Box<dyn FunctionParser>);
+            functions_vec.push(function_name);
         }
 
-        Ok(Plugin {
-            name,
-            version,
-            functions: function_parsers,
-            parsers: Vec::new(),
-        })
+        (plugin_name, plugin_version, functions_vec)
+    };
+
+    let mut function_parsers = Vec::with_capacity(functions.len());
+    for function in &functions {
+        function_parsers.push(Box::new(JSFunctionParser {
+            name: function.clone(),
+            context: global_context.clone(),
+            runtime: runtime.clone(),
+        }) as Box<dyn FunctionParser>);
+    }
+
+    Ok(Plugin {
+        name,
+        version,
+        functions: function_parsers,
+        parsers: Vec::new(),
     })
 }
 
@@ -166,6 +196,7 @@ impl FunctionParser for JSFunctionParser {
         Ok(Box::new(JSFunction {
             name: self.name.clone(),
             context: self.context.clone(),
+            runtime: self.runtime.clone(),
             args: raw.positional_args,
         }))
     }
@@ -173,50 +204,106 @@ impl FunctionParser for JSFunctionParser {
 
 impl Function for JSFunction {
     fn name(&self) -> &str {
-        todo!()
+        &self.name
     }
 
     fn execute(&self, stuart_scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
-        let self_token = stuart_scope.tokens.current().unwrap().clone();
+        let active_scope = self.runtime.active_scope.get();
 
-        ISOLATE.with(|isolate| {
-            let mut isolate = isolate.borrow_mut();
+        if active_scope.is_null() {
+            let mut isolate = self.runtime.isolate.borrow_mut();
             let handle_scope = &mut v8::HandleScope::new(&mut *isolate);
-            let context = v8::Local::new(handle_scope, &self.context);
-            let scope = &mut v8::ContextScope::new(handle_scope, context);
-
-            let evaluated_args =
-                self.args
-                    .iter()
-                    .map(|a| match a {
-                        RawArgument::Variable(name) => match stuart_scope.get_variable(name) {
-                            Some(v) => Ok(json::json_to_js(Some(v), scope)),
-                            None => Err(self_token
-                                .traceback(ProcessError::UndefinedVariable(name.to_string()))),
-                        },
-                        RawArgument::String(s) => Ok(v8::String::new(scope, s).unwrap().into()),
-                        RawArgument::Integer(i) => Ok(v8::Integer::new(scope, *i).into()),
-                        _ => Err(self_token.traceback(ProcessError::StackError)),
-                    })
-                    .collect::<Result<Vec<_>, _>>()?;
-
-            let key = v8::String::new(scope, &format!("_stuart_{}", self.name)).unwrap();
-            let function_obj = context.global(scope).get(scope, key.into()).unwrap();
-            let function = v8::Local::<v8::Function>::try_from(function_obj).unwrap();
-
-            // Make the `stuart_scope`` variable accessible from JavaScript calls back into Rust.
-            // If I've done this right (which is a big if), this should be safe because the V8 scope is dropped/GC'd as soon as `execute` returns.
-            context::set_stuart_context(scope, stuart_scope);
-
-            if let Some(result) = function.call(scope, function_obj, &evaluated_args) {
+            self.call(handle_scope, stuart_scope)
+        } else {
+            // The isolate is already borrowed by the call whose block is being processed, so re-enter it through the
+            //   handle scope of that call's callback, which is set for exactly as long as the block is processed.
+            let parent = unsafe { &mut *active_scope };
+            let handle_scope = &mut v8::HandleScope::new(parent);
+            self.call(handle_scope, stuart_scope)
+        }
+    }
+}
+
+impl JSFunction {
+    /// Calls the JavaScript function within the given handle scope, writing its result to the output.
+    ///
+    /// If the function throws, the exception is returned as a plugin error, unless it was caused by an error
+    ///   processing a block, in which case that error is returned instead.
+    fn call(
+        &self,
+        handle_scope: &mut v8::HandleScope<()>,
+        stuart_scope: &mut Scope,
+    ) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = stuart_scope.tokens.current().unwrap().clone();
+
+        let context = v8::Local::new(handle_scope, &self.context);
+        let scope = &mut v8::ContextScope::new(handle_scope, context);
+        let scope = &mut v8::TryCatch::new(scope);
+
+        let evaluated_args = self
+            .args
+            .iter()
+            .map(|a| match a {
+                RawArgument::Variable(name) => match stuart_scope.get_variable(name) {
+                    Some(v) => Ok(json::json_to_js(Some(v), scope)),
+                    None => {
+                        Err(self_token.traceback(ProcessError::UndefinedVariable(name.to_string())))
+                    }
+                },
+                RawArgument::String(s) => Ok(v8::String::new(scope, s).unwrap().into()),
+                RawArgument::Integer(i) => Ok(v8::Integer::new(scope, *i).into()),
+                RawArgument::Bool(b) => Ok(v8::Boolean::new(scope, *b).into()),
+                _ => Err(self_token.traceback(ProcessError::StackError)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let key = v8::String::new(scope, &format!("_stuart_{}", self.name)).unwrap();
+        let function_obj = context.global(scope).get(scope, key.into()).unwrap();
+        let function = v8::Local::<v8::Function>::try_from(function_obj).unwrap();
+
+        // Make the `stuart_scope`` variable accessible from JavaScript calls back into Rust.
+        // If I've done this right (which is a big if), this should be safe because the V8 scope is dropped/GC'd as soon as `execute` returns.
+        // The previous `STUART` object is restored afterwards in case this is a nested call in the same context.
+        let previous_context =
+            context::set_stuart_context(scope, stuart_scope, &self.runtime, &self.name);
+        let result = function.call(scope, function_obj, &evaluated_args);
+        context::restore_stuart_context(scope, previous_context);
+
+        match result {
+            Some(result) => {
                 if !result.is_undefined() {
                     stuart_scope
                         .output(result.to_rust_string_lossy(scope))
                         .unwrap();
                 }
+
+                Ok(())
+            }
+            None => {
+                if let Some(e) = self.runtime.block_error.take() {
+                    return Err(e);
+                }
+
+                let message = match scope.exception() {
+                    Some(exception) => exception.to_rust_string_lossy(scope),
+                    None => "execution was terminated".to_string(),
+                };
+
+                Err(self_token.traceback(ProcessError::PluginError(format!(
+                    "js function `{}` failed: {}",
+                    self.name, message
+                ))))
             }
+        }
+    }
+}
 
-            Ok(())
-        })
+impl std::fmt::Debug for JSFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JSFunction")
+            .field("name", &self.name)
+            .field("context", &self.context)
+            .field("args", &self.args)
+            .finish()
     }
 }
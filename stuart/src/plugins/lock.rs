@@ -0,0 +1,63 @@
+//! Provides the `stuart.lock` lockfile, which records the exact versions of Git and downloaded plugins.
+
+use crate::error::StuartError;
+
+use serde_derive::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::fs::{read_to_string, write};
+use std::path::Path;
+
+/// The name of the lockfile, which is stored next to the manifest.
+pub const LOCKFILE: &str = "stuart.lock";
+
+/// The comment at the top of the lockfile.
+const HEADER: &str = "# This file is generated by Stuart to record the exact versions of plugins.\n# It should not be edited manually.\n\n";
+
+/// The contents of a lockfile.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    /// The locked plugins, keyed by name.
+    #[serde(default)]
+    pub plugins: BTreeMap<String, LockedPlugin>,
+}
+
+/// The exact version of a plugin.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPlugin {
+    /// The source which the plugin was loaded from.
+    pub source: String,
+    /// The commit which the plugin's Git repository was checked out at.
+    pub commit: Option<String>,
+    /// The SHA-256 hash of the downloaded plugin.
+    pub sha256: Option<String>,
+}
+
+impl Lockfile {
+    /// Reads the lockfile in the given project directory, returning an empty lockfile if there isn't one.
+    pub fn read(root: &Path) -> Result<Self, Box<dyn StuartError>> {
+        match read_to_string(root.join(LOCKFILE)) {
+            Ok(lockfile) => Ok(toml::from_str(&lockfile)
+                .map_err(|e| format!("failed to parse `{}`:\n  {}", LOCKFILE, e))?),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Writes the lockfile to the given project directory.
+    pub fn write(&self, root: &Path) -> Result<(), Box<dyn StuartError>> {
+        let lockfile = toml::to_string(self)
+            .map_err(|e| format!("failed to serialize `{}`:\n  {}", LOCKFILE, e))?;
+
+        write(root.join(LOCKFILE), format!("{}{}", HEADER, lockfile))
+            .map_err(|e| format!("failed to write `{}`:\n  {}", LOCKFILE, e))?;
+
+        Ok(())
+    }
+
+    /// Returns the locked version of the plugin with the given name, if it was locked from the same source.
+    pub fn get(&self, name: &str, source: &str) -> Option<&LockedPlugin> {
+        self.plugins
+            .get(name)
+            .filter(|locked| locked.source == source)
+    }
+}
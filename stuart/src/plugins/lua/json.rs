@@ -0,0 +1,69 @@
+//! Provides functionality for converting between JSON and Lua values.
+
+use humphrey_json::Value;
+use mlua::Lua;
+
+/// Converts a JSON value to a Lua value.
+///
+/// Arrays and objects are both converted to tables, with arrays indexed from one as is conventional in Lua.
+pub fn json_to_lua(value: Value, lua: &Lua) -> mlua::Result<mlua::Value> {
+    Ok(match value {
+        Value::Null => mlua::Value::Nil,
+        Value::Bool(boolean) => mlua::Value::Boolean(boolean),
+        Value::Number(number) => mlua::Value::Number(number),
+        Value::String(string) => mlua::Value::String(lua.create_string(string)?),
+        Value::Array(array) => {
+            let table = lua.create_table_with_capacity(array.len(), 0)?;
+            for (i, value) in array.into_iter().enumerate() {
+                table.raw_seti(i + 1, json_to_lua(value, lua)?)?;
+            }
+            mlua::Value::Table(table)
+        }
+        Value::Object(object) => {
+            let table = lua.create_table_with_capacity(0, object.len())?;
+            for (key, value) in object {
+                table.raw_set(key, json_to_lua(value, lua)?)?;
+            }
+            mlua::Value::Table(table)
+        }
+    })
+}
+
+/// Converts a Lua value to a JSON value.
+///
+/// Returns `None` if `value` is `nil` or cannot be represented as JSON, such as a function.
+/// Tables with a non-empty sequence part are converted to arrays, and all other tables to objects.
+pub fn lua_to_json(value: mlua::Value) -> Option<Value> {
+    match value {
+        mlua::Value::Boolean(boolean) => Some(Value::Bool(boolean)),
+        mlua::Value::Integer(integer) => Some(Value::Number(integer as f64)),
+        mlua::Value::Number(number) => Some(Value::Number(number)),
+        mlua::Value::String(string) => Some(Value::String(string.to_string_lossy())),
+        mlua::Value::Table(table) if table.raw_len() > 0 => {
+            let array = table
+                .sequence_values::<mlua::Value>()
+                .map(|value| value.ok().and_then(lua_to_json).unwrap_or(Value::Null))
+                .collect();
+
+            Some(Value::Array(array))
+        }
+        mlua::Value::Table(table) => {
+            let object = table
+                .pairs::<mlua::Value, mlua::Value>()
+                .filter_map(|pair| {
+                    let (key, value) = pair.ok()?;
+                    let key = match key {
+                        mlua::Value::String(key) => key.to_string_lossy(),
+                        mlua::Value::Integer(key) => key.to_string(),
+                        _ => return None,
+                    };
+
+                    Some((key, lua_to_json(value)?))
+                })
+                .collect();
+
+            Some(Value::Object(object))
+        }
+        _ => None,
+    }
+}
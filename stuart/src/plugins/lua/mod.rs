@@ -0,0 +1,162 @@
+//! Implements Lua plugins.
+
+mod json;
+
+use stuart_core::functions::{Function, FunctionParser};
+use stuart_core::parse::{ParseError, RawArgument, RawFunction};
+use stuart_core::plugins::Plugin;
+use stuart_core::process::{ProcessError, Scope};
+use stuart_core::TracebackError;
+
+use humphrey_json::Value;
+use mlua::{Lua, MultiValue, Table};
+
+use std::fmt::Debug;
+use std::path::Path;
+
+/// A parser for Lua functions.
+///
+/// Like JavaScript functions, Lua functions can take a variable number of arguments of different types, so this
+///   parser just passes the function's arguments to `LuaFunction` as-is.
+pub struct LuaFunctionParser {
+    /// The name of the function.
+    name: String,
+    /// The Lua state of this plugin.
+    lua: Lua,
+    /// The Lua function itself.
+    function: mlua::Function,
+}
+
+/// A Stuart function that executes Lua code.
+pub struct LuaFunction {
+    /// The name of the function.
+    name: String,
+    /// The Lua state of this plugin, which must be kept alive for as long as the function.
+    lua: Lua,
+    /// The Lua function itself.
+    function: mlua::Function,
+    /// The function's arguments.
+    args: Vec<RawArgument>,
+}
+
+/// Attempts to load a Lua plugin from the given path, creating a new Lua state for it.
+///
+/// The plugin is a Lua chunk which returns a table containing the plugin metadata, for example:
+/// ```lua
+/// return {
+///   name = "my_plugin",
+///   version = "1.0.0",
+///   functions = {
+///     add = function(a, b) return a + b end
+///   }
+/// }
+/// ```
+pub fn load_lua_plugin(path: impl AsRef<Path>) -> Result<Plugin, String> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+    let lua = Lua::new();
+    let plugin: Table = lua
+        .load(&source)
+        .set_name(path.to_string_lossy())
+        .eval()
+        .map_err(|e| format!("failed to evaluate plugin: {}", e))?;
+
+    let name: String = plugin.get("name").map_err(|_| "missing plugin name")?;
+    let version: String = plugin
+        .get("version")
+        .map_err(|_| "missing plugin version")?;
+    let functions: Table = plugin
+        .get("functions")
+        .map_err(|_| "missing plugin functions")?;
+
+    let mut function_parsers = Vec::new();
+
+    for pair in functions.pairs::<String, mlua::Function>() {
+        let (name, function) = pair.map_err(|e| format!("invalid function: {}", e))?;
+
+        function_parsers.push(Box::new(LuaFunctionParser {
+            name,
+            lua: lua.clone(),
+            function,
+        }) as Box<dyn FunctionParser>);
+    }
+
+    Ok(Plugin {
+        name,
+        version,
+        functions: function_parsers,
+        parsers: Vec::new(),
+    })
+}
+
+impl FunctionParser for LuaFunctionParser {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        Ok(Box::new(LuaFunction {
+            name: self.name.clone(),
+            lua: self.lua.clone(),
+            function: self.function.clone(),
+            args: raw.positional_args,
+        }))
+    }
+}
+
+impl Function for LuaFunction {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+        let lua_error = |e: mlua::Error| {
+            self_token.traceback(ProcessError::PluginError(format!(
+                "lua function `{}` failed: {}",
+                self.name, e
+            )))
+        };
+
+        let args = self
+            .args
+            .iter()
+            .map(|a| match a {
+                RawArgument::Variable(name) => match scope.get_variable(name) {
+                    Some(v) => json::json_to_lua(v, &self.lua).map_err(lua_error),
+                    None => {
+                        Err(self_token.traceback(ProcessError::UndefinedVariable(name.to_string())))
+                    }
+                },
+                RawArgument::String(s) => self
+                    .lua
+                    .create_string(s)
+                    .map(mlua::Value::String)
+                    .map_err(lua_error),
+                RawArgument::Integer(i) => Ok(mlua::Value::Integer(*i as i64)),
+                RawArgument::Bool(b) => Ok(mlua::Value::Boolean(*b)),
+                _ => Err(self_token.traceback(ProcessError::StackError)),
+            })
+            .collect::<Result<MultiValue, _>>()?;
+
+        let result = self.function.call::<mlua::Value>(args).map_err(lua_error)?;
+
+        match json::lua_to_json(result) {
+            Some(Value::String(s)) => scope.output(s),
+            Some(value) => scope.output(value.serialize()),
+            None => Ok(()),
+        }
+        .map_err(|e| self_token.traceback(e))
+    }
+}
+
+impl Debug for LuaFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LuaFunction")
+            .field("name", &self.name)
+            .field("function", &self.function)
+            .field("args", &self.args)
+            .finish()
+    }
+}
@@ -1,22 +1,35 @@
 //! Provides support for dynamically-loaded plugins.
 
-mod source;
+pub mod lock;
+pub mod source;
 
 #[cfg(feature = "js")]
 mod js;
 
-use crate::config::git;
+#[cfg(feature = "lua")]
+mod lua;
+
+#[cfg(feature = "wasm")]
+mod wasm;
+
+use crate::config::{git, Fetch};
 use crate::error::StuartError;
 
+use self::lock::{LockedPlugin, Lockfile};
+
 use stuart_core::error::{Error, FsError};
 use stuart_core::plugins::{Manager, Plugin};
 
 use libloading::Library;
 
 use std::collections::HashMap;
-use std::fs::create_dir_all;
-use std::path::Path;
-use std::time::Instant;
+use std::fs::{copy, create_dir_all, read_to_string, write};
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "js")]
+use std::rc::Rc;
 
 /// Represents an external function that initializes a plugin.
 type PluginInitFn = unsafe extern "C" fn() -> *mut Plugin;
@@ -28,6 +41,31 @@ pub struct DynamicPluginManager {
     plugins: Vec<Plugin>,
     /// The libraries which belong to the loaded plugins.
     libraries: Vec<Library>,
+    /// The JavaScript runtime shared by the loaded JavaScript plugins, created when the first one is loaded.
+    #[cfg(feature = "js")]
+    js_runtime: Option<Rc<js::JsRuntime>>,
+}
+
+/// How cloning, pulling and downloading plugins are retried when they fail, such as because of a network error.
+#[derive(Clone, Copy, Debug)]
+pub struct Retry {
+    /// The number of times to retry after the first attempt fails.
+    pub retries: u32,
+    /// The delay before the first retry, which doubles after each retry.
+    pub delay: Duration,
+}
+
+/// Options for loading plugins.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LoadOptions {
+    /// How failed clones, pulls and downloads are retried.
+    pub retry: Retry,
+    /// Whether to update Git and downloaded plugins to their latest versions, ignoring `stuart.lock`.
+    pub update: bool,
+    /// Whether to save the versions of the loaded plugins to `stuart.lock`.
+    ///
+    /// This should only be enabled when every plugin of the project is loaded, since the lockfile is replaced.
+    pub save_lock: bool,
 }
 
 /// Attempts to load the plugins configured in the hash map.
@@ -37,6 +75,12 @@ pub struct DynamicPluginManager {
 /// Sources can be separated within the string with a semicolon (`;`), and will
 /// be tried in order until one succeeds or all fail.
 ///
+/// Plugins are loaded in alphabetical order of their names, so that when parsers of
+/// the same priority claim the same extension, the same one is always used.
+///
+/// Git plugins are checked out at the commits recorded in `stuart.lock`, and downloaded
+/// plugins are verified against the hashes recorded there, unless `update` is set.
+///
 /// Example configuration:
 /// ```toml
 /// [dependencies]
@@ -44,23 +88,39 @@ pub struct DynamicPluginManager {
 /// git_plugin = "https://github.com/username/another_plugin.git"
 /// src_plugin = "/path/to/cargo_project"
 /// download_plugin = "https://example.com/plugin.so"
+/// verified_plugin = "https://example.com/plugin.so#sha256=<hex>"
 /// os_independent_plugin = "/path/to/plugin.dll;/path/to/plugin.so"
 /// ```
 pub fn load(
     plugins: &Option<HashMap<String, String>>,
     root: &Path,
+    options: &LoadOptions,
 ) -> Result<DynamicPluginManager, Box<dyn StuartError>> {
     let plugins_start = Instant::now();
 
     let mut manager = DynamicPluginManager::new();
+    let lockfile = Lockfile::read(root)?;
+    let mut new_lockfile = Lockfile::default();
 
     if let Some(plugins) = plugins {
-        'outer: for (name, src) in plugins {
+        let mut names = plugins.keys().collect::<Vec<_>>();
+        names.sort();
+
+        'outer: for name in names {
+            let src = &plugins[name];
+
+            if name.ends_with(".sha256") {
+                continue;
+            }
+
+            let checksum = plugins.get(&format!("{}.sha256", name));
             let mut e: Option<Box<dyn StuartError>> = None;
 
             for source in src.split(';') {
+                let path = source.split('#').next().unwrap();
+
                 #[cfg(target_os = "windows")]
-                if source.ends_with(".so") {
+                if path.ends_with(".so") {
                     log!(
                         "Skipping",
                         "plugin file `{}` (not supported on Windows)",
@@ -70,7 +130,7 @@ pub fn load(
                 }
 
                 #[cfg(not(target_os = "windows"))]
-                if source.ends_with(".dll") {
+                if path.ends_with(".dll") {
                     log!(
                         "Skipping",
                         "plugin file `{}` (not supported on non-Windows platforms)",
@@ -80,7 +140,7 @@ pub fn load(
                 }
 
                 #[cfg(not(feature = "js"))]
-                if source.ends_with(".js") || source.ends_with(".mjs") {
+                if path.ends_with(".js") || path.ends_with(".mjs") {
                     log!(
                         "Skipping",
                         "plugin file `{}` (JavaScript support is not enabled)",
@@ -89,13 +149,50 @@ pub fn load(
                     continue;
                 }
 
-                if let Err(err) = load_from_source(&mut manager, name, source, root) {
-                    if e.is_none() {
-                        err.print();
-                        e = Some(err);
+                #[cfg(not(feature = "lua"))]
+                if path.ends_with(".lua") {
+                    log!(
+                        "Skipping",
+                        "plugin file `{}` (Lua support is not enabled)",
+                        source
+                    );
+                    continue;
+                }
+
+                #[cfg(not(feature = "wasm"))]
+                if path.ends_with(".wasm") || path.ends_with(".wat") {
+                    log!(
+                        "Skipping",
+                        "plugin file `{}` (WebAssembly support is not enabled)",
+                        source
+                    );
+                    continue;
+                }
+
+                let locked = lockfile.get(name, source).filter(|_| !options.update);
+
+                match load_from_source(
+                    &mut manager,
+                    name,
+                    source,
+                    checksum,
+                    root,
+                    &options.retry,
+                    locked,
+                ) {
+                    Ok(locked) => {
+                        if let Some(locked) = locked {
+                            new_lockfile.plugins.insert(name.to_string(), locked);
+                        }
+
+                        continue 'outer;
+                    }
+                    Err(err) => {
+                        if e.is_none() {
+                            err.print();
+                            e = Some(err);
+                        }
                     }
-                } else {
-                    continue 'outer;
                 }
             }
 
@@ -103,6 +200,13 @@ pub fn load(
         }
     }
 
+    if options.save_lock
+        && new_lockfile != lockfile
+        && (!new_lockfile.plugins.is_empty() || root.join(lock::LOCKFILE).exists())
+    {
+        new_lockfile.write(root)?;
+    }
+
     let plugins_duration = (plugins_start.elapsed().as_micros() / 100) as f64 / 10.0;
 
     if !manager.plugins().is_empty() {
@@ -117,13 +221,22 @@ pub fn load(
     Ok(manager)
 }
 
-/// Attempts to load one specific plugin from the given source.
+/// Attempts to load one specific plugin from the given source, returning its version to record in `stuart.lock`
+///   if it was cloned or downloaded.
+///
+/// If the plugin is downloaded, it is verified against the checksum in the source's `#sha256=` fragment if present,
+///   or otherwise the given checksum, before being loaded. Cloning, pulling and downloading are retried as
+///   configured before giving up. If the plugin is locked, Git plugins are checked out at the locked commit
+///   instead of being pulled, and downloaded plugins must match the locked hash.
 fn load_from_source(
     manager: &mut DynamicPluginManager,
     name: &str,
     src: &str,
+    checksum: Option<&String>,
     root: &Path,
-) -> Result<(), Box<dyn StuartError>> {
+    retry: &Retry,
+    locked: Option<&LockedPlugin>,
+) -> Result<Option<LockedPlugin>, Box<dyn StuartError>> {
     let source = root.join(src);
 
     if source.exists() && source.is_file() {
@@ -131,75 +244,285 @@ fn load_from_source(
 
         manager.load(source)?;
 
-        Ok(())
+        Ok(None)
     } else if source.join("Cargo.toml").exists() {
-        log!("Compiling", "plugin `{}` from `{}`", name, src);
-
-        let path = source::build_cargo_project(&source)?;
+        let path = build_cached(name, &source, root, stuart_core::VERSION, || {
+            log!("Compiling", "plugin `{}` from `{}`", name, src);
+            Ok(source::build_cargo_project(&source)?)
+        })?;
 
         unsafe { manager.load_binary(path)? };
 
-        Ok(())
+        Ok(None)
     } else if git::exists(src) {
         let repo_dir = root.join(format!("_build/plugins/{}", name));
-        let repo_dir_string = repo_dir
-            .to_string_lossy()
-            .to_string()
-            .trim_start_matches("\\\\?\\")
-            .to_string();
+        let commit = fetch_git(
+            name,
+            src,
+            &repo_dir,
+            retry,
+            locked.and_then(|locked| locked.commit.as_deref()),
+        )?;
 
-        if !repo_dir.exists() {
-            log!("Cloning", "plugin `{}` from `{}`", name, src);
-
-            create_dir_all(root.join("_build/plugins")).map_err(|_| Error::Fs(FsError::Write))?;
+        let project = source::find_cargo_project(&repo_dir, name)
+            .ok_or_else(|| format!("failed to find plugin `{}` in Git repository", name))?;
 
-            if !git::clone(src, &repo_dir_string) {
-                Err(format!(
-                    "failed to clone Git repository for plugin `{}`",
-                    name
-                ))?;
-            }
-        } else {
-            log!("Pulling", "plugin `{}` from `{}`", name, src);
+        let path = build_cached(name, &project, root, stuart_core::VERSION, || {
+            log!("Compiling", "plugin `{}`", name);
+            Ok(source::build_cargo_project(&project)?)
+        })?;
 
-            if !git::pull(&repo_dir_string) {
-                Err(format!(
-                    "failed to pull Git repository for plugin `{}`",
-                    name
-                ))?;
-            }
-        }
+        unsafe { manager.load_binary(path)? };
 
-        let project = source::find_cargo_project(&repo_dir, name)
-            .ok_or_else(|| format!("failed to find plugin `{}` in Git repository", name))?;
+        Ok(Some(LockedPlugin {
+            source: src.to_string(),
+            commit: Some(commit),
+            sha256: None,
+        }))
+    } else if let Some(plugin) = download(name, src.split('#').next().unwrap(), retry) {
+        let (url, fragment) = src.split_once('#').unwrap_or((src, ""));
 
-        log!("Compiling", "plugin `{}`", name);
+        log!("Downloading", "plugin `{}` from `{}`", name, url);
 
-        let path = source::build_cargo_project(project)?;
+        if let Some(checksum) = source::expected_checksum(fragment, checksum.map(|c| c.as_str())) {
+            source::verify_checksum(&plugin, checksum)
+                .map_err(|e| format!("failed to verify plugin `{}`: {}", name, e))?;
 
-        unsafe { manager.load_binary(path)? };
+            log!("Verified", "checksum of plugin `{}`", name);
+        }
 
-        Ok(())
-    } else if let Some(plugin) = source::download_plugin(src) {
-        log!("Downloading", "plugin `{}` from `{}`", name, src);
+        if let Some(sha256) = locked.and_then(|locked| locked.sha256.as_deref()) {
+            source::verify_checksum(&plugin, sha256).map_err(|e| {
+                format!(
+                    "plugin `{}` does not match `{}` ({}), run with `--update` to update it",
+                    name,
+                    lock::LOCKFILE,
+                    e
+                )
+            })?;
+        }
 
         let plugin_dir = root.join(format!("_build/plugins/{}", name));
-        let plugin_path = plugin_dir.join(src.rsplit('/').next().unwrap());
+        let plugin_path = plugin_dir.join(url.rsplit('/').next().unwrap());
 
         if !plugin_dir.exists() {
             create_dir_all(&plugin_dir).map_err(|_| Error::Fs(FsError::Write))?;
         }
 
-        std::fs::write(&plugin_path, plugin).map_err(|_| Error::Fs(FsError::Write))?;
+        std::fs::write(&plugin_path, &plugin).map_err(|_| Error::Fs(FsError::Write))?;
 
         manager.load(plugin_path)?;
 
-        Ok(())
+        Ok(Some(LockedPlugin {
+            source: src.to_string(),
+            commit: None,
+            sha256: Some(source::sha256(&plugin)),
+        }))
     } else {
         Err(format!("invalid source for plugin `{}`", name))?
     }
 }
 
+/// Clones the Git repository of a plugin into the given directory, or updates it if it has already been cloned,
+///   returning the commit it is checked out at.
+///
+/// If a commit is locked, the repository is checked out at that commit instead of being pulled.
+pub fn fetch_git(
+    name: &str,
+    src: &str,
+    repo_dir: &Path,
+    retry: &Retry,
+    locked: Option<&str>,
+) -> Result<String, Box<dyn StuartError>> {
+    let repo_dir_string = repo_dir
+        .to_string_lossy()
+        .to_string()
+        .trim_start_matches("\\\\?\\")
+        .to_string();
+
+    if !repo_dir.exists() {
+        log!("Cloning", "plugin `{}` from `{}`", name, src);
+
+        if let Some(parent) = repo_dir.parent() {
+            create_dir_all(parent).map_err(|_| Error::Fs(FsError::Write))?;
+        }
+
+        let cloned = retry.run(&format!("clone of plugin `{}`", name), || {
+            git::clone(src, &repo_dir_string).then_some(())
+        });
+
+        if cloned.is_none() {
+            Err(format!(
+                "failed to clone Git repository for plugin `{}`",
+                name
+            ))?;
+        }
+    } else if locked.is_none() {
+        log!("Pulling", "plugin `{}` from `{}`", name, src);
+
+        let pulled = retry.run(&format!("pull of plugin `{}`", name), || {
+            git::pull(&repo_dir_string).then_some(())
+        });
+
+        if pulled.is_none() {
+            Err(format!(
+                "failed to pull Git repository for plugin `{}`",
+                name
+            ))?;
+        }
+    }
+
+    if let Some(commit) = locked {
+        if git::head(&repo_dir_string).as_deref() != Some(commit) {
+            log!(
+                "Checking out",
+                "plugin `{}` at locked commit `{}`",
+                name,
+                commit
+            );
+
+            let checked_out = retry.run(&format!("checkout of plugin `{}`", name), || {
+                git::checkout(&repo_dir_string, commit).then_some(())
+            });
+
+            if checked_out.is_none() {
+                Err(format!(
+                    "failed to check out locked commit `{}` of plugin `{}`",
+                    commit, name
+                ))?;
+            }
+        }
+    }
+
+    Ok(git::head(&repo_dir_string)
+        .ok_or_else(|| format!("failed to find the commit of plugin `{}`", name))?)
+}
+
+/// Downloads the plugin from the given URL, retrying failed downloads if it is an HTTP or HTTPS URL.
+///
+/// Other sources are only tried once, since they are only tried at all because they weren't found locally.
+fn download(name: &str, url: &str, retry: &Retry) -> Option<Vec<u8>> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        retry.run(&format!("download of plugin `{}`", name), || {
+            source::download_plugin(url)
+        })
+    } else {
+        source::download_plugin(url)
+    }
+}
+
+/// Returns `true` if any of the semicolon-separated sources could be loaded by [`load_from_source`], without
+///   loading it.
+///
+/// Local files and Cargo projects must exist, Git repositories must be reachable, and anything else must be
+///   downloadable.
+pub fn source_exists(src: &str, root: &Path) -> bool {
+    src.split(';').any(|source| {
+        let path = source.split('#').next().unwrap();
+        let local = root.join(path);
+
+        (local.exists() && local.is_file())
+            || local.join("Cargo.toml").exists()
+            || git::exists(path)
+            || source::download_plugin(path).is_some()
+    })
+}
+
+/// Builds the Cargo project of a plugin with the given function, or reuses the binary cached in `_build/plugins`
+///   if neither the plugin's source nor the given version of Stuart has changed since it was last built.
+///
+/// The cache is keyed by a `{name}.hash` file containing the Stuart version and a hash of the source.
+/// `build` is called only if the plugin actually needs to be compiled, and returns the path of the built binary.
+pub fn build_cached(
+    name: &str,
+    project: &Path,
+    root: &Path,
+    version: &str,
+    build: impl FnOnce() -> Result<PathBuf, Box<dyn StuartError>>,
+) -> Result<PathBuf, Box<dyn StuartError>> {
+    let cache_dir = root.join("_build/plugins");
+    let hash_path = cache_dir.join(format!("{}.hash", name));
+
+    #[cfg(target_os = "windows")]
+    let binary_path = cache_dir.join(format!("{}.dll", name));
+    #[cfg(not(target_os = "windows"))]
+    let binary_path = cache_dir.join(format!("lib{}.so", name));
+
+    let hash = source::hash_cargo_project(project).map(|hash| format!("{}:{}", version, hash));
+
+    if let Some(hash) = &hash {
+        if binary_path.exists() && read_to_string(&hash_path).ok().as_ref() == Some(hash) {
+            log!("Cached", "plugin `{}` is up to date", name);
+
+            return Ok(binary_path);
+        }
+    }
+
+    let built_path = build()?;
+
+    create_dir_all(&cache_dir).map_err(|_| Error::Fs(FsError::Write))?;
+    copy(&built_path, &binary_path).map_err(|_| Error::Fs(FsError::Write))?;
+
+    if let Some(hash) = hash {
+        write(&hash_path, hash).map_err(|_| Error::Fs(FsError::Write))?;
+    }
+
+    Ok(binary_path)
+}
+
+impl Retry {
+    /// Calls the function until it succeeds or the retries run out, waiting between attempts and logging each
+    ///   retry with the given description of the operation.
+    pub fn run<T>(&self, description: &str, mut attempt: impl FnMut() -> Option<T>) -> Option<T> {
+        let mut delay = self.delay;
+
+        for retry in 1..=self.retries {
+            if let Some(result) = attempt() {
+                return Some(result);
+            }
+
+            log!(
+                "Retrying",
+                "{} in {}ms (retry {} of {})",
+                description,
+                delay.as_millis(),
+                retry,
+                self.retries
+            );
+
+            sleep(delay);
+            delay *= 2;
+        }
+
+        attempt()
+    }
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            delay: Duration::from_millis(500),
+        }
+    }
+}
+
+impl From<Option<&Fetch>> for Retry {
+    fn from(fetch: Option<&Fetch>) -> Self {
+        let default = Self::default();
+
+        Self {
+            retries: fetch
+                .and_then(|fetch| fetch.retries)
+                .unwrap_or(default.retries),
+            delay: fetch
+                .and_then(|fetch| fetch.retry_delay)
+                .map(Duration::from_millis)
+                .unwrap_or(default.delay),
+        }
+    }
+}
+
 impl DynamicPluginManager {
     /// Creates a new, empty plugin manager.
     pub fn new() -> Self {
@@ -215,6 +538,14 @@ impl DynamicPluginManager {
             #[cfg(feature = "js")]
             self.load_js(path)?;
             Ok(())
+        } else if ext == "lua" {
+            #[cfg(feature = "lua")]
+            self.load_lua(path)?;
+            Ok(())
+        } else if ext == "wasm" || ext == "wat" {
+            #[cfg(feature = "wasm")]
+            self.load_wasm(path)?;
+            Ok(())
         } else {
             unsafe { self.load_binary(path) }
         }
@@ -244,7 +575,28 @@ impl DynamicPluginManager {
     /// Attempts to load a JavaScript plugin from the given path.
     #[cfg(feature = "js")]
     pub fn load_js(&mut self, path: impl AsRef<Path>) -> Result<(), String> {
-        let plugin = js::load_js_plugin(path)?;
+        let runtime = self
+            .js_runtime
+            .get_or_insert_with(|| Rc::new(js::JsRuntime::new()));
+        let plugin = js::load_js_plugin(path, runtime)?;
+        self.plugins.push(plugin);
+
+        Ok(())
+    }
+
+    /// Attempts to load a Lua plugin from the given path.
+    #[cfg(feature = "lua")]
+    pub fn load_lua(&mut self, path: impl AsRef<Path>) -> Result<(), String> {
+        let plugin = lua::load_lua_plugin(path)?;
+        self.plugins.push(plugin);
+
+        Ok(())
+    }
+
+    /// Attempts to load a WebAssembly plugin from the given path.
+    #[cfg(feature = "wasm")]
+    pub fn load_wasm(&mut self, path: impl AsRef<Path>) -> Result<(), String> {
+        let plugin = wasm::load_wasm_plugin(path)?;
         self.plugins.push(plugin);
 
         Ok(())
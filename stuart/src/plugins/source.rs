@@ -3,8 +3,9 @@
 use crate::scripts::ScriptError;
 
 use humphrey::Client;
+use sha2::{Digest, Sha256, Sha512};
 
-use std::fs::{read_dir, read_to_string};
+use std::fs::{read, read_dir, read_to_string};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
@@ -86,3 +87,89 @@ pub fn download_plugin(url: &str) -> Option<Vec<u8>> {
 
     Some(response.body)
 }
+
+/// Verifies the given bytes against the expected hex-encoded checksum.
+///
+/// SHA-256 and SHA-512 checksums are supported, and are detected by the length of the expected hash.
+pub fn verify_checksum(bytes: &[u8], expected: &str) -> Result<(), String> {
+    let expected = expected.trim().to_ascii_lowercase();
+
+    let actual = match expected.len() {
+        64 => to_hex(&Sha256::digest(bytes)),
+        128 => to_hex(&Sha512::digest(bytes)),
+        _ => return Err(format!("invalid checksum `{}`", expected)),
+    };
+
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "checksum mismatch (expected `{}`, found `{}`)",
+            expected, actual
+        ))
+    }
+}
+
+/// Returns the checksum which a downloaded plugin must match, given the fragment of its source URL and the
+///   checksum configured in the manifest.
+///
+/// A `sha256=` or `sha512=` fragment takes precedence over the configured checksum.
+pub fn expected_checksum<'a>(fragment: &'a str, configured: Option<&'a str>) -> Option<&'a str> {
+    fragment
+        .strip_prefix("sha256=")
+        .or_else(|| fragment.strip_prefix("sha512="))
+        .or(configured)
+}
+
+/// Computes the SHA-256 hash of the source files of the Cargo project at the given path, as a hex string.
+///
+/// The `target` directory and hidden files are ignored, since they do not affect the compiled plugin.
+pub fn hash_cargo_project(root: impl AsRef<Path>) -> Option<String> {
+    let root = root.as_ref();
+    let mut files = Vec::new();
+    find_source_files(root, &mut files)?;
+    files.sort();
+
+    let mut hasher = Sha256::new();
+
+    for file in files {
+        hasher.update(file.strip_prefix(root).ok()?.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(read(&file).ok()?);
+        hasher.update([0]);
+    }
+
+    Some(to_hex(&hasher.finalize()))
+}
+
+/// Recursively finds the source files of a Cargo project, adding them to the given list.
+fn find_source_files(dir: &Path, files: &mut Vec<PathBuf>) -> Option<()> {
+    for entry in read_dir(dir).ok()?.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+
+        if name.starts_with('.') || (name == "target" && dir.join("Cargo.toml").exists()) {
+            continue;
+        }
+
+        let metadata = entry.metadata().ok()?;
+
+        if metadata.is_dir() {
+            find_source_files(&entry.path(), files)?;
+        } else if metadata.is_file() {
+            files.push(entry.path());
+        }
+    }
+
+    Some(())
+}
+
+/// Computes the SHA-256 hash of the given bytes, as a hex string.
+pub fn sha256(bytes: &[u8]) -> String {
+    to_hex(&Sha256::digest(bytes))
+}
+
+/// Encodes the given bytes as a lowercase hex string.
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
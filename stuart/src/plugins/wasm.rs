@@ -0,0 +1,367 @@
+//! Implements WebAssembly plugins.
+//!
+//! WebAssembly plugins can be compiled from any language and run on any platform, unlike native plugins which must
+//!   be compiled for the same platform and Rust version as Stuart. Since values can't be passed to WebAssembly
+//!   directly, plugins communicate with Stuart through their memory, which they must export as `memory`.
+//!
+//! Buffers are passed to the plugin as a pointer and length, and returned from it packed into an `i64`, with the
+//!   pointer in the upper 32 bits and the length in the lower 32 bits. Plugins must export the following:
+//!
+//! - `stuart_alloc(len: i32) -> i32` allocates a buffer of the given length for Stuart to write an input into.
+//! - `stuart_plugin() -> i64` returns the plugin's metadata as a JSON object, for example
+//!   `{"name": "my_plugin", "version": "1.0.0", "functions": ["add"], "parsers": []}`.
+//! - `stuart_fn_{name}(ptr: i32, len: i32) -> i64` for each function, which takes the function's arguments as a
+//!   JSON array and returns its result as JSON. Strings are output as-is, `null` outputs nothing, and any other
+//!   value is output as JSON.
+//! - `stuart_parser_{name}(ptr: i32, len: i32) -> i64` for each parser, which takes the contents of a file and
+//!   returns its new contents.
+//!
+//! Each parser is described in the metadata by an object such as
+//!   `{"name": "upper", "extensions": ["txt"], "output_extension": "html", "priority": 1}`, where
+//!   `output_extension` is optional and replaces the extension of the file in the output, and `priority` is
+//!   optional and decides which parser is used when several claim the same extension.
+
+use stuart_core::functions::{Function, FunctionParser};
+use stuart_core::parse::{ParseError, RawArgument, RawFunction};
+use stuart_core::plugins::{NodeParser, NodeProcessor, Plugin};
+use stuart_core::process::{ProcessError, ProcessOutput, Scope};
+use stuart_core::{Environment, Stuart, TracebackError};
+
+use humphrey_json::Value;
+use wasmtime::{Engine, Instance, Memory, Module, Store, TypedFunc};
+
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// An instantiated WebAssembly plugin, shared by its functions and parsers.
+///
+/// Like JavaScript plugins, WebAssembly plugins are only used by the thread which loaded them, and their calls
+///   never overlap, so the instance is shared with `Rc` and borrowed through a `RefCell`.
+struct WasmInstance {
+    /// The store which owns the instance's state.
+    store: Store<()>,
+    /// The instance of the plugin's module.
+    instance: Instance,
+    /// The exported memory of the instance.
+    memory: Memory,
+    /// The exported allocation function of the instance.
+    alloc: TypedFunc<i32, i32>,
+}
+
+/// A parser for WebAssembly functions, which passes the function's arguments to `WasmFunction` as-is.
+pub struct WasmFunctionParser {
+    /// The name of the function.
+    name: String,
+    /// The plugin instance.
+    instance: Rc<RefCell<WasmInstance>>,
+}
+
+/// A Stuart function that executes WebAssembly code.
+pub struct WasmFunction {
+    /// The name of the function.
+    name: String,
+    /// The plugin instance.
+    instance: Rc<RefCell<WasmInstance>>,
+    /// The function's arguments.
+    args: Vec<RawArgument>,
+}
+
+/// A parser for files with the extensions claimed by a parser of a WebAssembly plugin.
+pub struct WasmNodeParser {
+    /// The name of the parser.
+    name: String,
+    /// The extensions of the files which the parser parses.
+    extensions: Vec<&'static str>,
+    /// The extension which replaces that of the file in the output, if any.
+    output_extension: Option<String>,
+    /// The priority of the parser.
+    priority: i32,
+    /// The plugin instance.
+    instance: Rc<RefCell<WasmInstance>>,
+}
+
+/// The contents of a file to be processed by a parser of a WebAssembly plugin.
+pub struct WasmNodeProcessor {
+    /// The name of the parser.
+    name: String,
+    /// The contents of the file.
+    contents: Vec<u8>,
+    /// The path of the file.
+    path: PathBuf,
+    /// The extension which replaces that of the file in the output, if any.
+    output_extension: Option<String>,
+    /// The plugin instance.
+    instance: Rc<RefCell<WasmInstance>>,
+}
+
+/// Attempts to load a WebAssembly plugin from the given path, which may be a binary `.wasm` module or a `.wat`
+///   module in the text format.
+pub fn load_wasm_plugin(path: impl AsRef<Path>) -> Result<Plugin, String> {
+    let engine = Engine::default();
+    let module = Module::from_file(&engine, path).map_err(|e| e.to_string())?;
+    let mut store = Store::new(&engine, ());
+    let instance = Instance::new(&mut store, &module, &[]).map_err(|e| e.to_string())?;
+
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .ok_or("missing exported memory")?;
+    let alloc = instance
+        .get_typed_func::<i32, i32>(&mut store, "stuart_alloc")
+        .map_err(|_| "missing `stuart_alloc` function")?;
+    let metadata = instance
+        .get_typed_func::<(), i64>(&mut store, "stuart_plugin")
+        .map_err(|_| "missing `stuart_plugin` function")?;
+
+    let mut instance = WasmInstance {
+        store,
+        instance,
+        memory,
+        alloc,
+    };
+
+    let packed = metadata
+        .call(&mut instance.store, ())
+        .map_err(|e| e.to_string())?;
+    let metadata = String::from_utf8(instance.read(packed)?)
+        .ok()
+        .and_then(|metadata| Value::parse(metadata).ok())
+        .ok_or("invalid plugin metadata")?;
+
+    let name = metadata["name"]
+        .as_str()
+        .ok_or("missing plugin name")?
+        .to_string();
+    let version = metadata["version"]
+        .as_str()
+        .ok_or("missing plugin version")?
+        .to_string();
+
+    let instance = Rc::new(RefCell::new(instance));
+
+    let functions = match metadata.get("functions") {
+        Some(Value::Array(functions)) => functions
+            .iter()
+            .map(|function| {
+                let name = function.as_str().ok_or("invalid function name")?;
+
+                Ok(Box::new(WasmFunctionParser {
+                    name: name.to_string(),
+                    instance: instance.clone(),
+                }) as Box<dyn FunctionParser>)
+            })
+            .collect::<Result<Vec<_>, String>>()?,
+        None => Vec::new(),
+        _ => return Err("invalid plugin functions".to_string()),
+    };
+
+    let parsers = match metadata.get("parsers") {
+        Some(Value::Array(parsers)) => parsers
+            .iter()
+            .map(|parser| {
+                let name = parser["name"].as_str().ok_or("invalid parser name")?;
+                let extensions = match parser.get("extensions") {
+                    // `NodeParser::extensions` returns static strings, so the extensions are leaked. This happens
+                    //   once for each parser when the plugin is loaded.
+                    Some(Value::Array(extensions)) => extensions
+                        .iter()
+                        .map(|extension| {
+                            extension
+                                .as_str()
+                                .map(|extension| &*Box::leak(extension.into()))
+                                .ok_or("invalid parser extension")
+                        })
+                        .collect::<Result<Vec<&'static str>, _>>()?,
+                    _ => return Err("invalid parser extensions".to_string()),
+                };
+
+                Ok(Box::new(WasmNodeParser {
+                    name: name.to_string(),
+                    extensions,
+                    output_extension: parser
+                        .get("output_extension")
+                        .and_then(|extension| extension.as_str())
+                        .map(|extension| extension.to_string()),
+                    priority: parser
+                        .get("priority")
+                        .and_then(|priority| priority.as_number())
+                        .unwrap_or(0.0) as i32,
+                    instance: instance.clone(),
+                }) as Box<dyn NodeParser>)
+            })
+            .collect::<Result<Vec<_>, String>>()?,
+        None => Vec::new(),
+        _ => return Err("invalid plugin parsers".to_string()),
+    };
+
+    Ok(Plugin {
+        name,
+        version,
+        functions,
+        parsers,
+    })
+}
+
+impl WasmInstance {
+    /// Calls the exported function with the given name, passing it the input and returning its output.
+    fn call(&mut self, export: &str, input: &[u8]) -> Result<Vec<u8>, String> {
+        let function = self
+            .instance
+            .get_typed_func::<(i32, i32), i64>(&mut self.store, export)
+            .map_err(|_| format!("missing `{}` function", export))?;
+
+        let len = input.len() as i32;
+        let ptr = self
+            .alloc
+            .call(&mut self.store, len)
+            .map_err(|e| e.to_string())?;
+
+        self.memory
+            .write(&mut self.store, ptr as usize, input)
+            .map_err(|e| e.to_string())?;
+
+        let packed = function
+            .call(&mut self.store, (ptr, len))
+            .map_err(|e| e.to_string())?;
+
+        self.read(packed)
+    }
+
+    /// Reads the buffer which the packed pointer and length refer to from the instance's memory.
+    fn read(&self, packed: i64) -> Result<Vec<u8>, String> {
+        let ptr = (packed as u64 >> 32) as usize;
+        let len = (packed as u64 & 0xffff_ffff) as usize;
+        let mut buf = vec![0; len];
+
+        self.memory
+            .read(&self.store, ptr, &mut buf)
+            .map_err(|e| e.to_string())?;
+
+        Ok(buf)
+    }
+}
+
+impl FunctionParser for WasmFunctionParser {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn parse(&self, raw: RawFunction) -> Result<Box<dyn Function>, ParseError> {
+        Ok(Box::new(WasmFunction {
+            name: self.name.clone(),
+            instance: self.instance.clone(),
+            args: raw.positional_args,
+        }))
+    }
+}
+
+impl Function for WasmFunction {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execute(&self, scope: &mut Scope) -> Result<(), TracebackError<ProcessError>> {
+        let self_token = scope.tokens.current().unwrap().clone();
+
+        let args = self
+            .args
+            .iter()
+            .map(|a| match a {
+                RawArgument::Variable(name) => scope.get_variable(name).ok_or_else(|| {
+                    self_token.traceback(ProcessError::UndefinedVariable(name.to_string()))
+                }),
+                RawArgument::String(s) => Ok(Value::String(s.clone())),
+                RawArgument::Integer(i) => Ok(Value::Number(*i as f64)),
+                RawArgument::Bool(b) => Ok(Value::Bool(*b)),
+                _ => Err(self_token.traceback(ProcessError::StackError)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let output = self
+            .instance
+            .borrow_mut()
+            .call(
+                &format!("stuart_fn_{}", self.name),
+                Value::Array(args).serialize().as_bytes(),
+            )
+            .and_then(|output| {
+                String::from_utf8(output)
+                    .ok()
+                    .and_then(|output| Value::parse(output).ok())
+                    .ok_or_else(|| "invalid json output".to_string())
+            })
+            .map_err(|e| {
+                self_token.traceback(ProcessError::PluginError(format!(
+                    "wasm function `{}` failed: {}",
+                    self.name, e
+                )))
+            })?;
+
+        match output {
+            Value::Null => Ok(()),
+            Value::String(s) => scope.output(s),
+            value => scope.output(value.serialize()),
+        }
+        .map_err(|e| self_token.traceback(e))
+    }
+}
+
+impl Debug for WasmFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmFunction")
+            .field("name", &self.name)
+            .field("args", &self.args)
+            .finish()
+    }
+}
+
+impl NodeParser for WasmNodeParser {
+    fn extensions(&self) -> Vec<&'static str> {
+        self.extensions.clone()
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn parse(&self, contents: &[u8], path: &Path) -> Result<Box<dyn NodeProcessor>, String> {
+        Ok(Box::new(WasmNodeProcessor {
+            name: self.name.clone(),
+            contents: contents.to_vec(),
+            path: path.to_path_buf(),
+            output_extension: self.output_extension.clone(),
+            instance: self.instance.clone(),
+        }))
+    }
+}
+
+impl NodeProcessor for WasmNodeProcessor {
+    fn process(&self, _: &Stuart, _: Environment) -> Result<ProcessOutput, String> {
+        let contents = self
+            .instance
+            .borrow_mut()
+            .call(&format!("stuart_parser_{}", self.name), &self.contents)
+            .map_err(|e| {
+                format!(
+                    "wasm parser `{}` failed on {}: {}",
+                    self.name,
+                    self.path.display(),
+                    e
+                )
+            })?;
+
+        let new_name = self.output_extension.as_ref().map(|extension| {
+            format!(
+                "{}.{}",
+                self.path.file_stem().unwrap_or_default().to_string_lossy(),
+                extension
+            )
+        });
+
+        Ok(ProcessOutput {
+            new_contents: Some(contents),
+            new_name,
+        })
+    }
+}
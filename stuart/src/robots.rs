@@ -0,0 +1,44 @@
+//! Provides generation of `robots.txt` files from the `[robots]` section of the manifest.
+
+use crate::config::Robots;
+
+/// Generates the contents of a `robots.txt` file following the Robots Exclusion Standard.
+///
+/// The URL of the site is required to point crawlers to its sitemap, since the `Sitemap` directive must be
+///   an absolute URL.
+pub fn generate(robots: &Robots, site_url: Option<&str>) -> Result<String, String> {
+    let mut groups = Vec::with_capacity(robots.rules.len() + 1);
+
+    for rule in &robots.rules {
+        let mut group = format!("User-agent: {}\n", rule.user_agent);
+
+        for path in &rule.allow {
+            group.push_str(&format!("Allow: {}\n", path));
+        }
+
+        for path in &rule.disallow {
+            group.push_str(&format!("Disallow: {}\n", path));
+        }
+
+        // A group without any rules must still contain a `Disallow` line, which allows everything when empty.
+        if rule.allow.is_empty() && rule.disallow.is_empty() {
+            group.push_str("Disallow:\n");
+        }
+
+        groups.push(group);
+    }
+
+    if robots.sitemap == Some(true) {
+        let url = site_url.ok_or_else(|| {
+            "`sitemap` in the `[robots]` section requires `url` to be set in the `[site]` section"
+                .to_string()
+        })?;
+
+        groups.push(format!(
+            "Sitemap: {}/sitemap.xml\n",
+            url.trim_end_matches('/')
+        ));
+    }
+
+    Ok(groups.join("\n"))
+}
@@ -82,6 +82,16 @@ impl Scripts {
         self
     }
 
+    /// Returns the paths of the scripts to run before building.
+    pub fn pre_build(&self) -> &[PathBuf] {
+        &self.on_pre_build
+    }
+
+    /// Returns the paths of the scripts to run after building.
+    pub fn post_build(&self) -> &[PathBuf] {
+        &self.on_post_build
+    }
+
     /// Executes pre-build scripts.
     pub fn execute_pre_build(&self) -> Result<(), ScriptError> {
         self.execute(&self.on_pre_build)
@@ -1,6 +1,6 @@
 //! Provides the `stuart dev` functionality.
 
-use crate::build::StuartContext;
+use crate::build::{manifest_file, StuartContext};
 use crate::error::StuartError;
 use crate::logger::LOGGER;
 
@@ -15,47 +15,79 @@ use humphrey_ws::{Message, WebsocketStream};
 
 use clap::ArgMatches;
 
-use notify::{raw_watcher, RawEvent, RecursiveMode, Watcher};
+use stuart_core::fs::Changes;
+use stuart_core::Node;
 
-use std::fs::File;
-use std::io::Read;
-use std::path::PathBuf;
-use std::sync::mpsc::{channel, Receiver};
+use std::collections::HashMap;
+use std::fs::{metadata, read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread::spawn;
+use std::time::SystemTime;
 
 /// The WebSocket-based JavaScript to inject into HTML pages, allowing for hot reload.
 static JS: &[u8] = include_bytes!("main.js");
 
 /// The state of the Humphrey application used to serve the site.
 #[derive(Default)]
-struct State {
+pub(crate) struct State {
     /// Connected WebSocket streams to broadcast updates to.
-    streams: Arc<Mutex<Vec<WebsocketStream>>>,
+    pub(crate) streams: Arc<Mutex<Vec<WebsocketStream>>>,
     /// The directory of files to serve.
-    path: String,
+    pub(crate) path: String,
+    /// Whether HTML files were saved as `index.html` files in directories of the same name, so requests for
+    ///   them with their extension should be redirected.
+    pub(crate) strip_extensions: bool,
+    /// The files which have been served since the last build, cleared whenever the site is rebuilt.
+    pub(crate) cache: Arc<FileCache>,
+    /// The headers to add to every response, from the `[dev.headers]` section of the manifest.
+    pub(crate) headers: Vec<(String, String)>,
+}
+
+/// An in-memory cache of served files, with the hot reload script already injected.
+///
+/// Files are keyed by path and reread if their modification time changes, so files written outside of a
+///   rebuild are never served stale.
+#[derive(Default)]
+pub(crate) struct FileCache {
+    /// The cached files.
+    files: Mutex<HashMap<PathBuf, CachedFile>>,
+    /// The number of times a file has been read from disk.
+    pub(crate) reads: AtomicUsize,
+}
+
+/// A file in the cache.
+#[derive(Clone)]
+pub(crate) struct CachedFile {
+    /// The modification time of the file when it was read.
+    modified: SystemTime,
+    /// The contents of the file, with the hot reload script injected.
+    pub(crate) contents: Vec<u8>,
+    /// The ETag of the contents.
+    pub(crate) etag: String,
 }
 
 /// Serves the site with the given arguments.
 pub fn serve(args: ArgMatches) -> Result<(), Box<dyn StuartError>> {
     let manifest_path: String = args.value_of("manifest-path").unwrap().to_string();
     let output: String = args.value_of("output").unwrap().to_string();
-    let output_path: PathBuf = PathBuf::from(&output);
-    let path: PathBuf = PathBuf::from(&manifest_path)
-        .canonicalize()
-        .ok()
-        .and_then(|p| p.parent().map(|p| p.to_path_buf()))
+    let path: PathBuf = manifest_file(&manifest_path)?
+        .parent()
+        .map(|p| p.to_path_buf())
         .ok_or("invalid manifest path")?;
 
-    let full_output_path = if output_path.is_relative() {
-        path.join(output_path)
-    } else {
-        output_path
-    }
-    .to_string_lossy()
-    .to_string();
+    let mut ctx = StuartContext::init_with_update(
+        &manifest_path,
+        &output,
+        "development",
+        args.is_present("update"),
+    )?;
+    let full_output_path = ctx.output_dir().to_string_lossy().to_string();
 
-    let mut ctx = StuartContext::init(&manifest_path, &output, "development")?;
+    // Minified HTML may not have the closing tags needed to inject the hot reload script.
+    ctx.stuart.config.minify_html = Some(false);
+    ctx.deny_warnings = args.is_present("deny-warnings");
 
     log!("Started", "development server at http://localhost:6904\n");
 
@@ -64,14 +96,16 @@ pub fn serve(args: ArgMatches) -> Result<(), Box<dyn StuartError>> {
     }
 
     let streams = Arc::new(Mutex::new(Vec::new()));
+    let cache = Arc::new(FileCache::default());
     let state = State {
         streams: streams.clone(),
         path: full_output_path,
+        strip_extensions: ctx.stuart.config.strip_extensions(),
+        cache: cache.clone(),
+        headers: response_headers(&ctx.dev_headers),
     };
 
-    let (tx, rx) = channel();
-    let mut watcher = raw_watcher(tx).unwrap();
-    watcher.watch(&path, RecursiveMode::Recursive).unwrap();
+    let changes = Node::watch(&path)?;
 
     spawn(move || {
         let app = App::new_with_config(8, state)
@@ -82,23 +116,22 @@ pub fn serve(args: ArgMatches) -> Result<(), Box<dyn StuartError>> {
             .map_err(|_| Box::new("failed to start development server") as Box<dyn StuartError>)
     });
 
-    build_watcher(rx, streams, path, ctx);
+    build_watcher(changes, streams, cache, path, ctx);
 
     Ok(())
 }
 
 /// Watches for changes to the site, rebuilding and notifying subscribers when necessary.
 fn build_watcher(
-    rx: Receiver<RawEvent>,
+    mut changes: Changes,
     streams: Arc<Mutex<Vec<WebsocketStream>>>,
+    cache: Arc<FileCache>,
     path: PathBuf,
     mut ctx: StuartContext,
 ) {
     loop {
-        if let Ok(e) = rx.recv() {
-            let p = e.path.as_ref().unwrap().strip_prefix(&path).unwrap();
-
-            if p.starts_with("dist") || p.starts_with("temp") {
+        if let Some(p) = changes.next() {
+            if path.join(&p).starts_with(ctx.output_dir()) || p.starts_with("temp") {
                 continue;
             }
 
@@ -115,13 +148,17 @@ fn build_watcher(
             log!(
                 "Detected",
                 "change at {}, rebuilding",
-                e.path
-                    .unwrap()
+                path.join(&p)
                     .to_string_lossy()
                     .trim_start_matches("\\\\?\\")
             );
 
-            if let Err(e) = ctx.build() {
+            let result = ctx.build();
+
+            // Files may have changed even if the build failed part-way through.
+            cache.clear();
+
+            if let Err(e) = result {
                 error_handler(&e);
             } else {
                 let mut streams = streams.lock().unwrap();
@@ -141,7 +178,7 @@ fn build_watcher(
 
             // TODO: WebSocket stuff
 
-            while rx.try_recv().is_ok() {}
+            while changes.try_next().is_some() {}
         }
     }
 }
@@ -156,46 +193,233 @@ fn websocket_handler(request: Request, stream: Stream, state: Arc<State>) {
 /// Serves a directory.
 ///
 /// Taken from Humphrey ([permalink](https://github.com/w-henderson/Humphrey/blob/8bf07aada8acb7e25991ac9e9f9462d9fb3086b0/humphrey/src/handlers.rs#L78)) and modified to correctly inject the WebSocket code.
-fn serve_dir(request: Request, state: Arc<State>) -> Response {
-    let uri_without_route = request.uri.strip_prefix('/').unwrap_or(&request.uri);
-
-    let located = try_find_path(&state.path, uri_without_route, &["index.html"]);
-
-    if let Some(located) = located {
-        match located {
-            LocatedPath::Directory => Response::empty(StatusCode::MovedPermanently)
-                .with_header(HeaderType::Location, format!("{}/", &request.uri)),
-            LocatedPath::File(path) => {
-                if let Ok(mut file) = File::open(&path) {
-                    let mut buf = Vec::new();
-
-                    if file.read_to_end(&mut buf).is_ok() {
-                        if let Some(index) = buf.windows(7).position(|w| w == b"</body>") {
-                            let mut to_inject = Vec::with_capacity(JS.len() + 17);
-                            to_inject.extend_from_slice(b"<script>");
-                            to_inject.extend_from_slice(JS);
-                            to_inject.extend_from_slice(b"</script>");
-
-                            buf.splice(index..index, to_inject);
-                        }
+///
+/// Responses include an `ETag` header, and `304 Not Modified` is returned if the request's `If-None-Match`
+///   header matches it, so browsers revalidate files instead of caching stale versions across rebuilds.
+///
+/// The headers configured in the manifest are added to every response.
+pub(crate) fn serve_dir(request: Request, state: Arc<State>) -> Response {
+    state
+        .headers
+        .iter()
+        .fold(respond(&request, &state), |response, (name, value)| {
+            response.with_header(HeaderType::from(name.as_str()), value)
+        })
+}
+
+/// Responds to a request for a file in the served directory.
+fn respond(request: &Request, state: &State) -> Response {
+    match resolve_path(state, &request.uri) {
+        ResolvedPath::Redirect(location) => Response::empty(StatusCode::MovedPermanently)
+            .with_header(HeaderType::Location, location),
+        ResolvedPath::File(path) => {
+            let file = match state.cache.get(&path) {
+                Some(file) => file,
+                None => return Response::new(StatusCode::InternalError, "Internal Server Error"),
+            };
+
+            if etag_matches(request.headers.get("If-None-Match"), &file.etag) {
+                return not_modified(&file.etag);
+            }
+
+            let response = Response::new(StatusCode::OK, file.contents)
+                .with_header(HeaderType::ETag, &file.etag)
+                .with_header(HeaderType::CacheControl, "no-cache");
+
+            if let Some(extension) = path.extension() {
+                response.with_header(
+                    HeaderType::ContentType,
+                    MimeType::from_extension(extension.to_str().unwrap()).to_string(),
+                )
+            } else {
+                response
+            }
+        }
+        ResolvedPath::NotFound => Response::new(StatusCode::NotFound, "Not Found"),
+    }
+}
+
+/// Returns the headers to add to every response, allowing inline scripts in any `Content-Security-Policy` header
+///   so that the hot reload script can run.
+///
+/// A warning is logged if a policy is changed, since the development server then differs from production.
+fn response_headers(headers: &[(String, String)]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            if !name.eq_ignore_ascii_case("content-security-policy") {
+                return (name.clone(), value.clone());
+            }
+
+            match allow_inline_scripts(value) {
+                Some(policy) => {
+                    warn!(
+                        "added `'unsafe-inline'` to `{}` so the hot reload script can run",
+                        name
+                    );
+
+                    (name.clone(), policy)
+                }
+                None => (name.clone(), value.clone()),
+            }
+        })
+        .collect()
+}
+
+/// Adds `'unsafe-inline'` to the `script-src` directive of a `Content-Security-Policy`, returning the new
+///   policy, or `None` if inline scripts are already allowed.
+///
+/// If the policy has no `script-src` directive but restricts scripts with `default-src`, a `script-src`
+///   directive with the same sources is added instead.
+pub(crate) fn allow_inline_scripts(policy: &str) -> Option<String> {
+    let mut directives = policy
+        .split(';')
+        .map(|directive| directive.trim().to_string())
+        .filter(|directive| !directive.is_empty())
+        .collect::<Vec<_>>();
+
+    let name = |directive: &str| {
+        directive
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+    };
+
+    let script_src = match directives.iter().position(|d| name(d) == "script-src") {
+        Some(index) => index,
+        None => {
+            let default_src = directives.iter().find(|d| name(d) == "default-src")?;
+            let sources = default_src["default-src".len()..].trim().to_string();
+
+            directives.push(format!("script-src {}", sources).trim().to_string());
+            directives.len() - 1
+        }
+    };
 
-                        return if let Some(extension) = path.extension() {
-                            Response::new(StatusCode::OK, buf).with_header(
-                                HeaderType::ContentType,
-                                MimeType::from_extension(extension.to_str().unwrap()).to_string(),
-                            )
-                        } else {
-                            Response::new(StatusCode::OK, buf)
-                        };
+    if directives[script_src]
+        .split_whitespace()
+        .any(|source| source.eq_ignore_ascii_case("'unsafe-inline'"))
+    {
+        return None;
+    }
+
+    directives[script_src].push_str(" 'unsafe-inline'");
+
+    Some(directives.join("; "))
+}
+
+/// The result of resolving a request URI to a file in the output directory.
+enum ResolvedPath {
+    /// The URI refers to this file.
+    File(PathBuf),
+    /// The URI refers to a page with a different canonical URI, which the client should be redirected to.
+    Redirect(String),
+    /// The URI does not refer to anything.
+    NotFound,
+}
+
+/// Resolves a request URI in the same way as the output would be served in production.
+///
+/// Directories are served by their `index.html` file, and requests for directories without a trailing slash are
+///   redirected to add one. If extensions were stripped, requests for `/page.html` are redirected to `/page/`,
+///   since that is where the page was saved.
+fn resolve_path(state: &State, uri: &str) -> ResolvedPath {
+    let uri_without_route = uri.strip_prefix('/').unwrap_or(uri);
+
+    match try_find_path(&state.path, uri_without_route, &["index.html"]) {
+        Some(LocatedPath::File(path)) => ResolvedPath::File(path),
+        Some(LocatedPath::Directory) => ResolvedPath::Redirect(format!("{}/", uri)),
+        None => {
+            let stripped = uri
+                .strip_suffix(".html")
+                .filter(|stripped| state.strip_extensions && !stripped.ends_with('/'));
+
+            match stripped {
+                Some(stripped) => {
+                    let directory = format!("{}/", stripped.strip_prefix('/').unwrap_or(stripped));
+
+                    match try_find_path(&state.path, &directory, &["index.html"]) {
+                        Some(LocatedPath::File(_)) => {
+                            ResolvedPath::Redirect(format!("{}/", stripped))
+                        }
+                        _ => ResolvedPath::NotFound,
                     }
                 }
+                None => ResolvedPath::NotFound,
+            }
+        }
+    }
+}
+
+impl FileCache {
+    /// Gets the file at the given path, reading it from disk and injecting the hot reload script if it is not
+    ///   cached or has been modified since it was cached.
+    pub(crate) fn get(&self, path: &Path) -> Option<CachedFile> {
+        let modified = metadata(path).and_then(|m| m.modified()).ok()?;
 
-                Response::new(StatusCode::InternalError, "Internal Server Error")
+        if let Some(file) = self.files.lock().unwrap().get(path) {
+            if file.modified == modified {
+                return Some(file.clone());
             }
         }
-    } else {
-        Response::new(StatusCode::NotFound, "Not Found")
+
+        let mut buf = read(path).ok()?;
+        self.reads.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(index) = buf.windows(7).position(|w| w == b"</body>") {
+            let mut to_inject = Vec::with_capacity(JS.len() + 17);
+            to_inject.extend_from_slice(b"<script>");
+            to_inject.extend_from_slice(JS);
+            to_inject.extend_from_slice(b"</script>");
+
+            buf.splice(index..index, to_inject);
+        }
+
+        let file = CachedFile {
+            modified,
+            etag: format!("\"{:016x}\"", fnv1a(&buf)),
+            contents: buf,
+        };
+
+        self.files
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), file.clone());
+
+        Some(file)
     }
+
+    /// Removes all files from the cache.
+    pub(crate) fn clear(&self) {
+        self.files.lock().unwrap().clear();
+    }
+}
+
+/// Returns `true` if the value of an `If-None-Match` header matches the given ETag.
+fn etag_matches(if_none_match: Option<&str>, etag: &str) -> bool {
+    if_none_match
+        .map(|header| {
+            header
+                .split(',')
+                .map(|tag| tag.trim())
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+        })
+        .unwrap_or(false)
+}
+
+/// Creates a `304 Not Modified` response for the given ETag.
+fn not_modified(etag: &str) -> Response {
+    Response::empty(StatusCode::NotModified)
+        .with_header(HeaderType::ETag, etag)
+        .with_header(HeaderType::CacheControl, "no-cache")
+}
+
+/// Computes the 64-bit FNV-1a hash of the given bytes, which is fast and sufficient for ETags.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
 }
 
 /// Prints errors.
@@ -1,10 +1,26 @@
 #![allow(clippy::redundant_closure_call)]
 
-use crate::{app, build};
+use crate::config::Robots;
+use crate::error::StuartError;
+use crate::plugins::lock::{LockedPlugin, Lockfile};
+use crate::plugins::{self, source, Retry};
+use crate::serve::{allow_inline_scripts, serve_dir, State};
+use crate::{add, app, build, config, info, new, robots};
 
-use std::fs::{remove_dir_all, remove_file};
+use humphrey::http::address::Address;
+use humphrey::http::headers::{HeaderType, Headers};
+use humphrey::http::method::Method;
+use humphrey::http::{Request, StatusCode};
+use stuart_core::error::ParseError;
+use termcolor::Buffer;
+
+use std::cell::Cell;
+use std::fs::{create_dir_all, remove_dir_all, remove_file, write};
 use std::path::Path;
 use std::process::exit;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
 
 macro_rules! test {
     ($name:ident, $manifest_path:expr, $post_build_checks:expr) => {
@@ -46,6 +62,19 @@ macro_rules! test {
 
 test!(basic, "/tests/basic", |_| ());
 
+test!(blog, "/tests/blog", |index: &str| {
+    assert!(index.contains("<a href=\"/posts/hello-world/\">Hello, world!</a>"));
+    assert!(index.find("Writing posts") < index.find("Hello, world!"));
+});
+
+test!(docs, "/tests/docs", |index: &str| {
+    assert!(index.contains("<a href=\"/docs/getting-started/\">Getting Started</a>"));
+});
+
+test!(portfolio, "/tests/portfolio", |index: &str| {
+    assert!(index.contains("<a href=\"/projects/first-project/\">First Project</a>"));
+});
+
 #[cfg(feature = "js")]
 test!(js, "/tests/js", |index: &str| {
     let mut lines = index.lines().map(|s| s.trim());
@@ -55,6 +84,10 @@ test!(js, "/tests/js", |index: &str| {
     assert_eq!(lines.next().unwrap(), "5"); // magnitude({ x: 3, y: 4 })
     assert_eq!(lines.next().unwrap(), "set by JavaScript!"); // set()
     assert_eq!(lines.next().unwrap(), "set by JavaScript!"); // get()
+    assert_eq!(lines.next().unwrap(), "2"); // add(1, 1) inside `ifeq`
+    assert_eq!(lines.next().unwrap(), "<b>set by JavaScript!</b>"); // wrap("b")
+    assert_eq!(lines.next().unwrap(), "<i>2</i>"); // add(1, 1) inside wrap("i")
+    assert_eq!(lines.next().unwrap(), "js: 7 true"); // describe()
 });
 
 #[cfg(feature = "js")]
@@ -62,6 +95,561 @@ test!(js_isolation, "/tests/js-isolation", |index: &str| {
     assert_eq!(index.trim(), "0 1 2 0 1 3"); // A::inc() A::inc() A::inc() B::inc() B::inc() A::inc()
 });
 
+#[cfg(feature = "js")]
+test!(js_many, "/tests/js-many", |index: &str| {
+    let numbers = index.split_whitespace().collect::<Vec<_>>();
+    assert_eq!(numbers.len(), 500); // increment($item) for each of 0..500
+    assert_eq!(numbers.first(), Some(&"1"));
+    assert_eq!(numbers.last(), Some(&"500"));
+});
+
+#[cfg(feature = "lua")]
+test!(lua, "/tests/lua", |index: &str| {
+    let mut lines = index.lines().map(|s| s.trim());
+    assert_eq!(lines.next().unwrap(), "5"); // add(2, 3)
+    assert_eq!(lines.next().unwrap(), "Hello, world!"); // greet("world")
+    assert_eq!(lines.next().unwrap(), "0 1 2"); // inc() inc() inc()
+    assert_eq!(lines.next().unwrap(), "5"); // magnitude({ x: 3, y: 4 })
+    assert_eq!(lines.next().unwrap(), "[1,2,3]"); // range(3)
+});
+
+#[cfg(feature = "wasm")]
+#[test]
+fn wasm() {
+    let manifest_path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/wasm/stuart.toml");
+    let result = full_build(manifest_path);
+    let index = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/wasm/dist/index.html"
+    ));
+    let loud = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/wasm/dist/loud.txt"
+    ));
+
+    cleanup(manifest_path);
+
+    assert!(result);
+
+    let index = index.unwrap();
+    let mut lines = index.trim().lines().map(|s| s.trim());
+    assert_eq!(lines.next().unwrap(), "Hello from WebAssembly!"); // hello()
+    assert_eq!(lines.next().unwrap(), "[\"a\",1,true]"); // echo("a", 1, true)
+    assert_eq!(loud.unwrap(), "HELLO, WORLD"); // the `upper` parser
+}
+
+#[test]
+fn info() {
+    let ctx = build::StuartContext::init(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/blog/stuart.toml"),
+        "dist",
+        "development",
+    )
+    .unwrap_or_else(|_| panic!("failed to initialise the project"));
+
+    let report = info::report(&ctx);
+
+    assert!(report.contains("Project blog ("));
+    assert!(report.contains("STUART_ENV development"));
+    assert!(report.contains("Directories content, static"));
+    assert!(report.contains("Plugins none"));
+    assert!(report.contains("for, frontmatter, get"));
+    assert!(report.contains("\n  strip_extensions              true\n"));
+    assert!(report.contains("\n  text_extensions               none\n"));
+    assert!(report.contains("\n  delimiters                    open={{, close=}}\n"));
+    assert!(!report.contains("Some("));
+}
+
+#[test]
+fn add_dependency() {
+    let dir = std::env::temp_dir().join("stuart-test-add-dependency");
+    let manifest_path = dir.join("stuart.toml");
+    let _ = remove_dir_all(&dir);
+    create_dir_all(&dir).unwrap();
+
+    let dependencies = || {
+        let manifest = std::fs::read_to_string(&manifest_path).unwrap();
+        config::load(&manifest).unwrap().dependencies.unwrap()
+    };
+
+    // The table is created if it doesn't exist.
+    write(&manifest_path, "[site]\nname = \"add\"\n").unwrap();
+    assert!(add::add_dependency(&manifest_path, "first", "./plugins/first.lua").is_ok());
+    assert_eq!(
+        std::fs::read_to_string(&manifest_path).unwrap(),
+        "[site]\nname = \"add\"\n\n[dependencies]\nfirst = \"./plugins/first.lua\"\n"
+    );
+
+    // Entries are added to the end of the existing table, leaving later tables untouched.
+    write(
+        &manifest_path,
+        "[site]\nname = \"add\"\n\n[dependencies]\nfirst = \"./plugins/first.lua\"\n\n[settings]\nstrip_extensions = false\n",
+    )
+    .unwrap();
+    assert!(add::add_dependency(&manifest_path, "second", "C:\\plugins\\second.dll").is_ok());
+    assert_eq!(dependencies().len(), 2);
+    assert_eq!(dependencies()["second"], "C:\\plugins\\second.dll");
+    assert!(std::fs::read_to_string(&manifest_path)
+        .unwrap()
+        .ends_with("\n\n[settings]\nstrip_extensions = false\n"));
+
+    // Existing dependencies and invalid names are rejected without changing the manifest.
+    assert!(add::add_dependency(&manifest_path, "first", "./other.lua").is_err());
+    assert!(add::add_dependency(&manifest_path, "bad name", "./other.lua").is_err());
+    assert_eq!(dependencies()["first"], "./plugins/first.lua");
+    assert_eq!(dependencies().len(), 2);
+
+    remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn fetch_retry() {
+    let retry = Retry {
+        retries: 3,
+        delay: Duration::ZERO,
+    };
+
+    // A fetcher which fails twice before succeeding.
+    let mut attempts = 0;
+    let result = retry.run("test", || {
+        attempts += 1;
+        (attempts == 3).then_some("plugin")
+    });
+
+    assert_eq!(result, Some("plugin"));
+    assert_eq!(attempts, 3);
+
+    // After the retries run out, the failure is returned.
+    let mut attempts = 0;
+    let result = retry.run("test", || {
+        attempts += 1;
+        None::<()>
+    });
+
+    assert_eq!(result, None);
+    assert_eq!(attempts, 4);
+
+    let fetch: config::Fetch = toml::from_str("retries = 5").unwrap();
+    let retry = Retry::from(Some(&fetch));
+    assert_eq!(retry.retries, 5);
+    assert_eq!(retry.delay, Retry::default().delay);
+}
+
+#[test]
+fn plugin_lock() {
+    let dir = std::env::temp_dir().join("stuart-test-plugin-lock");
+    let origin = dir.join("origin");
+    let repo_dir = dir.join("_build/plugins/locked");
+    let _ = remove_dir_all(&dir);
+    create_dir_all(&origin).unwrap();
+
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args([
+                "-c",
+                "user.name=Stuart",
+                "-c",
+                "user.email=stuart@example.com",
+            ])
+            .args(args)
+            .current_dir(&origin)
+            .output()
+            .unwrap()
+            .status;
+
+        assert!(status.success());
+    };
+
+    let commit = |contents: &str| {
+        write(origin.join("plugin.txt"), contents).unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-m", contents]);
+    };
+
+    let retry = Retry {
+        retries: 0,
+        delay: Duration::ZERO,
+    };
+    let src = origin.to_string_lossy().to_string();
+    let fetch = |locked: Option<&str>| {
+        plugins::fetch_git("locked", &src, &repo_dir, &retry, locked)
+            .unwrap_or_else(|_| panic!("failed to fetch the plugin"))
+    };
+    let contents = || std::fs::read_to_string(repo_dir.join("plugin.txt")).unwrap();
+
+    git(&["init"]);
+    commit("first");
+
+    // The first build clones the latest commit, which is then locked.
+    let first = fetch(None);
+    assert_eq!(contents(), "first");
+
+    commit("second");
+
+    // The second build uses the locked commit instead of pulling the new one.
+    assert_eq!(fetch(Some(&first)), first);
+    assert_eq!(contents(), "first");
+
+    // Updating pulls the new commit, and the old one can still be checked out.
+    let second = fetch(None);
+    assert_ne!(second, first);
+    assert_eq!(contents(), "second");
+    assert_eq!(fetch(Some(&first)), first);
+    assert_eq!(contents(), "first");
+
+    let mut lockfile = Lockfile::default();
+    lockfile.plugins.insert(
+        "locked".to_string(),
+        LockedPlugin {
+            source: src.clone(),
+            commit: Some(first.clone()),
+            sha256: None,
+        },
+    );
+    assert!(lockfile.write(&dir).is_ok());
+
+    let lockfile = Lockfile::read(&dir).unwrap_or_else(|_| panic!("failed to read the lockfile"));
+    assert_eq!(lockfile.get("locked", &src).unwrap().commit, Some(first));
+    assert!(lockfile
+        .get("locked", "https://example.com/other")
+        .is_none());
+
+    remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn plugin_build_cache() {
+    let dir = std::env::temp_dir().join("stuart-test-plugin-build-cache");
+    let project = dir.join("cached");
+    let built = dir.join("built");
+    let _ = remove_dir_all(&dir);
+    create_dir_all(project.join("src")).unwrap();
+    write(project.join("Cargo.toml"), "[package]\nname = \"cached\"").unwrap();
+    write(project.join("src/lib.rs"), "// first").unwrap();
+    write(&built, "binary").unwrap();
+
+    let builds = Cell::new(0);
+    let build = |version: &str| {
+        plugins::build_cached("cached", &project, &dir, version, || {
+            builds.set(builds.get() + 1);
+            Ok(built.clone())
+        })
+        .unwrap_or_else(|_| panic!("failed to build the plugin"))
+    };
+
+    // The first build compiles the plugin and caches the binary.
+    let path = build("0.1.0");
+    assert_eq!(builds.get(), 1);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "binary");
+
+    // Building again with the same source and version reuses the cached binary.
+    assert_eq!(build("0.1.0"), path);
+    assert_eq!(builds.get(), 1);
+
+    // Changing the source or the version of Stuart invalidates the cache.
+    write(project.join("src/lib.rs"), "// second").unwrap();
+    build("0.1.0");
+    assert_eq!(builds.get(), 2);
+
+    build("0.2.0");
+    assert_eq!(builds.get(), 3);
+    build("0.2.0");
+    assert_eq!(builds.get(), 3);
+
+    remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn plugin_checksum() {
+    let plugin = b"plugin";
+    let sha256 = source::sha256(plugin);
+    let other = source::sha256(b"other");
+
+    assert!(source::verify_checksum(plugin, &sha256).is_ok());
+    assert!(source::verify_checksum(plugin, &sha256.to_ascii_uppercase()).is_ok());
+    assert!(source::verify_checksum(plugin, &other)
+        .unwrap_err()
+        .starts_with("checksum mismatch"));
+    assert!(source::verify_checksum(plugin, "abc")
+        .unwrap_err()
+        .starts_with("invalid checksum"));
+
+    // The fragment of the source URL takes precedence over the checksum in the manifest.
+    let fragment = format!("sha256={}", sha256);
+    assert_eq!(
+        source::expected_checksum(&fragment, Some(&other)),
+        Some(sha256.as_str())
+    );
+    assert_eq!(
+        source::expected_checksum("", Some(&other)),
+        Some(other.as_str())
+    );
+    assert_eq!(source::expected_checksum("", None), None);
+}
+
+#[test]
+fn getting_started() {
+    let blog = new::getting_started("my-blog", "blog");
+
+    assert!(blog.starts_with("\nGetting started:\n  1. `cd my-blog`"));
+    assert!(blog.contains("  2. `stuart dev`"));
+    assert!(blog.contains("  3. write posts as markdown files in `content/posts`\n"));
+    assert!(blog.contains("  4. `stuart build`"));
+
+    assert!(new::getting_started("site", "minimal").contains("edit `content/index.html`"));
+}
+
+#[test]
+fn manifest_path_directory() {
+    let init = |manifest_path| {
+        build::StuartContext::init(manifest_path, "dist", "production")
+            .unwrap_or_else(|_| panic!("failed to initialise the project"))
+    };
+
+    let from_dir = init(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/blog"));
+    let from_file = init(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/blog/stuart.toml"
+    ));
+
+    assert_eq!(from_dir.project_dir, from_file.project_dir);
+    assert_eq!(from_dir.stuart.config.name, "blog");
+    assert!(build::manifest_file(concat!(env!("CARGO_MANIFEST_DIR"), "/tests")).is_err());
+}
+
+#[test]
+fn absolute_output() {
+    let manifest_path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/portfolio/stuart.toml");
+    let output = std::env::temp_dir().join("stuart-test-absolute_output");
+    let output = output.to_str().unwrap();
+
+    let args = app().get_matches_from(vec![
+        "stuart",
+        "build",
+        "--manifest-path",
+        manifest_path,
+        "--output",
+        output,
+    ]);
+    let result = match args.subcommand() {
+        Some(("build", args)) => build(args),
+        _ => unreachable!(),
+    };
+
+    let index_exists = Path::new(output).join("index.html").exists();
+    let _ = remove_dir_all(output);
+
+    assert!(result.is_ok());
+    assert!(index_exists);
+}
+
+#[test]
+fn robots() {
+    let manifest_path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/robots/stuart.toml");
+    let result = full_build(manifest_path);
+    let robots = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/robots/dist/robots.txt"
+    ));
+
+    cleanup(manifest_path);
+
+    assert!(result);
+    assert_eq!(
+        robots.unwrap(),
+        "User-agent: *\nAllow: /drafts/public/\nDisallow: /drafts/\n\n\
+         User-agent: ExampleBot\nDisallow:\n\n\
+         Sitemap: https://example.com/sitemap.xml\n"
+    );
+}
+
+#[test]
+fn robots_sitemap_without_url() {
+    let robots: Robots = toml::from_str("sitemap = true").unwrap();
+
+    assert!(robots::generate(&robots, None).is_err());
+    assert_eq!(
+        robots::generate(&robots, Some("https://example.com")).unwrap(),
+        "Sitemap: https://example.com/sitemap.xml\n"
+    );
+}
+
+#[test]
+fn argument_errors() {
+    let message = |error: ParseError| {
+        let mut buf = Buffer::no_color();
+        error.display(&mut buf);
+        (String::from_utf8(buf.into_inner()).unwrap(), error.help())
+    };
+
+    let wrong_count = |min, max, found| ParseError::WrongArgumentCount {
+        function: "for".to_string(),
+        min,
+        max,
+        found,
+    };
+
+    assert_eq!(
+        message(wrong_count(2, Some(2), 3)).0,
+        "`for` expects 2 positional arguments, got 3\n"
+    );
+    assert_eq!(
+        message(wrong_count(1, Some(1), 0)).0,
+        "`for` expects 1 positional argument, got 0\n"
+    );
+    assert_eq!(
+        message(wrong_count(1, Some(2), 3)).0,
+        "`for` expects 1 or 2 positional arguments, got 3\n"
+    );
+    assert_eq!(
+        message(wrong_count(2, None, 1)).0,
+        "`for` expects at least 2 positional arguments, got 1\n"
+    );
+
+    assert_eq!(
+        message(ParseError::UnknownNamedArgument {
+            function: "for".to_string(),
+            name: "sort".to_string(),
+            expected: vec!["skip".to_string(), "sortby".to_string()],
+        }),
+        (
+            "`for` has no named argument `sort`\n".to_string(),
+            Some("expected one of `skip`, `sortby`".to_string())
+        )
+    );
+    assert_eq!(
+        message(ParseError::UnknownNamedArgument {
+            function: "text".to_string(),
+            name: "raw".to_string(),
+            expected: Vec::new(),
+        })
+        .1,
+        Some("`text` does not accept any named arguments".to_string())
+    );
+}
+
+#[test]
+fn dev_server_cache() {
+    let dir = std::env::temp_dir().join("stuart-test-dev-server-cache");
+    let _ = remove_dir_all(&dir);
+    create_dir_all(&dir).unwrap();
+    write(dir.join("index.html"), "<html><body>Hello</body></html>").unwrap();
+
+    let state = Arc::new(State {
+        path: dir.to_string_lossy().to_string(),
+        ..Default::default()
+    });
+
+    let first = serve_dir(request("/"), state.clone());
+    let second = serve_dir(request("/"), state.clone());
+
+    assert_eq!(first.status_code, StatusCode::OK);
+    assert_eq!(first.body, second.body);
+    assert!(String::from_utf8(first.body).unwrap().contains("<script>"));
+    assert_eq!(state.cache.reads.load(Ordering::Relaxed), 1);
+
+    state.cache.clear();
+    serve_dir(request("/"), state.clone());
+
+    assert_eq!(state.cache.reads.load(Ordering::Relaxed), 2);
+
+    remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dev_server_paths() {
+    let dir = std::env::temp_dir().join("stuart-test-dev-server-paths");
+    let _ = remove_dir_all(&dir);
+    create_dir_all(dir.join("about")).unwrap();
+    write(
+        dir.join("about/index.html"),
+        "<html><body>About</body></html>",
+    )
+    .unwrap();
+
+    let state = Arc::new(State {
+        path: dir.to_string_lossy().to_string(),
+        strip_extensions: true,
+        ..Default::default()
+    });
+
+    let location = |uri: &str| {
+        let response = serve_dir(request(uri), state.clone());
+        assert_eq!(response.status_code, StatusCode::MovedPermanently);
+        response
+            .get_headers()
+            .get(HeaderType::Location)
+            .unwrap()
+            .to_string()
+    };
+
+    assert_eq!(location("/about"), "/about/");
+    assert_eq!(location("/about.html"), "/about/");
+
+    let response = serve_dir(request("/about/"), state.clone());
+    assert_eq!(response.status_code, StatusCode::OK);
+    assert!(String::from_utf8(response.body).unwrap().contains("About"));
+
+    assert_eq!(
+        serve_dir(request("/missing.html"), state.clone()).status_code,
+        StatusCode::NotFound
+    );
+
+    remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dev_server_headers() {
+    let dir = std::env::temp_dir().join("stuart-test-dev-server-headers");
+    let _ = remove_dir_all(&dir);
+    create_dir_all(&dir).unwrap();
+    write(dir.join("index.html"), "<html><body>Hello</body></html>").unwrap();
+
+    let state = Arc::new(State {
+        path: dir.to_string_lossy().to_string(),
+        headers: vec![("X-Frame-Options".to_string(), "DENY".to_string())],
+        ..Default::default()
+    });
+
+    for uri in ["/", "/missing.html"] {
+        let response = serve_dir(request(uri), state.clone());
+        assert_eq!(response.get_headers().get("x-frame-options"), Some("DENY"));
+    }
+
+    remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dev_server_csp() {
+    assert_eq!(
+        allow_inline_scripts("default-src 'self'; script-src 'self' https://example.com"),
+        Some(
+            "default-src 'self'; script-src 'self' https://example.com 'unsafe-inline'".to_string()
+        )
+    );
+    assert_eq!(
+        allow_inline_scripts("default-src 'self'"),
+        Some("default-src 'self'; script-src 'self' 'unsafe-inline'".to_string())
+    );
+    assert_eq!(
+        allow_inline_scripts("script-src 'self' 'unsafe-inline'"),
+        None
+    );
+    assert_eq!(allow_inline_scripts("img-src *"), None);
+}
+
+fn request(uri: &str) -> Request {
+    Request {
+        method: Method::Get,
+        uri: uri.to_string(),
+        query: String::new(),
+        version: "HTTP/1.1".to_string(),
+        headers: Headers::new(),
+        content: None,
+        address: Address::new("127.0.0.1:80").unwrap(),
+    }
+}
+
 fn full_build(manifest_path: &str) -> bool {
     let args = app().get_matches_from(vec!["stuart", "build", "--manifest-path", manifest_path]);
     let result = match args.subcommand() {
@@ -83,3 +671,59 @@ fn cleanup(manifest_path: &str) {
     let _ = remove_dir_all(dist);
     let _ = remove_file(path.parent().unwrap().join("metadata.json"));
 }
+
+#[test]
+fn deny_warnings() {
+    let manifest_path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/robots-conflict/stuart.toml"
+    );
+
+    let build_with = |args: &[&str]| {
+        let args = app().get_matches_from(["stuart"].iter().chain(args).chain(&[
+            "build",
+            "--manifest-path",
+            manifest_path,
+        ]));
+        let result = match args.subcommand() {
+            Some(("build", args)) => build(args),
+            _ => unreachable!(),
+        };
+
+        cleanup(manifest_path);
+
+        result.is_ok()
+    };
+
+    assert!(build_with(&[]));
+    assert!(!build_with(&["--deny-warnings"]));
+}
+
+#[test]
+fn error_marker_alignment() {
+    let path = std::env::temp_dir().join("stuart-test-error_marker_alignment.html");
+    write(
+        &path,
+        "<p>Crème brûlée 中文 {{ for($dish, order=\"asc\", $menu) }}</p>\n",
+    )
+    .unwrap();
+
+    let error =
+        stuart_core::parse::parse_html(&std::fs::read_to_string(&path).unwrap(), &path, None)
+            .unwrap_err();
+
+    let mut buf = Buffer::no_color();
+    error.display(&mut buf);
+    let _ = remove_file(&path);
+
+    let output = String::from_utf8(buf.into_inner()).unwrap();
+    let marker = output.lines().find(|line| line.contains("^^^")).unwrap();
+
+    // The error is at column 51, the `u` of `$menu`, and the marker starts one character before it. The accented
+    //   characters take one space each, but `中文` is displayed two spaces wide per character.
+    assert_eq!(error.column, 51);
+    assert_eq!(
+        marker,
+        format!("  | {}^^^ error occurred here", " ".repeat(51))
+    );
+}
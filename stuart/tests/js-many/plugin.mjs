@@ -0,0 +1,14 @@
+function increment(n) {
+  return n + 1;
+}
+
+export default {
+  name: "counter",
+  version: "0.0.1",
+  functions: [
+    {
+      name: "increment",
+      fn: increment
+    }
+  ]
+}
@@ -40,6 +40,18 @@ function getVariable() {
   return STUART.get("my_var");
 }
 
+/* Test block function which wraps its body in a tag */
+function wrap(tag) {
+  return `<${tag}>${STUART.block().trim()}</${tag}>`;
+}
+
+/* Test function which reads a data file and the configuration */
+function describe() {
+  const data = STUART.json("data.json");
+  const missing = STUART.json("missing.json");
+  return `${STUART.config("name")}: ${data.x + data.y} ${missing === undefined}`;
+}
+
 export default {
   name: "my_js_plugin",
   version: "0.0.1",
@@ -67,6 +79,14 @@ export default {
     {
       name: "get",
       fn: getVariable
+    },
+    {
+      name: "wrap",
+      fn: wrap
+    },
+    {
+      name: "describe",
+      fn: describe
     }
   ]
 }
\ No newline at end of file
@@ -0,0 +1,38 @@
+local state = 0
+
+return {
+  name = "my_lua_plugin",
+  version = "0.0.1",
+  functions = {
+    -- Basic add function
+    add = function(a, b)
+      return a + b
+    end,
+
+    -- Test function which takes a string
+    greet = function(name)
+      return "Hello, " .. name .. "!"
+    end,
+
+    -- Test function with internal (to the plugin) state
+    inc = function()
+      local value = state
+      state = state + 1
+      return value
+    end,
+
+    -- Test function which takes in a more complex type
+    magnitude = function(v)
+      return math.sqrt(v.x * v.x + v.y * v.y)
+    end,
+
+    -- Test function which returns a table
+    range = function(n)
+      local items = {}
+      for i = 1, n do
+        items[i] = i
+      end
+      return items
+    end
+  }
+}
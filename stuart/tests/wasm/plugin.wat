@@ -0,0 +1,52 @@
+;; A minimal WebAssembly plugin, written in the text format.
+(module
+  (memory (export "memory") 1)
+
+  ;; Buffers are allocated after the static data and never freed.
+  (global $next (mut i32) (i32.const 1024))
+
+  (data (i32.const 0) "{\"name\":\"my_wasm_plugin\",\"version\":\"0.0.1\",\"functions\":[\"hello\",\"echo\"],\"parsers\":[{\"name\":\"upper\",\"extensions\":[\"shout\"],\"output_extension\":\"txt\"}]}")
+  (data (i32.const 512) "\"Hello from WebAssembly!\"")
+
+  ;; Packs a pointer and length into the return value.
+  (func $pack (param $ptr i32) (param $len i32) (result i64)
+    (i64.or
+      (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+      (i64.extend_i32_u (local.get $len))))
+
+  (func (export "stuart_alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $next))
+    (global.set $next (i32.add (global.get $next) (local.get $len)))
+    (local.get $ptr))
+
+  (func (export "stuart_plugin") (result i64)
+    (call $pack (i32.const 0) (i32.const 149)))
+
+  ;; Test function which returns a string
+  (func (export "stuart_fn_hello") (param $ptr i32) (param $len i32) (result i64)
+    (call $pack (i32.const 512) (i32.const 25)))
+
+  ;; Test function which returns its arguments
+  (func (export "stuart_fn_echo") (param $ptr i32) (param $len i32) (result i64)
+    (call $pack (local.get $ptr) (local.get $len)))
+
+  ;; Test parser which converts ASCII letters to uppercase in place
+  (func (export "stuart_parser_upper") (param $ptr i32) (param $len i32) (result i64)
+    (local $i i32)
+    (local $c i32)
+    (block $done
+      (loop $loop
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (local.set $c (i32.load8_u (i32.add (local.get $ptr) (local.get $i))))
+        (if (i32.and
+              (i32.ge_u (local.get $c) (i32.const 97))
+              (i32.le_u (local.get $c) (i32.const 122)))
+          (then
+            (i32.store8
+              (i32.add (local.get $ptr) (local.get $i))
+              (i32.sub (local.get $c) (i32.const 32)))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $loop)))
+    (call $pack (local.get $ptr) (local.get $len)))
+)